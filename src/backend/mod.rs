@@ -6,6 +6,7 @@ mod drm;
 mod libinput;
 mod multi;
 mod session;
+pub mod remote;
 
 pub use self::backend::*;
 pub use self::session::*;
@@ -0,0 +1,233 @@
+//! Building blocks for serving the compositor over a remote desktop protocol
+//! such as VNC or RDP.
+//!
+//! A `Remote` owns a virtual output and a virtual pointer and keyboard. The
+//! output is rendered to like any other output, and `Remote::capture` hands
+//! its contents to a `FrameSink` as shared memory. Input received from the
+//! remote client is fed back in through the `Remote` and shows up as regular
+//! input events through the input manager.
+//!
+//! Frames are only delivered as shared memory, this version of wlroots has no
+//! way to export the output buffer as a dmabuf.
+
+use std::{mem, time::Duration};
+
+use wayland_sys::server::signal::wl_signal_emit;
+use wlroots_sys::{wl_shm_format, wlr_axis_orientation, wlr_axis_source, wlr_button_state,
+                  wlr_event_keyboard_key, wlr_event_pointer_axis, wlr_event_pointer_button,
+                  wlr_event_pointer_motion_absolute, wlr_input_device, wlr_input_device_destroy,
+                  wlr_input_device_type, wlr_key_state, wlr_keyboard, wlr_keyboard_notify_key,
+                  wlr_output_destroy, wlr_pointer, wl_shm_format::WL_SHM_FORMAT_ARGB8888};
+
+use {compositor::Compositor,
+     input,
+     output,
+     render::Renderer,
+     stats,
+     utils::ToMs};
+
+/// A frame of a remote output.
+#[derive(Debug)]
+pub struct Frame<'frame> {
+    pub width: u32,
+    pub height: u32,
+    /// Bytes per row of `data`.
+    pub stride: u32,
+    pub format: wl_shm_format,
+    pub data: &'frame [u8]
+}
+
+/// Receives the frames of a `Remote` output, e.g to encode and send them to a
+/// VNC client.
+pub trait FrameSink {
+    fn frame(&mut self, frame: Frame);
+}
+
+/// A virtual output and input devices that are driven by a remote client.
+pub struct Remote {
+    output: output::Handle,
+    pointer: (*mut wlr_input_device, *mut wlr_pointer),
+    keyboard: (*mut wlr_input_device, *mut wlr_keyboard),
+    /// The handles of `pointer` and `keyboard`, to destroy them again.
+    devices: Vec<input::Handle>,
+    sink: Box<FrameSink>,
+    buffer: stats::PixelBuffer
+}
+
+impl Remote {
+    /// Create the virtual output and input devices.
+    ///
+    /// Returns `None` if the backend doesn't support virtual outputs, see
+    /// `Compositor::create_virtual_output`.
+    pub fn new(compositor: &mut Compositor,
+               width: u32,
+               height: u32,
+               sink: Box<FrameSink>)
+               -> Option<Self> {
+        use self::wlr_input_device_type::*;
+        let output = compositor.create_virtual_output(width, height)?;
+        let mut devices = Vec::with_capacity(2);
+        for &dev_type in &[WLR_INPUT_DEVICE_POINTER, WLR_INPUT_DEVICE_KEYBOARD] {
+            match compositor.create_virtual_input_device(dev_type) {
+                Some(device) => devices.push(device),
+                None => break
+            }
+        }
+        let pointer = match devices.get(0) {
+            Some(&input::Handle::Pointer(ref handle)) => {
+                handle.run(|pointer| unsafe { (pointer.input_device().as_ptr(), pointer.as_ptr()) })
+                      .ok()
+            }
+            _ => None
+        };
+        let keyboard = match devices.get(1) {
+            Some(&input::Handle::Keyboard(ref handle)) => {
+                handle.run(|keyboard| unsafe {
+                                (keyboard.input_device().as_ptr(), keyboard.as_ptr())
+                            })
+                      .ok()
+            }
+            _ => None
+        };
+        match (pointer, keyboard) {
+            (Some(pointer), Some(keyboard)) => Some(Remote { output,
+                                                             pointer,
+                                                             keyboard,
+                                                             devices,
+                                                             sink,
+                                                             buffer: stats::PixelBuffer::new() }),
+            _ => {
+                wlr_log!(WLR_ERROR, "Could not create the input devices for a remote output");
+                // Don't leave behind the ones that could be created.
+                destroy_devices(&mut devices);
+                compositor.destroy_virtual_output(output);
+                None
+            }
+        }
+    }
+
+    /// The virtual output the remote client is shown.
+    pub fn output(&self) -> output::Handle {
+        self.output.clone()
+    }
+
+    /// Read the output contents and pass them to the `FrameSink`.
+    ///
+    /// Call this after rendering to the remote output, before the `Renderer`
+    /// is dropped. Returns false if `renderer` is not rendering the remote
+    /// output or the pixels could not be read.
+    pub fn capture(&mut self, renderer: &mut Renderer) -> bool {
+        let is_remote = unsafe { renderer.output.as_ptr() == self.output.as_ptr() };
+        if !is_remote {
+            return false
+        }
+        let (width, height) = renderer.output.size();
+        let (width, height) = (width as u32, height as u32);
+        let stride = width * 4;
+        self.buffer.resize((stride * height) as usize);
+        if !renderer.read_pixels(WL_SHM_FORMAT_ARGB8888,
+                                 stride,
+                                 width,
+                                 height,
+                                 0,
+                                 0,
+                                 0,
+                                 0,
+                                 &mut self.buffer) {
+            return false
+        }
+        self.sink.frame(Frame { width,
+                                height,
+                                stride,
+                                format: WL_SHM_FORMAT_ARGB8888,
+                                data: &self.buffer });
+        true
+    }
+
+    /// Move the pointer to (`x`, `y`), both in the range 0 to 1 relative to
+    /// the output.
+    pub fn pointer_motion(&mut self, x: f64, y: f64, time: Duration) {
+        unsafe {
+            let (device, pointer) = self.pointer;
+            let mut event: wlr_event_pointer_motion_absolute = mem::zeroed();
+            event.device = device;
+            event.time_msec = time.to_ms();
+            event.x = x;
+            event.y = y;
+            wl_signal_emit(&mut (*pointer).events.motion_absolute as *mut _ as _,
+                           &mut event as *mut _ as _);
+        }
+    }
+
+    pub fn pointer_button(&mut self, button: u32, state: wlr_button_state, time: Duration) {
+        unsafe {
+            let (device, pointer) = self.pointer;
+            let mut event: wlr_event_pointer_button = mem::zeroed();
+            event.device = device;
+            event.time_msec = time.to_ms();
+            event.button = button;
+            event.state = state;
+            wl_signal_emit(&mut (*pointer).events.button as *mut _ as _,
+                           &mut event as *mut _ as _);
+        }
+    }
+
+    pub fn pointer_axis(&mut self,
+                        orientation: wlr_axis_orientation,
+                        delta: f64,
+                        time: Duration) {
+        unsafe {
+            let (device, pointer) = self.pointer;
+            let mut event: wlr_event_pointer_axis = mem::zeroed();
+            event.device = device;
+            event.time_msec = time.to_ms();
+            event.source = wlr_axis_source::WLR_AXIS_SOURCE_WHEEL;
+            event.orientation = orientation;
+            event.delta = delta;
+            wl_signal_emit(&mut (*pointer).events.axis as *mut _ as _,
+                           &mut event as *mut _ as _);
+        }
+    }
+
+    /// Press or release a key. `keycode` is an evdev keycode (i.e without the
+    /// offset of 8 xkb uses).
+    pub fn key(&mut self, keycode: u32, state: wlr_key_state, time: Duration) {
+        unsafe {
+            let (_, keyboard) = self.keyboard;
+            let mut event: wlr_event_keyboard_key = mem::zeroed();
+            event.time_msec = time.to_ms();
+            event.keycode = keycode;
+            event.update_state = true;
+            event.state = state;
+            wlr_keyboard_notify_key(keyboard, &mut event);
+        }
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        destroy_devices(&mut self.devices);
+        if self.output.is_alive() && !self.output.is_borrowed() {
+            unsafe { wlr_output_destroy(self.output.as_ptr()) }
+        }
+    }
+}
+
+/// Destroy the virtual input devices that are still around.
+fn destroy_devices(devices: &mut Vec<input::Handle>) {
+    for device in devices.drain(..) {
+        let device_ptr = match device {
+            input::Handle::Pointer(handle) => {
+                handle.run(|pointer| unsafe { pointer.input_device().as_ptr() })
+            }
+            input::Handle::Keyboard(handle) => {
+                handle.run(|keyboard| unsafe { keyboard.input_device().as_ptr() })
+            }
+            _ => continue
+        };
+        // NOTE The device must not be borrowed while destroying it.
+        if let Ok(device_ptr) = device_ptr {
+            unsafe { wlr_input_device_destroy(device_ptr) }
+        }
+    }
+}
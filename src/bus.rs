@@ -0,0 +1,240 @@
+//! A lightweight event bus.
+//!
+//! Handler traits only have one implementor per resource, which makes it
+//! awkward for independent features (a status bar, window rules, animations)
+//! to all react to e.g. focus changes. Subsystems publish typed events on
+//! this bus instead, and any number of listeners can subscribe to them.
+//!
+//! Any `'static` type can be an event. The events published by wlroots-rs
+//! itself are defined in this module:
+//!
+//! * `OutputAdded` and `OutputRemoved` by the output manager.
+//! * `SeatActivity` whenever input is sent through a `Seat`, which is what
+//!   idle tracking should listen to.
+//! * `FocusChanged` by `wm::focus::Policy`.
+//! * `ModeChanged` by `wm::keybinding::Modes`.
+//! * `WorkspaceChanged` by `wm::workspaces::Workspaces`.
+//!
+//! ```rust,no_run,ignore
+//! let subscription = bus::subscribe(|event: &bus::OutputAdded| {
+//!     wlr_log!(WLR_INFO, "output {} ({}) added", event.id, event.name);
+//! });
+//! // ...
+//! bus::unsubscribe(subscription);
+//! ```
+//!
+//! The bus is local to the thread the compositor runs on. Listeners are
+//! called synchronously from `publish`, in the order they subscribed. They
+//! may publish events of their own, but a listener is never re-entered while
+//! it is already running.
+//!
+//! Features shouldn't depend on the order they subscribed in. To test that
+//! they don't, `set_dispatch_seed` shuffles the listeners of every event in
+//! an order that only depends on the seed, so a property test that fails for
+//! one ordering fails the same way when run with the same seed again:
+//!
+//! ```rust,no_run,ignore
+//! bus::set_dispatch_seed(Some(seed));
+//! // Replay the generated input sequence.
+//! bus::set_dispatch_seed(None);
+//! ```
+
+use std::{any::{Any, TypeId}, cell::{Cell, RefCell}, collections::HashMap};
+
+#[cfg(feature = "unstable")]
+use seat::Capability;
+use utils::Id;
+
+type Listener = Box<FnMut(&Any)>;
+
+#[derive(Default)]
+struct Bus {
+    next_id: u64,
+    /// Listeners per event type. A listener is `None` while it is running.
+    listeners: HashMap<TypeId, Vec<(u64, Option<Listener>)>>
+}
+
+thread_local!(static BUS: RefCell<Bus> = RefCell::new(Bus::default()));
+
+/// The state of the generator the listeners are shuffled with, if they are.
+thread_local!(static DISPATCH_RNG: Cell<Option<u64>> = Cell::new(None));
+
+/// Token returned by `subscribe`, used to remove the listener again.
+///
+/// Dropping it does *not* unsubscribe the listener.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Subscription {
+    type_id: TypeId,
+    id: u64
+}
+
+/// An output was accepted by the output manager.
+#[derive(Debug, Clone)]
+pub struct OutputAdded {
+    pub id: Id,
+    pub name: String
+}
+
+/// An output was destroyed (e.g. unplugged).
+#[derive(Debug, Clone)]
+pub struct OutputRemoved {
+    pub id: Id,
+    pub name: String
+}
+
+/// Input was sent to clients through a seat.
+#[cfg(feature = "unstable")]
+#[derive(Debug, Clone)]
+pub struct SeatActivity {
+    /// The name of the seat.
+    pub seat: Option<String>,
+    /// The kind of device the input came from.
+    pub source: Capability
+}
+
+/// The session became active or inactive, e.g because of a VT switch or
+/// the system suspending and waking up.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SessionChanged {
+    pub active: bool
+}
+
+/// Keyboard focus moved from one view to another.
+#[derive(Debug, Clone)]
+pub struct FocusChanged<T> {
+    pub previous: Option<T>,
+    pub focused: Option<T>
+}
+
+/// A keybinding mode was entered or left.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModeChanged {
+    pub previous: String,
+    pub mode: String,
+    /// How many modes are entered on top of the default mode.
+    pub depth: usize
+}
+
+/// An output switched to another workspace.
+#[derive(Debug, Clone)]
+pub struct WorkspaceChanged<K> {
+    pub output: K,
+    pub previous: u32,
+    pub workspace: u32
+}
+
+/// Register a listener for events of type `E`.
+pub fn subscribe<E, F>(mut listener: F) -> Subscription
+    where E: Any,
+          F: FnMut(&E) + 'static
+{
+    let type_id = TypeId::of::<E>();
+    let listener: Listener = Box::new(move |event: &Any| {
+                                          if let Some(event) = event.downcast_ref::<E>() {
+                                              listener(event)
+                                          }
+                                      });
+    BUS.with(|bus| {
+        let mut bus = bus.borrow_mut();
+        bus.next_id += 1;
+        let id = bus.next_id;
+        bus.listeners.entry(type_id).or_insert_with(Vec::new).push((id, Some(listener)));
+        Subscription { type_id, id }
+    })
+}
+
+/// Remove a listener.
+///
+/// Returns false if it was already removed. It is safe to call this from
+/// within a listener, including the one being removed.
+pub fn unsubscribe(subscription: Subscription) -> bool {
+    BUS.with(|bus| {
+        let mut bus = bus.borrow_mut();
+        let listeners = match bus.listeners.get_mut(&subscription.type_id) {
+            Some(listeners) => listeners,
+            None => return false
+        };
+        let len = listeners.len();
+        listeners.retain(|&(id, _)| id != subscription.id);
+        listeners.len() != len
+    })
+}
+
+/// Remove every listener on the bus.
+pub fn clear() {
+    BUS.with(|bus| bus.borrow_mut().listeners.clear())
+}
+
+/// Call the listeners of every event in an order picked by `seed`, instead
+/// of the order they subscribed in. `None` goes back to subscription order.
+///
+/// The same seed gives the same order for the same sequence of subscriptions
+/// and events, on any machine.
+pub fn set_dispatch_seed(seed: Option<u64>) {
+    // xorshift gets stuck on zero.
+    DISPATCH_RNG.with(|rng| rng.set(seed.map(|seed| if seed == 0 { 1 } else { seed })))
+}
+
+/// Shuffle the ids if there is a dispatch seed.
+fn shuffle(ids: &mut [u64]) {
+    DISPATCH_RNG.with(|rng| {
+        let mut state = match rng.get() {
+            Some(state) => state,
+            None => return
+        };
+        for i in (1..ids.len()).rev() {
+            // xorshift64*
+            state ^= state >> 12;
+            state ^= state << 25;
+            state ^= state >> 27;
+            let random = state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+            ids.swap(i, (random % (i as u64 + 1)) as usize);
+        }
+        rng.set(Some(state))
+    })
+}
+
+/// Send an event to every listener subscribed to its type.
+///
+/// Returns how many listeners were called.
+pub fn publish<E: Any>(event: &E) -> usize {
+    let type_id = TypeId::of::<E>();
+    let mut ids: Vec<u64> = BUS.with(|bus| {
+        bus.borrow()
+           .listeners
+           .get(&type_id)
+           .map(|listeners| listeners.iter().map(|&(id, _)| id).collect())
+           .unwrap_or_else(Vec::new)
+    });
+    shuffle(&mut ids);
+    let mut called = 0;
+    for id in ids {
+        // Take the listener out while it runs, so it can use the bus itself.
+        let listener = BUS.with(|bus| {
+            bus.borrow_mut()
+               .listeners
+               .get_mut(&type_id)
+               .and_then(|listeners| listeners.iter_mut().find(|&&mut (lid, _)| lid == id))
+               .and_then(|&mut (_, ref mut listener)| listener.take())
+        });
+        let mut listener = match listener {
+            Some(listener) => listener,
+            None => continue
+        };
+        listener(event);
+        called += 1;
+        // Put it back, unless it was unsubscribed in the meantime.
+        BUS.with(|bus| {
+            let mut bus = bus.borrow_mut();
+            let slot = bus.listeners
+                          .get_mut(&type_id)
+                          .and_then(|listeners| {
+                                        listeners.iter_mut().find(|&&mut (lid, _)| lid == id)
+                                    });
+            if let Some(&mut (_, ref mut slot)) = slot {
+                *slot = Some(listener);
+            }
+        });
+    }
+    called
+}
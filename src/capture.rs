@@ -0,0 +1,274 @@
+//! Listing what can be captured, e.g for the picker of a screencast portal.
+//!
+//! `sources` lists the enabled outputs and the mapped toplevel windows, with
+//! the `Id`s they can be referred to by from outside of the compositor. The
+//! lists only contain owned data, so (with the `serde` feature) they can be
+//! sent over an IPC socket as they are. Once the user picked something,
+//! `output` and `window` turn the `Id` back into a handle.
+//!
+//! Windows are captured with `capture_view`, which draws the surfaces of the
+//! window on their own, regardless of what covers them or which output they
+//! are on. It draws into a buffer of its own, so it can be called at any
+//! point while an output is rendered:
+//!
+//! ```rust,no_run,ignore
+//! let mut renderer = generic_renderer.render(output, None);
+//! draw_scene(&mut renderer);
+//! if let Some(window) = capture::window(picked) {
+//!     if let Some(capture) = capture::capture_view(&mut renderer, &window) {
+//!         send_to_portal(capture);
+//!     }
+//! }
+//! ```
+//!
+//! `render::screencast::Source::Window` does the same for screencasts, and
+//! `screencopy::Handler::window` for screencopy clients.
+
+use wlroots_sys::wl_output_transform::WL_OUTPUT_TRANSFORM_NORMAL;
+
+use area::{Area, Origin, Size};
+use output::{self, Output};
+use render::{matrix, offscreen::Offscreen, Renderer};
+use shell::{xdg_shell, xdg_shell_v6};
+use snapshot;
+use surface;
+use utils::{Handleable, Id};
+
+/// An output that can be captured.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct OutputSource {
+    pub id: Id,
+    pub name: String,
+    /// A description to show to the user, e.g "Dell Inc. U2415 (DP-1)".
+    pub description: String,
+    /// Position in layout coordinates and the effective resolution.
+    pub geometry: Area,
+    pub scale: f32
+}
+
+/// A toplevel window that can be captured.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct WindowSource {
+    pub id: Id,
+    pub title: String,
+    pub app_id: String,
+    /// The size of the window, without client side shadows.
+    pub size: Size
+}
+
+/// Everything that can be captured.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Sources {
+    pub outputs: Vec<OutputSource>,
+    pub windows: Vec<WindowSource>
+}
+
+/// A handle to a toplevel window of either XDG shell.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Window {
+    XdgShell(xdg_shell::Handle),
+    XdgShellV6(xdg_shell_v6::Handle)
+}
+
+/// The pixels of a captured window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowCapture {
+    pub width: u32,
+    pub height: u32,
+    /// Bytes per row of `data`.
+    pub stride: u32,
+    /// The pixels, in `WL_SHM_FORMAT_ARGB8888`.
+    pub data: Vec<u8>
+}
+
+impl Window {
+    /// Get the geometry of the window and its surfaces, with their
+    /// positions relative to the window.
+    fn surface_tree(&self) -> Option<(Area, Vec<(surface::Handle, i32, i32)>)> {
+        match *self {
+            Window::XdgShell(ref handle) => {
+                handle.run(|shell_surface| {
+                          let mut surfaces = Vec::new();
+                          shell_surface.for_each_surface(|surface, sx, sy| {
+                                                             surfaces.push((surface, sx, sy))
+                                                         });
+                          let main_surface = shell_surface.surface();
+                          (geometry_or_size(shell_surface.geometry(), main_surface), surfaces)
+                      })
+                      .ok()
+            }
+            Window::XdgShellV6(ref handle) => {
+                handle.run(|shell_surface| {
+                          let mut surfaces = Vec::new();
+                          shell_surface.for_each_surface(|surface, sx, sy| {
+                                                             surfaces.push((surface, sx, sy))
+                                                         });
+                          let main_surface = shell_surface.surface();
+                          (geometry_or_size(shell_surface.geometry(), main_surface), surfaces)
+                      })
+                      .ok()
+            }
+        }
+    }
+}
+
+/// List the outputs and windows of the running compositor that can be
+/// captured, ordered by when they were created.
+///
+/// Resources that are currently borrowed are left out.
+pub fn sources() -> Sources {
+    Sources { outputs: outputs(),
+              windows: windows() }
+}
+
+/// List the enabled outputs of the running compositor.
+pub fn outputs() -> Vec<OutputSource> {
+    unsafe { snapshot::outputs() }.iter()
+                                 .filter_map(|handle| handle.run(output_source).unwrap_or(None))
+                                 .collect()
+}
+
+/// List the mapped toplevel windows of the running compositor.
+pub fn windows() -> Vec<WindowSource> {
+    let (xdg_surfaces, xdg_v6_surfaces) = unsafe { snapshot::xdg_surfaces() };
+    let mut windows: Vec<WindowSource> =
+        xdg_surfaces.iter()
+                    .filter_map(|handle| handle.run(xdg_window_source).unwrap_or(None))
+                    .collect();
+    windows.extend(xdg_v6_surfaces.iter().filter_map(|handle| {
+                                             handle.run(xdg_v6_window_source).unwrap_or(None)
+                                         }));
+    windows
+}
+
+/// Find the output with the id, if it's still around.
+pub fn output(id: Id) -> Option<output::Handle> {
+    unsafe { snapshot::outputs() }.into_iter()
+                                 .find(|handle| handle.run(|output| output.id() == id)
+                                                      .unwrap_or(false))
+}
+
+/// Find the window with the id, if it's still around.
+pub fn window(id: Id) -> Option<Window> {
+    let (xdg_surfaces, xdg_v6_surfaces) = unsafe { snapshot::xdg_surfaces() };
+    let xdg = xdg_surfaces.into_iter()
+                          .find(|handle| handle.run(|surface| surface.id() == id)
+                                               .unwrap_or(false))
+                          .map(Window::XdgShell);
+    xdg.or_else(|| {
+               xdg_v6_surfaces.into_iter()
+                              .find(|handle| handle.run(|surface| surface.id() == id)
+                                                   .unwrap_or(false))
+                              .map(Window::XdgShellV6)
+           })
+}
+
+fn output_source(output: &mut Output) -> Option<OutputSource> {
+    if !output.enabled() {
+        return None
+    }
+    let (x, y) = output.layout_space_pos();
+    let (width, height) = output.effective_resolution();
+    let description = format!("{} {} ({})", output.make(), output.model(), output.name());
+    Some(OutputSource { id: output.id(),
+                        name: output.name(),
+                        description: description.trim().into(),
+                        geometry: Area::new(Origin::new(x, y), Size::new(width, height)),
+                        scale: output.scale() })
+}
+
+fn xdg_window_source(surface: &mut xdg_shell::Surface) -> Option<WindowSource> {
+    let mapped = unsafe { (*surface.as_ptr()).mapped };
+    let id = surface.id();
+    let size = surface.geometry().size;
+    match surface.state() {
+        Some(&mut xdg_shell::ShellState::TopLevel(ref toplevel)) if mapped => {
+            Some(WindowSource { id,
+                                title: toplevel.title(),
+                                app_id: toplevel.app_id(),
+                                size })
+        }
+        _ => None
+    }
+}
+
+fn xdg_v6_window_source(surface: &mut xdg_shell_v6::Surface) -> Option<WindowSource> {
+    let mapped = unsafe { (*surface.as_ptr()).mapped };
+    let id = surface.id();
+    let size = surface.geometry().size;
+    match surface.state() {
+        Some(&mut xdg_shell_v6::ShellState::TopLevel(ref toplevel)) if mapped => {
+            Some(WindowSource { id,
+                                title: toplevel.title(),
+                                app_id: toplevel.app_id(),
+                                size })
+        }
+        _ => None
+    }
+}
+
+/// Capture the window by drawing its surfaces offscreen, at the scale of the
+/// output being rendered.
+///
+/// The window is drawn into a buffer of its size, so it's captured whole
+/// and what was drawn on the output so far is left alone.
+///
+/// Returns `None` if the window is gone, has no size or its pixels could not
+/// be read.
+pub fn capture_view(renderer: &mut Renderer, window: &Window) -> Option<WindowCapture> {
+    let (geometry, surfaces) = match window.surface_tree() {
+        Some(tree) => tree,
+        None => return None
+    };
+    let scale = renderer.output.scale();
+    let width = (geometry.size.width as f32 * scale) as i32;
+    let height = (geometry.size.height as f32 * scale) as i32;
+    if width <= 0 || height <= 0 {
+        return None
+    }
+    let offscreen = Offscreen::new(renderer, width, height)?;
+    let projection = matrix::projection([0.0; 9], width, height, WL_OUTPUT_TRANSFORM_NORMAL);
+    renderer.clear([0.0; 4]);
+    for (surface, sx, sy) in surfaces {
+        let _ = surface.run(|surface| {
+            let ((surface_width, surface_height), transform) = {
+                let state = surface.current_state();
+                (state.size(), state.transform())
+            };
+            let origin = Origin::new(((sx - geometry.origin.x) as f32 * scale) as i32,
+                                     ((sy - geometry.origin.y) as f32 * scale) as i32);
+            let size = Size::new((surface_width as f32 * scale) as i32,
+                                 (surface_height as f32 * scale) as i32);
+            if let Some(texture) = surface.texture() {
+                let matrix = matrix::project_box(Area::new(origin, size),
+                                                 transform.invert(),
+                                                 0.0,
+                                                 projection);
+                renderer.render_texture_with_matrix(&texture, matrix);
+            }
+        });
+    }
+    let (width, height) = (width as u32, height as u32);
+    let stride = width * 4;
+    let mut data = vec![0; (stride * height) as usize];
+    if !offscreen.read_pixels(&mut data) {
+        return None
+    }
+    Some(WindowCapture { width,
+                         height,
+                         stride,
+                         data })
+}
+
+/// Windows that never set their geometry are as large as their surface.
+fn geometry_or_size(geometry: Area, surface: surface::Handle) -> Area {
+    if geometry.size.width > 0 && geometry.size.height > 0 {
+        return geometry
+    }
+    let (width, height) = surface.run(|surface| surface.current_state().size())
+                                 .unwrap_or((0, 0));
+    Area::new(Origin::default(), Size::new(width, height))
+}
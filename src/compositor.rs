@@ -1,31 +1,52 @@
 //! Main entry point to the library.
 //! See examples for documentation on how to use this struct.
 
-use std::{env, panic, ptr, any::Any, cell::{Cell, UnsafeCell},
-          ffi::CStr, rc::{Rc, Weak}, sync::atomic::{AtomicBool, Ordering}};
+use std::{cmp, env, fs, mem, panic, ptr, any::Any, cell::{Cell, UnsafeCell},
+          ffi::{CStr, OsStr}, os::unix::{ffi::OsStrExt, process::CommandExt},
+          path::{Path, PathBuf}, process::Command, rc::{Rc, Weak},
+          sync::atomic::{AtomicBool, Ordering}, time::{Duration, Instant}};
 
 use libc;
-use wayland_sys::server::{wl_display, wl_event_loop, signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
-use wlroots_sys::{wlr_backend_destroy, wlr_backend_start,
+use wayland_sys::server::{wl_display, wl_event_loop, wl_event_source, signal::wl_signal_add,
+                          WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_session, wlr_backend_destroy, wlr_backend_start, wlr_output_destroy,
                   wlr_compositor, wlr_compositor_create, wlr_compositor_destroy,
+                  wlr_input_device_type, wlr_xdg_surface_ping, wlr_xdg_surface_v6_ping,
+                  wlr_xdg_surface_role::WLR_XDG_SURFACE_ROLE_TOPLEVEL,
+                  wlr_xdg_surface_v6_role::WLR_XDG_SURFACE_V6_ROLE_TOPLEVEL,
+                  wlr_xdg_toplevel_send_close, wlr_xdg_surface_v6_send_close,
                   wlr_xdg_shell_v6, wlr_xdg_shell_v6_create,
-                  wlr_xdg_shell, wlr_xdg_shell_create};
+                  wlr_xdg_shell, wlr_xdg_shell_create,
+                  wlr_layer_shell, wlr_layer_shell_create};
 
 
 use {backend::{self, UnsafeRenderSetupFunction, Backend, Session},
+     bus,
+     capture,
+     crash,
      data_device,
-     extensions::{server_decoration, gamma_control, screencopy, screenshooter, idle, gtk_primary_selection},
+     extensions::{server_decoration, gamma_control, screencopy, export_dmabuf, screenshooter, idle,
+                  input_inhibit, virtual_keyboard, pointer_constraints, tablet,
+                  gtk_primary_selection, river_status, linux_dmabuf, xdg_output},
      surface::{self, Surface, InternalSurface},
      input,
+     kiosk,
      output,
      render::GenericRenderer,
-     shell::{xdg_shell, xdg_shell_v6},
+     shell::{layer_shell, xdg_shell, xdg_shell_v6},
+     snapshot::{self, Snapshot},
+     stats,
      xwayland,
-     utils::{HandleErr, HandleResult, Handleable}};
+     utils::{self, HandleErr, HandleResult, Handleable, ToMs}};
 
 /// Global compositor pointer, used to refer to the compositor state unsafely.
 pub(crate) static mut COMPOSITOR_PTR: *mut Compositor = 0 as *mut _;
 
+/// Environment variables used to hand the listening socket over to the
+/// new process when restarting.
+const SOCKET_FD_VAR: &str = "_WLROOTS_RS_SOCKET_FD";
+const SOCKET_NAME_VAR: &str = "_WLROOTS_RS_SOCKET_NAME";
+
 /// Callback that's triggered when a surface is provided to the compositor.
 pub type NewSurface = fn(compositor_handle: Handle,
                             surface_handle: surface::Handle);
@@ -33,6 +54,29 @@ pub type NewSurface = fn(compositor_handle: Handle,
 /// Callback that's triggered during shutdown.
 pub type OnShutdown = fn();
 
+/// Callback that's triggered when the shutdown grace period ran out while
+/// there are still toplevels open. It's given the number of open toplevels.
+pub type ShutdownHook = fn(open_toplevels: usize) -> Shutdown;
+
+/// How often the open toplevels are checked during the shutdown grace period.
+const SHUTDOWN_POLL_MS: libc::c_int = 100;
+
+/// How often memory pressure is checked, if the compositor trims on memory
+/// pressure.
+const MEMORY_PRESSURE_POLL_MS: libc::c_int = 10_000;
+
+/// What to do when the shutdown grace period ran out, see
+/// `Builder::shutdown_grace_period`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Shutdown {
+    /// Shut down now, disconnecting the remaining clients.
+    Proceed,
+    /// Give the clients more time.
+    Extend(Duration),
+    /// Don't shut down after all.
+    Veto
+}
+
 /// A check to ensure that we only have one builder at a time.
 /// This is necessary because it uses global state to keep track
 /// of callback pointers.
@@ -49,8 +93,35 @@ wayland_listener_static!{
             let surface_ptr = data as _;
             let compositor = (&mut *COMPOSITOR_PTR).weak_reference();
             let surface = Surface::new(surface_ptr);
+            // Taken out while it runs, the handler might use the compositor.
+            let surface_manager = (*COMPOSITOR_PTR).surface_manager.take();
+            let action = match surface_manager {
+                Some(mut surface_manager) => {
+                    let action = surface_manager.new_surface(compositor.clone(),
+                                                             surface.weak_reference());
+                    (*COMPOSITOR_PTR).surface_manager = Some(surface_manager);
+                    action
+                }
+                None => surface::NewSurfaceAction::Accept(Box::new(()))
+            };
+            let surface_handler = match action {
+                surface::NewSurfaceAction::Accept(surface_handler) => surface_handler,
+                surface::NewSurfaceAction::Reject => {
+                    wlr_log!(WLR_INFO, "Rejected new surface {:p}", surface_ptr);
+                    // Nothing else is set up for the surface, it's destroyed
+                    // along with the client.
+                    surface.discard();
+                    let message = utils::safe_as_cstring("the compositor rejected the surface");
+                    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                  wl_resource_post_error,
+                                  (*surface_ptr).resource as _,
+                                  surface::REJECTED_SURFACE_ERROR,
+                                  message.as_ptr());
+                    return
+                }
+            };
             handler.surface_added.map(|f| f(compositor.clone(), surface.weak_reference()));
-            let mut internal_surface = InternalSurface::new((surface, Box::new(())));
+            let mut internal_surface = InternalSurface::new((surface, surface_handler));
             wl_signal_add(&mut (*surface_ptr).events.commit as *mut _ as _,
                           internal_surface.on_commit_listener() as _);
             wl_signal_add(&mut (*surface_ptr).events.new_subsurface as *mut _ as _,
@@ -68,6 +139,33 @@ wayland_listener_static!{
     ]
 }
 
+wayland_listener!(pub(crate) SessionWatcher, *mut wlr_session, [
+    session_listener => session_notify: |this: &mut SessionWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        if COMPOSITOR_PTR.is_null() {
+            return
+        }
+        let compositor = &mut *COMPOSITOR_PTR;
+        if (*this.data).active {
+            wlr_log!(WLR_INFO, "Session is active again");
+            compositor.resume_from_sleep()
+        } else {
+            wlr_log!(WLR_INFO, "Session is inactive");
+            compositor.prepare_for_sleep()
+        }
+    };
+]);
+
+impl Drop for SessionWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.session_listener()).link as *mut _ as _);
+        }
+    }
+}
+
 // NOTE This handle is handled differently from the others, so we can't use
 // the generic `utils::Handle` implementation. This is due to how we need
 // to be able to return a "full" `Compositor` for `upgrade` but that's
@@ -98,10 +196,18 @@ pub struct Compositor {
     /// Pointer to the xdg_shell_v6 global.
     /// If xdg_v6_shell_manager is `None`, this value will be `NULL`.
     xdg_v6_shell_global: *mut wlr_xdg_shell_v6,
+    /// Manager for layer shells.
+    layer_shell_manager: Option<&'static mut layer_shell::Manager>,
+    /// Pointer to the layer_shell global.
+    /// If layer_shell_manager is `None`, this value will be `NULL`.
+    layer_shell_global: *mut wlr_layer_shell,
     /// Pointer to the wlr_compositor.
     compositor: *mut wlr_compositor,
     /// Pointer to the wlroots backend in use.
     backend: Backend,
+    /// Headless backend the virtual outputs are created on.
+    /// Created on first use.
+    virtual_backend: Option<backend::Headless>,
     /// Pointer to the wayland display.
     pub display: *mut wl_display,
     /// Pointer to the event loop.
@@ -111,16 +217,34 @@ pub struct Compositor {
     wl_shm_fd: Option<i32>,
     /// Name of the Wayland socket that we are binding to.
     socket_name: String,
+    /// Decides what happens to new surfaces, if set.
+    surface_manager: Option<Box<surface::ManagerHandler>>,
     /// Optional decoration manager extension.
     pub server_decoration_manager: Option<server_decoration::Manager>,
     /// Optional gamma manager extension.
     pub gamma_control_manager: Option<gamma_control::ZManagerV1>,
     /// Optional idle manager extension.
     pub idle_manager: Option<idle::Manager>,
+    /// Optional input inhibit manager extension
+    pub input_inhibit_manager: Option<input_inhibit::ZManagerV1>,
+    /// Optional virtual keyboard manager extension
+    pub virtual_keyboard_manager: Option<virtual_keyboard::ZManagerV1>,
+    /// Optional pointer constraints manager extension
+    pub pointer_constraints_manager: Option<pointer_constraints::ZManagerV1>,
+    /// Optional tablet manager extension
+    pub tablet_manager: Option<tablet::ZManagerV2>,
     /// Optional GTK primary selection manager
     pub gtk_primary_selection_manager: Option<gtk_primary_selection::Manager>,
+    /// Optional river status manager extension
+    pub river_status_manager: Option<river_status::ZManagerV1>,
+    /// Optional linux dmabuf extension
+    pub linux_dmabuf: Option<linux_dmabuf::LinuxDmabufV1>,
+    /// Optional xdg output manager extension
+    pub xdg_output_manager: Option<xdg_output::ZManagerV1>,
     /// Optional screencopy manager extension
     pub screencopy_manager: Option<screencopy::ZManagerV1>,
+    /// Optional export-dmabuf manager extension
+    pub export_dmabuf_manager: Option<export_dmabuf::ZManagerV1>,
     /// Optional screenshooter manager extension
     pub screenshooter: Option<screenshooter::Screenshooter>,
     /// The renderer used to draw things to the screen.
@@ -129,10 +253,46 @@ pub struct Compositor {
     pub xwayland: Option<xwayland::Server>,
     /// The DnD manager
     data_device_manager: Option<data_device::Manager>,
+    /// Resources that show up in state dumps.
+    pub(crate) tracked: snapshot::Tracked,
+    /// Where to write the state dump on `SIGUSR1`, if anywhere.
+    state_dump_path: Option<PathBuf>,
+    /// The `SIGUSR1` event source, if `state_dump_path` is set.
+    state_dump_source: *mut wl_event_source,
+    /// How often the XDG clients are pinged, if at all.
+    ping_interval: Option<Duration>,
+    /// The timer the pings are sent from, if `ping_interval` is set.
+    ping_source: *mut wl_event_source,
+    /// The memory pressure above which caches are trimmed, if at all.
+    memory_pressure_threshold: Option<f64>,
+    /// The timer memory pressure is checked from.
+    memory_pressure_source: *mut wl_event_source,
+    /// How long clients get to close their toplevels when terminating.
+    grace_period: Option<Duration>,
+    /// Decides what happens once the grace period is over.
+    shutdown_hook: Option<ShutdownHook>,
+    /// The timer checking the open toplevels during the grace period.
+    shutdown_source: *mut wl_event_source,
+    /// When the grace period is over, if the compositor is shutting down.
+    shutdown_deadline: Option<Instant>,
+    /// Called with a crash report when a callback panics.
+    crash_report_hook: Option<crash::ReportHook>,
+    /// Listens for the session becoming active again, if there is a session.
+    session_watcher: Option<Box<SessionWatcher>>,
+    /// Listens for the system suspending and waking up.
+    #[cfg(feature = "logind")]
+    sleep_watcher: Option<Box<::logind::SleepWatcher>>,
+    /// The outputs that were enabled when the session became inactive.
+    sleeping_outputs: Vec<output::Handle>,
     /// The error from the panic, if there was one.
     panic_error: Option<Box<Any + Send>>,
     /// Custom function to run at shutdown (or when a panic occurs).
     user_terminate: Option<fn()>,
+    /// Set once `terminate` has been called, so it only runs once.
+    terminating: bool,
+    /// Set by `restart`, the binary is executed again once the event loop
+    /// stops.
+    restart_requested: bool,
     /// Lock used to borrow the compositor globally.
     /// Should always be set before passing a reference to the compositor
     /// in a callback.
@@ -142,24 +302,46 @@ pub struct Compositor {
 #[derive(Default)]
 pub struct Builder {
     compositor_event_builder: Option<EventBuilder>,
+    surface_manager: Option<Box<surface::ManagerHandler>>,
     input_manager_builder: Option<input::manager::Builder>,
     output_manager_builder: Option<output::manager::Builder>,
     xdg_shell_manager_builder: Option<xdg_shell::manager::Builder>,
+    /// Whether the managers were set by `kiosk`.
+    kiosk: bool,
     xdg_v6_shell_manager_builder: Option<xdg_shell_v6::manager::Builder>,
+    layer_shell_manager_builder: Option<layer_shell::manager::Builder>,
     wl_shm: bool,
     gles2: bool,
     render_setup_function: Option<UnsafeRenderSetupFunction>,
     server_decoration_manager: bool,
     gamma_control_manager: bool,
+    gamma_control_handler: Option<Box<gamma_control::Handler>>,
     idle_manager: bool,
+    input_inhibit_manager: bool,
+    input_inhibit_handler: Option<Box<input_inhibit::Handler>>,
+    virtual_keyboard_manager: bool,
+    pointer_constraints_manager: bool,
+    pointer_constraints_handler: Option<Box<pointer_constraints::Handler>>,
+    tablet_manager: bool,
     gtk_primary_selection_manager: bool,
+    river_status_manager: bool,
+    linux_dmabuf: bool,
+    xdg_output_manager: Option<output::layout::Handle>,
     screencopy_manager: bool,
+    screencopy_handler: Option<Box<screencopy::Handler>>,
+    export_dmabuf_manager: bool,
     screenshooter: bool,
     wayland_remote: Option<String>,
     x11_display: Option<String>,
     data_device_manager: bool,
     xwayland: Option<xwayland::manager::Builder>,
-    user_terminate: Option<fn()>
+    user_terminate: Option<fn()>,
+    state_dump_path: Option<PathBuf>,
+    ping_interval: Option<Duration>,
+    memory_pressure_threshold: Option<f64>,
+    grace_period: Option<Duration>,
+    shutdown_hook: Option<ShutdownHook>,
+    crash_report_hook: Option<crash::ReportHook>
 }
 
 impl Builder {
@@ -190,14 +372,29 @@ impl Builder {
         self
     }
 
+    /// Decide what happens to every surface when it's created, before it
+    /// has a role.
+    pub fn surface_manager(mut self, surface_manager: Box<surface::ManagerHandler>) -> Self {
+        self.surface_manager = Some(surface_manager);
+        self
+    }
+
     /// Set callbacks for managing input resources.
+    ///
+    /// # Panics
+    /// Panics if `kiosk` was called, which sets its own.
     pub fn input_manager(mut self, input_manager_builder: input::manager::Builder) -> Self {
+        assert!(!self.kiosk, "The kiosk preset handles the input devices itself");
         self.input_manager_builder = Some(input_manager_builder);
         self
     }
 
     /// Set callbacks for managing output resources.
+    ///
+    /// # Panics
+    /// Panics if `kiosk` was called, which sets its own.
     pub fn output_manager(mut self, output_manager_builder: output::manager::Builder) -> Self {
+        assert!(!self.kiosk, "The kiosk preset handles the outputs itself");
         self.output_manager_builder = Some(output_manager_builder);
         self
     }
@@ -206,6 +403,7 @@ impl Builder {
     pub fn xdg_shell_manager(mut self,
                              xdg_shell_manager_builder: xdg_shell::manager::Builder)
                              -> Self {
+        assert!(!self.kiosk, "The kiosk preset handles the XDG shell itself");
         self.xdg_shell_manager_builder = Some(xdg_shell_manager_builder);
         self
     }
@@ -218,6 +416,17 @@ impl Builder {
         self
     }
 
+    /// Set callbacks for managing layer shell resources.
+    ///
+    /// This enables the wlr-layer-shell protocol, which is used by panels,
+    /// bars, wallpapers and lock screens.
+    pub fn layer_shell_manager(mut self,
+                               layer_shell_manager_builder: layer_shell::manager::Builder)
+                               -> Self {
+        self.layer_shell_manager_builder = Some(layer_shell_manager_builder);
+        self
+    }
+
     /// Decide whether or not to enable the wl_shm global.
     ///
     /// This is used to allocate shared memory between clients and the
@@ -250,11 +459,23 @@ impl Builder {
 
     /// Decide whether or not to enable the gamma control manager protocol
     /// extension.
+    ///
+    /// This lets clients such as redshift adjust the gamma of the outputs.
     pub fn gamma_control_manager(mut self, gamma_control_manager: bool) -> Self {
         self.gamma_control_manager = gamma_control_manager;
         self
     }
 
+    /// Enable the gamma control manager protocol extension, telling the
+    /// handler when clients take and release control of an output.
+    pub fn gamma_control_handler(mut self,
+                                 gamma_control_handler: Box<gamma_control::Handler>)
+                                 -> Self {
+        self.gamma_control_manager = true;
+        self.gamma_control_handler = Some(gamma_control_handler);
+        self
+    }
+
     /// Decide whether or not to enable the idle manager protocol
     /// extension.
     pub fn idle_manager(mut self, idle_manager: bool) -> Self {
@@ -262,6 +483,64 @@ impl Builder {
         self
     }
 
+    /// Decide whether or not to enable the input inhibitor protocol
+    /// extension.
+    ///
+    /// This lets screen lockers take all input for themselves.
+    pub fn input_inhibit_manager(mut self, input_inhibit_manager: bool) -> Self {
+        self.input_inhibit_manager = input_inhibit_manager;
+        self
+    }
+
+    /// Enable the input inhibitor protocol extension, telling the handler
+    /// when a client takes or gives up exclusive input.
+    pub fn input_inhibit_handler(mut self,
+                                 input_inhibit_handler: Box<input_inhibit::Handler>)
+                                 -> Self {
+        self.input_inhibit_manager = true;
+        self.input_inhibit_handler = Some(input_inhibit_handler);
+        self
+    }
+
+    /// Decide whether or not to enable the virtual keyboard protocol
+    /// extension.
+    ///
+    /// Virtual keyboards are passed to the input manager like any other
+    /// keyboard.
+    pub fn virtual_keyboard_manager(mut self, virtual_keyboard_manager: bool) -> Self {
+        self.virtual_keyboard_manager = virtual_keyboard_manager;
+        self
+    }
+
+    /// Decide whether or not to enable the pointer constraints protocol
+    /// extension.
+    ///
+    /// Without a handler, constraints only apply when the compositor
+    /// activates them with `pointer_constraints::ZManagerV1::constraint_for`.
+    pub fn pointer_constraints_manager(mut self, pointer_constraints_manager: bool) -> Self {
+        self.pointer_constraints_manager = pointer_constraints_manager;
+        self
+    }
+
+    /// Enable the pointer constraints protocol extension, telling the
+    /// handler when constraints come and go.
+    pub fn pointer_constraints_handler(mut self,
+                                       handler: Box<pointer_constraints::Handler>)
+                                       -> Self {
+        self.pointer_constraints_manager = true;
+        self.pointer_constraints_handler = Some(handler);
+        self
+    }
+
+    /// Decide whether or not to enable the tablet protocol extension.
+    ///
+    /// Tablets and pads have to be added to a seat with
+    /// `tablet::ZManagerV2::add_tablet` and `add_pad` before clients see them.
+    pub fn tablet_manager(mut self, tablet_manager: bool) -> Self {
+        self.tablet_manager = tablet_manager;
+        self
+    }
+
     /// Decide whether or not to enable the GTK primary selection manager protocol
     /// extension.
     pub fn gtk_primary_selection_manager(mut self, gtk_primary_selection_manager: bool) -> Self {
@@ -269,6 +548,29 @@ impl Builder {
         self
     }
 
+    /// Decide whether or not to enable the river status protocol
+    /// extension, which status bars use to show workspaces and focus.
+    pub fn river_status_manager(mut self, river_status_manager: bool) -> Self {
+        self.river_status_manager = river_status_manager;
+        self
+    }
+
+    /// Decide whether or not to enable the linux dmabuf protocol extension.
+    ///
+    /// It's only created when the gles2 renderer is enabled, which imports
+    /// the buffers.
+    pub fn linux_dmabuf(mut self, linux_dmabuf: bool) -> Self {
+        self.linux_dmabuf = linux_dmabuf;
+        self
+    }
+
+    /// Enable the xdg output protocol extension, telling clients where the
+    /// outputs are in `layout`.
+    pub fn xdg_output_manager(mut self, layout: output::layout::Handle) -> Self {
+        self.xdg_output_manager = Some(layout);
+        self
+    }
+
     /// Decide whether or not to enable the screencopy protocol
     /// extension.
     pub fn screencopy_manager(mut self, screencopy_manager: bool) -> Self {
@@ -276,6 +578,21 @@ impl Builder {
         self
     }
 
+    /// Enable the screencopy protocol extension, asking the handler before
+    /// a client gets a copy of an output.
+    pub fn screencopy_handler(mut self, screencopy_handler: Box<screencopy::Handler>) -> Self {
+        self.screencopy_manager = true;
+        self.screencopy_handler = Some(screencopy_handler);
+        self
+    }
+
+    /// Decide whether or not to enable the export-dmabuf protocol
+    /// extension.
+    pub fn export_dmabuf_manager(mut self, export_dmabuf_manager: bool) -> Self {
+        self.export_dmabuf_manager = export_dmabuf_manager;
+        self
+    }
+
 
     /// Decide whether or not to enable the screenshooter protocol
     /// extension.
@@ -299,6 +616,90 @@ impl Builder {
         self
     }
 
+    /// Write a state dump (see `Compositor::dump_state`) to the given file
+    /// every time the compositor receives `SIGUSR1`.
+    ///
+    /// The file is overwritten by each dump.
+    pub fn dump_state_on_sigusr1<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.state_dump_path = Some(path.into());
+        self
+    }
+
+    /// Ask the clients to close their toplevels when terminating, and keep
+    /// running for up to `grace_period` to give them time to save their
+    /// state.
+    ///
+    /// The compositor shuts down as soon as all toplevels are closed.
+    /// Calling `compositor::terminate` again during the grace period, or a
+    /// panic in a callback, shuts it down right away.
+    pub fn shutdown_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Decide what happens when the shutdown grace period is over but there
+    /// are still toplevels open, e.g to give a client that is still saving
+    /// more time.
+    ///
+    /// Without a hook the compositor shuts down.
+    pub fn shutdown_hook(mut self, hook: ShutdownHook) -> Self {
+        self.shutdown_hook = Some(hook);
+        self
+    }
+
+    /// Put together a `crash::Report` whenever a callback panics and pass it
+    /// to `hook`, before the compositor shuts down.
+    pub fn crash_report_hook(mut self, hook: crash::ReportHook) -> Self {
+        self.crash_report_hook = Some(hook);
+        self
+    }
+
+    /// Ping the clients of all XDG surfaces every `interval`.
+    ///
+    /// Clients that don't answer in time are reported through the
+    /// `responsiveness_changed` callback of the XDG shell handlers, and again
+    /// once they answer a later ping.
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = Some(interval);
+        self
+    }
+
+    /// Run a single application fullscreen and nothing else, see the
+    /// `kiosk` module.
+    ///
+    /// This sets up the renderer, shared memory and the input, output and
+    /// XDG shell managers.
+    ///
+    /// # Panics
+    /// Panics if the input, output or XDG shell manager was set, before or
+    /// after this.
+    pub fn kiosk(mut self, config: kiosk::Config) -> Self {
+        assert!(self.input_manager_builder.is_none() && self.output_manager_builder.is_none()
+                && self.xdg_shell_manager_builder.is_none(),
+                "The kiosk preset can't be combined with input, output or XDG shell managers");
+        if let Some(interval) = config.ping_interval() {
+            self.ping_interval = Some(interval);
+        }
+        kiosk::install(config);
+        let mut builder = self.gles2(true)
+                              .wl_shm(true)
+                              .input_manager(kiosk::input_manager())
+                              .output_manager(kiosk::output_manager())
+                              .xdg_shell_manager(kiosk::xdg_shell_manager());
+        builder.kiosk = true;
+        builder
+    }
+
+    /// Call `stats::trim` whenever the memory pressure reported by the
+    /// kernel goes over `threshold`, the share of time tasks were stalled
+    /// waiting for memory (e.g 0.1).
+    ///
+    /// Does nothing on kernels that don't report memory pressure.
+    pub fn trim_on_memory_pressure(mut self, threshold: f64) -> Self {
+        self.memory_pressure_threshold = Some(threshold);
+        self
+    }
+
     /// Give an unsafe function to setup the renderer instead of the default renderer.
     pub unsafe fn render_setup_function(mut self, func: UnsafeRenderSetupFunction) -> Self {
         self.render_setup_function = Some(func);
@@ -412,7 +813,7 @@ impl Builder {
                               data: D,
                               display: *mut wl_display,
                               event_loop: *mut wl_event_loop,
-                              backend: Backend)
+                              mut backend: Backend)
                               -> Compositor
     where D: Any + 'static {
         // Set up the wl_compositor and wl_subcompositor globals,
@@ -440,7 +841,7 @@ impl Builder {
             None
         };
         let gamma_control_manager = if self.gamma_control_manager {
-            gamma_control::ZManagerV1::new(display)
+            gamma_control::ZManagerV1::new(display, self.gamma_control_handler.take())
         } else {
             None
         };
@@ -449,13 +850,57 @@ impl Builder {
         } else {
             None
         };
+        let input_inhibit_manager = if self.input_inhibit_manager {
+            input_inhibit::ZManagerV1::new(display, self.input_inhibit_handler.take())
+        } else {
+            None
+        };
+        let virtual_keyboard_manager = if self.virtual_keyboard_manager {
+            virtual_keyboard::ZManagerV1::new(display)
+        } else {
+            None
+        };
+        let pointer_constraints_manager = if self.pointer_constraints_manager {
+            let handler = self.pointer_constraints_handler.take().unwrap_or_else(|| Box::new(()));
+            pointer_constraints::ZManagerV1::new(display, handler)
+        } else {
+            None
+        };
+        let tablet_manager = if self.tablet_manager {
+            tablet::ZManagerV2::new(display)
+        } else {
+            None
+        };
         let gtk_primary_selection_manager = if self.gtk_primary_selection_manager {
             gtk_primary_selection::Manager::new(display)
         } else {
             None
         };
+        let river_status_manager = if self.river_status_manager {
+            river_status::ZManagerV1::new(display)
+        } else {
+            None
+        };
+        let linux_dmabuf = match renderer {
+            Some(ref renderer) if self.linux_dmabuf => {
+                linux_dmabuf::LinuxDmabufV1::new(display, renderer.as_ptr())
+            }
+            _ => None
+        };
+        let xdg_output_manager = self.xdg_output_manager.take().and_then(|layout| {
+            layout.run(|layout_ref| xdg_output::ZManagerV1::new(display,
+                                                                layout.clone(),
+                                                                layout_ref.as_ptr()))
+                  .ok()
+                  .and_then(|manager| manager)
+        });
         let screencopy_manager = if self.screencopy_manager {
-            screencopy::ZManagerV1::new(display)
+            screencopy::ZManagerV1::new(display, self.screencopy_handler.take())
+        } else {
+            None
+        };
+        let export_dmabuf_manager = if self.export_dmabuf_manager {
+            export_dmabuf::ZManagerV1::new(display)
         } else {
             None
         };
@@ -525,6 +970,17 @@ impl Builder {
             xdg_v6_shell_manager
         });
 
+        // Set up the layer_shell handler and associated Wayland global,
+        // if user provided a manager for it.
+        let mut layer_shell_global = ptr::null_mut();
+        let layer_shell_manager = self.layer_shell_manager_builder.take().map(|builder| {
+            layer_shell_global = wlr_layer_shell_create(display as *mut _);
+            let layer_shell_manager = layer_shell::Manager::build(builder);
+            wl_signal_add(&mut (*layer_shell_global).events.new_surface as *mut _ as _,
+                          (&mut layer_shell_manager.add_listener) as *mut _ as _);
+            layer_shell_manager
+        });
+
         // Set up the XWayland server, if the user wants it.
         let xwayland = self.xwayland.take().and_then(|builder| {
             Some(xwayland::Server::new(display as _,
@@ -535,19 +991,94 @@ impl Builder {
 
         let user_terminate = self.user_terminate;
 
-        // Open the socket to the Wayland server.
-        let socket = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_add_socket_auto, display);
-        if socket.is_null() {
-            // NOTE Rationale for panicking:
-            // * Won't be in C land just yet, so it's safe to panic
-            // * Can always be returned in a Result instead, but for now
-            //   if you auto create it's assumed you can't recover.
-            panic!("Unable to open wayland socket");
+        // Dump the state on SIGUSR1, if the user asked for it.
+        let state_dump_path = self.state_dump_path.take();
+        let state_dump_source = if state_dump_path.is_some() {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_loop_add_signal,
+                          event_loop,
+                          libc::SIGUSR1,
+                          dump_state_on_signal,
+                          ptr::null_mut())
+        } else {
+            ptr::null_mut()
+        };
+
+        if self.crash_report_hook.is_some() {
+            crash::install_panic_hook();
         }
-        let socket_name = CStr::from_ptr(socket).to_string_lossy().into_owned();
+
+        // Periodically check the clients are still responding, if the user
+        // wants it.
+        let ping_interval = self.ping_interval;
+        let ping_source = if ping_interval.is_some() {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_loop_add_timer,
+                          event_loop,
+                          ping_clients,
+                          ptr::null_mut())
+        } else {
+            ptr::null_mut()
+        };
+        if let Some(interval) = ping_interval {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_source_timer_update,
+                          ping_source,
+                          cmp::max(interval.to_ms(), 1) as libc::c_int);
+        }
+
+        // Keep an eye on memory pressure, if the user wants caches trimmed.
+        let memory_pressure_threshold =
+            self.memory_pressure_threshold.filter(|_| stats::memory_pressure().is_some());
+        let memory_pressure_source = if memory_pressure_threshold.is_some() {
+            let source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_event_loop_add_timer,
+                                       event_loop,
+                                       check_memory_pressure,
+                                       ptr::null_mut());
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_source_timer_update,
+                          source,
+                          MEMORY_PRESSURE_POLL_MS);
+            source
+        } else {
+            ptr::null_mut()
+        };
+
+        // Bring the outputs back when the session becomes active again,
+        // e.g after switching back to this VT or waking up from suspend.
+        let session_watcher = backend.get_session().map(|session| {
+            let session = session.as_ptr();
+            let mut session_watcher = SessionWatcher::new(session);
+            wl_signal_add(&mut (*session).session_signal as *mut _ as _,
+                          session_watcher.session_listener() as _);
+            session_watcher
+        });
+        // Suspending doesn't always switch the VT, logind tells about it too.
+        #[cfg(feature = "logind")]
+        let sleep_watcher = ::logind::SleepWatcher::new(event_loop);
+
+        // Open the socket to the Wayland server, unless the previous process
+        // handed one over to us when restarting.
+        let socket_name = match inherited_socket(display) {
+            Some(socket_name) => socket_name,
+            None => {
+                let socket =
+                    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_add_socket_auto, display);
+                if socket.is_null() {
+                    // NOTE Rationale for panicking:
+                    // * Won't be in C land just yet, so it's safe to panic
+                    // * Can always be returned in a Result instead, but for now
+                    //   if you auto create it's assumed you can't recover.
+                    panic!("Unable to open wayland socket");
+                }
+                CStr::from_ptr(socket).to_string_lossy().into_owned()
+            }
+        };
         env::set_var("_WAYLAND_DISPLAY", socket_name.clone());
         let compositor = Compositor { data: Box::new(data),
                                       compositor_handler,
+                                      surface_manager: self.surface_manager.take(),
                                       socket_name,
                                       input_manager,
                                       output_manager,
@@ -555,21 +1086,50 @@ impl Builder {
                                       xdg_shell_global,
                                       xdg_v6_shell_manager,
                                       xdg_v6_shell_global,
+                                      layer_shell_manager,
+                                      layer_shell_global,
                                       data_device_manager,
                                       compositor,
                                       backend,
+                                      virtual_backend: None,
                                       display,
                                       event_loop,
                                       wl_shm_fd,
                                       server_decoration_manager,
                                       gamma_control_manager,
                                       idle_manager,
+                                      input_inhibit_manager,
+                                      virtual_keyboard_manager,
+                                      pointer_constraints_manager,
+                                      tablet_manager,
                                       gtk_primary_selection_manager,
+                                      river_status_manager,
+                                      linux_dmabuf,
+                                      xdg_output_manager,
                                       screencopy_manager,
+                                      export_dmabuf_manager,
                                       screenshooter,
                                       renderer,
                                       xwayland,
                                       user_terminate,
+                                      terminating: false,
+                                      restart_requested: false,
+                                      tracked: snapshot::Tracked::default(),
+                                      state_dump_path,
+                                      state_dump_source,
+                                      ping_interval,
+                                      ping_source,
+                                      memory_pressure_threshold,
+                                      memory_pressure_source,
+                                      grace_period: self.grace_period,
+                                      shutdown_hook: self.shutdown_hook,
+                                      shutdown_source: ptr::null_mut(),
+                                      shutdown_deadline: None,
+                                      crash_report_hook: self.crash_report_hook,
+                                      session_watcher,
+                                      #[cfg(feature = "logind")]
+                                      sleep_watcher,
+                                      sleeping_outputs: Vec::new(),
                                       panic_error: None,
                                       lock: Rc::new(Cell::new(false)) };
         // Forget so we can't construct another builder.
@@ -623,6 +1183,152 @@ impl Compositor {
         self.socket_name.as_str()
     }
 
+    /// Take a snapshot of the compositor state.
+    ///
+    /// The snapshot lists the outputs, XDG shell surfaces, seats, input devices,
+    /// and protocol globals the compositor knows about. Resources that are
+    /// currently borrowed (e.g because this is called from within their `run`)
+    /// are left out.
+    pub fn dump_state(&mut self) -> Snapshot {
+        Snapshot::take(self)
+    }
+
+    /// List the outputs and windows that can be captured, e.g for the
+    /// picker of a screencast portal.
+    ///
+    /// See the `capture` module for turning the picked ids back into
+    /// handles.
+    pub fn capture_sources(&mut self) -> capture::Sources {
+        capture::sources()
+    }
+
+    /// Remember which outputs are enabled, so `resume_from_sleep` can turn
+    /// them back on.
+    ///
+    /// This is called when the session becomes inactive, and with the
+    /// `logind` feature when logind announces a suspend. Otherwise call it
+    /// yourself before the system suspends.
+    pub fn prepare_for_sleep(&mut self) {
+        self.sleeping_outputs = unsafe { snapshot::outputs() }
+            .into_iter()
+            .filter(|handle| handle.run(|output| output.enabled()).unwrap_or(false))
+            .collect();
+        bus::publish(&bus::SessionChanged { active: false });
+    }
+
+    /// Bring the outputs back after the session was inactive or the system
+    /// was suspended.
+    ///
+    /// The outputs that were enabled are enabled again, their gamma is
+    /// restored, they are damaged completely and a frame is scheduled on
+    /// them. All clients, including layer shell and Xwayland clients, are
+    /// sent a frame event so they redraw right away, instead of waiting for
+    /// input.
+    ///
+    /// This is called when the session becomes active again, and with the
+    /// `logind` feature when logind announces the system woke up. Otherwise
+    /// call it yourself after the system woke up.
+    pub fn resume_from_sleep(&mut self) {
+        let sleeping = mem::replace(&mut self.sleeping_outputs, Vec::new());
+        // Gamma clients know best what their outputs should look like.
+        let controlled = self.gamma_control_manager
+                             .as_ref()
+                             .map(|manager| manager.controlled_outputs())
+                             .unwrap_or_default();
+        for handle in unsafe { snapshot::outputs() } {
+            let was_enabled = sleeping.contains(&handle);
+            let gamma_controlled = controlled.contains(&handle);
+            handle.run(|output| {
+                      if was_enabled && !output.enabled() {
+                          output.enable(true);
+                      }
+                      if output.enabled() {
+                          if !gamma_controlled {
+                              output.restore_gamma();
+                          }
+                          output.damage().add_whole();
+                          output.schedule_frame();
+                      }
+                  })
+                  .ok();
+        }
+        let now = utils::current_time();
+        for surface in unsafe { snapshot::surfaces() } {
+            surface.run(|surface| surface.send_frame_done(now)).ok();
+        }
+        bus::publish(&bus::SessionChanged { active: true });
+    }
+
+    /// The names of the Wayland globals this compositor advertises, not
+    /// counting outputs and seats.
+    pub(crate) fn globals(&self) -> Vec<&'static str> {
+        let mut globals = vec!["wl_compositor"];
+        if self.wl_shm_fd.is_some() {
+            globals.push("wl_shm")
+        }
+        if self.data_device_manager.is_some() {
+            globals.push("wl_data_device_manager")
+        }
+        if !self.xdg_shell_global.is_null() {
+            globals.push("xdg_wm_base")
+        }
+        if !self.xdg_v6_shell_global.is_null() {
+            globals.push("zxdg_shell_v6")
+        }
+        if !self.layer_shell_global.is_null() {
+            globals.push("zwlr_layer_shell_v1")
+        }
+        if self.server_decoration_manager.is_some() {
+            globals.push("org_kde_kwin_server_decoration_manager")
+        }
+        if self.gamma_control_manager.is_some() {
+            globals.push("zwlr_gamma_control_manager_v1")
+        }
+        if self.idle_manager.is_some() {
+            globals.push("org_kde_kwin_idle")
+        }
+        if self.input_inhibit_manager.is_some() {
+            globals.push("zwlr_input_inhibit_manager_v1")
+        }
+        if self.virtual_keyboard_manager.is_some() {
+            globals.push("zwp_virtual_keyboard_manager_v1")
+        }
+        if self.pointer_constraints_manager.is_some() {
+            globals.push("zwp_pointer_constraints_v1")
+        }
+        if self.tablet_manager.is_some() {
+            globals.push("zwp_tablet_manager_v2")
+        }
+        if self.gtk_primary_selection_manager.is_some() {
+            globals.push("gtk_primary_selection_device_manager")
+        }
+        if self.river_status_manager.is_some() {
+            globals.push("zriver_status_manager_v1")
+        }
+        if self.linux_dmabuf.is_some() {
+            globals.push("zwp_linux_dmabuf_v1")
+        }
+        if self.xdg_output_manager.is_some() {
+            globals.push("zxdg_output_manager_v1")
+        }
+        if self.screencopy_manager.is_some() {
+            globals.push("zwlr_screencopy_manager_v1")
+        }
+        if self.export_dmabuf_manager.is_some() {
+            globals.push("zwlr_export_dmabuf_manager_v1")
+        }
+        if self.screenshooter.is_some() {
+            globals.push("orbital_screenshooter")
+        }
+        globals
+    }
+
+    /// Whether an Xwayland server was started. It isn't a Wayland global,
+    /// so it's reported here rather than in `globals`.
+    pub(crate) fn xwayland_started(&self) -> bool {
+        self.xwayland.is_some()
+    }
+
     /// Enters the wayland event loop. Won't return until the compositor is
     /// shut off.
     pub fn run(self) {
@@ -662,6 +1368,11 @@ impl Compositor {
                 panic!("Failed to start backend");
             }
             runner(&*COMPOSITOR_PTR);
+            if (*compositor.get()).restart_requested {
+                // Only returns if the exec failed, in which case we shut
+                // down like normal.
+                (*compositor.get()).exec_restart();
+            }
             match (*compositor.get()).panic_error.take() {
                 None => {}
                 Some(err) => {
@@ -679,6 +1390,177 @@ impl Compositor {
         }
     }
 
+    /// Ask all toplevels to close and wait for them, if there is a grace
+    /// period.
+    ///
+    /// Returns false if the compositor should shut down right away instead.
+    unsafe fn begin_graceful_shutdown(&mut self) -> bool {
+        let grace_period = match self.grace_period {
+            Some(grace_period) => grace_period,
+            None => return false
+        };
+        if self.panic_error.is_some() || self.shutdown_deadline.is_some() {
+            return false
+        }
+        let toplevels = open_toplevels();
+        if toplevels == 0 {
+            return false
+        }
+        if self.shutdown_source.is_null() {
+            self.shutdown_source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                                 wl_event_loop_add_timer,
+                                                 self.event_loop,
+                                                 shutdown_tick,
+                                                 ptr::null_mut());
+            if self.shutdown_source.is_null() {
+                return false
+            }
+        }
+        wlr_log!(WLR_INFO,
+                 "Asking {} toplevels to close before shutting down",
+                 toplevels);
+        close_toplevels();
+        self.shutdown_deadline = Some(Instant::now() + grace_period);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_event_source_timer_update,
+                      self.shutdown_source,
+                      SHUTDOWN_POLL_MS);
+        true
+    }
+
+    /// Stop the event loop and run the user clean up code.
+    unsafe fn finish_terminate(&mut self) {
+        self.terminating = true;
+        self.shutdown_deadline = None;
+        if !self.shutdown_source.is_null() {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.shutdown_source);
+            self.shutdown_source = ptr::null_mut();
+        }
+        self.terminate();
+        self.user_terminate.map(|f| f());
+    }
+
+    /// Execute the running binary again, handing over the listening socket.
+    ///
+    /// Only returns if the exec failed.
+    unsafe fn exec_restart(&mut self) {
+        let socket_path = match env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => Path::new(&dir).join(&self.socket_name),
+            None => {
+                wlr_log!(WLR_ERROR, "XDG_RUNTIME_DIR is not set, can't restart");
+                return
+            }
+        };
+        match find_fd(|fd| unix_socket_path(fd).as_ref() == Some(&socket_path)) {
+            Some(fd) => {
+                libc::fcntl(fd, libc::F_SETFD, 0);
+                env::set_var(SOCKET_FD_VAR, fd.to_string());
+                env::set_var(SOCKET_NAME_VAR, &self.socket_name);
+            }
+            None => wlr_log!(WLR_ERROR,
+                             "Could not find the listening socket, clients will need \
+                              a new WAYLAND_DISPLAY after restarting")
+        }
+        // Keep holding the lock file, so no other compositor grabs the
+        // socket name while we are exec'ing.
+        let lock_path = socket_path.with_extension("lock");
+        let lock_link = |fd| fs::read_link(format!("/proc/self/fd/{}", fd)).ok();
+        if let Some(fd) = find_fd(|fd| lock_link(fd).as_ref() == Some(&lock_path)) {
+            libc::fcntl(fd, libc::F_SETFD, 0);
+        }
+        let exe = match env::current_exe() {
+            Ok(exe) => exe,
+            Err(err) => {
+                wlr_log!(WLR_ERROR, "Could not find the compositor binary: {}", err);
+                return
+            }
+        };
+        wlr_log!(WLR_INFO, "Restarting compositor as {:?}", exe);
+        let err = Command::new(exe).args(env::args_os().skip(1)).exec();
+        wlr_log!(WLR_ERROR, "Could not restart compositor: {}", err);
+        env::remove_var(SOCKET_FD_VAR);
+        env::remove_var(SOCKET_NAME_VAR);
+    }
+
+    /// Create a virtual output, e.g for a VNC or streaming session to draw to.
+    ///
+    /// The output is announced through the output manager like any other
+    /// output, so it can be set up and added to the layout in `output_added`.
+    /// It is backed by an offscreen framebuffer that isn't displayed anywhere.
+    ///
+    /// This is only supported when running on the multi backend (i.e. the
+    /// compositor was made with `build_auto`) or the headless backend,
+    /// otherwise `None` is returned.
+    pub fn create_virtual_output(&mut self, width: u32, height: u32) -> Option<output::Handle> {
+        unsafe {
+            if self.virtual_backend.is_none() {
+                self.virtual_backend = self.create_virtual_backend();
+            }
+            self.virtual_backend.as_ref()?.add_output(width, height)
+        }
+    }
+
+    /// Destroy a virtual output made with `create_virtual_output`.
+    ///
+    /// Returns false if the output was not a virtual output or was already
+    /// destroyed.
+    pub fn destroy_virtual_output(&mut self, output: output::Handle) -> bool {
+        let backend = match self.virtual_backend.as_ref() {
+            Some(backend) => backend,
+            None => return false
+        };
+        match output.run(|output| backend.is_headless_output(output)) {
+            Ok(true) => {},
+            _ => return false
+        }
+        // NOTE The output must not be borrowed while destroying it.
+        unsafe { wlr_output_destroy(output.as_ptr()) }
+        true
+    }
+
+    /// Create a virtual input device, on the same backend as the virtual
+    /// outputs.
+    ///
+    /// It is announced through the input manager like any other input device.
+    /// It is up to the caller to emit events on it, see `backend::remote::Remote`.
+    pub fn create_virtual_input_device(&mut self,
+                                       dev_type: wlr_input_device_type)
+                                       -> Option<input::Handle> {
+        unsafe {
+            if self.virtual_backend.is_none() {
+                self.virtual_backend = self.create_virtual_backend();
+            }
+            self.virtual_backend.as_ref()?.add_input_device(dev_type)
+        }
+    }
+
+    unsafe fn create_virtual_backend(&mut self) -> Option<backend::Headless> {
+        match self.backend {
+            Backend::Headless(ref headless) => Some(backend::Headless { backend: headless.backend }),
+            Backend::Multi(ref multi) => {
+                let headless = backend::Headless::new(self.display as *mut _, None);
+                if !multi.add_backend(headless.as_ptr()) {
+                    wlr_log!(WLR_ERROR, "Could not add virtual output backend");
+                    wlr_backend_destroy(headless.as_ptr());
+                    return None
+                }
+                // If we are already running the multi backend won't start it for us.
+                if !COMPOSITOR_PTR.is_null() && !wlr_backend_start(headless.as_ptr()) {
+                    wlr_log!(WLR_ERROR, "Could not start virtual output backend");
+                    multi.remove_backend(headless.as_ptr());
+                    wlr_backend_destroy(headless.as_ptr());
+                    return None
+                }
+                Some(headless)
+            }
+            _ => {
+                wlr_log!(WLR_ERROR,
+                         "Virtual outputs need the multi or headless backend");
+                None
+            }
+        }
+    }
+
     /// Get a reference to the currently running backend.
     pub fn backend(&self) -> &Backend {
         &self.backend
@@ -689,9 +1571,21 @@ impl Compositor {
         &mut self.backend
     }
 
+    /// Determines if a callback panicked, and the compositor is shutting
+    /// down because of it.
+    pub(crate) fn has_panicked(&self) -> bool {
+        self.panic_error.is_some()
+    }
+
     /// Saves the panic error information in the compositor, to be re-thrown
     /// later when we are out of the C callback stack.
     pub(crate) fn save_panic_error(&mut self, error: Box<Any + Send>) {
+        if let Some(hook) = self.crash_report_hook {
+            if self.panic_error.is_none() {
+                let report = crash::report(self, &error);
+                hook(&report);
+            }
+        }
         self.panic_error = Some(error);
     }
 
@@ -710,6 +1604,24 @@ impl Drop for Compositor {
             assert_eq!(BUILDER_ACTIVE.compare_and_swap(true, false, Ordering::AcqRel),
                        true,
                        "Builder was in improper state");
+            if !self.state_dump_source.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_event_source_remove,
+                              self.state_dump_source);
+            }
+            if !self.ping_source.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.ping_source);
+            }
+            if !self.memory_pressure_source.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_event_source_remove,
+                              self.memory_pressure_source);
+            }
+            if !self.shutdown_source.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.shutdown_source);
+            }
+            // Stops the application while its connection is still there.
+            kiosk::uninstall();
             ffi_dispatch!(WAYLAND_SERVER_HANDLE,
                           wl_display_destroy_clients,
                           self.display);
@@ -792,14 +1704,45 @@ impl Handle {
 }
 
 /// Terminates the compositor and execute any user clean up code.
+///
+/// If a shutdown grace period is set (see
+/// `Builder::shutdown_grace_period`) the toplevels are asked to close
+/// first, and the compositor keeps running until they are closed or the
+/// grace period is over.
+///
+/// Calling this more than once is harmless, the clean up code only runs
+/// the first time.
 pub fn terminate() {
     unsafe {
         if COMPOSITOR_PTR != 0 as _ {
             let compositor = &mut *COMPOSITOR_PTR;
-            compositor.terminate();
-            compositor.user_terminate.map(|f| f());
+            if compositor.terminating || compositor.begin_graceful_shutdown() {
+                return
+            }
+            compositor.finish_terminate();
+        }
+    }
+}
+
+/// Restarts the compositor in place.
+///
+/// This terminates the compositor like `terminate` and then executes the
+/// running binary again with the same arguments, instead of returning from
+/// `Compositor::run`.
+///
+/// The listening Wayland socket is handed over to the new process, so
+/// `WAYLAND_DISPLAY` stays the same and clients can reconnect. Connected
+/// clients lose their connection and the session (e.g DRM master) is
+/// acquired again by the new process, wlroots has no way of handing those
+/// over.
+pub fn restart() {
+    unsafe {
+        if COMPOSITOR_PTR.is_null() {
+            return
         }
+        (*COMPOSITOR_PTR).restart_requested = true;
     }
+    terminate()
 }
 
 /// Gets a handle to the compositor.
@@ -815,3 +1758,210 @@ pub fn handle() -> Option<Handle> {
         }
     }
 }
+
+/// Writes the state dump to the configured path when `SIGUSR1` is received.
+unsafe extern "C" fn dump_state_on_signal(_signal: libc::c_int,
+                                          _data: *mut libc::c_void)
+                                          -> libc::c_int {
+    let compositor = match handle() {
+        Some(handle) => handle,
+        None => return 0
+    };
+    let res = compositor.run(|compositor| {
+        let path = match compositor.state_dump_path.clone() {
+            Some(path) => path,
+            None => return
+        };
+        let snapshot = compositor.dump_state();
+        match fs::write(&path, snapshot.to_string()) {
+            Ok(()) => wlr_log!(WLR_INFO, "Wrote state dump to {:?}", path),
+            Err(err) => wlr_log!(WLR_ERROR, "Could not write state dump to {:?}: {}", path, err)
+        }
+    });
+    if res.is_err() {
+        wlr_log!(WLR_ERROR, "Could not dump state, compositor is already borrowed");
+    }
+    0
+}
+
+/// Pings the clients of every XDG surface, noting the ones that answered
+/// since the last time.
+unsafe extern "C" fn ping_clients(_data: *mut libc::c_void) -> libc::c_int {
+    if COMPOSITOR_PTR.is_null() {
+        return 0
+    }
+    let (xdg_surfaces, xdg_v6_surfaces) = snapshot::xdg_surfaces();
+    for handle in xdg_surfaces {
+        let shell_surface = handle.as_ptr();
+        let state = (*shell_surface).data as *mut xdg_shell::SurfaceState;
+        if state.is_null() || (*state).shell.is_null() {
+            continue
+        }
+        if !(*state).responsive && xdg_shell::pong_received(shell_surface) {
+            (*(*state).shell).set_responsive(true);
+        }
+        wlr_xdg_surface_ping(shell_surface);
+    }
+    for handle in xdg_v6_surfaces {
+        let shell_surface = handle.as_ptr();
+        let state = (*shell_surface).data as *mut xdg_shell_v6::SurfaceState;
+        if state.is_null() || (*state).shell.is_null() {
+            continue
+        }
+        if !(*state).responsive && xdg_shell_v6::pong_received(shell_surface) {
+            (*(*state).shell).set_responsive(true);
+        }
+        wlr_xdg_surface_v6_ping(shell_surface);
+    }
+    let compositor = &*COMPOSITOR_PTR;
+    if let Some(interval) = compositor.ping_interval {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_event_source_timer_update,
+                      compositor.ping_source,
+                      cmp::max(interval.to_ms(), 1) as libc::c_int);
+    }
+    0
+}
+
+/// Trims the caches if the memory pressure is over the threshold.
+unsafe extern "C" fn check_memory_pressure(_data: *mut libc::c_void) -> libc::c_int {
+    if COMPOSITOR_PTR.is_null() {
+        return 0
+    }
+    let compositor = &*COMPOSITOR_PTR;
+    if let (Some(threshold), Some(pressure)) =
+        (compositor.memory_pressure_threshold, stats::memory_pressure())
+    {
+        if pressure > threshold {
+            wlr_log!(WLR_INFO, "Memory pressure at {:.2}, trimming caches", pressure);
+            stats::trim();
+        }
+    }
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_event_source_timer_update,
+                  compositor.memory_pressure_source,
+                  MEMORY_PRESSURE_POLL_MS);
+    0
+}
+
+/// Counts the mapped and unmapped toplevels of the running compositor.
+unsafe fn open_toplevels() -> usize {
+    let (xdg_surfaces, xdg_v6_surfaces) = snapshot::xdg_surfaces();
+    let xdg = xdg_surfaces.iter()
+                          .filter(|handle| {
+                                      (*handle.as_ptr()).role == WLR_XDG_SURFACE_ROLE_TOPLEVEL
+                                  })
+                          .count();
+    let xdg_v6 = xdg_v6_surfaces.iter()
+                                .filter(|handle| {
+                                            (*handle.as_ptr()).role
+                                            == WLR_XDG_SURFACE_V6_ROLE_TOPLEVEL
+                                        })
+                                .count();
+    xdg + xdg_v6
+}
+
+/// Sends a close request to every toplevel of the running compositor.
+unsafe fn close_toplevels() {
+    let (xdg_surfaces, xdg_v6_surfaces) = snapshot::xdg_surfaces();
+    for handle in xdg_surfaces {
+        if (*handle.as_ptr()).role == WLR_XDG_SURFACE_ROLE_TOPLEVEL {
+            wlr_xdg_toplevel_send_close(handle.as_ptr());
+        }
+    }
+    for handle in xdg_v6_surfaces {
+        if (*handle.as_ptr()).role == WLR_XDG_SURFACE_V6_ROLE_TOPLEVEL {
+            wlr_xdg_surface_v6_send_close(handle.as_ptr());
+        }
+    }
+}
+
+/// Finishes the shutdown once the toplevels are closed or the grace period
+/// is over.
+unsafe extern "C" fn shutdown_tick(_data: *mut libc::c_void) -> libc::c_int {
+    if COMPOSITOR_PTR.is_null() {
+        return 0
+    }
+    let compositor = &mut *COMPOSITOR_PTR;
+    let deadline = match compositor.shutdown_deadline {
+        Some(deadline) => deadline,
+        None => return 0
+    };
+    let toplevels = open_toplevels();
+    if toplevels > 0 && Instant::now() >= deadline {
+        let decision = compositor.shutdown_hook.map(|hook| hook(toplevels))
+                                 .unwrap_or(Shutdown::Proceed);
+        match decision {
+            Shutdown::Proceed => {}
+            Shutdown::Extend(extension) => {
+                compositor.shutdown_deadline = Some(Instant::now() + extension);
+            }
+            Shutdown::Veto => {
+                wlr_log!(WLR_INFO, "Shutdown was vetoed");
+                compositor.shutdown_deadline = None;
+                return 0
+            }
+        }
+    }
+    let expired = compositor.shutdown_deadline
+                            .map_or(false, |deadline| Instant::now() >= deadline);
+    if toplevels == 0 || expired {
+        if toplevels > 0 {
+            wlr_log!(WLR_INFO, "Shutting down with {} toplevels still open", toplevels);
+        }
+        compositor.finish_terminate();
+    } else {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_event_source_timer_update,
+                      compositor.shutdown_source,
+                      SHUTDOWN_POLL_MS);
+    }
+    0
+}
+
+/// Adopts the listening socket handed over by `restart`, if there is one.
+///
+/// Returns the name of the socket.
+unsafe fn inherited_socket(display: *mut wl_display) -> Option<String> {
+    let fd = env::var(SOCKET_FD_VAR).ok().and_then(|fd| fd.parse::<libc::c_int>().ok());
+    let socket_name = env::var(SOCKET_NAME_VAR).ok();
+    env::remove_var(SOCKET_FD_VAR);
+    env::remove_var(SOCKET_NAME_VAR);
+    let (fd, socket_name) = match (fd, socket_name) {
+        (Some(fd), Some(socket_name)) => (fd, socket_name),
+        _ => return None
+    };
+    libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+    if ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_add_socket_fd, display, fd) != 0 {
+        wlr_log!(WLR_ERROR, "Could not reuse the socket {} from before the restart", socket_name);
+        libc::close(fd);
+        return None
+    }
+    wlr_log!(WLR_INFO, "Reusing socket {} from before the restart", socket_name);
+    Some(socket_name)
+}
+
+/// Finds an open file descriptor of this process matching the predicate.
+fn find_fd<F: Fn(libc::c_int) -> bool>(predicate: F) -> Option<libc::c_int> {
+    fs::read_dir("/proc/self/fd").ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str().and_then(|fd| fd.parse().ok()))
+        .find(|&fd| predicate(fd))
+}
+
+/// The path a Unix socket is bound to, if `fd` is one.
+fn unix_socket_path(fd: libc::c_int) -> Option<PathBuf> {
+    unsafe {
+        let mut addr: libc::sockaddr_un = mem::zeroed();
+        let mut len = mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+        if libc::getsockname(fd, &mut addr as *mut _ as *mut libc::sockaddr, &mut len) != 0 ||
+           addr.sun_family != libc::AF_UNIX as libc::sa_family_t {
+            return None
+        }
+        let path: Vec<u8> = addr.sun_path.iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as u8)
+            .collect();
+        Some(Path::new(OsStr::from_bytes(&path)).to_path_buf())
+    }
+}
@@ -0,0 +1,261 @@
+//! Conversions between the coordinate spaces of a compositor.
+//!
+//! A point on the screen can be expressed in a handful of spaces:
+//!
+//! * layout: the space all outputs are arranged in (`output::layout`).
+//! * output: logical coordinates relative to the top left of an output.
+//! * output buffer: pixels in the buffer of an output, after its scale and
+//!   transform are applied.
+//! * view: relative to the top left of the window geometry of a view, which
+//!   is what the user thinks of as the corner of the window.
+//! * surface: relative to the top left of the surface of a view. This is
+//!   offset from the view by client side decorations like shadows.
+//! * surface buffer: pixels in the buffer the client attached, after the
+//!   buffer scale and transform of the surface are applied.
+//!
+//! Every space has its own point type, and the only way to get from one to
+//! another is through the `OutputSpace`, `ViewSpace` and `SurfaceBufferSpace`
+//! that describe how they relate, so a layout point can't be passed where an
+//! output point was expected:
+//!
+//! ```rust,no_run,ignore
+//! let output_space = coords::OutputSpace::from_output(output);
+//! let view_space = coords::ViewSpace::new(view_position, shell_surface.geometry());
+//! let cursor = coords::LayoutPoint::new(cursor.coords().0, cursor.coords().1);
+//! let on_output = output_space.layout_to_output(cursor);
+//! let in_surface = view_space.layout_to_surface(cursor);
+//! ```
+
+use libc::c_float;
+use wlroots_sys::wl_output_transform::*;
+
+use {area::{Area, Origin, Size},
+     output::{Output, Transform}};
+
+macro_rules! point {
+    ($(#[$attr: meta])* $name: ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Default, Clone, Copy, PartialEq)]
+        pub struct $name {
+            pub x: f64,
+            pub y: f64
+        }
+
+        impl $name {
+            pub fn new(x: f64, y: f64) -> Self {
+                $name { x, y }
+            }
+
+            /// Round down to whole units, e.g to look up the pixel the point
+            /// is in.
+            pub fn floor(self) -> (i32, i32) {
+                (self.x.floor() as i32, self.y.floor() as i32)
+            }
+        }
+    }
+}
+
+point!(
+    /// A point in the layout all outputs are arranged in.
+    LayoutPoint);
+point!(
+    /// A point relative to the top left of an output, in logical units.
+    OutputPoint);
+point!(
+    /// A pixel in the buffer of an output.
+    OutputBufferPoint);
+point!(
+    /// A point relative to the top left of the window geometry of a view.
+    ViewPoint);
+point!(
+    /// A point relative to the top left of a surface.
+    SurfacePoint);
+point!(
+    /// A pixel in the buffer attached to a surface.
+    SurfaceBufferPoint);
+
+/// How an output relates to the layout, and to its buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputSpace {
+    /// Where the top left of the output is in the layout.
+    pub position: Origin,
+    /// The size of the output in pixels, after the transform, e.g 1080x1920
+    /// for a 1920x1080 mode rotated by 90 degrees.
+    pub resolution: Size,
+    pub scale: c_float,
+    pub transform: Transform
+}
+
+impl OutputSpace {
+    /// Describe the output as it is now.
+    pub fn from_output(output: &Output) -> Self {
+        let (x, y) = output.layout_space_pos();
+        let (width, height) = output.transformed_resolution();
+        OutputSpace { position: Origin::new(x, y),
+                      resolution: Size::new(width, height),
+                      scale: output.scale(),
+                      transform: output.get_transform() }
+    }
+
+    /// The area the output takes up in the layout.
+    pub fn layout_area(&self) -> Area {
+        let scale = self.scale as f64;
+        let size = Size::new((self.resolution.width as f64 / scale).round() as i32,
+                             (self.resolution.height as f64 / scale).round() as i32);
+        Area::new(self.position, size)
+    }
+
+    /// Determines if the point is on the output.
+    pub fn contains(&self, point: LayoutPoint) -> bool {
+        let area = self.layout_area();
+        let (x, y) = (point.x - area.origin.x as f64, point.y - area.origin.y as f64);
+        x >= 0.0 && y >= 0.0 && x < area.size.width as f64 && y < area.size.height as f64
+    }
+
+    pub fn layout_to_output(&self, point: LayoutPoint) -> OutputPoint {
+        OutputPoint::new(point.x - self.position.x as f64, point.y - self.position.y as f64)
+    }
+
+    pub fn output_to_layout(&self, point: OutputPoint) -> LayoutPoint {
+        LayoutPoint::new(point.x + self.position.x as f64, point.y + self.position.y as f64)
+    }
+
+    pub fn output_to_buffer(&self, point: OutputPoint) -> OutputBufferPoint {
+        let scale = self.scale as f64;
+        let (x, y) = apply_transform(invert(self.transform),
+                                     (point.x * scale, point.y * scale),
+                                     self.resolution);
+        OutputBufferPoint::new(x, y)
+    }
+
+    pub fn buffer_to_output(&self, point: OutputBufferPoint) -> OutputPoint {
+        let scale = self.scale as f64;
+        let buffer_size = transformed_size(self.transform, self.resolution);
+        let (x, y) = apply_transform(self.transform, (point.x, point.y), buffer_size);
+        OutputPoint::new(x / scale, y / scale)
+    }
+
+    pub fn layout_to_buffer(&self, point: LayoutPoint) -> OutputBufferPoint {
+        self.output_to_buffer(self.layout_to_output(point))
+    }
+
+    pub fn buffer_to_layout(&self, point: OutputBufferPoint) -> LayoutPoint {
+        self.output_to_layout(self.buffer_to_output(point))
+    }
+}
+
+/// How a view relates to the layout, and to its surface.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ViewSpace {
+    /// Where the top left of the window geometry is in the layout.
+    pub position: LayoutPoint,
+    /// The window geometry of the view, in surface coordinates, e.g
+    /// `xdg_shell::Surface::geometry`. Without client side decorations it
+    /// covers the whole surface.
+    pub geometry: Area
+}
+
+impl ViewSpace {
+    pub fn new(position: LayoutPoint, geometry: Area) -> Self {
+        ViewSpace { position, geometry }
+    }
+
+    /// The area the view takes up in the layout.
+    pub fn layout_area(&self) -> Area {
+        let (x, y) = self.position.floor();
+        Area::new(Origin::new(x, y), self.geometry.size)
+    }
+
+    pub fn layout_to_view(&self, point: LayoutPoint) -> ViewPoint {
+        ViewPoint::new(point.x - self.position.x, point.y - self.position.y)
+    }
+
+    pub fn view_to_layout(&self, point: ViewPoint) -> LayoutPoint {
+        LayoutPoint::new(point.x + self.position.x, point.y + self.position.y)
+    }
+
+    pub fn view_to_surface(&self, point: ViewPoint) -> SurfacePoint {
+        SurfacePoint::new(point.x + self.geometry.origin.x as f64,
+                          point.y + self.geometry.origin.y as f64)
+    }
+
+    pub fn surface_to_view(&self, point: SurfacePoint) -> ViewPoint {
+        ViewPoint::new(point.x - self.geometry.origin.x as f64,
+                       point.y - self.geometry.origin.y as f64)
+    }
+
+    pub fn layout_to_surface(&self, point: LayoutPoint) -> SurfacePoint {
+        self.view_to_surface(self.layout_to_view(point))
+    }
+
+    pub fn surface_to_layout(&self, point: SurfacePoint) -> LayoutPoint {
+        self.view_to_layout(self.surface_to_view(point))
+    }
+
+    /// Where the top left of the surface is in the layout, which is where it
+    /// has to be drawn.
+    pub fn surface_origin(&self) -> LayoutPoint {
+        self.surface_to_layout(SurfacePoint::new(0.0, 0.0))
+    }
+}
+
+/// How a surface relates to the buffer the client attached to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceBufferSpace {
+    /// The size of the surface, in surface coordinates.
+    pub size: Size,
+    /// The buffer scale the client set.
+    pub scale: i32,
+    /// The buffer transform the client set.
+    pub transform: Transform
+}
+
+impl SurfaceBufferSpace {
+    pub fn surface_to_buffer(&self, point: SurfacePoint) -> SurfaceBufferPoint {
+        let scale = self.scale as f64;
+        let (x, y) = apply_transform(invert(self.transform), (point.x, point.y), self.size);
+        SurfaceBufferPoint::new(x * scale, y * scale)
+    }
+
+    pub fn buffer_to_surface(&self, point: SurfaceBufferPoint) -> SurfacePoint {
+        let scale = self.scale as f64;
+        let buffer_size = transformed_size(self.transform, self.size);
+        let (x, y) = apply_transform(self.transform, (point.x / scale, point.y / scale), buffer_size);
+        SurfacePoint::new(x, y)
+    }
+}
+
+/// The transform that undoes `transform`.
+fn invert(transform: Transform) -> Transform {
+    match transform {
+        WL_OUTPUT_TRANSFORM_90 => WL_OUTPUT_TRANSFORM_270,
+        WL_OUTPUT_TRANSFORM_270 => WL_OUTPUT_TRANSFORM_90,
+        // Flips are their own inverse, and so is everything else.
+        transform => transform
+    }
+}
+
+/// The size of a `size` sized space after `transform` is applied to it.
+fn transformed_size(transform: Transform, size: Size) -> Size {
+    match transform {
+        WL_OUTPUT_TRANSFORM_90 | WL_OUTPUT_TRANSFORM_270 | WL_OUTPUT_TRANSFORM_FLIPPED_90 |
+        WL_OUTPUT_TRANSFORM_FLIPPED_270 => Size::new(size.height, size.width),
+        _ => size
+    }
+}
+
+/// Apply `transform` to a point in a space of `size`, the same way
+/// `wlr_box_transform` does for boxes.
+fn apply_transform(transform: Transform, (x, y): (f64, f64), size: Size) -> (f64, f64) {
+    let (width, height) = (size.width as f64, size.height as f64);
+    match transform {
+        WL_OUTPUT_TRANSFORM_NORMAL => (x, y),
+        WL_OUTPUT_TRANSFORM_90 => (height - y, x),
+        WL_OUTPUT_TRANSFORM_180 => (width - x, height - y),
+        WL_OUTPUT_TRANSFORM_270 => (y, width - x),
+        WL_OUTPUT_TRANSFORM_FLIPPED => (width - x, y),
+        WL_OUTPUT_TRANSFORM_FLIPPED_90 => (height - y, width - x),
+        WL_OUTPUT_TRANSFORM_FLIPPED_180 => (x, height - y),
+        WL_OUTPUT_TRANSFORM_FLIPPED_270 => (y, x)
+    }
+}
@@ -0,0 +1,155 @@
+//! Crash reports for panics in callbacks.
+//!
+//! When a callback panics the compositor is terminated and the panic is
+//! resumed once the event loop has stopped. By then the state that led up to
+//! it is gone, so if a hook is set with `compositor::Builder::crash_report_hook`
+//! a `Report` is put together right where the panic was caught, while the
+//! outputs, surfaces and seats are still around.
+//!
+//! ```rust,no_run,ignore
+//! fn report_crash(report: &crash::Report) {
+//!     match crash::write_to_dir(report, "/tmp") {
+//!         Ok(path) => eprintln!("Wrote crash report to {:?}", path),
+//!         Err(err) => eprintln!("Could not write crash report: {}", err)
+//!     }
+//! }
+//!
+//! compositor::Builder::new().crash_report_hook(report_crash)
+//! ```
+//!
+//! Backtraces are only captured with the `backtrace` feature.
+
+use std::{any::Any, cell::RefCell, fmt, fs, io, panic, process,
+          path::{Path, PathBuf}, sync::{Once, ONCE_INIT}};
+
+#[cfg(feature = "backtrace")]
+use backtrace::Backtrace;
+
+use {compositor::Compositor, snapshot::Snapshot, utils::{self, log}};
+
+/// Called with the report of a panic in a callback.
+pub type ReportHook = fn(report: &Report);
+
+/// What is known about a panic in a callback.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Report {
+    /// The panic message.
+    pub message: String,
+    /// Where the panic happened, as `file:line:column`.
+    pub location: Option<String>,
+    /// The backtrace of the panic, if the `backtrace` feature is enabled.
+    pub backtrace: Option<String>,
+    /// The compositor state when the panic was caught.
+    ///
+    /// `None` if taking the snapshot panicked as well.
+    pub snapshot: Option<Snapshot>,
+    /// The most recent log messages, see `utils::log::recent_messages`.
+    pub recent_log: Vec<String>
+}
+
+/// What the panic hook saw, it knows more than the caught payload does.
+struct Panic {
+    message: String,
+    location: Option<String>,
+    backtrace: Option<String>
+}
+
+thread_local! {
+    static LAST_PANIC: RefCell<Option<Panic>> = RefCell::new(None);
+}
+
+static INSTALL_PANIC_HOOK: Once = ONCE_INIT;
+
+/// Record the location and backtrace of every panic, before running the
+/// panic hook that was set before.
+pub(crate) fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let panic = Panic { message: payload_message(info.payload()),
+                                location: info.location().map(|location| {
+                                    format!("{}:{}:{}",
+                                            location.file(),
+                                            location.line(),
+                                            location.column())
+                                }),
+                                backtrace: capture_backtrace() };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(panic));
+            previous(info)
+        }));
+    });
+}
+
+/// Put together the report for a caught panic.
+pub(crate) fn report(compositor: &mut Compositor, error: &Box<Any + Send>) -> Report {
+    let panic = LAST_PANIC.with(|last| last.borrow_mut().take());
+    let (message, location, backtrace) = match panic {
+        Some(panic) => (panic.message, panic.location, panic.backtrace),
+        None => (payload_message(&**error), None, None)
+    };
+    let snapshot = panic::catch_unwind(panic::AssertUnwindSafe(|| compositor.dump_state())).ok();
+    Report { message,
+             location,
+             backtrace,
+             snapshot,
+             recent_log: log::recent_messages() }
+}
+
+/// Write the report to a new file in `dir`, named after the time and the
+/// process id.
+///
+/// Returns the path of the file.
+pub fn write_to_dir<P: AsRef<Path>>(report: &Report, dir: P) -> io::Result<PathBuf> {
+    let name = format!("wlroots-crash-{}-{}.txt",
+                       utils::current_time().as_secs(),
+                       process::id());
+    let path = dir.as_ref().join(name);
+    fs::write(&path, report.to_string())?;
+    Ok(path)
+}
+
+fn payload_message(payload: &(Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).into()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<Any>".into()
+    }
+}
+
+#[cfg(feature = "backtrace")]
+fn capture_backtrace() -> Option<String> {
+    Some(format!("{:?}", Backtrace::new()))
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn capture_backtrace() -> Option<String> {
+    None
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Panic: {}", self.message)?;
+        if let Some(ref location) = self.location {
+            writeln!(f, "Location: {}", location)?;
+        }
+        writeln!(f)?;
+        match self.backtrace {
+            Some(ref backtrace) => writeln!(f, "Backtrace:\n{}", backtrace)?,
+            None => writeln!(f, "Backtrace: not captured")?
+        }
+        writeln!(f)?;
+        match self.snapshot {
+            Some(ref snapshot) => writeln!(f, "{}", snapshot)?,
+            None => writeln!(f, "State: could not be captured")?
+        }
+        writeln!(f)?;
+        writeln!(f, "Recent log messages:")?;
+        for message in &self.recent_log {
+            writeln!(f, "  {}", message)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! The environment the programs of the session need to find the compositor.
+//!
+//! Clients started by the compositor inherit its environment, but e.g
+//! programs started by D-Bus activation or as systemd user services don't.
+//! `Environment` collects the variables, sets them for the compositor and
+//! pushes them to the activation environments as well:
+//!
+//! ```rust,no_run,ignore
+//! let environment = Environment::new(&compositor).cursor_theme("Adwaita", 24)
+//!                                                .set("XDG_CURRENT_DESKTOP", "mywm");
+//! environment.export();
+//! environment.update_systemd().ok();
+//! environment.update_dbus().ok();
+//! ```
+//!
+//! Call it once the compositor is built, and again whenever a variable
+//! changes, e.g when Xwayland is started later on.
+
+use std::{env, io, process::Command};
+
+use compositor::Compositor;
+
+/// Variables for the processes of the session.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Environment {
+    vars: Vec<(String, String)>
+}
+
+impl Environment {
+    /// Collect the variables that point to the compositor.
+    ///
+    /// These are `WAYLAND_DISPLAY`, `XDG_SESSION_TYPE` and, if Xwayland is
+    /// running, `DISPLAY`.
+    pub fn new(compositor: &Compositor) -> Self {
+        let environment = Environment::default().set("WAYLAND_DISPLAY", compositor.socket_name())
+                                                .set("XDG_SESSION_TYPE", "wayland");
+        match compositor.xwayland {
+            Some(ref xwayland) => environment.set("DISPLAY", format!(":{}", xwayland.display())),
+            None => environment
+        }
+    }
+
+    /// Set the cursor theme for clients that draw their own cursor, i.e
+    /// `XCURSOR_THEME` and `XCURSOR_SIZE`.
+    pub fn cursor_theme<S: Into<String>>(self, theme: S, size: u32) -> Self {
+        self.set("XCURSOR_THEME", theme).set("XCURSOR_SIZE", size.to_string())
+    }
+
+    /// Set a variable, replacing it if it's set already.
+    pub fn set<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        let (key, value) = (key.into(), value.into());
+        self.vars.retain(|&(ref other, _)| *other != key);
+        self.vars.push((key, value));
+        self
+    }
+
+    /// Get the value of a variable, if it's set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.vars
+            .iter()
+            .find(|&&(ref other, _)| other == key)
+            .map(|&(_, ref value)| value.as_str())
+    }
+
+    pub fn vars(&self) -> &[(String, String)] {
+        &self.vars
+    }
+
+    /// Set the variables in the environment of the compositor, so the
+    /// programs it starts inherit them.
+    pub fn export(&self) {
+        for &(ref key, ref value) in &self.vars {
+            env::set_var(key, value)
+        }
+    }
+
+    /// Set the variables in the environment of the systemd user manager,
+    /// for the services it starts.
+    ///
+    /// This waits for `systemctl` to finish.
+    pub fn update_systemd(&self) -> io::Result<()> {
+        run(Command::new("systemctl").arg("--user")
+                                     .arg("set-environment")
+                                     .args(self.assignments()))
+    }
+
+    /// Set the variables in the environment of the D-Bus session bus, for
+    /// the services it activates.
+    ///
+    /// This waits for `dbus-update-activation-environment` to finish.
+    pub fn update_dbus(&self) -> io::Result<()> {
+        run(Command::new("dbus-update-activation-environment").args(self.assignments()))
+    }
+
+    fn assignments(&self) -> Vec<String> {
+        self.vars
+            .iter()
+            .map(|&(ref key, ref value)| format!("{}={}", key, value))
+            .collect()
+    }
+}
+
+/// Run the command, failing if it doesn't exit successfully.
+fn run(command: &mut Command) -> io::Result<()> {
+    let status = command.status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        wlr_log!(WLR_ERROR, "Updating the activation environment failed with {}", status);
+        Err(io::Error::new(io::ErrorKind::Other, format!("{:?} failed with {}", command, status)))
+    }
+}
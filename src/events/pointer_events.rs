@@ -32,7 +32,8 @@ pub struct Axis {
 #[derive(Debug)]
 pub struct Button {
     event: *mut wlr_event_pointer_button,
-    device: input::Device
+    device: input::Device,
+    click_count: u32
 }
 
 /// Event that triggers when the pointer moves.
@@ -56,7 +57,13 @@ impl Button {
     /// Constructs a `Button` from the raw event pointer.
     pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_button) -> Self {
         Button { device: input::Device::from_ptr((*event).device),
-                      event }
+                      event,
+                      click_count: 1 }
+    }
+
+    /// Sets the number of consecutive clicks this event is part of.
+    pub(crate) fn with_click_count(self, click_count: u32) -> Self {
+        Button { click_count, ..self }
     }
 
     /// Get the device this event refers to.
@@ -82,6 +89,16 @@ impl Button {
     pub fn button(&self) -> u32 {
         unsafe { (*self.event).button }
     }
+
+    /// Get how many times in a row this button was clicked, e.g 2 for a
+    /// double click. Release events report the count of their press.
+    ///
+    /// Clicks are only counted for events delivered through a
+    /// `cursor::Handler`, using the thresholds set with
+    /// `Cursor::set_click_thresholds`. Otherwise this is always 1.
+    pub fn click_count(&self) -> u32 {
+        self.click_count
+    }
 }
 
 impl Axis {
@@ -115,6 +132,13 @@ impl Axis {
     pub fn delta(&self) -> f64 {
         unsafe { (*self.event).delta }
     }
+
+    /// Get the change in discrete steps, e.g clicks of a scroll wheel.
+    ///
+    /// Zero for sources without steps, like touchpads.
+    pub fn delta_discrete(&self) -> i32 {
+        unsafe { (*self.event).delta_discrete }
+    }
 }
 
 impl Motion {
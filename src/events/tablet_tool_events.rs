@@ -32,6 +32,10 @@ impl Axis {
         Axis { event }
     }
 
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_event_tablet_tool_axis {
+        self.event
+    }
+
     pub fn time_msec(&self) -> u32 {
         unsafe { (*self.event).time_msec }
     }
@@ -76,6 +80,10 @@ impl Proximity {
         Proximity { event }
     }
 
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_event_tablet_tool_proximity {
+        self.event
+    }
+
     pub fn time_msec(&self) -> u32 {
         unsafe { (*self.event).time_msec }
     }
@@ -97,6 +105,10 @@ impl Tip {
         Tip { event }
     }
 
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_event_tablet_tool_tip {
+        self.event
+    }
+
     pub fn time_msec(&self) -> u32 {
         unsafe { (*self.event).time_msec }
     }
@@ -118,6 +130,10 @@ impl Button {
         Button { event }
     }
 
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_event_tablet_tool_button {
+        self.event
+    }
+
     pub fn time_msec(&self) -> u32 {
         unsafe { (*self.event).time_msec }
     }
@@ -0,0 +1,106 @@
+//! Support for the wlroots Export DMA-BUF (Version 1) Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://github.com/swaywm/wlroots/blob/master/protocol/wlr-export-dmabuf-unstable-v1.xml
+//!
+//! Screen recorders like wf-recorder and OBS use it to capture outputs
+//! without copying the frames to system memory. Enable it with
+//! `compositor::Builder::export_dmabuf_manager`.
+//!
+//! Use `Output::set_dmabuf_export_allowed` to keep clients from capturing
+//! an output. wlroots hands the client the buffers of the output as soon as
+//! it asks, so for outputs that may not be exported the frame is cancelled
+//! before the client is told it's ready to be read.
+
+use wayland_sys::server::{wl_display as wl_server_display, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_export_dmabuf_frame_v1, wlr_export_dmabuf_manager_v1,
+                  wlr_export_dmabuf_manager_v1_create, wlr_export_dmabuf_manager_v1_destroy,
+                  wlr_output};
+
+use {output::{self, OutputState},
+     utils::Handleable};
+
+/// The opcode of the `cancel` event of `zwlr_export_dmabuf_frame_v1`.
+const FRAME_CANCEL: u32 = 3;
+/// The `permanent` cancel reason, telling the client not to try again.
+const CANCEL_REASON_PERMANENT: u32 = 1;
+
+#[derive(Debug)]
+/// Manager that lets clients export the buffers of outputs
+pub struct ZManagerV1 {
+    manager: *mut wlr_export_dmabuf_manager_v1
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display) -> Option<Self> {
+        let manager_raw = wlr_export_dmabuf_manager_v1_create(display as *mut wl_display);
+
+        if !manager_raw.is_null() {
+            Some(ZManagerV1 { manager: manager_raw })
+        } else {
+            None
+        }
+    }
+
+    /// Get the outputs clients are currently capturing a frame of.
+    pub fn exported_outputs(&self) -> Vec<output::Handle> {
+        let mut outputs: Vec<output::Handle> = Vec::new();
+        unsafe {
+            wl_list_for_each!((*self.manager).frames,
+                              link,
+                              (frame: wlr_export_dmabuf_frame_v1) => {
+                let output = (*frame).output;
+                if !output.is_null() && !(*output).data.is_null()
+                    && !outputs.iter().any(|handle| handle.as_ptr() == output) {
+                    outputs.push(output::Handle::from_ptr(output))
+                }
+            });
+        }
+        outputs
+    }
+
+    /// Cancel the frames of the output if it may not be exported, before the
+    /// output swaps its buffers and they would be marked as ready.
+    pub(crate) unsafe fn review_frames(&mut self, output: *mut wlr_output) {
+        let state = (*output).data as *mut OutputState;
+        if state.is_null() || (*state).dmabuf_export {
+            return
+        }
+        let mut frames = Vec::new();
+        wl_list_for_each!((*self.manager).frames,
+                          link,
+                          (frame: wlr_export_dmabuf_frame_v1) => {
+            if (*frame).output == output {
+                frames.push(frame)
+            }
+        });
+        for frame in frames {
+            wlr_log!(WLR_INFO, "Cancelled dmabuf export of output {:p}", output);
+            cancel(frame);
+        }
+    }
+}
+
+/// Keep the frame from becoming ready, and tell the client it was cancelled.
+unsafe fn cancel(frame: *mut wlr_export_dmabuf_frame_v1) {
+    // The frame stays around until the client destroys it, so it's taken
+    // out of the frame list to not be cancelled twice. The links are reset
+    // so destroying the frame later is safe.
+    let link = &mut (*frame).link as *mut _ as *mut _;
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_remove, link);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_init, link);
+    let link = &mut (*frame).output_swap_buffers.link as *mut _ as *mut _;
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_remove, link);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_init, link);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  (*frame).resource as _,
+                  FRAME_CANCEL,
+                  CANCEL_REASON_PERMANENT);
+}
+
+impl Drop for ZManagerV1 {
+    fn drop(&mut self) {
+        unsafe { wlr_export_dmabuf_manager_v1_destroy(self.manager) }
+    }
+}
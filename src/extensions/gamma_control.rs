@@ -2,28 +2,120 @@
 //!
 //! Warning: This protocol is unstable and can change in the future
 //! Current Protocol: https://github.com/swaywm/wlroots/blob/master/protocol/wlr-gamma-control-unstable-v1.xml
+//!
+//! Enable it with `compositor::Builder::gamma_control_manager` so tools like
+//! gammastep or redshift can adjust the gamma of the outputs. wlroots applies
+//! the gamma tables the clients send directly, and restores the original
+//! gamma when a client lets go of an output.
+//!
+//! To hear about clients taking and releasing outputs, e.g to stop applying
+//! a night light of your own, use `compositor::Builder::gamma_control_handler`
+//! instead. wlroots doesn't signal either, so the handler is told when the
+//! next frame of any output is drawn. `ZManagerV1::controlled_outputs` is
+//! always up to date.
 
 use wayland_sys::server::wl_display as wl_server_display;
 use wlroots_sys::{wl_display, wlr_gamma_control_manager_v1, wlr_gamma_control_manager_v1_create, 
-    wlr_gamma_control_manager_v1_destroy};
+    wlr_gamma_control_manager_v1_destroy, wlr_gamma_control_v1};
+
+use {compositor, output, utils::Handleable};
+
+/// Handles clients taking and releasing control of the gamma of outputs.
+#[allow(unused_variables)]
+pub trait Handler {
+    /// A client took control of the gamma of an output.
+    fn control_started(&mut self,
+                       compositor_handle: compositor::Handle,
+                       output_handle: output::Handle) {}
+
+    /// A client let go of the gamma of an output, and wlroots restored its
+    /// original gamma.
+    ///
+    /// Not called for outputs that were destroyed.
+    fn control_stopped(&mut self,
+                       compositor_handle: compositor::Handle,
+                       output_handle: output::Handle) {}
+}
 
-#[derive(Debug)]
 /// Manager that can adjust gamma controls for an output
 pub struct ZManagerV1 {
-    manager: *mut wlr_gamma_control_manager_v1
+    manager: *mut wlr_gamma_control_manager_v1,
+    handler: Option<Box<Handler>>,
+    /// The controls the handler was told about, with their outputs.
+    known: Vec<(*mut wlr_gamma_control_v1, output::Handle)>
 }
 
 impl ZManagerV1 {
-    pub(crate) unsafe fn new(display: *mut wl_server_display) -> Option<Self> {
+    pub(crate) unsafe fn new(display: *mut wl_server_display,
+                             handler: Option<Box<Handler>>)
+                             -> Option<Self> {
         let manager_raw = wlr_gamma_control_manager_v1_create(display as *mut wl_display);
 
         if !manager_raw.is_null() {
-            Some(ZManagerV1 { manager: manager_raw })
+            Some(ZManagerV1 { manager: manager_raw,
+                              handler,
+                              known: Vec::new() })
         } else {
             None
         }
     }
 
+    /// Get the outputs a client is currently controlling the gamma of.
+    ///
+    /// Gamma set with `Output::set_gamma` is overridden on these outputs.
+    pub fn controlled_outputs(&self) -> Vec<output::Handle> {
+        let mut outputs = Vec::new();
+        unsafe {
+            wl_list_for_each!((*self.manager).controls,
+                              link,
+                              (control: wlr_gamma_control_v1) => {
+                let output = (*control).output;
+                if !output.is_null() && !(*output).data.is_null() {
+                    outputs.push(output::Handle::from_ptr(output))
+                }
+            });
+        }
+        outputs
+    }
+
+    /// Determines if a client is controlling the gamma of the output.
+    pub fn is_controlled(&self, output: &output::Output) -> bool {
+        self.controlled_outputs()
+            .iter()
+            .any(|handle| unsafe { handle.as_ptr() == output.as_ptr() })
+    }
+
+    /// Tell the handler about the controls that were made or destroyed
+    /// since the last time.
+    pub(crate) unsafe fn review_controls(&mut self, compositor: compositor::Handle) {
+        let handler = match self.handler {
+            Some(ref mut handler) => handler,
+            None => return
+        };
+        let mut controls = Vec::new();
+        wl_list_for_each!((*self.manager).controls,
+                          link,
+                          (control: wlr_gamma_control_v1) => {
+            let output = (*control).output;
+            if !output.is_null() && !(*output).data.is_null() {
+                controls.push((control, output::Handle::from_ptr(output)))
+            }
+        });
+        let (kept, stopped) = self.known
+                                  .drain(..)
+                                  .partition::<Vec<_>, _>(|known| controls.contains(known));
+        for (_, output) in stopped {
+            if output.is_alive() {
+                handler.control_stopped(compositor.clone(), output);
+            }
+        }
+        for control in &controls {
+            if !kept.contains(control) {
+                handler.control_started(compositor.clone(), control.1.clone());
+            }
+        }
+        self.known = controls;
+    }
 }
 
 impl Drop for ZManagerV1 {
@@ -0,0 +1,192 @@
+//! Support for letting trusted clients register global shortcuts.
+//!
+//! wlroots does not implement a global shortcuts protocol, so this is the
+//! compositor half of one. Whatever carries the requests from the client
+//! (e.g a private Wayland protocol or the xdg-desktop-portal GlobalShortcuts
+//! interface) registers shortcuts here, and the compositor passes every key
+//! press through `Manager::key` before it is sent to the focused client.
+//!
+//! Only applications that have been explicitly trusted with
+//! `Manager::trust` can register shortcuts.
+
+use std::{mem, time::Duration};
+
+use {input::keyboard::{self, Modifier}, utils::current_time};
+
+/// A shortcut that a client has asked to be told about.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Shortcut {
+    /// The application that registered the shortcut.
+    pub app_id: String,
+    /// The identifier the application gave the shortcut.
+    pub id: String,
+    /// A human readable description, e.g "Push to talk".
+    pub description: String,
+    /// The key that triggers the shortcut.
+    pub key: keyboard::Key,
+    /// The modifiers that must be held for the shortcut to trigger.
+    pub modifiers: Modifier
+}
+
+/// The reasons registering a shortcut can fail.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    /// The application has not been trusted to register shortcuts.
+    Untrusted,
+    /// Another shortcut is already bound to the same key combination.
+    AlreadyBound(Shortcut)
+}
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// The key combination for the shortcut was pressed.
+    ///
+    /// This is where the activation should be sent to the client.
+    fn activated(&mut self, shortcut: &Shortcut, time: Duration) {}
+
+    /// The key combination for the shortcut was released.
+    fn deactivated(&mut self, shortcut: &Shortcut, time: Duration) {}
+}
+
+/// Keeps track of the registered global shortcuts and dispatches key presses
+/// to them.
+pub struct Manager {
+    handler: Box<Handler>,
+    trusted: Vec<String>,
+    shortcuts: Vec<Shortcut>,
+    /// Indices into `shortcuts` that are currently held down.
+    active: Vec<usize>
+}
+
+impl Manager {
+    pub fn new(handler: Box<Handler>) -> Self {
+        Manager { handler,
+                  trusted: Vec::new(),
+                  shortcuts: Vec::new(),
+                  active: Vec::new() }
+    }
+
+    /// Allow the application to register global shortcuts.
+    pub fn trust<T: Into<String>>(&mut self, app_id: T) {
+        let app_id = app_id.into();
+        if !self.is_trusted(&app_id) {
+            self.trusted.push(app_id)
+        }
+    }
+
+    /// Revoke the trust of an application, removing all of its shortcuts.
+    pub fn untrust(&mut self, app_id: &str) {
+        self.trusted.retain(|trusted| trusted != app_id);
+        self.unregister_all(app_id);
+    }
+
+    /// Determines if the application may register global shortcuts.
+    pub fn is_trusted(&self, app_id: &str) -> bool {
+        self.trusted.iter().any(|trusted| trusted == app_id)
+    }
+
+    /// Register a shortcut on behalf of an application.
+    ///
+    /// Registering a shortcut with the same `app_id` and `id` as an existing
+    /// one replaces it. If the key combination is taken by another shortcut
+    /// nothing changes, the existing shortcut stays registered.
+    pub fn register(&mut self, shortcut: Shortcut) -> Result<(), Error> {
+        if !self.is_trusted(&shortcut.app_id) {
+            return Err(Error::Untrusted)
+        }
+        if let Some(existing) = self.find(shortcut.key, shortcut.modifiers) {
+            let existing = &self.shortcuts[existing];
+            if existing.app_id != shortcut.app_id || existing.id != shortcut.id {
+                return Err(Error::AlreadyBound(existing.clone()))
+            }
+        }
+        self.unregister(&shortcut.app_id, &shortcut.id);
+        self.shortcuts.push(shortcut);
+        Ok(())
+    }
+
+    /// Remove a shortcut registered by an application.
+    pub fn unregister(&mut self, app_id: &str, id: &str) {
+        self.remove_where(|shortcut| shortcut.app_id == app_id && shortcut.id == id)
+    }
+
+    /// Remove every shortcut registered by an application.
+    ///
+    /// This should be called when the client disconnects.
+    pub fn unregister_all(&mut self, app_id: &str) {
+        self.remove_where(|shortcut| shortcut.app_id == app_id)
+    }
+
+    /// Get all of the registered shortcuts.
+    pub fn shortcuts(&self) -> &[Shortcut] {
+        &self.shortcuts
+    }
+
+    /// Feed a key event through the registered shortcuts.
+    ///
+    /// Returns true if the key was bound to a shortcut, in which case it
+    /// should not be sent on to the focused client.
+    pub fn key(&mut self,
+               key: keyboard::Key,
+               modifiers: Modifier,
+               state: keyboard::wlr_key_state,
+               time: Duration)
+               -> bool {
+        use self::keyboard::wlr_key_state::*;
+        match state {
+            WLR_KEY_PRESSED => {
+                let index = match self.find(key, modifiers) {
+                    Some(index) => index,
+                    None => return false
+                };
+                if !self.active.contains(&index) {
+                    self.active.push(index);
+                    self.handler.activated(&self.shortcuts[index], time);
+                }
+                true
+            }
+            WLR_KEY_RELEASED => {
+                // Modifiers may already be released, so only match on the key.
+                let shortcuts = &self.shortcuts;
+                let released: Vec<usize> =
+                    self.active.iter().cloned()
+                        .filter(|&index| shortcuts[index].key == key)
+                        .collect();
+                self.active.retain(|index| !released.contains(index));
+                for &index in &released {
+                    self.handler.deactivated(&self.shortcuts[index], time);
+                }
+                !released.is_empty()
+            }
+        }
+    }
+
+    fn find(&self, key: keyboard::Key, modifiers: Modifier) -> Option<usize> {
+        self.shortcuts.iter()
+            .position(|shortcut| shortcut.key == key && shortcut.modifiers == modifiers)
+    }
+
+    /// Remove the matching shortcuts, telling the handler that the ones that
+    /// are held down were released.
+    fn remove_where<F>(&mut self, mut predicate: F)
+        where F: FnMut(&Shortcut) -> bool
+    {
+        let time = current_time();
+        // Active indices shift with the removal, so they are rebuilt.
+        let active = mem::replace(&mut self.active, Vec::new());
+        let shortcuts = mem::replace(&mut self.shortcuts, Vec::new());
+        for (index, shortcut) in shortcuts.into_iter().enumerate() {
+            let held = active.contains(&index);
+            if predicate(&shortcut) {
+                if held {
+                    self.handler.deactivated(&shortcut, time);
+                }
+                continue
+            }
+            if held {
+                self.active.push(self.shortcuts.len());
+            }
+            self.shortcuts.push(shortcut);
+        }
+    }
+}
@@ -1,16 +1,59 @@
 //! Support for the KDE Idle Protocol
+//!
+//! Clients like swayidle use it to run commands (e.g lock the screen or
+//! turn off the outputs) once the user has been idle for a while. Enable it
+//! with `compositor::Builder::idle_manager`.
+//!
+//! Input sent through a `Seat` counts as activity automatically. Activity
+//! that doesn't go through a seat, e.g a video playing, has to be reported
+//! with `Manager::notify_activity`.
+//!
+//! The compositor can also be told about idleness itself, by adding a
+//! timeout with `Manager::add_timeout`:
+//!
+//! ```rust,no_run,ignore
+//! let idle = compositor.idle_manager.as_mut().unwrap();
+//! idle.add_timeout(None,
+//!                  Duration::from_secs(300),
+//!                  || dim_outputs(),
+//!                  || undim_outputs());
+//! ```
 
+use std::{ptr, time::Duration};
 
+use libc;
 use seat::Seat;
 
-use wayland_sys::server::wl_display as wl_server_display;
+use wayland_sys::server::{wl_display as wl_server_display, wl_event_source,
+                          WAYLAND_SERVER_HANDLE};
 use wlroots_sys::{wl_display, wlr_idle, wlr_idle_create,
                   wlr_idle_destroy, wlr_idle_notify_activity,
                   wlr_idle_set_enabled};
 
-#[derive(Debug)]
+use utils::ToMs;
+
+/// Identifies a timeout added with `Manager::add_timeout`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct TimeoutId(u64);
+
+struct Timeout {
+    id: TimeoutId,
+    /// The seat the timeout watches, or `None` for all seats.
+    seat: Option<String>,
+    duration: Duration,
+    on_idle: Box<FnMut()>,
+    on_resume: Box<FnMut()>,
+    idle: bool,
+    timer: *mut wl_event_source
+}
+
 pub struct Manager {
-    manager: *mut wlr_idle
+    manager: *mut wlr_idle,
+    display: *mut wl_server_display,
+    next_timeout: u64,
+    timeouts: Vec<Box<Timeout>>,
+    /// The seats idleness isn't tracked for, e.g because a video is playing.
+    disabled_seats: Vec<Option<String>>
 }
 
 impl Manager {
@@ -18,25 +61,168 @@ impl Manager {
         let manager_raw = wlr_idle_create(display as *mut wl_display);
 
         if !manager_raw.is_null() {
-            Some(Manager { manager: manager_raw })
+            Some(Manager { manager: manager_raw,
+                           display,
+                           next_timeout: 0,
+                           timeouts: Vec::new(),
+                           disabled_seats: Vec::new() })
         } else {
             None
         }
     }
 
     /// Restart the timers for the seat
+    ///
+    /// This is done automatically for input sent through the seat.
     pub fn notify_activity(&mut self, seat: &Seat) {
         unsafe { wlr_idle_notify_activity(self.manager, seat.as_ptr()) }
+        let name = seat.name();
+        let disabled = self.disabled_seats.contains(&name);
+        for timeout in &mut self.timeouts {
+            if timeout.seat.is_none() || timeout.seat == name {
+                timeout.activity(!disabled);
+            }
+        }
     }
 
     /// If we are passed a null pointer, update timers for all seats.
+    ///
+    /// While disabled the seat is never considered idle, neither by the
+    /// clients nor by the timeouts of the compositor.
     pub fn set_enabled(&mut self, seat: &Seat, enabled: bool) {
         unsafe { wlr_idle_set_enabled(self.manager, seat.as_ptr(), enabled) }
+        let name = seat.name();
+        self.disabled_seats.retain(|disabled| *disabled != name);
+        if !enabled {
+            self.disabled_seats.push(name.clone());
+        }
+        for timeout in &mut self.timeouts {
+            if timeout.seat.is_none() || timeout.seat == name {
+                if enabled {
+                    timeout.arm();
+                } else {
+                    timeout.disarm();
+                }
+            }
+        }
+    }
+
+    /// Call `on_idle` once `seat` (or any seat, if `None`) has been idle for
+    /// `duration`, and `on_resume` on the first activity after that.
+    ///
+    /// A `duration` of zero fires right away. To stop a timeout, remove it
+    /// with `remove_timeout`, or disable idleness for the seat with
+    /// `set_enabled`.
+    ///
+    /// The callbacks run while the seat that saw the activity is borrowed,
+    /// so they should not try to use it, and they must not add or remove
+    /// timeouts.
+    pub fn add_timeout<I, R>(&mut self,
+                             seat: Option<String>,
+                             duration: Duration,
+                             on_idle: I,
+                             on_resume: R)
+                             -> TimeoutId
+        where I: FnMut() + 'static,
+              R: FnMut() + 'static
+    {
+        self.next_timeout += 1;
+        let id = TimeoutId(self.next_timeout);
+        let mut timeout = Box::new(Timeout { id,
+                                             seat,
+                                             duration,
+                                             on_idle: Box::new(on_idle),
+                                             on_resume: Box::new(on_resume),
+                                             idle: false,
+                                             timer: ptr::null_mut() });
+        unsafe {
+            let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                           wl_display_get_event_loop,
+                                           self.display);
+            timeout.timer = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                          wl_event_loop_add_timer,
+                                          event_loop,
+                                          timeout_fired,
+                                          &mut *timeout as *mut Timeout as *mut _);
+        }
+        let disabled = self.disabled_seats.iter().any(|disabled| {
+            timeout.seat.is_none() || *disabled == timeout.seat
+        });
+        if !disabled {
+            timeout.arm();
+        }
+        self.timeouts.push(timeout);
+        id
+    }
+
+    /// Remove a timeout. Returns false if there is no such timeout.
+    pub fn remove_timeout(&mut self, id: TimeoutId) -> bool {
+        let len = self.timeouts.len();
+        self.timeouts.retain(|timeout| timeout.id != id);
+        self.timeouts.len() != len
+    }
+}
+
+impl Timeout {
+    fn activity(&mut self, rearm: bool) {
+        if self.idle {
+            self.idle = false;
+            (self.on_resume)();
+        }
+        if rearm {
+            self.arm()
+        }
+    }
+
+    fn arm(&mut self) {
+        if self.timer.is_null() {
+            return
+        }
+        // A timeout of 0 would disarm the timer instead.
+        let ms = ::std::cmp::max(self.duration.to_ms(), 1);
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_source_timer_update,
+                          self.timer,
+                          ms as libc::c_int);
+        }
+    }
+
+    fn disarm(&mut self) {
+        if self.timer.is_null() {
+            return
+        }
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_source_timer_update,
+                          self.timer,
+                          0);
+        }
+    }
+}
+
+impl Drop for Timeout {
+    fn drop(&mut self) {
+        if !self.timer.is_null() {
+            unsafe {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.timer);
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn timeout_fired(data: *mut libc::c_void) -> libc::c_int {
+    let timeout = &mut *(data as *mut Timeout);
+    if !timeout.idle {
+        timeout.idle = true;
+        (timeout.on_idle)();
     }
+    0
 }
 
 impl Drop for Manager {
     fn drop(&mut self) {
+        self.timeouts.clear();
         unsafe { wlr_idle_destroy(self.manager) }
     }
 }
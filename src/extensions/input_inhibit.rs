@@ -0,0 +1,131 @@
+//! Support for the wlroots Input Inhibitor Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://github.com/swaywm/wlroots/blob/master/protocol/wlr-input-inhibitor-unstable-v1.xml
+//!
+//! Screen lockers like swaylock use this to take all input for themselves,
+//! so nothing typed at the lock screen ends up in another client. Enable it
+//! with `compositor::Builder::input_inhibit_manager`.
+//!
+//! While a client holds the inhibitor, the compositor should only send input
+//! to that client, and stop handling its own keybindings. When the inhibitor
+//! is activated the keyboard and pointer focus of every seat is taken away
+//! from the other clients. After that it's up to the compositor to check
+//! `Seat::may_receive_input` before it gives focus to a surface.
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, wl_display as wl_server_display,
+                          WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_client, wl_display, wlr_input_inhibit_manager,
+                  wlr_input_inhibit_manager_create, wlr_input_inhibit_manager_destroy};
+
+use {compositor, snapshot, utils};
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// A client took exclusive input, e.g because it locked the screen.
+    ///
+    /// `client_pid` is the process id of the client, if it's known.
+    fn activated(&mut self, compositor_handle: compositor::Handle, client_pid: Option<libc::pid_t>) {}
+
+    /// The client gave up exclusive input, or it went away.
+    fn deactivated(&mut self, compositor_handle: compositor::Handle) {}
+}
+
+wayland_listener!(pub(crate) InhibitWatcher, (*mut wlr_input_inhibit_manager, Option<Box<Handler>>), [
+    activate_listener => activate_notify: |this: &mut InhibitWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        let (manager, ref mut handler) = this.data;
+        let client = (*manager).active_client;
+        wlr_log!(WLR_INFO, "Input inhibited for client {:p}", client);
+        for seat in snapshot::seats() {
+            let _ = seat.run(|seat| seat.enforce_input_inhibitor());
+        }
+        if let (Some(handler), Some(compositor)) = (handler.as_mut(), compositor::handle()) {
+            handler.activated(compositor, utils::client_pid(client));
+        }
+    };
+    deactivate_listener => deactivate_notify: |this: &mut InhibitWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        wlr_log!(WLR_INFO, "Input no longer inhibited");
+        if let (Some(handler), Some(compositor)) = (this.data.1.as_mut(), compositor::handle()) {
+            handler.deactivated(compositor);
+        }
+    };
+]);
+
+impl Drop for InhibitWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.activate_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.deactivate_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+/// Manager that lets a single client take all input
+pub struct ZManagerV1 {
+    manager: *mut wlr_input_inhibit_manager,
+    watcher: Box<InhibitWatcher>
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display,
+                             handler: Option<Box<Handler>>)
+                             -> Option<Self> {
+        let manager_raw = wlr_input_inhibit_manager_create(display as *mut wl_display);
+
+        if !manager_raw.is_null() {
+            let mut watcher = InhibitWatcher::new((manager_raw, handler));
+            wl_signal_add(&mut (*manager_raw).events.activate as *mut _ as _,
+                          watcher.activate_listener() as *mut _ as _);
+            wl_signal_add(&mut (*manager_raw).events.deactivate as *mut _ as _,
+                          watcher.deactivate_listener() as *mut _ as _);
+            Some(ZManagerV1 { manager: manager_raw,
+                              watcher })
+        } else {
+            None
+        }
+    }
+
+    /// Determines if a client currently has exclusive input.
+    pub fn is_active(&self) -> bool {
+        unsafe { !(*self.manager).active_client.is_null() }
+    }
+
+    /// Get the process id of the client that has exclusive input, if there
+    /// is one and it's known.
+    pub fn active_client_pid(&self) -> Option<libc::pid_t> {
+        unsafe { utils::client_pid((*self.manager).active_client) }
+    }
+
+    /// The client that has exclusive input, or null.
+    pub(crate) unsafe fn active_client(&self) -> *mut wl_client {
+        (*self.manager).active_client
+    }
+}
+
+impl Drop for ZManagerV1 {
+    fn drop(&mut self) {
+        unsafe {
+            // Unlink the listeners before the signals they are on are freed.
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.watcher.activate_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_init,
+                          &mut (*self.watcher.activate_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.watcher.deactivate_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_init,
+                          &mut (*self.watcher.deactivate_listener()).link as *mut _ as _);
+            wlr_input_inhibit_manager_destroy(self.manager)
+        }
+    }
+}
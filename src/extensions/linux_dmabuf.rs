@@ -0,0 +1,74 @@
+//! Support for the Linux DMA-BUF Protocol
+//!
+//! Current Protocol: https://gitlab.freedesktop.org/wayland/wayland-protocols/blob/main/stable/linux-dmabuf/linux-dmabuf-v1.xml
+//!
+//! Lets GPU clients hand their buffers to the compositor as dmabufs, instead
+//! of copying them into shared memory. Enable it with
+//! `compositor::Builder::linux_dmabuf`, which needs the renderer to be
+//! enabled too.
+//!
+//! Clients are told which formats and modifiers the renderer can import.
+
+use std::slice;
+
+use libc;
+use wayland_sys::server::wl_display as wl_server_display;
+use wlroots_sys::{wl_display, wlr_linux_dmabuf_v1, wlr_linux_dmabuf_v1_create, wlr_renderer,
+                  wlr_renderer_get_dmabuf_formats, wlr_renderer_get_dmabuf_modifiers};
+
+/// A DRM fourcc format and the modifiers it's supported with.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Format {
+    pub format: u32,
+    pub modifiers: Vec<u64>
+}
+
+/// Manager that lets clients submit dmabufs
+///
+/// It's destroyed along with the display.
+pub struct LinuxDmabufV1 {
+    _dmabuf: *mut wlr_linux_dmabuf_v1
+}
+
+impl LinuxDmabufV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display,
+                             renderer: *mut wlr_renderer)
+                             -> Option<Self> {
+        let dmabuf_raw = wlr_linux_dmabuf_v1_create(display as *mut wl_display, renderer);
+
+        if !dmabuf_raw.is_null() {
+            Some(LinuxDmabufV1 { _dmabuf: dmabuf_raw })
+        } else {
+            None
+        }
+    }
+}
+
+/// Read the formats the renderer can import dmabufs in.
+pub(crate) unsafe fn formats(renderer: *mut wlr_renderer) -> Vec<Format> {
+    let mut formats_ptr: *mut libc::c_int = ::std::ptr::null_mut();
+    let count = wlr_renderer_get_dmabuf_formats(renderer, &mut formats_ptr);
+    if count <= 0 || formats_ptr.is_null() {
+        return Vec::new()
+    }
+    // The renderer allocates the arrays, the caller frees them.
+    let formats = slice::from_raw_parts(formats_ptr, count as usize)
+        .iter()
+        .map(|&format| {
+                 let mut modifiers_ptr: *mut u64 = ::std::ptr::null_mut();
+                 let count =
+                     wlr_renderer_get_dmabuf_modifiers(renderer, format, &mut modifiers_ptr);
+                 let modifiers = if count > 0 && !modifiers_ptr.is_null() {
+                     slice::from_raw_parts(modifiers_ptr, count as usize).to_vec()
+                 } else {
+                     Vec::new()
+                 };
+                 libc::free(modifiers_ptr as *mut libc::c_void);
+                 Format { format: format as u32,
+                          modifiers }
+             })
+        .collect();
+    libc::free(formats_ptr as *mut libc::c_void);
+    formats
+}
@@ -2,5 +2,14 @@ pub mod server_decoration;
 pub mod gamma_control;
 pub mod screenshooter;
 pub mod screencopy;
+pub mod export_dmabuf;
 pub mod idle;
+pub mod input_inhibit;
+pub mod virtual_keyboard;
+pub mod pointer_constraints;
+pub mod tablet;
 pub mod gtk_primary_selection;
+pub mod river_status;
+pub mod linux_dmabuf;
+pub mod xdg_output;
+pub mod global_shortcuts;
@@ -0,0 +1,271 @@
+//! Support for the Pointer Constraints (Version 1) Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://cgit.freedesktop.org/wayland/wayland-protocols/tree/unstable/pointer-constraints/pointer-constraints-unstable-v1.xml
+//!
+//! Games, VMs and 3D modelling tools use this to keep the pointer from
+//! leaving their surface: a locked pointer doesn't move at all (the client
+//! usually reads relative motion instead), a confined pointer can move but
+//! not past the region the client gave. Enable it with
+//! `compositor::Builder::pointer_constraints_manager`.
+//!
+//! The compositor decides when a constraint applies. Typically that's while
+//! the surface has pointer focus:
+//!
+//! ```rust,no_run,ignore
+//! constraints.constraint_for(surface, seat, |constraint| constraint.activate());
+//! ```
+//!
+//! While a constraint is active, pointer motion has to be dropped (locked) or
+//! clamped with `Constraint::confine` (confined) before it's sent to the
+//! seat. Deactivate the constraint when the surface loses focus.
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, wl_display as wl_server_display,
+                          WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_pointer_constraint_v1, wlr_pointer_constraint_v1_send_activated,
+                  wlr_pointer_constraint_v1_send_deactivated, wlr_pointer_constraints_v1,
+                  wlr_pointer_constraints_v1_constraint_for_surface,
+                  wlr_pointer_constraints_v1_create, wlr_pointer_constraints_v1_destroy,
+                  pixman_region32_copy, wlr_region_confine,
+                  wlr_pointer_constraint_v1_type::*};
+
+use {compositor,
+     seat::{self, Seat},
+     surface::{self, Surface},
+     utils::region::PixmanRegion32};
+
+/// How a constraint restricts the pointer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Kind {
+    /// The pointer may not move at all.
+    Locked,
+    /// The pointer may only move inside the region of the constraint.
+    Confined
+}
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// A client asked for the pointer to be constrained to one of its
+    /// surfaces. It doesn't apply until it's activated.
+    fn new_constraint(&mut self,
+                      compositor_handle: compositor::Handle,
+                      constraint: &mut Constraint) {}
+
+    /// The client changed the region of the constraint.
+    fn region_changed(&mut self,
+                      compositor_handle: compositor::Handle,
+                      constraint: &mut Constraint) {}
+
+    /// The constraint is going away, e.g because the client destroyed it or
+    /// its surface. If it was active the pointer is free again.
+    fn constraint_destroyed(&mut self,
+                            compositor_handle: compositor::Handle,
+                            constraint: &mut Constraint) {}
+}
+
+impl Handler for () {}
+
+/// A request to lock or confine the pointer of a seat to a surface.
+///
+/// This is only handed out for the duration of a callback, as the client can
+/// destroy it at any time.
+#[derive(Debug)]
+pub struct Constraint {
+    constraint: *mut wlr_pointer_constraint_v1
+}
+
+impl Constraint {
+    pub fn kind(&self) -> Kind {
+        unsafe {
+            match (*self.constraint).type_ {
+                WLR_POINTER_CONSTRAINT_V1_LOCKED => Kind::Locked,
+                WLR_POINTER_CONSTRAINT_V1_CONFINED => Kind::Confined
+            }
+        }
+    }
+
+    /// Get a handle to the surface the pointer is constrained to.
+    pub fn surface(&self) -> surface::Handle {
+        unsafe { surface::Handle::from_ptr((*self.constraint).surface) }
+    }
+
+    /// Get a handle to the seat whose pointer is constrained.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*self.constraint).seat) }
+    }
+
+    /// Get the region of the surface the pointer is restricted to, in
+    /// surface coordinates.
+    pub fn region(&self) -> PixmanRegion32 {
+        let mut region = PixmanRegion32::new();
+        unsafe { pixman_region32_copy(&mut region.region, &mut (*self.constraint).region) };
+        region
+    }
+
+    /// Restrict a pointer movement from `(x1, y1)` to `(x2, y2)`, in surface
+    /// coordinates, to the region of the constraint.
+    ///
+    /// Returns where the pointer should end up, or `None` if the pointer
+    /// wasn't in the region to begin with.
+    pub fn confine(&self, x1: f64, y1: f64, x2: f64, y2: f64) -> Option<(f64, f64)> {
+        unsafe {
+            let (mut x, mut y) = (0.0, 0.0);
+            if wlr_region_confine(&mut (*self.constraint).region, x1, y1, x2, y2, &mut x, &mut y) {
+                Some((x, y))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Tell the client the constraint applies now.
+    pub fn activate(&mut self) {
+        unsafe { wlr_pointer_constraint_v1_send_activated(self.constraint) }
+    }
+
+    /// Tell the client the constraint no longer applies.
+    ///
+    /// A constraint that only applies once is destroyed by this.
+    pub fn deactivate(&mut self) {
+        unsafe { wlr_pointer_constraint_v1_send_deactivated(self.constraint) }
+    }
+}
+
+wayland_listener!(pub(crate) ConstraintsWatcher, Box<Handler>, [
+    new_constraint_listener => new_constraint_notify: |this: &mut ConstraintsWatcher,
+                                                       data: *mut libc::c_void,|
+    unsafe {
+        let constraint_ptr = data as *mut wlr_pointer_constraint_v1;
+        wlr_log!(WLR_DEBUG, "New pointer constraint {:p}", constraint_ptr);
+        let mut watcher = ConstraintWatcher::new(constraint_ptr);
+        wl_signal_add(&mut (*constraint_ptr).events.set_region as *mut _ as _,
+                      watcher.set_region_listener() as *mut _ as _);
+        wl_signal_add(&mut (*constraint_ptr).events.destroy as *mut _ as _,
+                      watcher.destroy_listener() as *mut _ as _);
+        (*constraint_ptr).data = Box::into_raw(watcher) as *mut _;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        this.data.new_constraint(compositor, &mut Constraint { constraint: constraint_ptr });
+    };
+]);
+
+wayland_listener!(pub(crate) ConstraintWatcher, *mut wlr_pointer_constraint_v1, [
+    set_region_listener => set_region_notify: |this: &mut ConstraintWatcher,
+                                               _data: *mut libc::c_void,|
+    unsafe {
+        let constraint_ptr = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        if let Some(handler) = handler(constraint_ptr) {
+            handler.region_changed(compositor, &mut Constraint { constraint: constraint_ptr });
+        }
+    };
+    destroy_listener => destroy_notify: |this: &mut ConstraintWatcher,
+                                         _data: *mut libc::c_void,|
+    unsafe {
+        let constraint_ptr = this.data;
+        if let (Some(handler), Some(compositor)) = (handler(constraint_ptr), compositor::handle()) {
+            let mut constraint = Constraint { constraint: constraint_ptr };
+            handler.constraint_destroyed(compositor, &mut constraint);
+        }
+        let watcher = (*constraint_ptr).data as *mut ConstraintWatcher;
+        (*constraint_ptr).data = ::std::ptr::null_mut();
+        Box::from_raw(watcher);
+    };
+]);
+
+impl Drop for ConstraintsWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.new_constraint_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+impl Drop for ConstraintWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.set_region_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.destroy_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+/// The handler of the manager the constraint belongs to.
+unsafe fn handler<'a>(constraint: *mut wlr_pointer_constraint_v1) -> Option<&'a mut Box<Handler>> {
+    let watcher = (*(*constraint).pointer_constraints).data as *mut ConstraintsWatcher;
+    if watcher.is_null() {
+        None
+    } else {
+        Some(&mut (*watcher).data)
+    }
+}
+
+/// Manager that lets clients lock or confine the pointer
+pub struct ZManagerV1 {
+    manager: *mut wlr_pointer_constraints_v1,
+    watcher: Box<ConstraintsWatcher>
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display,
+                             handler: Box<Handler>)
+                             -> Option<Self> {
+        let manager_raw = wlr_pointer_constraints_v1_create(display as *mut wl_display);
+
+        if !manager_raw.is_null() {
+            let mut watcher = ConstraintsWatcher::new(handler);
+            wl_signal_add(&mut (*manager_raw).events.new_constraint as *mut _ as _,
+                          watcher.new_constraint_listener() as *mut _ as _);
+            (*manager_raw).data = &mut *watcher as *mut ConstraintsWatcher as *mut _;
+            Some(ZManagerV1 { manager: manager_raw,
+                              watcher })
+        } else {
+            None
+        }
+    }
+
+    /// Run a function with the constraint the client asked for on the
+    /// pointer of the seat for the surface, if there is one.
+    pub fn constraint_for<F, R>(&self, surface: &Surface, seat: &Seat, runner: F) -> Option<R>
+        where F: FnOnce(&mut Constraint) -> R
+    {
+        unsafe {
+            let constraint = wlr_pointer_constraints_v1_constraint_for_surface(self.manager,
+                                                                               surface.as_ptr(),
+                                                                               seat.as_ptr());
+            if constraint.is_null() {
+                None
+            } else {
+                Some(runner(&mut Constraint { constraint }))
+            }
+        }
+    }
+}
+
+impl Drop for ZManagerV1 {
+    fn drop(&mut self) {
+        unsafe {
+            // The signal and the constraints go away with the manager.
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.watcher.new_constraint_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_init,
+                          &mut (*self.watcher.new_constraint_listener()).link as *mut _ as _);
+            (*self.manager).data = ::std::ptr::null_mut();
+            wlr_pointer_constraints_v1_destroy(self.manager)
+        }
+    }
+}
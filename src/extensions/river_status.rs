@@ -0,0 +1,527 @@
+//! Support for the River Status Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://github.com/riverwm/river/blob/master/protocol/river-status-unstable-v1.xml
+//!
+//! Status bars (e.g waybar's river modules) use this to show the workspaces
+//! of each output, which of them are focused or urgent, the title of the
+//! focused view and the keybinding mode. wlroots doesn't implement it, so
+//! the global is implemented here. Enable it with
+//! `compositor::Builder::river_status_manager`.
+//!
+//! Workspaces are sent as tags, a bitfield of up to 32 workspaces: bit `n`
+//! stands for workspace `n`. The compositor pushes its state whenever it
+//! changes, clients binding later get the latest state:
+//!
+//! ```rust,no_run,ignore
+//! if let Some(ref mut status) = compositor.river_status_manager {
+//!     status.set_focused_tags(output, 1 << workspace);
+//!     status.set_focused_view(seat, view.title().as_ref().map(String::as_str));
+//!     status.set_mode(seat, "resize");
+//! }
+//! ```
+
+use std::{mem, ptr};
+
+use libc::{self, c_int};
+use wayland_sys::{common::wl_array,
+                  server::{wl_client, wl_display as wl_server_display, wl_global, wl_resource,
+                           WAYLAND_SERVER_HANDLE}};
+use wlroots_sys::{protocols::river_status::c_interfaces::{zriver_output_status_v1_interface,
+                                                          zriver_seat_status_v1_interface,
+                                                          zriver_status_manager_v1_interface},
+                  wl_list, wlr_output, wlr_output_from_resource, wlr_seat_client_from_resource};
+
+use {output::{self, Output},
+     seat::{self, Seat},
+     utils::{safe_as_cstring, Handleable}};
+
+const VERSION: u32 = 3;
+
+/// Opcodes of the events, in the order of the protocol.
+const OUTPUT_FOCUSED_TAGS: u32 = 0;
+const OUTPUT_VIEW_TAGS: u32 = 1;
+const OUTPUT_URGENT_TAGS: u32 = 2;
+const SEAT_FOCUSED_OUTPUT: u32 = 0;
+const SEAT_UNFOCUSED_OUTPUT: u32 = 1;
+const SEAT_FOCUSED_VIEW: u32 = 2;
+const SEAT_MODE: u32 = 3;
+
+/// What status bars are told about an output.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OutputStatus {
+    /// The workspaces shown on the output.
+    pub focused_tags: u32,
+    /// The workspaces of each view on the output.
+    pub view_tags: Vec<u32>,
+    /// The workspaces with an urgent view on the output.
+    pub urgent_tags: u32
+}
+
+/// What status bars are told about a seat.
+#[derive(Debug, Clone, Default)]
+pub struct SeatStatus {
+    pub focused_output: Option<output::Handle>,
+    /// The title of the focused view, empty if there is none or it has no
+    /// title.
+    pub focused_view: String,
+    /// The name of the keybinding mode, e.g "default".
+    pub mode: String
+}
+
+#[derive(Default)]
+struct State {
+    outputs: Vec<(output::Handle, OutputStatus)>,
+    seats: Vec<(seat::Handle, SeatStatus)>,
+    managers: Vec<*mut wl_resource>,
+    output_statuses: Vec<(*mut wl_resource, output::Handle)>,
+    seat_statuses: Vec<(*mut wl_resource, seat::Handle)>
+}
+
+impl State {
+    fn output(&mut self, output: &output::Handle) -> &mut OutputStatus {
+        if let Some(index) = self.outputs.iter().position(|&(ref other, _)| other == output) {
+            return &mut self.outputs[index].1
+        }
+        self.outputs.push((output.clone(), OutputStatus::default()));
+        &mut self.outputs.last_mut().unwrap().1
+    }
+
+    fn seat(&mut self, seat: &seat::Handle) -> &mut SeatStatus {
+        if let Some(index) = self.seats.iter().position(|&(ref other, _)| other == seat) {
+            return &mut self.seats[index].1
+        }
+        self.seats.push((seat.clone(), SeatStatus::default()));
+        &mut self.seats.last_mut().unwrap().1
+    }
+
+    /// Get the status objects bars created for the output.
+    fn output_resources(&self, output: &output::Handle) -> Vec<*mut wl_resource> {
+        self.output_statuses
+            .iter()
+            .filter(|&&(_, ref other)| other == output)
+            .map(|&(resource, _)| resource)
+            .collect()
+    }
+
+    /// Get the status objects bars created for the seat.
+    fn seat_resources(&self, seat: &seat::Handle) -> Vec<*mut wl_resource> {
+        self.seat_statuses
+            .iter()
+            .filter(|&&(_, ref other)| other == seat)
+            .map(|&(resource, _)| resource)
+            .collect()
+    }
+}
+
+/// Manager that tells status bars about workspaces and focus
+///
+/// Unlike the other extensions it's implemented by these bindings, its
+/// global is removed when it's dropped.
+pub struct ZManagerV1 {
+    global: *mut wl_global,
+    state: Box<State>
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display) -> Option<Self> {
+        let mut state = Box::new(State::default());
+        let global = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                   wl_global_create,
+                                   display,
+                                   &zriver_status_manager_v1_interface as *const _ as _,
+                                   VERSION as c_int,
+                                   &mut *state as *mut State as *mut _,
+                                   bind_manager);
+        if global.is_null() {
+            wlr_log!(WLR_ERROR, "Could not create the river status global");
+            return None
+        }
+        Some(ZManagerV1 { global, state })
+    }
+
+    /// Set the workspaces shown on the output.
+    pub fn set_focused_tags(&mut self, output: &Output, tags: u32) {
+        let output = output.weak_reference();
+        {
+            let status = self.state.output(&output);
+            if status.focused_tags == tags {
+                return
+            }
+            status.focused_tags = tags;
+        }
+        for resource in self.state.output_resources(&output) {
+            unsafe { send_focused_tags(resource, tags) }
+        }
+    }
+
+    /// Set the workspaces of the views on the output, one entry per view.
+    pub fn set_view_tags(&mut self, output: &Output, tags: &[u32]) {
+        let output = output.weak_reference();
+        {
+            let status = self.state.output(&output);
+            if status.view_tags.as_slice() == tags {
+                return
+            }
+            status.view_tags = tags.to_vec();
+        }
+        for resource in self.state.output_resources(&output) {
+            unsafe { send_view_tags(resource, tags) }
+        }
+    }
+
+    /// Set the workspaces of the output that have an urgent view.
+    pub fn set_urgent_tags(&mut self, output: &Output, tags: u32) {
+        let output = output.weak_reference();
+        {
+            let status = self.state.output(&output);
+            if status.urgent_tags == tags {
+                return
+            }
+            status.urgent_tags = tags;
+        }
+        for resource in self.state.output_resources(&output) {
+            unsafe { send_urgent_tags(resource, tags) }
+        }
+    }
+
+    /// Set the output the seat focuses.
+    pub fn set_focused_output(&mut self, seat: &Seat, output: Option<&Output>) {
+        let seat = seat.weak_reference();
+        let output = output.map(|output| output.weak_reference());
+        let previous = {
+            let status = self.state.seat(&seat);
+            if status.focused_output == output {
+                return
+            }
+            mem::replace(&mut status.focused_output, output.clone())
+        };
+        for resource in self.state.seat_resources(&seat) {
+            unsafe {
+                if let Some(ref previous) = previous {
+                    send_output(resource, SEAT_UNFOCUSED_OUTPUT, previous);
+                }
+                if let Some(ref output) = output {
+                    send_output(resource, SEAT_FOCUSED_OUTPUT, output);
+                }
+            }
+        }
+    }
+
+    /// Set the title of the view the seat focuses, `None` if no view has
+    /// focus or it has no title.
+    pub fn set_focused_view(&mut self, seat: &Seat, title: Option<&str>) {
+        let seat = seat.weak_reference();
+        let title = title.unwrap_or("");
+        {
+            let status = self.state.seat(&seat);
+            if status.focused_view == title {
+                return
+            }
+            status.focused_view = title.into();
+        }
+        for resource in self.state.seat_resources(&seat) {
+            unsafe { send_string(resource, SEAT_FOCUSED_VIEW, title) }
+        }
+    }
+
+    /// Set the name of the keybinding mode of the seat, e.g "resize".
+    pub fn set_mode(&mut self, seat: &Seat, mode: &str) {
+        let seat = seat.weak_reference();
+        {
+            let status = self.state.seat(&seat);
+            if status.mode == mode {
+                return
+            }
+            status.mode = mode.into();
+        }
+        for resource in self.state.seat_resources(&seat) {
+            unsafe { send_mode(resource, mode) }
+        }
+    }
+
+    /// Get what bars are told about the output.
+    pub fn output_status(&self, output: &Output) -> Option<&OutputStatus> {
+        let output = output.weak_reference();
+        self.state
+            .outputs
+            .iter()
+            .find(|&&(ref other, _)| *other == output)
+            .map(|&(_, ref status)| status)
+    }
+
+    /// Get what bars are told about the seat.
+    pub fn seat_status(&self, seat: &Seat) -> Option<&SeatStatus> {
+        let seat = seat.weak_reference();
+        self.state
+            .seats
+            .iter()
+            .find(|&&(ref other, _)| *other == seat)
+            .map(|&(_, ref status)| status)
+    }
+
+    /// Forget the state of outputs and seats that were destroyed.
+    ///
+    /// Call it when an output or a seat is destroyed.
+    pub fn prune(&mut self) {
+        let state = &mut *self.state;
+        state.outputs.retain(|&(ref output, _)| output.is_alive());
+        state.seats.retain(|&(ref seat, _)| seat.is_alive());
+        for &mut (_, ref mut status) in &mut state.seats {
+            let dead = match status.focused_output {
+                Some(ref output) => !output.is_alive(),
+                None => false
+            };
+            if dead {
+                status.focused_output = None
+            }
+        }
+    }
+}
+
+impl Drop for ZManagerV1 {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_global_destroy, self.global);
+            // The objects of the clients that are still around become inert.
+            let state = &mut *self.state;
+            let mut resources = state.managers.clone();
+            resources.extend(state.output_statuses.iter().map(|&(resource, _)| resource));
+            resources.extend(state.seat_statuses.iter().map(|&(resource, _)| resource));
+            for resource in resources {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_resource_set_user_data,
+                              resource,
+                              ptr::null_mut());
+            }
+        }
+    }
+}
+
+#[repr(C)]
+struct ManagerInterface {
+    destroy: unsafe extern "C" fn(*mut wl_client, *mut wl_resource),
+    get_river_output_status:
+        unsafe extern "C" fn(*mut wl_client, *mut wl_resource, u32, *mut wl_resource),
+    get_river_seat_status:
+        unsafe extern "C" fn(*mut wl_client, *mut wl_resource, u32, *mut wl_resource)
+}
+
+#[repr(C)]
+struct StatusInterface {
+    destroy: unsafe extern "C" fn(*mut wl_client, *mut wl_resource)
+}
+
+static MANAGER_INTERFACE: ManagerInterface =
+    ManagerInterface { destroy: destroy_resource,
+                       get_river_output_status,
+                       get_river_seat_status };
+
+static STATUS_INTERFACE: StatusInterface = StatusInterface { destroy: destroy_resource };
+
+unsafe extern "C" fn bind_manager(client: *mut wl_client,
+                                  data: *mut libc::c_void,
+                                  version: u32,
+                                  id: u32) {
+    let resource = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                 wl_resource_create,
+                                 client,
+                                 &zriver_status_manager_v1_interface as *const _ as _,
+                                 version as c_int,
+                                 id);
+    if resource.is_null() {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_post_no_memory, client);
+        return
+    }
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_set_implementation,
+                  resource,
+                  &MANAGER_INTERFACE as *const _ as *const _,
+                  data,
+                  Some(manager_destroyed));
+    (*(data as *mut State)).managers.push(resource);
+}
+
+unsafe extern "C" fn get_river_output_status(client: *mut wl_client,
+                                             manager: *mut wl_resource,
+                                             id: u32,
+                                             output_resource: *mut wl_resource) {
+    let state = user_data(manager);
+    let resource = match create_status(client, manager, id, true) {
+        Some(resource) => resource,
+        None => return
+    };
+    let output = wlr_output_from_resource(output_resource as _);
+    if state.is_null() || output.is_null() {
+        // Inert, the output is gone already.
+        return
+    }
+    let state = &mut *state;
+    let output = output::Handle::from_ptr(output);
+    state.output_statuses.push((resource, output.clone()));
+    let status = state.output(&output).clone();
+    send_focused_tags(resource, status.focused_tags);
+    send_view_tags(resource, &status.view_tags);
+    send_urgent_tags(resource, status.urgent_tags);
+}
+
+unsafe extern "C" fn get_river_seat_status(client: *mut wl_client,
+                                           manager: *mut wl_resource,
+                                           id: u32,
+                                           seat_resource: *mut wl_resource) {
+    let state = user_data(manager);
+    let resource = match create_status(client, manager, id, false) {
+        Some(resource) => resource,
+        None => return
+    };
+    let seat_client = wlr_seat_client_from_resource(seat_resource as _);
+    if state.is_null() || seat_client.is_null() {
+        return
+    }
+    let state = &mut *state;
+    let seat = seat::Handle::from_ptr((*seat_client).seat);
+    state.seat_statuses.push((resource, seat.clone()));
+    let status = state.seat(&seat).clone();
+    if let Some(ref output) = status.focused_output {
+        send_output(resource, SEAT_FOCUSED_OUTPUT, output);
+    }
+    send_string(resource, SEAT_FOCUSED_VIEW, &status.focused_view);
+    send_mode(resource, &status.mode);
+}
+
+/// Create an output or seat status object, with the state of the manager.
+unsafe fn create_status(client: *mut wl_client,
+                        manager: *mut wl_resource,
+                        id: u32,
+                        output: bool)
+                        -> Option<*mut wl_resource> {
+    let interface = if output {
+        &zriver_output_status_v1_interface
+    } else {
+        &zriver_seat_status_v1_interface
+    };
+    let resource = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                 wl_resource_create,
+                                 client,
+                                 interface as *const _ as _,
+                                 ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                               wl_resource_get_version,
+                                               manager),
+                                 id);
+    if resource.is_null() {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_post_no_memory, client);
+        return None
+    }
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_set_implementation,
+                  resource,
+                  &STATUS_INTERFACE as *const _ as *const _,
+                  user_data(manager) as *mut _,
+                  Some(status_destroyed));
+    Some(resource)
+}
+
+unsafe extern "C" fn destroy_resource(_client: *mut wl_client, resource: *mut wl_resource) {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_destroy, resource);
+}
+
+unsafe extern "C" fn manager_destroyed(resource: *mut wl_resource) {
+    let state = user_data(resource);
+    if !state.is_null() {
+        (*state).managers.retain(|&manager| manager != resource);
+    }
+}
+
+unsafe extern "C" fn status_destroyed(resource: *mut wl_resource) {
+    let state = user_data(resource);
+    if !state.is_null() {
+        (*state).output_statuses.retain(|&(status, _)| status != resource);
+        (*state).seat_statuses.retain(|&(status, _)| status != resource);
+    }
+}
+
+unsafe fn user_data(resource: *mut wl_resource) -> *mut State {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_user_data, resource) as *mut State
+}
+
+unsafe fn version(resource: *mut wl_resource) -> c_int {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_version, resource)
+}
+
+unsafe fn send_focused_tags(resource: *mut wl_resource, tags: u32) {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  resource,
+                  OUTPUT_FOCUSED_TAGS,
+                  tags);
+}
+
+unsafe fn send_view_tags(resource: *mut wl_resource, tags: &[u32]) {
+    let mut tags = tags.to_vec();
+    // libwayland copies the array, it only has to live until it's sent.
+    let mut array = wl_array { size: tags.len() * 4,
+                               alloc: tags.capacity() * 4,
+                               data: tags.as_mut_ptr() as *mut _ };
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  resource,
+                  OUTPUT_VIEW_TAGS,
+                  &mut array as *mut wl_array);
+}
+
+unsafe fn send_urgent_tags(resource: *mut wl_resource, tags: u32) {
+    if version(resource) >= 2 {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_resource_post_event,
+                      resource,
+                      OUTPUT_URGENT_TAGS,
+                      tags);
+    }
+}
+
+unsafe fn send_mode(resource: *mut wl_resource, mode: &str) {
+    if version(resource) >= 3 {
+        send_string(resource, SEAT_MODE, mode)
+    }
+}
+
+unsafe fn send_string(resource: *mut wl_resource, opcode: u32, string: &str) {
+    let string = safe_as_cstring(string);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  resource,
+                  opcode,
+                  string.as_ptr());
+}
+
+/// Send an event with the output as argument, once for each `wl_output` of
+/// the client. Clients that didn't bind the output don't get it.
+unsafe fn send_output(resource: *mut wl_resource, opcode: u32, output: &output::Handle) {
+    if !output.is_alive() {
+        return
+    }
+    let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_client, resource);
+    for output_resource in output_resources(output.as_ptr(), client) {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_resource_post_event,
+                      resource,
+                      opcode,
+                      output_resource);
+    }
+}
+
+unsafe fn output_resources(output: *mut wlr_output,
+                           client: *mut wl_client)
+                           -> Vec<*mut wl_resource> {
+    let mut resources = Vec::new();
+    let head = &mut (*output).resources as *mut wl_list;
+    let mut link = (*head).next;
+    while link != head {
+        let resource = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_from_link, link as _);
+        if ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_resource_get_client, resource) == client {
+            resources.push(resource)
+        }
+        link = (*link).next;
+    }
+    resources
+}
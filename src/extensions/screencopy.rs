@@ -2,28 +2,273 @@
 //!
 //! Warning: This protocol is unstable and can change in the future
 //! Current Protocol: https://github.com/swaywm/wlroots/blob/master/protocol/wlr-screencopy-unstable-v1.xml  
+//!
+//! This is what screenshot and screen recording tools like grim and
+//! wf-recorder use. Enable it with `compositor::Builder::screencopy_manager`,
+//! or with `compositor::Builder::screencopy_handler` to decide which captures
+//! are allowed: every frame a client asks to be copied is passed to the
+//! `Handler` right before the output is drawn, and the client is told the
+//! copy failed if the handler refuses it.
+//!
+//! The handler can also hand the client a single window instead of the
+//! output, see `Handler::window`. Those are drawn offscreen when
+//! `ZManagerV1::copy_windows` is called while rendering the output.
+
+use std::slice;
+
+use libc;
+use wayland_sys::server::{wl_display as wl_server_display, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_output, wlr_screencopy_frame_v1, wlr_screencopy_manager_v1,
+                  wlr_screencopy_manager_v1_create, wlr_screencopy_manager_v1_destroy,
+                  wl_shm_format::{WL_SHM_FORMAT_ARGB8888, WL_SHM_FORMAT_XRGB8888}};
+
+use {area::{Area, Origin, Size},
+     capture::{self, WindowCapture},
+     output,
+     render::Renderer,
+     utils::{self, current_time, Handleable}};
+
+/// The opcode of the `flags` event of `zwlr_screencopy_frame_v1`.
+const FRAME_FLAGS: u32 = 1;
+/// The opcode of the `ready` event of `zwlr_screencopy_frame_v1`.
+const FRAME_READY: u32 = 2;
+/// The opcode of the `failed` event of `zwlr_screencopy_frame_v1`.
+const FRAME_FAILED: u32 = 3;
+
+/// A request from a client to copy a frame of an output.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// The output that would be copied.
+    pub output: output::Handle,
+    /// The part of the output that would be copied, in buffer coordinates.
+    pub area: Area,
+    /// Whether the client wants the cursor to be part of the copy.
+    pub overlay_cursor: bool,
+    /// The process id of the client, if it's known.
+    pub client_pid: Option<libc::pid_t>
+}
+
+pub trait Handler {
+    /// A client wants a copy of the next frame of an output.
+    ///
+    /// Return `false` to refuse, e.g because the output shows something
+    /// sensitive or the client isn't trusted. Allows every copy by default.
+    fn frame_requested(&mut self, frame: &Frame) -> bool {
+        let _ = frame;
+        true
+    }
 
-use wayland_sys::server::wl_display as wl_server_display;
-use wlroots_sys::{wl_display, wlr_screencopy_manager_v1, wlr_screencopy_manager_v1_create,
-    wlr_screencopy_manager_v1_destroy};
+    /// Copy a window into the frame instead of the output, e.g the window
+    /// the user picked to share.
+    ///
+    /// Asked for every copy that was allowed. The window is drawn on its own
+    /// into the top left corner of the client's buffer, anything it doesn't
+    /// cover is left transparent. Copies the output by default.
+    fn window(&mut self, frame: &Frame) -> Option<capture::Window> {
+        let _ = frame;
+        None
+    }
+}
 
-#[derive(Debug)]
 /// Manager that offers requests to start capturing from a source
 pub struct ZManagerV1 {
-    manager: *mut wlr_screencopy_manager_v1
+    manager: *mut wlr_screencopy_manager_v1,
+    handler: Option<Box<Handler>>,
+    /// Frames the handler already decided on that are still around.
+    reviewed: Vec<*mut wlr_screencopy_frame_v1>,
+    /// Frames that get a copy of a window instead of their output.
+    windows: Vec<(*mut wlr_screencopy_frame_v1, capture::Window)>
 }
 
 impl ZManagerV1 {
-    pub(crate) unsafe fn new(display: *mut wl_server_display) -> Option<Self> {
+    pub(crate) unsafe fn new(display: *mut wl_server_display,
+                             handler: Option<Box<Handler>>)
+                             -> Option<Self> {
         let manager_raw = wlr_screencopy_manager_v1_create(display as *mut wl_display);
 
         if !manager_raw.is_null() {
-            Some(ZManagerV1 { manager: manager_raw })
+            Some(ZManagerV1 { manager: manager_raw,
+                              handler,
+                              reviewed: Vec::new(),
+                              windows: Vec::new() })
         } else {
             None
         }
     }
 
+    /// Get the outputs clients are waiting to copy the next frame of.
+    pub fn pending_outputs(&self) -> Vec<output::Handle> {
+        let mut outputs: Vec<output::Handle> = Vec::new();
+        unsafe {
+            wl_list_for_each!((*self.manager).frames,
+                              link,
+                              (frame: wlr_screencopy_frame_v1) => {
+                let output = (*frame).output;
+                if !output.is_null() && !(*output).data.is_null()
+                    && !outputs.iter().any(|handle| handle.as_ptr() == output) {
+                    outputs.push(output::Handle::from_ptr(output))
+                }
+            });
+        }
+        outputs
+    }
+
+    /// Pass the frames waiting to be copied from the output to the handler,
+    /// before the output swaps its buffers and they are copied.
+    pub(crate) unsafe fn review_frames(&mut self, output: *mut wlr_output) {
+        let handler = match self.handler {
+            Some(ref mut handler) => handler,
+            None => return
+        };
+        let mut frames = Vec::new();
+        wl_list_for_each!((*self.manager).frames,
+                          link,
+                          (frame: wlr_screencopy_frame_v1) => {
+            frames.push(frame)
+        });
+        self.reviewed.retain(|frame| frames.contains(frame));
+        for frame in frames {
+            // Frames are only copied once the client gave them a buffer.
+            if (*frame).output != output || (*frame).buffer.is_null()
+                || self.reviewed.contains(&frame) {
+                continue
+            }
+            self.reviewed.push(frame);
+            if (*output).data.is_null() {
+                continue
+            }
+            let area = (*frame).box_;
+            let request = Frame { output: output::Handle::from_ptr(output),
+                                  area: Area::new(Origin::new(area.x, area.y),
+                                                  Size::new(area.width, area.height)),
+                                  overlay_cursor: (*frame).overlay_cursor,
+                                  client_pid: client_pid(frame) };
+            if !handler.frame_requested(&request) {
+                wlr_log!(WLR_INFO, "Refused screencopy of output {:p}", output);
+                refuse(frame);
+            } else if let Some(window) = handler.window(&request) {
+                unhook(frame);
+                self.windows.push((frame, window));
+            }
+        }
+    }
+
+    /// Copy the windows the `Handler` picked into the frames that wait for
+    /// the output being rendered.
+    ///
+    /// Call this while rendering the output, before the `Renderer` is
+    /// dropped.
+    pub fn copy_windows(&mut self, renderer: &mut Renderer) {
+        if self.windows.is_empty() {
+            return
+        }
+        unsafe {
+            let mut frames = Vec::new();
+            wl_list_for_each!((*self.manager).frames,
+                              link,
+                              (frame: wlr_screencopy_frame_v1) => {
+                frames.push(frame)
+            });
+            // The client may have destroyed the frame in the meantime.
+            self.windows.retain(|&(frame, _)| frames.contains(&frame));
+            let output = renderer.output.as_ptr();
+            let mut index = 0;
+            while index < self.windows.len() {
+                if (*self.windows[index].0).output != output {
+                    index += 1;
+                    continue
+                }
+                let (frame, window) = self.windows.remove(index);
+                match capture::capture_view(renderer, &window) {
+                    Some(ref capture) if copy_into(frame, capture) => ready(frame),
+                    _ => {
+                        wlr_log!(WLR_ERROR, "Could not copy a window for screencopy");
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                      wl_resource_post_event,
+                                      (*frame).resource as _,
+                                      FRAME_FAILED);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Keep the frame from being copied from its output.
+unsafe fn unhook(frame: *mut wlr_screencopy_frame_v1) {
+    // The links are reset so destroying the frame later is safe.
+    let link = &mut (*frame).output_swap_buffers.link as *mut _ as *mut _;
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_remove, link);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_list_init, link);
+}
+
+/// Stop the frame from being copied, and tell the client.
+unsafe fn refuse(frame: *mut wlr_screencopy_frame_v1) {
+    unhook(frame);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  (*frame).resource as _,
+                  FRAME_FAILED);
+}
+
+/// Copy the window into the buffer of the frame.
+unsafe fn copy_into(frame: *mut wlr_screencopy_frame_v1, capture: &WindowCapture) -> bool {
+    let buffer = (*frame).buffer;
+    // XRGB8888 is laid out just like ARGB8888.
+    let format_matches = match (*frame).format {
+        WL_SHM_FORMAT_ARGB8888 | WL_SHM_FORMAT_XRGB8888 => true,
+        _ => false
+    };
+    if buffer.is_null() || !format_matches {
+        return false
+    }
+    let stride = (*frame).stride as usize;
+    let (width, height) = ((*frame).box_.width as usize, (*frame).box_.height as usize);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_shm_buffer_begin_access, buffer as _);
+    let data = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_shm_buffer_get_data, buffer as _);
+    let data = slice::from_raw_parts_mut(data as *mut u8, stride * height);
+    let copied = (capture.width as usize).min(width) * 4;
+    for row in 0..height {
+        let dest = &mut data[row * stride..row * stride + width * 4];
+        let filled = if row < capture.height as usize {
+            let src = row * capture.stride as usize;
+            dest[..copied].copy_from_slice(&capture.data[src..src + copied]);
+            copied
+        } else {
+            0
+        };
+        for byte in &mut dest[filled..] {
+            *byte = 0
+        }
+    }
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_shm_buffer_end_access, buffer as _);
+    true
+}
+
+/// Tell the client its copy is done.
+unsafe fn ready(frame: *mut wlr_screencopy_frame_v1) {
+    let time = current_time();
+    let secs = time.as_secs();
+    // No flags, the rows are top to bottom.
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  (*frame).resource as _,
+                  FRAME_FLAGS,
+                  0u32);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_post_event,
+                  (*frame).resource as _,
+                  FRAME_READY,
+                  (secs >> 32) as u32,
+                  secs as u32,
+                  time.subsec_nanos());
+}
+
+unsafe fn client_pid(frame: *mut wlr_screencopy_frame_v1) -> Option<libc::pid_t> {
+    let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                               wl_resource_get_client,
+                               (*frame).resource as _);
+    utils::client_pid(client as _)
 }
 
 impl Drop for ZManagerV1 {
@@ -0,0 +1,423 @@
+//! Support for the Tablet (Version 2) Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://cgit.freedesktop.org/wayland/wayland-protocols/tree/unstable/tablet/tablet-unstable-v2.xml
+//!
+//! Drawing programs like Krita and GIMP use this to read the pressure, tilt
+//! and buttons of graphics tablets, instead of only seeing the pointer
+//! motion the tablet is turned into. Enable it with
+//! `compositor::Builder::tablet_manager`.
+//!
+//! Tablets and pads have to be announced on a seat before clients see them,
+//! usually when the input manager hands them out:
+//!
+//! ```rust,no_run,ignore
+//! tablet_manager.add_tablet(seat, tablet_tool);
+//! tablet_manager.add_pad(seat, tablet_pad);
+//! ```
+//!
+//! After that the events of the tablet are forwarded from the `cursor::Handler`
+//! tablet callbacks, together with the surface under the tool, and the events
+//! of the pad from its `tablet_pad::Handler`. Tools (pens, erasers, ...) are
+//! announced automatically the first time they are used.
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, wl_display as wl_server_display,
+                          WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_input_device, wlr_seat, wlr_surface, wlr_tablet_create,
+                  wlr_tablet_manager_v2, wlr_tablet_pad_create, wlr_tablet_tool,
+                  wlr_tablet_tool_create, wlr_tablet_v2_create, wlr_tablet_v2_tablet,
+                  wlr_tablet_v2_tablet_pad, wlr_tablet_v2_tablet_pad_notify_button,
+                  wlr_tablet_v2_tablet_pad_notify_enter, wlr_tablet_v2_tablet_pad_notify_leave,
+                  wlr_tablet_v2_tablet_pad_notify_ring, wlr_tablet_v2_tablet_pad_notify_strip,
+                  wlr_tablet_v2_tablet_tool, wlr_tablet_v2_tablet_tool_notify_button,
+                  wlr_tablet_v2_tablet_tool_notify_distance,
+                  wlr_tablet_v2_tablet_tool_notify_down, wlr_tablet_v2_tablet_tool_notify_motion,
+                  wlr_tablet_v2_tablet_tool_notify_pressure,
+                  wlr_tablet_v2_tablet_tool_notify_proximity_in,
+                  wlr_tablet_v2_tablet_tool_notify_proximity_out,
+                  wlr_tablet_v2_tablet_tool_notify_rotation,
+                  wlr_tablet_v2_tablet_tool_notify_slider, wlr_tablet_v2_tablet_tool_notify_tilt,
+                  wlr_tablet_v2_tablet_tool_notify_up, wlr_tablet_v2_tablet_tool_notify_wheel,
+                  wlr_surface_accepts_tablet_v2, wlr_button_state, wlr_tablet_tool_axes::*,
+                  wlr_tablet_pad_ring_source::WLR_TABLET_PAD_RING_SOURCE_FINGER,
+                  wlr_tablet_pad_strip_source::WLR_TABLET_PAD_STRIP_SOURCE_FINGER,
+                  wlr_tablet_tool_proximity_state::WLR_TABLET_TOOL_PROXIMITY_IN,
+                  wlr_tablet_tool_tip_state::WLR_TABLET_TOOL_TIP_DOWN,
+                  zwp_tablet_pad_v2_button_state::{self, *}};
+
+use {input::{tablet_pad::{self, TabletPad}, tablet_tool::{self, TabletTool}},
+     seat::Seat,
+     surface::{self, Surface},
+     utils::Handleable};
+
+/// Something wlroots created for the protocol, which is freed along with
+/// the device or tool it was created for.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    /// The `wlr_input_device` or `wlr_tablet_tool` the entry was made for.
+    /// Null once it was destroyed.
+    key: *mut libc::c_void,
+    /// The `wlr_tablet_v2_*` object made for it.
+    object: *mut libc::c_void,
+    seat: *mut wlr_seat,
+    /// The surface a pad was entered into.
+    focus: Option<surface::Handle>
+}
+
+wayland_listener!(pub(crate) EntryWatcher, Entry, [
+    destroy_listener => destroy_notify: |this: &mut EntryWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        // wlroots frees the object too, the entry is pruned later.
+        this.data.key = ::std::ptr::null_mut();
+        this.data.object = ::std::ptr::null_mut();
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_init,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+    };
+]);
+
+impl Drop for EntryWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.destroy_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+/// Manager that lets clients use graphics tablets
+pub struct ZManagerV2 {
+    manager: *mut wlr_tablet_manager_v2,
+    tablets: Vec<Box<EntryWatcher>>,
+    pads: Vec<Box<EntryWatcher>>,
+    tools: Vec<Box<EntryWatcher>>
+}
+
+impl ZManagerV2 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display) -> Option<Self> {
+        let manager_raw = wlr_tablet_v2_create(display as *mut wl_display);
+
+        if !manager_raw.is_null() {
+            Some(ZManagerV2 { manager: manager_raw,
+                              tablets: Vec::new(),
+                              pads: Vec::new(),
+                              tools: Vec::new() })
+        } else {
+            None
+        }
+    }
+
+    /// Make a tablet available to the clients of the seat.
+    ///
+    /// A tablet can only be on one seat. Returns false if it already is.
+    pub fn add_tablet(&mut self, seat: &Seat, tablet: &TabletTool) -> bool {
+        unsafe {
+            let device = tablet.input_device().as_ptr();
+            if find(&mut self.tablets, device as _).is_some() {
+                return false
+            }
+            let tablet = wlr_tablet_create(self.manager, seat.as_ptr(), device);
+            if tablet.is_null() {
+                return false
+            }
+            let entry = track(&mut (*device).events.destroy as *mut _ as _,
+                              device as _,
+                              tablet as _,
+                              seat.as_ptr());
+            self.tablets.push(entry);
+            true
+        }
+    }
+
+    /// Make a tablet pad available to the clients of the seat.
+    ///
+    /// A pad can only be on one seat. Returns false if it already is.
+    pub fn add_pad(&mut self, seat: &Seat, pad: &TabletPad) -> bool {
+        unsafe {
+            let device = pad.input_device().as_ptr();
+            if find(&mut self.pads, device as _).is_some() {
+                return false
+            }
+            let pad = wlr_tablet_pad_create(self.manager, seat.as_ptr(), device);
+            if pad.is_null() {
+                return false
+            }
+            let entry = track(&mut (*device).events.destroy as *mut _ as _,
+                              device as _,
+                              pad as _,
+                              seat.as_ptr());
+            self.pads.push(entry);
+            true
+        }
+    }
+
+    /// Determines if the client of the surface can receive events from the
+    /// tablet, i.e it bound the protocol on the seat of the tablet.
+    ///
+    /// If it can't, the compositor should emulate pointer events instead.
+    pub fn accepts_tablet(&mut self, tablet: &TabletTool, surface: &Surface) -> bool {
+        unsafe {
+            match find(&mut self.tablets, tablet.input_device().as_ptr() as _) {
+                Some(entry) => {
+                    wlr_surface_accepts_tablet_v2(entry.object as _, surface.as_ptr())
+                }
+                None => false
+            }
+        }
+    }
+
+    /// Forward a proximity event of a tool to the surface under it.
+    ///
+    /// `focus` is that surface with the position of the tool on it, in
+    /// surface coordinates. When the tool leaves proximity it leaves the
+    /// surface it was on as well.
+    pub fn notify_proximity(&mut self,
+                            event: &tablet_tool::event::Proximity,
+                            focus: Option<(&Surface, f64, f64)>) {
+        unsafe {
+            let event = event.as_ptr();
+            let (tablet, tool) = match self.tool((*event).device, (*event).tool) {
+                Some(tool) => tool,
+                None => return
+            };
+            if (*event).state == WLR_TABLET_TOOL_PROXIMITY_IN {
+                set_tool_focus(tablet, tool, focus);
+            } else {
+                set_tool_focus(tablet, tool, None);
+            }
+        }
+    }
+
+    /// Forward the axes that changed in the event, moving the tool to the
+    /// surface under it.
+    ///
+    /// `focus` is that surface with the position of the tool on it, in
+    /// surface coordinates.
+    pub fn notify_axis(&mut self,
+                       event: &tablet_tool::event::Axis,
+                       focus: Option<(&Surface, f64, f64)>) {
+        unsafe {
+            let event = event.as_ptr();
+            let (tablet, tool) = match self.tool((*event).device, (*event).tool) {
+                Some(tool) => tool,
+                None => return
+            };
+            set_tool_focus(tablet, tool, focus);
+            if (*tool).focused_surface.is_null() {
+                return
+            }
+            let updated = (*event).updated_axes;
+            let has = |axis| updated & axis as u32 != 0;
+            if has(WLR_TABLET_TOOL_AXIS_PRESSURE) {
+                wlr_tablet_v2_tablet_tool_notify_pressure(tool, (*event).pressure);
+            }
+            if has(WLR_TABLET_TOOL_AXIS_DISTANCE) {
+                wlr_tablet_v2_tablet_tool_notify_distance(tool, (*event).distance);
+            }
+            if has(WLR_TABLET_TOOL_AXIS_TILT_X) || has(WLR_TABLET_TOOL_AXIS_TILT_Y) {
+                wlr_tablet_v2_tablet_tool_notify_tilt(tool, (*event).tilt_x, (*event).tilt_y);
+            }
+            if has(WLR_TABLET_TOOL_AXIS_ROTATION) {
+                wlr_tablet_v2_tablet_tool_notify_rotation(tool, (*event).rotation);
+            }
+            if has(WLR_TABLET_TOOL_AXIS_SLIDER) {
+                wlr_tablet_v2_tablet_tool_notify_slider(tool, (*event).slider);
+            }
+            if has(WLR_TABLET_TOOL_AXIS_WHEEL) {
+                wlr_tablet_v2_tablet_tool_notify_wheel(tool, (*event).wheel_delta, 0);
+            }
+        }
+    }
+
+    /// Forward the tip of a tool touching or leaving the tablet.
+    pub fn notify_tip(&mut self, event: &tablet_tool::event::Tip) {
+        unsafe {
+            let event = event.as_ptr();
+            let (_, tool) = match self.tool((*event).device, (*event).tool) {
+                Some(tool) => tool,
+                None => return
+            };
+            if (*event).state == WLR_TABLET_TOOL_TIP_DOWN {
+                wlr_tablet_v2_tablet_tool_notify_down(tool);
+            } else {
+                wlr_tablet_v2_tablet_tool_notify_up(tool);
+            }
+        }
+    }
+
+    /// Forward a button on a tool being pressed or released.
+    pub fn notify_button(&mut self, event: &tablet_tool::event::Button) {
+        unsafe {
+            let event = event.as_ptr();
+            let (_, tool) = match self.tool((*event).device, (*event).tool) {
+                Some(tool) => tool,
+                None => return
+            };
+            wlr_tablet_v2_tablet_tool_notify_button(tool,
+                                                    (*event).button,
+                                                    button_state((*event).state));
+        }
+    }
+
+    /// Give the pad to a surface, usually the one with keyboard focus, so
+    /// its buttons, rings and strips are sent there. `None` takes it away.
+    ///
+    /// `tablet` is the tablet the pad belongs to.
+    pub fn set_pad_focus(&mut self,
+                         pad: &TabletPad,
+                         tablet: &TabletTool,
+                         surface: Option<&Surface>) {
+        unsafe {
+            let tablet = match find(&mut self.tablets, tablet.input_device().as_ptr() as _) {
+                Some(entry) => entry.object as *mut wlr_tablet_v2_tablet,
+                None => return
+            };
+            let entry = match find(&mut self.pads, pad.input_device().as_ptr() as _) {
+                Some(entry) => entry,
+                None => return
+            };
+            let pad = entry.object as *mut wlr_tablet_v2_tablet_pad;
+            if let Some(previous) = entry.focus.take() {
+                previous.run(|previous| {
+                            wlr_tablet_v2_tablet_pad_notify_leave(pad, previous.as_ptr());
+                        })
+                        .ok();
+            }
+            if let Some(surface) = surface {
+                wlr_tablet_v2_tablet_pad_notify_enter(pad, tablet, surface.as_ptr());
+                entry.focus = Some(surface.weak_reference());
+            }
+        }
+    }
+
+    /// Forward a button on the pad being pressed or released.
+    pub fn notify_pad_button(&mut self, pad: &TabletPad, event: &tablet_pad::event::Button) {
+        unsafe {
+            if let Some(pad) = self.pad(pad) {
+                wlr_tablet_v2_tablet_pad_notify_button(pad,
+                                                       event.button() as _,
+                                                       event.time_msec(),
+                                                       button_state(event.state()));
+            }
+        }
+    }
+
+    /// Forward a ring of the pad being used.
+    pub fn notify_pad_ring(&mut self, pad: &TabletPad, event: &tablet_pad::event::Ring) {
+        unsafe {
+            if let Some(pad) = self.pad(pad) {
+                wlr_tablet_v2_tablet_pad_notify_ring(pad,
+                                                     event.ring(),
+                                                     event.position(),
+                                                     event.source() ==
+                                                     WLR_TABLET_PAD_RING_SOURCE_FINGER,
+                                                     event.time_msec());
+            }
+        }
+    }
+
+    /// Forward a strip of the pad being used.
+    pub fn notify_pad_strip(&mut self, pad: &TabletPad, event: &tablet_pad::event::Strip) {
+        unsafe {
+            if let Some(pad) = self.pad(pad) {
+                wlr_tablet_v2_tablet_pad_notify_strip(pad,
+                                                      event.strip(),
+                                                      event.position(),
+                                                      event.source() ==
+                                                      WLR_TABLET_PAD_STRIP_SOURCE_FINGER,
+                                                      event.time_msec());
+            }
+        }
+    }
+
+    unsafe fn pad(&mut self, pad: &TabletPad) -> Option<*mut wlr_tablet_v2_tablet_pad> {
+        find(&mut self.pads, pad.input_device().as_ptr() as _)
+            .map(|entry| entry.object as *mut wlr_tablet_v2_tablet_pad)
+    }
+
+    /// Get the tablet an event came from and the tool that was used on it,
+    /// announcing the tool on the seat of the tablet the first time it's
+    /// used there.
+    unsafe fn tool(&mut self,
+                   device: *mut wlr_input_device,
+                   wlr_tool: *mut wlr_tablet_tool)
+                   -> Option<(*mut wlr_tablet_v2_tablet, *mut wlr_tablet_v2_tablet_tool)> {
+        let (tablet, seat) = match find(&mut self.tablets, device as _) {
+            Some(entry) => (entry.object as *mut wlr_tablet_v2_tablet, entry.seat),
+            None => return None
+        };
+        if wlr_tool.is_null() {
+            return None
+        }
+        self.tools.retain(|watcher| !watcher.data.key.is_null());
+        if let Some(entry) = self.tools
+                                 .iter()
+                                 .find(|watcher| {
+                                           watcher.data.key == wlr_tool as _ &&
+                                           watcher.data.seat == seat
+                                       }) {
+            return Some((tablet, entry.data.object as _))
+        }
+        let tool = wlr_tablet_tool_create(self.manager, seat, wlr_tool);
+        if tool.is_null() {
+            return None
+        }
+        let entry = track(&mut (*wlr_tool).events.destroy as *mut _ as _,
+                          wlr_tool as _,
+                          tool as _,
+                          seat);
+        self.tools.push(entry);
+        Some((tablet, tool))
+    }
+}
+
+/// Start tracking something wlroots created, until `signal` fires.
+unsafe fn track(signal: *mut libc::c_void,
+                key: *mut libc::c_void,
+                object: *mut libc::c_void,
+                seat: *mut wlr_seat)
+                -> Box<EntryWatcher> {
+    let mut watcher = EntryWatcher::new(Entry { key,
+                                                object,
+                                                seat,
+                                                focus: None });
+    wl_signal_add(signal as _, watcher.destroy_listener() as *mut _ as _);
+    watcher
+}
+
+/// Find the live entry for `key`, pruning the dead ones.
+fn find(entries: &mut Vec<Box<EntryWatcher>>, key: *mut libc::c_void) -> Option<&mut Entry> {
+    entries.retain(|watcher| !watcher.data.key.is_null());
+    entries.iter_mut()
+           .find(|watcher| watcher.data.key == key)
+           .map(|watcher| &mut watcher.data)
+}
+
+/// Move a tool to a surface, leaving the surface it was on.
+unsafe fn set_tool_focus(tablet: *mut wlr_tablet_v2_tablet,
+                         tool: *mut wlr_tablet_v2_tablet_tool,
+                         focus: Option<(&Surface, f64, f64)>) {
+    let surface = focus.map(|(surface, _, _)| surface.as_ptr())
+                       .unwrap_or(::std::ptr::null_mut::<wlr_surface>());
+    if (*tool).focused_surface != surface && !(*tool).focused_surface.is_null() {
+        wlr_tablet_v2_tablet_tool_notify_proximity_out(tool);
+    }
+    if let Some((surface, sx, sy)) = focus {
+        if (*tool).focused_surface.is_null() {
+            wlr_tablet_v2_tablet_tool_notify_proximity_in(tool, tablet, surface.as_ptr());
+        }
+        wlr_tablet_v2_tablet_tool_notify_motion(tool, sx, sy);
+    }
+}
+
+fn button_state(state: wlr_button_state) -> zwp_tablet_pad_v2_button_state {
+    match state {
+        wlr_button_state::WLR_BUTTON_PRESSED => ZWP_TABLET_PAD_V2_BUTTON_STATE_PRESSED,
+        wlr_button_state::WLR_BUTTON_RELEASED => ZWP_TABLET_PAD_V2_BUTTON_STATE_RELEASED
+    }
+}
@@ -0,0 +1,79 @@
+//! Support for the Virtual Keyboard (Version 1) Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://github.com/swaywm/wlroots/blob/master/protocol/virtual-keyboard-unstable-v1.xml
+//!
+//! On-screen keyboards and tools like wtype use this to type into other
+//! clients. Enable it with `compositor::Builder::virtual_keyboard_manager`.
+//!
+//! Virtual keyboards are ordinary keyboards as far as the compositor is
+//! concerned: each one is passed to the `keyboard_added` callback of the
+//! input manager, and removed like any other device when the client goes
+//! away. They start out with the default keymap, until the client sends the
+//! one it types with.
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, wl_display as wl_server_display,
+                          WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_virtual_keyboard_manager_v1,
+                  wlr_virtual_keyboard_manager_v1_create,
+                  wlr_virtual_keyboard_manager_v1_destroy, wlr_virtual_keyboard_v1};
+
+use manager::input_manager;
+
+wayland_listener!(pub(crate) VirtualKeyboardWatcher, (), [
+    new_keyboard_listener => new_keyboard_notify: |_this: &mut VirtualKeyboardWatcher,
+                                                   data: *mut libc::c_void,|
+    unsafe {
+        let keyboard = data as *mut wlr_virtual_keyboard_v1;
+        wlr_log!(WLR_DEBUG, "New virtual keyboard {:p}", keyboard);
+        input_manager::add_device(&mut (*keyboard).input_device);
+    };
+]);
+
+impl Drop for VirtualKeyboardWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.new_keyboard_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+/// Manager that lets clients create virtual keyboards
+pub struct ZManagerV1 {
+    manager: *mut wlr_virtual_keyboard_manager_v1,
+    watcher: Box<VirtualKeyboardWatcher>
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display) -> Option<Self> {
+        let manager_raw = wlr_virtual_keyboard_manager_v1_create(display as *mut wl_display);
+
+        if !manager_raw.is_null() {
+            let mut watcher = VirtualKeyboardWatcher::new(());
+            wl_signal_add(&mut (*manager_raw).events.new_virtual_keyboard as *mut _ as _,
+                          watcher.new_keyboard_listener() as *mut _ as _);
+            Some(ZManagerV1 { manager: manager_raw,
+                              watcher })
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for ZManagerV1 {
+    fn drop(&mut self) {
+        unsafe {
+            // The signal goes away with the manager.
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.watcher.new_keyboard_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_init,
+                          &mut (*self.watcher.new_keyboard_listener()).link as *mut _ as _);
+            wlr_virtual_keyboard_manager_v1_destroy(self.manager)
+        }
+    }
+}
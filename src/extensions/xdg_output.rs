@@ -0,0 +1,51 @@
+//! Support for the XDG Output Protocol
+//!
+//! Warning: This protocol is unstable and can change in the future
+//! Current Protocol: https://gitlab.freedesktop.org/wayland/wayland-protocols/blob/main/unstable/xdg-output/xdg-output-unstable-v1.xml
+//!
+//! Tells clients where each output is in the layout and how large it is in
+//! logical coordinates, i.e after scaling and transforming, along with its
+//! name and description. Panels, screenshot tools and screen sharing need
+//! this to make sense of a setup with several outputs. Enable it with
+//! `compositor::Builder::xdg_output_manager`, passing the output layout the
+//! positions are taken from.
+//!
+//! Clients are told again whenever an output in the layout is moved or its
+//! mode, scale or transform changes, there's nothing else to do.
+
+use wayland_sys::server::wl_display as wl_server_display;
+use wlroots_sys::{wl_display, wlr_output_layout, wlr_xdg_output_manager_v1,
+                  wlr_xdg_output_manager_v1_create};
+
+use output::layout;
+
+#[derive(Debug)]
+/// Manager that tells clients the logical geometry of outputs
+///
+/// It's destroyed along with the display, or the output layout it was
+/// created with.
+pub struct ZManagerV1 {
+    _manager: *mut wlr_xdg_output_manager_v1,
+    layout: layout::Handle
+}
+
+impl ZManagerV1 {
+    pub(crate) unsafe fn new(display: *mut wl_server_display,
+                             layout: layout::Handle,
+                             layout_ptr: *mut wlr_output_layout)
+                             -> Option<Self> {
+        let manager_raw = wlr_xdg_output_manager_v1_create(display as *mut wl_display, layout_ptr);
+
+        if !manager_raw.is_null() {
+            Some(ZManagerV1 { _manager: manager_raw,
+                              layout })
+        } else {
+            None
+        }
+    }
+
+    /// Get the output layout the positions of the outputs are taken from.
+    pub fn layout(&self) -> layout::Handle {
+        self.layout.clone()
+    }
+}
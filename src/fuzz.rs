@@ -0,0 +1,329 @@
+//! Running the compositor against arbitrary input, for fuzzing.
+//!
+//! Only available with the `fuzzing` feature, which also turns on internal
+//! assertions in the listener layer. `run` takes a headless compositor and a
+//! byte string, decodes the bytes into a stream of `Action`s (input events,
+//! outputs coming and going, and raw protocol messages from a client) and
+//! feeds them to the compositor, checking after every step that no callback
+//! panicked and that nothing was left borrowed.
+//!
+//! A cargo-fuzz target only needs to build the compositor it wants to test:
+//!
+//! ```rust,no_run,ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     let compositor = compositor::Builder::new().gles2(true)
+//!                                                .input_manager(input_builder())
+//!                                                .output_manager(output_builder())
+//!                                                .xdg_shell_manager(xdg_shell_builder())
+//!                                                .build_headless(State::default());
+//!     wlroots::fuzz::run(compositor, data);
+//! });
+//! ```
+//!
+//! Every byte string decodes to some actions, so the fuzzer never wastes time
+//! on input that is rejected up front.
+
+use std::{io, ptr, time::Duration};
+
+use libc;
+use wayland_sys::server::{wl_client, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_axis_orientation::*, wlr_button_state::*, wlr_key_state::*};
+
+use {backend::remote::{Frame, FrameSink, Remote},
+     compositor::{Compositor, COMPOSITOR_PTR},
+     input::pointer::event::BTN_LEFT,
+     output,
+     snapshot::{self, Snapshot}};
+
+/// The size of the output the input devices are attached to.
+const OUTPUT_SIZE: (u32, u32) = (640, 480);
+
+/// How far the clock moves on every `Action::Dispatch`.
+const FRAME_TIME_MS: u64 = 16;
+
+/// Messages with more arguments than this are cut short.
+const MAX_MESSAGE_ARGS: usize = 8;
+
+/// One step of a fuzzing run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Move the pointer, in the range 0 to 1 relative to the output.
+    PointerMotion { x: f64, y: f64 },
+    PointerButton { button: u32, pressed: bool },
+    PointerAxis { vertical: bool, delta: f64 },
+    /// Press or release an evdev keycode.
+    Key { keycode: u32, pressed: bool },
+    AddOutput { width: u32, height: u32 },
+    /// Remove one of the outputs added with `AddOutput`, by index.
+    RemoveOutput(usize),
+    /// Connect a client, replacing the current one.
+    ConnectClient,
+    DisconnectClient,
+    /// Send a request from the client, without checking that it makes sense.
+    ClientMessage { object: u32, opcode: u16, args: Vec<u32> },
+    /// Advance the clock and dispatch pending events.
+    Dispatch
+}
+
+/// Decode a byte string into actions.
+///
+/// Each action starts with a byte choosing its kind, followed by its
+/// arguments. Input that ends in the middle of an action is padded with
+/// zeroes.
+pub fn decode(data: &[u8]) -> Vec<Action> {
+    let mut bytes = data.iter().cloned();
+    let mut actions = Vec::new();
+    while let Some(kind) = bytes.next() {
+        let mut next = || bytes.next().unwrap_or(0);
+        let action = match kind % 10 {
+            0 => {
+                let x = u16::from(next()) << 8 | u16::from(next());
+                let y = u16::from(next()) << 8 | u16::from(next());
+                Action::PointerMotion { x: f64::from(x) / f64::from(u16::max_value()),
+                                        y: f64::from(y) / f64::from(u16::max_value()) }
+            }
+            1 => Action::PointerButton { button: BTN_LEFT + u32::from(next() % 8),
+                                         pressed: next() % 2 == 0 },
+            2 => Action::PointerAxis { vertical: next() % 2 == 0,
+                                       delta: f64::from(next() as i8) },
+            3 => Action::Key { keycode: u32::from(next()),
+                               pressed: next() % 2 == 0 },
+            4 => Action::AddOutput { width: u32::from(next()) * 16 + 16,
+                                     height: u32::from(next()) * 16 + 16 },
+            5 => Action::RemoveOutput(next() as usize),
+            6 => Action::ConnectClient,
+            7 => Action::DisconnectClient,
+            8 => {
+                let object = u32::from(next());
+                let opcode = u16::from(next() % 16);
+                let argc = next() as usize % (MAX_MESSAGE_ARGS + 1);
+                let args = (0..argc).map(|_| {
+                                             u32::from(next()) << 24 | u32::from(next()) << 16
+                                             | u32::from(next()) << 8
+                                             | u32::from(next())
+                                         })
+                                    .collect();
+                Action::ClientMessage { object, opcode, args }
+            }
+            _ => Action::Dispatch
+        };
+        actions.push(action);
+    }
+    actions
+}
+
+/// Feed the actions decoded from `data` to the compositor.
+///
+/// The compositor must have been built with `build_headless`. It is run
+/// until the actions are used up, or a callback panicked, in which case the
+/// panic is resumed here.
+pub fn run(compositor: Compositor, data: &[u8]) {
+    let actions = decode(data);
+    compositor.run_with(move |_| unsafe {
+        let compositor = &mut *COMPOSITOR_PTR;
+        let mut harness = match Harness::new(compositor) {
+            Some(harness) => harness,
+            None => panic!("Fuzzing needs a compositor built with build_headless")
+        };
+        for action in &actions {
+            harness.step(compositor, action);
+            if compositor.has_panicked() {
+                break
+            }
+            harness.check_invariants(compositor);
+        }
+        harness.disconnect();
+    });
+}
+
+wayland_listener!(ClientWatcher, *mut wl_client, [
+    destroy_listener => destroy_notify: |this: &mut ClientWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        // The client is also destroyed by libwayland, e.g after a protocol
+        // error, which most random requests are.
+        this.data = ptr::null_mut();
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_init,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+    };
+]);
+
+impl Drop for ClientWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.destroy_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+/// Throws the frames of the output away.
+struct NullSink;
+
+impl FrameSink for NullSink {
+    fn frame(&mut self, _frame: Frame) {}
+}
+
+struct Harness {
+    remote: Remote,
+    outputs: Vec<output::Handle>,
+    /// The connected client, and the client end of its socket.
+    client: Option<(Box<ClientWatcher>, libc::c_int)>,
+    time: Duration
+}
+
+impl Harness {
+    unsafe fn new(compositor: &mut Compositor) -> Option<Self> {
+        let remote = Remote::new(compositor, OUTPUT_SIZE.0, OUTPUT_SIZE.1, Box::new(NullSink))?;
+        let mut harness = Harness { remote,
+                                    outputs: Vec::new(),
+                                    client: None,
+                                    time: Duration::from_millis(0) };
+        harness.connect(compositor);
+        Some(harness)
+    }
+
+    unsafe fn step(&mut self, compositor: &mut Compositor, action: &Action) {
+        let time = self.time;
+        match *action {
+            Action::PointerMotion { x, y } => self.remote.pointer_motion(x, y, time),
+            Action::PointerButton { button, pressed } => {
+                let state = if pressed { WLR_BUTTON_PRESSED } else { WLR_BUTTON_RELEASED };
+                self.remote.pointer_button(button, state, time)
+            }
+            Action::PointerAxis { vertical, delta } => {
+                let orientation = if vertical {
+                    WLR_AXIS_ORIENTATION_VERTICAL
+                } else {
+                    WLR_AXIS_ORIENTATION_HORIZONTAL
+                };
+                self.remote.pointer_axis(orientation, delta, time)
+            }
+            Action::Key { keycode, pressed } => {
+                let state = if pressed { WLR_KEY_PRESSED } else { WLR_KEY_RELEASED };
+                self.remote.key(keycode, state, time)
+            }
+            Action::AddOutput { width, height } => {
+                if let Some(output) = compositor.create_virtual_output(width, height) {
+                    self.outputs.push(output)
+                }
+            }
+            Action::RemoveOutput(index) => {
+                if !self.outputs.is_empty() {
+                    let output = self.outputs.remove(index % self.outputs.len());
+                    compositor.destroy_virtual_output(output);
+                }
+            }
+            Action::ConnectClient => self.connect(compositor),
+            Action::DisconnectClient => self.disconnect(),
+            Action::ClientMessage { object, opcode, ref args } => {
+                self.send(object, opcode, args)
+            }
+            Action::Dispatch => {
+                self.time += Duration::from_millis(FRAME_TIME_MS);
+                self.dispatch(compositor)
+            }
+        }
+    }
+
+    /// Check that the last step left the compositor in a sane state.
+    unsafe fn check_invariants(&mut self, compositor: &mut Compositor) {
+        assert!(!compositor.lock.get(), "Compositor left borrowed");
+        // Walks every tracked resource.
+        Snapshot::take(compositor);
+        for output in snapshot::outputs() {
+            assert!(!output.is_borrowed(), "Output left borrowed");
+        }
+        self.outputs.retain(|output| output.is_alive());
+        for output in &self.outputs {
+            assert!(!output.is_borrowed(), "Virtual output left borrowed");
+        }
+    }
+
+    unsafe fn dispatch(&mut self, compositor: &mut Compositor) {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_event_loop_dispatch,
+                      compositor.event_loop,
+                      0);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_display_flush_clients,
+                      compositor.display as _);
+        self.drain();
+    }
+
+    /// Connect a client through a socket pair, so its requests can be
+    /// written directly.
+    unsafe fn connect(&mut self, compositor: &mut Compositor) {
+        self.disconnect();
+        let mut fds = [0; 2];
+        if libc::socketpair(libc::AF_UNIX,
+                            libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+                            0,
+                            fds.as_mut_ptr()) < 0 {
+            wlr_log!(WLR_ERROR, "Could not create socket pair: {}", io::Error::last_os_error());
+            return
+        }
+        // Whatever the compositor sends is read and thrown away, and writing
+        // to a full socket shouldn't block the run.
+        libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK);
+        let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                   wl_client_create,
+                                   compositor.display as _,
+                                   fds[0]);
+        if client.is_null() {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+            return
+        }
+        let mut watcher = ClientWatcher::new(client);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_client_add_destroy_listener,
+                      client,
+                      watcher.destroy_listener());
+        self.client = Some((watcher, fds[1]));
+    }
+
+    unsafe fn disconnect(&mut self) {
+        if let Some((mut watcher, fd)) = self.client.take() {
+            let client = watcher.data;
+            if !client.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*watcher.destroy_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_init,
+                              &mut (*watcher.destroy_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_destroy, client);
+            }
+            libc::close(fd);
+        }
+    }
+
+    /// Write a request to the client end of the socket.
+    unsafe fn send(&mut self, object: u32, opcode: u16, args: &[u32]) {
+        let fd = match self.client {
+            Some((_, fd)) => fd,
+            None => return
+        };
+        let size = ((2 + args.len()) * 4) as u32;
+        let mut message = Vec::with_capacity(2 + args.len());
+        message.push(object);
+        message.push(size << 16 | u32::from(opcode));
+        message.extend_from_slice(args);
+        libc::write(fd, message.as_ptr() as *const libc::c_void, size as usize);
+    }
+
+    /// Throw away everything the compositor sent to the client.
+    unsafe fn drain(&mut self) {
+        let fd = match self.client {
+            Some((_, fd)) => fd,
+            None => return
+        };
+        let mut buffer = [0u8; 4096];
+        while libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) > 0 {}
+    }
+}
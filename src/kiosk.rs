@@ -0,0 +1,677 @@
+//! A preset that runs a single application fullscreen, e.g for digital
+//! signage or an information terminal, without writing a window manager.
+//!
+//! The compositor starts the application itself and shows nothing else:
+//!
+//! ```rust,no_run,ignore
+//! let config = kiosk::Config::new("firefox").arg("--kiosk")
+//!                                           .arg("https://example.com")
+//!                                           .watchdog(Duration::from_secs(30));
+//! compositor::Builder::new().kiosk(config).build_auto(()).run()
+//! ```
+//!
+//! Toplevels of the application are made fullscreen on the first output and
+//! get all keyboard, pointer and touch input. Windows of other clients are
+//! neither shown nor given input. No key bindings are handled, so switching
+//! VTs and other shortcuts don't work either.
+//!
+//! The application is started again whenever it exits or crashes. With a
+//! watchdog it's also killed and started again when it stops answering
+//! pings for too long. It's killed as well when the compositor shuts down.
+//!
+//! The application gets a connection of its own in `WAYLAND_SOCKET`, and
+//! only windows made through that connection are shown. Processes it starts
+//! can still connect through `WAYLAND_DISPLAY`, but their windows aren't.
+//!
+//! The pointer isn't drawn. `compositor::Builder::kiosk` sets the input,
+//! output and XDG shell managers, so they can't be set on the builder as
+//! well.
+
+use std::{cmp, cell::RefCell, ffi::{OsStr, OsString}, process::Command, ptr,
+          time::{Duration, Instant}};
+
+use libc;
+use wayland_sys::server::{wl_display, wl_event_source, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::wlr_xdg_surface_role::WLR_XDG_SURFACE_ROLE_TOPLEVEL;
+
+use {area::{Area, Origin, Size},
+     compositor::{self, Compositor},
+     input::{self, keyboard, pointer, touch},
+     nested::ChildClient,
+     output,
+     render::Renderer,
+     seat::{self, Seat, TouchId},
+     shell::xdg_shell,
+     surface,
+     utils::current_time};
+
+/// How often the application is checked on.
+const SUPERVISE_MS: libc::c_int = 250;
+
+/// How often the application is pinged when there is a watchdog.
+const PING_INTERVAL_MS: u64 = 1000;
+
+thread_local!(static KIOSK: RefCell<Option<Kiosk>> = RefCell::new(None));
+
+/// What the kiosk runs, and how it's supervised.
+#[derive(Debug, Clone)]
+pub struct Config {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    restart: bool,
+    restart_delay: Duration,
+    watchdog: Option<Duration>,
+    background: [f32; 4]
+}
+
+impl Config {
+    /// Run `program`, restarting it a second after it exits, without a
+    /// watchdog and on a black background.
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        Config { program: program.as_ref().to_owned(),
+                 args: Vec::new(),
+                 envs: Vec::new(),
+                 restart: true,
+                 restart_delay: Duration::from_secs(1),
+                 watchdog: None,
+                 background: [0.0, 0.0, 0.0, 1.0] }
+    }
+
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Set an environment variable for the application, on top of the
+    /// environment of the compositor. `WAYLAND_SOCKET` and `WAYLAND_DISPLAY`
+    /// are always set.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.envs.push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    /// Whether to start the application again when it exits. Otherwise the
+    /// compositor terminates, e.g to leave restarting to the init system.
+    pub fn restart(mut self, restart: bool) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    /// How long to wait before starting the application again, so a crash
+    /// on startup doesn't keep the machine busy.
+    pub fn restart_delay(mut self, delay: Duration) -> Self {
+        self.restart_delay = delay;
+        self
+    }
+
+    /// Kill the application when it hasn't answered pings for `timeout`,
+    /// so it's started again.
+    pub fn watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// The color shown while the application isn't, and on other outputs.
+    pub fn background(mut self, color: [f32; 4]) -> Self {
+        self.background = color;
+        self
+    }
+
+    pub(crate) fn ping_interval(&self) -> Option<Duration> {
+        self.watchdog.map(|_| Duration::from_millis(PING_INTERVAL_MS))
+    }
+}
+
+struct Kiosk {
+    config: Config,
+    /// The application and its connection, while it runs.
+    child: Option<ChildClient>,
+    /// When to start the application, if it isn't running.
+    start_at: Option<Instant>,
+    /// Since when the application hasn't answered pings.
+    unresponsive_since: Option<Instant>,
+    socket_name: String,
+    display: *mut wl_display,
+    view: Option<xdg_shell::Handle>,
+    outputs: Vec<output::Handle>,
+    seat: Option<seat::Handle>,
+    keyboard: Option<keyboard::Handle>,
+    /// Where the pointer is, relative to the geometry of the view.
+    pointer: (f64, f64),
+    supervisor: *mut wl_event_source
+}
+
+/// Stop the application and the supervision, before the compositor goes
+/// away.
+pub(crate) fn uninstall() {
+    let kiosk = KIOSK.with(|kiosk| kiosk.try_borrow_mut().ok().and_then(|mut kiosk| kiosk.take()));
+    drop(kiosk)
+}
+
+/// Set up the kiosk state, see `compositor::Builder::kiosk`.
+pub(crate) fn install(config: Config) {
+    KIOSK.with(|kiosk| {
+                   *kiosk.borrow_mut() = Some(Kiosk { config,
+                                                      child: None,
+                                                      start_at: Some(Instant::now()),
+                                                      unresponsive_since: None,
+                                                      socket_name: String::new(),
+                                                      display: ptr::null_mut(),
+                                                      view: None,
+                                                      outputs: Vec::new(),
+                                                      seat: None,
+                                                      keyboard: None,
+                                                      pointer: (0.0, 0.0),
+                                                      supervisor: ptr::null_mut() })
+               })
+}
+
+pub(crate) fn input_manager() -> input::manager::Builder {
+    input::manager::Builder::default().keyboard_added(keyboard_added)
+                                      .pointer_added(pointer_added)
+                                      .touch_added(touch_added)
+}
+
+pub(crate) fn output_manager() -> output::manager::Builder {
+    output::manager::Builder::default().output_added(output_added)
+}
+
+pub(crate) fn xdg_shell_manager() -> xdg_shell::manager::Builder {
+    xdg_shell::manager::Builder::default().surface_added(surface_added)
+}
+
+/// Run `f` on the kiosk state, unless it's not installed or already in use.
+fn with_kiosk<F, R>(f: F) -> Option<R>
+    where F: FnOnce(&mut Kiosk) -> R
+{
+    KIOSK.with(|kiosk| match kiosk.try_borrow_mut() {
+                   Ok(mut kiosk) => kiosk.as_mut().map(f),
+                   Err(_) => None
+               })
+}
+
+impl Kiosk {
+    /// Create the seat and start supervising the application, once there is
+    /// an output to show it on.
+    fn start(&mut self, compositor: &mut Compositor) {
+        if self.seat.is_none() {
+            let seat = Seat::create(compositor, "seat0".into(), Box::new(KioskSeat));
+            seat.run(|seat| seat.set_capabilities(seat::Capability::all())).ok();
+            self.seat = Some(seat);
+        }
+        if self.supervisor.is_null() {
+            self.socket_name = compositor.socket_name().into();
+            self.display = compositor.display as _;
+            unsafe {
+                self.supervisor = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                                wl_event_loop_add_timer,
+                                                compositor.event_loop,
+                                                supervise,
+                                                ptr::null_mut());
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_event_source_timer_update,
+                              self.supervisor,
+                              1);
+            }
+        }
+    }
+
+    fn spawn(&mut self) {
+        self.start_at = None;
+        let mut command = Command::new(&self.config.program);
+        command.args(&self.config.args)
+               .envs(self.config.envs.iter().map(|&(ref key, ref value)| (key, value)))
+               .env("WAYLAND_DISPLAY", &self.socket_name);
+        match unsafe { ChildClient::spawn(self.display, &mut command) } {
+            Ok(child) => {
+                wlr_log!(WLR_INFO, "Started kiosk application with pid {}", child.child.id());
+                self.child = Some(child)
+            }
+            Err(err) => {
+                wlr_log!(WLR_ERROR, "Could not start kiosk application: {}", err);
+                self.exited()
+            }
+        }
+    }
+
+    /// The application is gone, start it again or shut down.
+    fn exited(&mut self) {
+        // Dropping it disconnects it, if it's still connected.
+        self.child = None;
+        self.view = None;
+        self.unresponsive_since = None;
+        if self.config.restart {
+            self.start_at = Some(Instant::now() + self.config.restart_delay);
+        } else {
+            compositor::terminate()
+        }
+    }
+
+    /// Check whether the application exited or hangs.
+    fn supervise(&mut self) {
+        let status = self.child
+                         .as_mut()
+                         .and_then(|child| child.child.try_wait().ok())
+                         .and_then(|status| status);
+        if let Some(status) = status {
+            wlr_log!(WLR_ERROR, "Kiosk application exited with {}", status);
+            self.exited();
+        }
+        let now = Instant::now();
+        if self.child.is_none() && self.start_at.map(|at| at <= now).unwrap_or(false) {
+            self.spawn();
+        }
+        if let (Some(timeout), Some(since)) = (self.config.watchdog, self.unresponsive_since) {
+            if now.duration_since(since) >= timeout {
+                wlr_log!(WLR_ERROR, "Kiosk application stopped responding, killing it");
+                self.unresponsive_since = None;
+                if let Some(child) = self.child.as_mut() {
+                    child.child.kill().ok();
+                }
+            }
+        }
+    }
+
+    /// Whether the surface was made through the connection of the
+    /// application.
+    fn owns(&self, shell: &xdg_shell::Surface) -> bool {
+        self.child
+            .as_ref()
+            .map(|child| child.owns(&shell.surface()))
+            .unwrap_or(false)
+    }
+
+    /// The output the application is shown on.
+    fn output(&self) -> Option<output::Handle> {
+        self.outputs.iter().find(|output| output.is_alive()).cloned()
+    }
+
+    fn size(&self) -> (i32, i32) {
+        self.output()
+            .and_then(|output| output.run(|output| output.effective_resolution()).ok())
+            .unwrap_or((0, 0))
+    }
+
+    /// Make the view cover the output, and give it focus.
+    fn configure(&mut self) {
+        let (width, height) = self.size();
+        let view = match self.view.clone() {
+            Some(view) => view,
+            None => return
+        };
+        view.run(|shell| {
+                     if let Some(&mut xdg_shell::ShellState::TopLevel(ref mut toplevel)) =
+                         shell.state()
+                     {
+                         toplevel.set_size(width as u32, height as u32);
+                         toplevel.set_fullscreen(true);
+                         toplevel.set_activated(true);
+                     }
+                 })
+            .ok();
+        self.focus();
+    }
+
+    fn focus(&mut self) {
+        let (view, seat, keyboard) = match (&self.view, &self.seat, &self.keyboard) {
+            (&Some(ref view), &Some(ref seat), &Some(ref keyboard)) => {
+                (view.clone(), seat.clone(), keyboard.clone())
+            }
+            _ => return
+        };
+        view.run(|shell| {
+            let surface = shell.surface();
+            seat.run(|seat| {
+                keyboard.run(|keyboard| {
+                    seat.set_keyboard(keyboard.input_device());
+                    surface.run(|surface| {
+                        seat.keyboard_notify_enter(surface,
+                                                   &mut keyboard.keycodes(),
+                                                   &mut keyboard.get_modifier_masks())
+                    }).ok();
+                }).ok();
+            }).ok();
+        }).ok();
+    }
+
+    /// Find the surface of the application under a point relative to the
+    /// geometry of the view, with the point in its coordinates.
+    fn surface_at(&self, (x, y): (f64, f64)) -> Option<(surface::Handle, f64, f64)> {
+        let view = self.view.as_ref()?;
+        view.run(|shell| {
+                     let geometry = shell.geometry();
+                     let (sx, sy) = (x + f64::from(geometry.origin.x),
+                                     y + f64::from(geometry.origin.y));
+                     let (mut sub_x, mut sub_y) = (0.0, 0.0);
+                     shell.surface_at(sx, sy, &mut sub_x, &mut sub_y)
+                          .map(|surface| (surface, sub_x, sub_y))
+                 })
+            .ok()
+            .and_then(|found| found)
+    }
+
+    /// Send the pointer to the surface under it.
+    fn pointer_moved(&mut self, time: Duration) {
+        let (width, height) = self.size();
+        let (x, y) = self.pointer;
+        self.pointer = (x.max(0.0).min(f64::from(cmp::max(width, 1) - 1)),
+                        y.max(0.0).min(f64::from(cmp::max(height, 1) - 1)));
+        let found = self.surface_at(self.pointer);
+        let seat = match self.seat.clone() {
+            Some(seat) => seat,
+            None => return
+        };
+        seat.run(|seat| match found {
+                     Some((surface, sx, sy)) => {
+                         surface.run(|surface| seat.pointer_notify_enter(surface, sx, sy)).ok();
+                         seat.pointer_notify_motion(time, sx, sy)
+                     }
+                     None => seat.pointer_clear_focus()
+                 })
+            .ok();
+    }
+
+    fn run_seat<F: FnOnce(&mut Seat)>(&self, f: F) {
+        if let Some(seat) = self.seat.as_ref() {
+            seat.run(f).ok();
+        }
+    }
+}
+
+impl Drop for Kiosk {
+    fn drop(&mut self) {
+        if !self.supervisor.is_null() {
+            unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.supervisor) }
+        }
+        // Dropping the child kills and reaps it.
+        if let Some(child) = self.child.take() {
+            wlr_log!(WLR_INFO, "Stopping kiosk application with pid {}", child.child.id());
+        }
+    }
+}
+
+/// Checks on the application, see `Kiosk::supervise`.
+unsafe extern "C" fn supervise(_data: *mut libc::c_void) -> libc::c_int {
+    if compositor::handle().is_none() {
+        return 0
+    }
+    with_kiosk(|kiosk| {
+                   kiosk.supervise();
+                   ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                 wl_event_source_timer_update,
+                                 kiosk.supervisor,
+                                 SUPERVISE_MS);
+               });
+    0
+}
+
+struct KioskSeat;
+
+impl seat::Handler for KioskSeat {}
+
+struct KioskOutput;
+
+impl output::Handler for KioskOutput {
+    fn on_frame(&mut self, compositor: compositor::Handle, output: output::Handle) {
+        // Other outputs only show the background.
+        let state = with_kiosk(|kiosk| {
+                                   let shown = kiosk.output() == Some(output.clone());
+                                   (kiosk.config.background, kiosk.view.clone().filter(|_| shown))
+                               });
+        let (background, view) = match state {
+            Some(state) => state,
+            None => return
+        };
+        compositor.run(|compositor| {
+                           let renderer = compositor.renderer
+                                                    .as_mut()
+                                                    .expect("Kiosk needs a renderer");
+                           output.run(|output| {
+                                          let mut renderer = renderer.render(output, None);
+                                          renderer.clear(background);
+                                          if let Some(view) = view {
+                                              render_view(&mut renderer, &view);
+                                          }
+                                      })
+                                 .ok();
+                       })
+                  .ok();
+    }
+
+    fn destroyed(&mut self, _: compositor::Handle, _: output::Handle) {
+        // The next output takes over, once this one is gone.
+        with_kiosk(|kiosk| {
+                       kiosk.outputs.retain(|output| output.is_alive());
+                       kiosk.configure()
+                   });
+    }
+}
+
+/// Render the view and its popups, filling the output.
+fn render_view(renderer: &mut Renderer, view: &xdg_shell::Handle) {
+    let scale = f64::from(renderer.output.scale());
+    view.run(|shell| {
+        let geometry = shell.geometry();
+        shell.for_each_surface(|surface, sx, sy| {
+            surface.run(|surface| {
+                let (width, height) = surface.current_state().size();
+                let origin = Origin::new((f64::from(sx - geometry.origin.x) * scale) as i32,
+                                         (f64::from(sy - geometry.origin.y) * scale) as i32);
+                let size = Size::new((f64::from(width) * scale) as i32,
+                                     (f64::from(height) * scale) as i32);
+                renderer.render_surface(surface, Area::new(origin, size));
+                surface.send_frame_done(current_time());
+            }).ok();
+        })
+    }).ok();
+}
+
+fn output_added<'output>(compositor: compositor::Handle,
+                         builder: output::Builder<'output>)
+                         -> Option<output::BuilderResult<'output>> {
+    let result = builder.build_best_mode(KioskOutput);
+    let output = result.output.clone();
+    with_kiosk(|kiosk| {
+                   kiosk.outputs.push(output);
+                   compositor.run(|compositor| kiosk.start(compositor)).ok();
+                   kiosk.configure()
+               });
+    Some(result)
+}
+
+struct KioskView;
+
+impl xdg_shell::Handler for KioskView {
+    fn destroyed(&mut self, _: compositor::Handle, shell: xdg_shell::Handle) {
+        with_kiosk(|kiosk| {
+                       if kiosk.view == Some(shell) {
+                           kiosk.view = None;
+                           kiosk.unresponsive_since = None;
+                       }
+                   });
+    }
+
+    fn responsiveness_changed(&mut self,
+                              _: compositor::Handle,
+                              _: surface::Handle,
+                              shell: xdg_shell::Handle,
+                              responsive: bool) {
+        with_kiosk(|kiosk| {
+                       if kiosk.view == Some(shell) {
+                           kiosk.unresponsive_since =
+                               if responsive { None } else { Some(Instant::now()) };
+                       }
+                   });
+    }
+
+    fn map_request(&mut self, _: compositor::Handle, _: surface::Handle, _: xdg_shell::Handle) {
+        with_kiosk(|kiosk| kiosk.focus());
+    }
+}
+
+fn surface_added(_: compositor::Handle,
+                 shell: xdg_shell::Handle)
+                 -> (Option<Box<xdg_shell::Handler>>, Option<Box<surface::Handler>>) {
+    with_kiosk(|kiosk| {
+        let (toplevel, owned) =
+            shell.run(|shell| (shell.role() == WLR_XDG_SURFACE_ROLE_TOPLEVEL, kiosk.owns(shell)))
+                 .unwrap_or((false, false));
+        if !toplevel {
+            return
+        }
+        if !owned {
+            wlr_log!(WLR_INFO, "Ignoring a window of another client than the kiosk application");
+            return
+        }
+        // A new toplevel of the application replaces the old one, e.g a
+        // browser restoring its session after a crash.
+        kiosk.view = Some(shell.clone());
+        kiosk.configure();
+    });
+    // Popups are shown along with the view they belong to.
+    (Some(Box::new(KioskView)), None)
+}
+
+struct KioskKeyboard;
+
+impl keyboard::Handler for KioskKeyboard {
+    fn on_key(&mut self,
+              _: compositor::Handle,
+              _: keyboard::Handle,
+              event: &keyboard::event::Key) {
+        with_kiosk(|kiosk| {
+                       kiosk.run_seat(|seat| {
+                                          seat.keyboard_notify_key(event.time_msec(),
+                                                                   event.keycode(),
+                                                                   event.key_state() as u32)
+                                      })
+                   });
+    }
+
+    fn modifiers(&mut self, _: compositor::Handle, keyboard: keyboard::Handle) {
+        let mut modifiers = match keyboard.run(|keyboard| keyboard.get_modifier_masks()) {
+            Ok(modifiers) => modifiers,
+            Err(_) => return
+        };
+        with_kiosk(|kiosk| kiosk.run_seat(|seat| seat.keyboard_notify_modifiers(&mut modifiers)));
+    }
+}
+
+fn keyboard_added(_: compositor::Handle,
+                  keyboard: keyboard::Handle)
+                  -> Option<Box<keyboard::Handler>> {
+    with_kiosk(|kiosk| {
+                   kiosk.keyboard = Some(keyboard);
+                   kiosk.focus()
+               });
+    Some(Box::new(KioskKeyboard))
+}
+
+struct KioskPointer;
+
+impl pointer::Handler for KioskPointer {
+    fn on_motion(&mut self,
+                 _: compositor::Handle,
+                 _: pointer::Handle,
+                 event: &pointer::event::Motion) {
+        let (dx, dy) = event.delta();
+        with_kiosk(|kiosk| {
+                       kiosk.pointer = (kiosk.pointer.0 + dx, kiosk.pointer.1 + dy);
+                       kiosk.pointer_moved(Duration::from_millis(u64::from(event.time_msec())))
+                   });
+    }
+
+    fn on_motion_absolute(&mut self,
+                          _: compositor::Handle,
+                          _: pointer::Handle,
+                          event: &pointer::event::AbsoluteMotion) {
+        let (x, y) = event.pos();
+        with_kiosk(|kiosk| {
+                       let (width, height) = kiosk.size();
+                       kiosk.pointer = (x * f64::from(width), y * f64::from(height));
+                       kiosk.pointer_moved(Duration::from_millis(u64::from(event.time_msec())))
+                   });
+    }
+
+    fn on_button(&mut self,
+                 _: compositor::Handle,
+                 _: pointer::Handle,
+                 event: &pointer::event::Button) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        with_kiosk(|kiosk| {
+                       kiosk.run_seat(|seat| {
+                                          seat.pointer_notify_button(time,
+                                                                     event.button(),
+                                                                     event.state() as u32);
+                                      })
+                   });
+    }
+
+    fn on_axis(&mut self,
+               _: compositor::Handle,
+               _: pointer::Handle,
+               event: &pointer::event::Axis) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        with_kiosk(|kiosk| {
+                       kiosk.run_seat(|seat| {
+                                          seat.pointer_notify_axis(time,
+                                                                   event.orientation(),
+                                                                   event.delta(),
+                                                                   event.delta_discrete(),
+                                                                   event.source())
+                                      })
+                   });
+    }
+}
+
+fn pointer_added(_: compositor::Handle, _: pointer::Handle) -> Option<Box<pointer::Handler>> {
+    Some(Box::new(KioskPointer))
+}
+
+struct KioskTouch;
+
+impl touch::Handler for KioskTouch {
+    fn on_down(&mut self, _: compositor::Handle, _: touch::Handle, event: &touch::event::Down) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        let touch_id = TouchId::from_raw(event.touch_id());
+        let (x, y) = event.location();
+        with_kiosk(|kiosk| {
+                       let (width, height) = kiosk.size();
+                       let point = (x * f64::from(width), y * f64::from(height));
+                       if let Some((surface, sx, sy)) = kiosk.surface_at(point) {
+                           kiosk.run_seat(|seat| {
+                               surface.run(|surface| {
+                                   seat.touch_notify_down(surface, time, touch_id, sx, sy);
+                               }).ok();
+                           })
+                       }
+                   });
+    }
+
+    fn on_up(&mut self, _: compositor::Handle, _: touch::Handle, event: &touch::event::Up) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        let touch_id = TouchId::from_raw(event.touch_id());
+        with_kiosk(|kiosk| kiosk.run_seat(|seat| seat.touch_notify_up(time, touch_id)));
+    }
+
+    fn on_motion(&mut self,
+                 _: compositor::Handle,
+                 _: touch::Handle,
+                 event: &touch::event::Motion) {
+        let time = Duration::from_millis(u64::from(event.time_msec()));
+        let touch_id = TouchId::from_raw(event.touch_id());
+        let (x, y) = event.location();
+        with_kiosk(|kiosk| {
+                       let (width, height) = kiosk.size();
+                       let point = (x * f64::from(width), y * f64::from(height));
+                       if let Some((_, sx, sy)) = kiosk.surface_at(point) {
+                           kiosk.run_seat(|seat| seat.touch_notify_motion(time, touch_id, sx, sy))
+                       }
+                   });
+    }
+}
+
+fn touch_added(_: compositor::Handle, _: touch::Handle) -> Option<Box<touch::Handler>> {
+    Some(Box::new(KioskTouch))
+}
@@ -3,10 +3,19 @@
 #![cfg_attr(not(feature = "unstable"), allow(unused_imports, unused_macros))]
 
 #![allow(unused_unsafe)]
+#[cfg(feature = "backtrace")]
+extern crate backtrace;
 #[macro_use]
 extern crate bitflags;
+#[cfg(feature = "logind")]
+extern crate dbus;
 extern crate log;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "sys")]
 extern crate vsprintf;
+#[cfg(feature = "sys")]
 #[macro_use]
 pub extern crate wlroots_sys;
 extern crate wlroots_dehandle;
@@ -15,28 +24,62 @@ pub extern crate xkbcommon;
 
 #[cfg(feature = "unstable")]
 pub use wlroots_dehandle::wlroots_dehandle;
+#[cfg(feature = "sys")]
 pub(crate) use wlroots_sys::wayland_sys;
+#[cfg(feature = "sys")]
 pub(crate) use wlroots_sys::libc;
 
+#[cfg(not(any(feature = "sys", feature = "mock")))]
+compile_error!("wlroots-rs needs the `sys` feature, unless it's built for the `mock` \
+                feature alone");
+
 #[macro_use]
 mod macros;
+#[cfg(any(feature = "unstable", feature = "mock"))]
+pub mod bus;
 #[cfg(feature = "unstable")]
 pub(crate) mod manager;
 #[cfg(feature = "unstable")]
+pub mod capture;
+#[cfg(feature = "unstable")]
 pub mod compositor;
 #[cfg(feature = "unstable")]
+pub mod coords;
+#[cfg(feature = "unstable")]
+pub mod crash;
+#[cfg(feature = "unstable")]
+pub mod environment;
+#[cfg(feature = "unstable")]
 pub(crate) mod events;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+#[cfg(any(feature = "sys", feature = "mock"))]
 mod types;
 #[cfg(feature = "unstable")]
 pub mod extensions;
 #[cfg(feature = "unstable")]
+pub mod kiosk;
+#[cfg(all(feature = "unstable", feature = "logind"))]
+pub(crate) mod logind;
+#[cfg(feature = "unstable")]
+pub mod nested;
+#[cfg(feature = "unstable")]
 pub mod render;
+#[cfg(feature = "unstable")]
+pub mod snapshot;
+#[cfg(feature = "unstable")]
+pub mod stats;
+#[cfg(feature = "unstable")]
+pub mod trace;
 pub mod utils;
 #[cfg(feature = "unstable")]
 pub mod xwayland;
 #[cfg(feature = "unstable")]
 pub mod backend;
+#[cfg(any(feature = "unstable", feature = "mock"))]
+pub mod wm;
 
+#[cfg(any(feature = "sys", feature = "mock"))]
 pub use types::*;
 
 #[cfg(feature = "unstable")]
@@ -0,0 +1,153 @@
+//! Waking the outputs up after a suspend, through logind.
+//!
+//! The session only becomes inactive when switching away from the VT, so a
+//! suspend and resume without a VT switch goes unnoticed by the session
+//! watcher of the compositor. logind announces both with its
+//! `PrepareForSleep` signal, and holds off the suspend for as long as someone
+//! holds a delay inhibitor lock, which gives the compositor the time to
+//! remember the state of the outputs.
+//!
+//! This is hooked up automatically when the `logind` feature is enabled, and
+//! calls `Compositor::prepare_for_sleep` and `Compositor::resume_from_sleep`.
+
+use dbus::{BusType, Connection, ConnectionItem, Message, OwnedFd, WatchEvent};
+use libc;
+use wayland_sys::server::{wl_event_loop, wl_event_source, WAYLAND_SERVER_HANDLE,
+                          WL_EVENT_READABLE};
+
+use compositor::COMPOSITOR_PTR;
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_MANAGER: &str = "org.freedesktop.login1.Manager";
+
+/// How long to wait for logind to hand out the inhibitor lock, in
+/// milliseconds.
+const INHIBIT_TIMEOUT: i32 = 1000;
+
+/// Listens for `PrepareForSleep` on the system bus.
+pub(crate) struct SleepWatcher {
+    connection: Connection,
+    /// The delay lock, held while the system is awake.
+    inhibitor: Option<OwnedFd>,
+    sources: Vec<*mut wl_event_source>
+}
+
+impl SleepWatcher {
+    /// Connect to the system bus and dispatch its messages on the event loop.
+    ///
+    /// Returns `None` if there is no system bus, e.g in a container.
+    pub(crate) unsafe fn new(event_loop: *mut wl_event_loop) -> Option<Box<Self>> {
+        let connection = match Connection::get_private(BusType::System) {
+            Ok(connection) => connection,
+            Err(err) => {
+                wlr_log!(WLR_INFO, "Not watching for suspend, no system bus: {:?}", err);
+                return None
+            }
+        };
+        let rule = format!("type='signal',interface='{}',member='PrepareForSleep'",
+                           LOGIND_MANAGER);
+        if let Err(err) = connection.add_match(&rule) {
+            wlr_log!(WLR_ERROR, "Could not listen for PrepareForSleep: {:?}", err);
+            return None
+        }
+        let mut watcher = Box::new(SleepWatcher { connection,
+                                                  inhibitor: None,
+                                                  sources: Vec::new() });
+        watcher.inhibit();
+        let data = &mut *watcher as *mut SleepWatcher as *mut libc::c_void;
+        for watch in watcher.connection.watch_fds() {
+            if !watch.readable() {
+                continue
+            }
+            let source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_event_loop_add_fd,
+                                       event_loop,
+                                       watch.fd(),
+                                       WL_EVENT_READABLE,
+                                       dbus_readable,
+                                       data);
+            if !source.is_null() {
+                watcher.sources.push(source)
+            }
+        }
+        Some(watcher)
+    }
+
+    /// Take a delay lock, so the suspend waits until the outputs are saved.
+    fn inhibit(&mut self) {
+        if self.inhibitor.is_some() {
+            return
+        }
+        let message =
+            Message::new_method_call(LOGIND_NAME, LOGIND_PATH, LOGIND_MANAGER, "Inhibit")
+                .map(|message| {
+                         message.append3("sleep", "wlroots", "Saving the state of the outputs")
+                                .append1("delay")
+                     });
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                wlr_log!(WLR_ERROR, "Could not make an Inhibit call: {}", err);
+                return
+            }
+        };
+        match self.connection.send_with_reply_and_block(message, INHIBIT_TIMEOUT) {
+            Ok(reply) => self.inhibitor = reply.get1::<OwnedFd>(),
+            Err(err) => wlr_log!(WLR_ERROR, "Could not take a sleep inhibitor lock: {:?}", err)
+        }
+    }
+
+    unsafe fn dispatch(&mut self, fd: libc::c_int) {
+        let mut sleeping = None;
+        for item in self.connection.watch_handle(fd, WatchEvent::Readable as libc::c_uint) {
+            if let ConnectionItem::Signal(message) = item {
+                let is_sleep_signal = message.member()
+                                             .map(|member| &*member == "PrepareForSleep")
+                                             .unwrap_or(false);
+                if is_sleep_signal {
+                    sleeping = message.get1::<bool>().or(sleeping);
+                }
+            }
+        }
+        let sleeping = match sleeping {
+            Some(sleeping) => sleeping,
+            None => return
+        };
+        if !COMPOSITOR_PTR.is_null() {
+            let compositor = &mut *COMPOSITOR_PTR;
+            if sleeping {
+                wlr_log!(WLR_INFO, "Preparing for suspend");
+                compositor.prepare_for_sleep()
+            } else {
+                wlr_log!(WLR_INFO, "Waking up from suspend");
+                compositor.resume_from_sleep()
+            }
+        }
+        if sleeping {
+            // Let the system go to sleep.
+            self.inhibitor = None;
+        } else {
+            self.inhibit();
+        }
+    }
+}
+
+impl Drop for SleepWatcher {
+    fn drop(&mut self) {
+        for source in self.sources.drain(..) {
+            unsafe { ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, source) };
+        }
+    }
+}
+
+unsafe extern "C" fn dbus_readable(fd: libc::c_int,
+                                   _mask: u32,
+                                   data: *mut libc::c_void)
+                                   -> libc::c_int {
+    if data.is_null() {
+        return 0
+    }
+    (*(data as *mut SleepWatcher)).dispatch(fd);
+    0
+}
@@ -46,6 +46,7 @@ macro_rules! wl_list_for_each {
 /// wlr_log!(L_DEBUG, "Hello world");
 /// wlr_log!(L_ERROR, "Could not {:#?} the {}", foo, bar);
 /// ```
+#[cfg(not(feature = "mock"))]
 #[macro_export]
 macro_rules! wlr_log {
     ($verb: expr, $($msg:tt)*) => {{
@@ -74,6 +75,29 @@ macro_rules! wlr_log {
     }}
 }
 
+/// With the `mock` feature messages go straight to the logging callback,
+/// without calling into wlroots.
+#[cfg(feature = "mock")]
+#[macro_export]
+macro_rules! wlr_log {
+    ($verb: expr, $($msg:tt)*) => {{
+        #[allow(unused_imports)]
+        use $crate::utils::log::{WLR_SILENT, WLR_ERROR, WLR_INFO, WLR_DEBUG};
+        $crate::utils::log::mock_log($verb,
+                                     format!("[{}:{}] {}", file!(), line!(), format!($($msg)*)))
+    }}
+}
+
+/// Asserts an internal invariant, but only when built with the `fuzzing`
+/// feature. Normal builds trust wlroots to hold up its end.
+macro_rules! fuzz_assert {
+    ($($arg: tt)*) => {
+        if cfg!(feature = "fuzzing") {
+            assert!($($arg)*);
+        }
+    }
+}
+
 /// Defines a new struct that contains a variable number of listeners that
 /// will trigger unsafe user-defined callbacks.
 ///
@@ -182,6 +206,9 @@ macro_rules! wayland_listener {
             $($(pub(crate) unsafe extern "C" fn $listener_func(listener:
                                                         *mut $crate::wlroots_sys::wl_listener,
                                                         data: *mut $crate::libc::c_void) {
+                // A listener that was removed has its links cleared.
+                fuzz_assert!(!(*listener).link.next.is_null(),
+                             concat!(stringify!($struct_name), " notified after being removed"));
                 let manager: &mut $struct_name = &mut (*container_of!(listener,
                                                                       $struct_name,
                                                                       $listener));
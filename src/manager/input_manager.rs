@@ -20,6 +20,7 @@ use {compositor,
              tablet_pad::{self, TabletPad, TabletPadWrapper},
              tablet_tool::{self, TabletTool, TabletToolWrapper},
              touch::{self, Touch, TouchWrapper}},
+     snapshot,
      utils::{Handleable, safe_as_cstring}};
 
 /// Callback triggered when an input device is added.
@@ -143,7 +144,7 @@ wayland_listener_static! {
                         let pointer_handle = pointer.weak_reference();
                         let res = manager.pointer_added.and_then(|f| f(compositor.clone(), pointer_handle));
                         if let Some(pointer_handler) = res {
-                            let mut pointer = PointerWrapper::new((pointer, pointer_handler));
+                            let mut pointer = PointerWrapper::new((pointer, pointer_handler, None));
                             wl_signal_add(&mut (*dev.dev_union().pointer).events.motion as *mut _ as _,
                                           pointer.motion_listener() as *mut _ as _);
                             wl_signal_add(&mut (*dev.dev_union().pointer)
@@ -256,6 +257,9 @@ wayland_listener_static! {
                         }
                     }
                 }
+                if !(*data).data.is_null() {
+                    snapshot::track(|tracked| tracked.add_device(dev.device()));
+                }
                 manager.input_added.map(|f| f(compositor, &mut dev))
             }));
             match res {
@@ -273,6 +277,16 @@ wayland_listener_static! {
     ]
 }
 
+/// Pass a device that didn't come from the backend (e.g a virtual keyboard)
+/// to the input manager, the same way the backend announces new devices.
+///
+/// Does nothing if there is no input manager.
+pub(crate) unsafe fn add_device(device: *mut wlr_input_device) {
+    if let Some(notify) = MANAGER.add_listener.notify {
+        notify(&mut MANAGER.add_listener, device as *mut libc::c_void)
+    }
+}
+
 pub(crate) unsafe fn add_keyboard(dev: &mut input::Device) {
     // Set the XKB settings
     let rules = safe_as_cstring(env::var("XKB_DEFAULT_RULES").unwrap_or("".into()));
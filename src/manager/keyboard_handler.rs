@@ -2,10 +2,11 @@
 
 use libc;
 use wayland_sys::server::WAYLAND_SERVER_HANDLE;
-use wlroots_sys::{wlr_input_device, wlr_event_keyboard_key};
+use wlroots_sys::{wlr_input_device, wlr_event_keyboard_key, wlr_key_state::WLR_KEY_PRESSED};
 
 use {compositor,
      input::keyboard::{self, Keyboard},
+     trace,
      utils::Handleable};
 
 #[allow(unused_variables)]
@@ -74,6 +75,7 @@ wayland_listener!(pub(crate) KeyboardWrapper, (Keyboard, Box<Handler>), [
         };
         let xkb_state = (*keyboard.as_ptr()).xkb_state;
         let key = keyboard::event::Key::new(data as *mut wlr_event_keyboard_key, xkb_state);
+        trace::record(trace::Kind::Key { pressed: key.key_state() == WLR_KEY_PRESSED }, None);
 
         keyboard_handler.on_key(compositor, keyboard.weak_reference(), &key);
     };
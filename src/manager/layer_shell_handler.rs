@@ -0,0 +1,123 @@
+//! Handler for layer shell clients.
+
+use libc;
+use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+use wlroots_sys::wlr_layer_surface;
+
+use {compositor,
+     surface,
+     shell::layer_shell::{self, SurfaceState},
+     trace,
+     utils::Handleable};
+
+/// Handles events from layer shell clients.
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when the surface committed new state, e.g a different anchor
+    /// or exclusive zone. This is a good time to arrange the layer surfaces
+    /// of its output again.
+    fn on_commit(&mut self,
+                 compositor_handle: compositor::Handle,
+                 surface_handle: surface::Handle,
+                 layer_surface_handle: layer_shell::Handle) {}
+
+    /// Called when the layer surface is destroyed (e.g by the client).
+    fn destroyed(&mut self, compositor::Handle, layer_shell::Handle) {}
+
+    /// Called when the surface is ready to be mapped. It should be drawn in
+    /// its layer from now on.
+    fn map_request(&mut self,
+                   compositor_handle: compositor::Handle,
+                   surface_handle: surface::Handle,
+                   layer_surface_handle: layer_shell::Handle) {}
+
+    /// Called when the surface should be unmapped. It should no longer be
+    /// drawn, but may be mapped again later.
+    fn unmap_request(&mut self,
+                     compositor_handle: compositor::Handle,
+                     surface_handle: surface::Handle,
+                     layer_surface_handle: layer_shell::Handle) {}
+}
+
+wayland_listener!(pub(crate) LayerShell, (layer_shell::Surface, Option<Box<Handler>>), [
+    destroy_listener => destroy_notify: |this: &mut LayerShell, data: *mut libc::c_void,| unsafe {
+        let (ref layer_surface, ref mut manager) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        if let Some(ref mut manager) = manager.as_mut() {
+            manager.destroyed(compositor, layer_surface.weak_reference());
+        }
+        let surface_ptr = data as *mut wlr_layer_surface;
+        let layer_state_ptr = (*surface_ptr).data as *mut SurfaceState;
+        Box::from_raw((*layer_state_ptr).shell);
+    };
+    commit_listener => commit_notify: |this: &mut LayerShell, _data: *mut libc::c_void,| unsafe {
+        trace::record(trace::Kind::Commit, Some(this.data.0.id()));
+        let (ref mut layer_surface, ref mut manager) = match &mut this.data {
+            (_, None) => return,
+            (ls, Some(manager)) => (ls, manager)
+        };
+        let surface = layer_surface.surface();
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        manager.on_commit(compositor,
+                          surface,
+                          layer_surface.weak_reference());
+    };
+    map_listener => map_notify: |this: &mut LayerShell, _data: *mut libc::c_void,| unsafe {
+        trace::record(trace::Kind::Map, Some(this.data.0.id()));
+        let (ref mut layer_surface, ref mut manager) = match &mut this.data {
+            (_, None) => return,
+            (ls, Some(manager)) => (ls, manager)
+        };
+        let surface = layer_surface.surface();
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        manager.map_request(compositor,
+                            surface,
+                            layer_surface.weak_reference());
+    };
+    unmap_listener => unmap_notify: |this: &mut LayerShell, _data: *mut libc::c_void,| unsafe {
+        trace::record(trace::Kind::Unmap, Some(this.data.0.id()));
+        let (ref mut layer_surface, ref mut manager) = match &mut this.data {
+            (_, None) => return,
+            (ls, Some(manager)) => (ls, manager)
+        };
+        let surface = layer_surface.surface();
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        manager.unmap_request(compositor,
+                              surface,
+                              layer_surface.weak_reference());
+    };
+]);
+
+impl Drop for LayerShell {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.destroy_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.commit_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.map_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.unmap_listener()).link as *mut _ as _);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Manager for layer shell clients.
+
+use libc;
+use wayland_sys::server::signal::wl_signal_add;
+use wlroots_sys::wlr_layer_surface;
+
+use {compositor,
+     shell::layer_shell,
+     snapshot,
+     surface,
+     utils::Handleable};
+use super::layer_shell_handler::LayerShell;
+
+/// Callback that is triggered when a new layer surface appears.
+///
+/// If the client didn't pick an output for the surface it has to be put on
+/// one here with `layer_shell::Surface::set_output`.
+pub type NewSurface = fn(compositor_handle: compositor::Handle,
+                         layer_surface_handle: layer_shell::Handle)
+                         -> (Option<Box<layer_shell::Handler>>, Option<Box<surface::Handler>>);
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (Manager, Builder): [
+        (NewSurface, add_listener, surface_added) => (add_notify, surface_added):
+        |manager: &mut Manager, data: *mut libc::c_void,|
+        unsafe {
+            let data = data as *mut wlr_layer_surface;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            wlr_log!(WLR_DEBUG, "New layer surface request {:p}", data);
+            let layer_surface = layer_shell::Surface::new(data);
+            snapshot::track(|tracked| tracked.add_layer_surface(layer_surface.weak_reference()));
+
+            let (layer_surface_manager, surface_handler) =
+                match manager.surface_added {
+                    None => (None, None),
+                    Some(f) => f(compositor, layer_surface.weak_reference())
+                };
+
+            let mut layer_surface = LayerShell::new((layer_surface, layer_surface_manager));
+            let surface_state = (*(*data).surface).data as *mut surface::InternalState;
+            if let Some(surface_handler) = surface_handler {
+                (*(*surface_state).surface).data().1 = surface_handler;
+            }
+
+            wl_signal_add(&mut (*data).events.destroy as *mut _ as _,
+                          layer_surface.destroy_listener() as _);
+            wl_signal_add(&mut (*(*data).surface).events.commit as *mut _ as _,
+                          layer_surface.commit_listener() as _);
+            wl_signal_add(&mut (*data).events.map as *mut _ as _,
+                          layer_surface.map_listener() as _);
+            wl_signal_add(&mut (*data).events.unmap as *mut _ as _,
+                          layer_surface.unmap_listener() as _);
+            let layer_data = (*data).data as *mut layer_shell::SurfaceState;
+            (*layer_data).shell = Box::into_raw(layer_surface);
+        };
+    ]
+}
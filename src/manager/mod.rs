@@ -10,5 +10,7 @@ pub(crate) mod xdg_shell_v6_manager;
 pub(crate) mod xdg_shell_v6_handler;
 pub(crate) mod xdg_shell_manager;
 pub(crate) mod xdg_shell_handler;
+pub(crate) mod layer_shell_manager;
+pub(crate) mod layer_shell_handler;
 pub(crate) mod tablet_pad_handler;
 pub(crate) mod tablet_tool_handler;
@@ -4,8 +4,11 @@ use libc;
 use wayland_sys::server::WAYLAND_SERVER_HANDLE;
 use wlroots_sys::wlr_output;
 
-use {compositor,
+use {bus,
+     compositor,
+     cursor,
      output::{self, Output, OutputState},
+     trace,
      utils::{HandleErr, Handleable}};
 
 #[allow(unused_variables)]
@@ -62,6 +65,9 @@ wayland_listener!(pub(crate) UserOutput, (Output, Box<Handler>), [
                 None => return
             };
             manager.destroyed(compositor, output.weak_reference());
+            trace::record(trace::Kind::OutputRemoved, Some(output.id()));
+            bus::publish(&bus::OutputRemoved { id: output.id(),
+                                               name: output.name() });
             // NOTE Remove the output from the output if there is one.
             if let Some(layout) = output.layout() {
                 match with_handles!([(layout: {layout})] => {
@@ -107,6 +113,17 @@ wayland_listener!(pub(crate) UserOutput, (Output, Box<Handler>), [
             Some(handle) => handle,
             None => return
         };
+        // Captures of the output happen when the buffers are swapped, so
+        // this is the last chance to refuse them.
+        if let Some(ref mut screencopy) = (*compositor::COMPOSITOR_PTR).screencopy_manager {
+            screencopy.review_frames(output.as_ptr());
+        }
+        if let Some(ref mut export_dmabuf) = (*compositor::COMPOSITOR_PTR).export_dmabuf_manager {
+            export_dmabuf.review_frames(output.as_ptr());
+        }
+        if let Some(ref mut gamma_control) = (*compositor::COMPOSITOR_PTR).gamma_control_manager {
+            gamma_control.review_controls(compositor.clone());
+        }
 
         manager.on_frame(compositor, output.weak_reference());
     };
@@ -137,6 +154,7 @@ wayland_listener!(pub(crate) UserOutput, (Output, Box<Handler>), [
         };
 
         manager.on_scale_change(compositor, output.weak_reference());
+        cursor::reload_xcursor_themes();
     };
     transform_listener => transform_notify: |this: &mut UserOutput, _output: *mut libc::c_void,|
     unsafe {
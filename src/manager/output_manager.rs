@@ -6,8 +6,11 @@ use libc;
 use wayland_sys::server::signal::wl_signal_add;
 use wlroots_sys::wlr_output;
 
-use {compositor,
+use {bus,
+     compositor,
      output::{self, Output, OutputState, UserOutput},
+     snapshot,
+     trace,
      utils::Handleable};
 
 
@@ -31,15 +34,32 @@ pub struct BuilderResult<'output> {
 pub struct Destroyed(output::Handle);
 
 impl<'output> OutputBuilder<'output> {
-    /// Build the output with the best mode.
+    /// Build the output with the best mode, and the whole number scale
+    /// suggested for its pixel density (see `output::suggested_scale`).
+    ///
+    /// If there is a saved configuration for the output, set its scale on
+    /// the output handle afterwards.
     ///
     /// To complete construction, return this in your implementation of
     /// `output::ManagerHandler::output_added`.
-    pub fn build_best_mode<T: output::Handler + 'static>(mut self,
+    pub fn build_best_mode<T: output::Handler + 'static>(self,
                                                        data: T)
                                                        -> BuilderResult<'output> {
+        self.build_best_mode_and_scale(data, false)
+    }
+
+    /// Build the output with the best mode, and the scale suggested for its
+    /// pixel density (see `output::suggested_scale`), which is rounded to a
+    /// quarter with `fractional`.
+    pub fn build_best_mode_and_scale<T: output::Handler + 'static>(mut self,
+                                                                 data: T,
+                                                                 fractional: bool)
+                                                                 -> BuilderResult<'output> {
         with_handles!([(output: {&mut self.output})] => {
             output.choose_best_mode();
+            let scale = output.suggested_scale(fractional);
+            wlr_log!(WLR_DEBUG, "Suggested scale {} for {}", scale, output.name());
+            output.set_scale(scale);
         }).expect("Output was borrowed");
         BuilderResult { output: self.output,
                         result: Box::new(data),
@@ -93,6 +113,9 @@ wayland_listener_static! {
                 Err(_) => ::std::process::abort()
             };
             if let Some(BuilderResult {result: output_ptr, .. }) = build_result {
+                snapshot::track(|tracked| tracked.add_output(output_clone.weak_reference()));
+                let added = bus::OutputAdded { id: output_clone.id(),
+                                               name: output_clone.name() };
                 let mut output = UserOutput::new((output_clone, output_ptr));
                 wl_signal_add(&mut (*data).events.frame as *mut _ as _,
                               output.frame_listener() as _);
@@ -112,6 +135,8 @@ wayland_listener_static! {
                               output.on_destroy_listener() as _);
                 let output_data = (*data).data as *mut OutputState;
                 (*output_data).output = Box::into_raw(output);
+                trace::record(trace::Kind::OutputAdded, Some(added.id));
+                bus::publish(&added);
             }
         };
     ]
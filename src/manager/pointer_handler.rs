@@ -1,12 +1,13 @@
 //! Handler for pointers
 
 use libc;
-use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+use wayland_sys::server::{wl_event_source, WAYLAND_SERVER_HANDLE};
 use wlroots_sys::{wlr_input_device, wlr_event_pointer_axis, wlr_event_pointer_button,
-                  wlr_event_pointer_motion};
+                  wlr_event_pointer_motion, wlr_button_state::WLR_BUTTON_PRESSED};
 
-use {compositor,
+use {compositor::{self, COMPOSITOR_PTR},
      input::pointer::{self, Pointer},
+     trace,
      utils::Handleable};
 
 #[allow(unused_variables)]
@@ -38,14 +39,36 @@ pub trait Handler {
     fn destroyed(&mut self,
                  compositor_handle: compositor::Handle,
                  pointer_handle: pointer::Handle) {}
+
+    /// Whether relative motion events should be coalesced.
+    ///
+    /// When this returns true, consecutive motion events are merged into one
+    /// and `on_motion` is called once per event loop dispatch with the summed
+    /// deltas. Pending motion is always delivered before the next button,
+    /// axis or absolute motion event, so ordering is preserved.
+    ///
+    /// This is checked on every motion event, so it can be toggled at runtime.
+    fn coalesce_motion(&self) -> bool {
+        false
+    }
+}
+
+/// Relative motion that has been merged but not yet delivered.
+pub(crate) struct PendingMotion {
+    event: wlr_event_pointer_motion,
+    /// The idle source that will deliver the motion.
+    source: *mut wl_event_source
 }
 
-wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>), [
+wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>, Option<PendingMotion>), [
     on_destroy_listener => on_destroy_notify: |this: &mut PointerWrapper, data: *mut libc::c_void,|
     unsafe {
         let input_device_ptr = data as *mut wlr_input_device;
+        if let Some(pending) = this.data.2.take() {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, pending.source);
+        }
         {
-            let (ref mut pointer, ref mut pointer_handler) = this.data;
+            let (ref mut pointer, ref mut pointer_handler, _) = this.data;
             let compositor = match compositor::handle() {
                 Some(handle) => handle,
                 None => return
@@ -70,8 +93,12 @@ wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>), [
         Box::from_raw((*input_device_ptr).data as *mut PointerWrapper);
     };
     button_listener => key_notify: |this: &mut PointerWrapper, data: *mut libc::c_void,| unsafe {
+        this.flush_motion(true);
         let pointer = &mut this.data.0;
         let event = pointer::event::Button::from_ptr(data as *mut wlr_event_pointer_button);
+        trace::record(trace::Kind::Button { button: event.button(),
+                                            pressed: event.state() == WLR_BUTTON_PRESSED },
+                      None);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -81,6 +108,26 @@ wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>), [
     };
     motion_listener => motion_notify:  |this: &mut PointerWrapper, data: *mut libc::c_void,|
     unsafe {
+        if this.data.1.coalesce_motion() {
+            let event = *(data as *mut wlr_event_pointer_motion);
+            match this.data.2 {
+                Some(ref mut pending) => {
+                    pending.event.time_msec = event.time_msec;
+                    pending.event.delta_x += event.delta_x;
+                    pending.event.delta_y += event.delta_y;
+                },
+                None => {
+                    let source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                               wl_event_loop_add_idle,
+                                               (*COMPOSITOR_PTR).event_loop,
+                                               flush_motion_idle,
+                                               this as *mut PointerWrapper as *mut _);
+                    this.data.2 = Some(PendingMotion { event, source });
+                }
+            }
+            return
+        }
+        this.flush_motion(true);
         let pointer = &mut this.data.0;
         let event = pointer::event::Motion::from_ptr(data as *mut wlr_event_pointer_motion);
         let compositor = match compositor::handle() {
@@ -92,6 +139,7 @@ wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>), [
     };
     motion_absolute_listener => motion_absolute_notify:
     |this: &mut PointerWrapper, data: *mut libc::c_void,| unsafe {
+        this.flush_motion(true);
         let pointer = &mut this.data.0;
         let event = pointer::event::AbsoluteMotion::from_ptr(data as *mut _);
         let compositor = match compositor::handle() {
@@ -102,6 +150,7 @@ wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>), [
         this.data.1.on_motion_absolute(compositor, pointer.weak_reference(), &event);
     };
     axis_listener => axis_notify:  |this: &mut PointerWrapper, data: *mut libc::c_void,| unsafe {
+        this.flush_motion(true);
         let pointer = &mut this.data.0;
         let event = pointer::event::Axis::from_ptr(data as *mut wlr_event_pointer_axis);
         let compositor = match compositor::handle() {
@@ -112,3 +161,31 @@ wayland_listener!(pub(crate) PointerWrapper, (Pointer, Box<Handler>), [
         this.data.1.on_axis(compositor, pointer.weak_reference(), &event);
     };
 ]);
+
+impl PointerWrapper {
+    /// Deliver any motion that was merged while coalescing.
+    ///
+    /// `remove_source` must be false when called from the idle source itself,
+    /// as the event loop removes it after it fires.
+    unsafe fn flush_motion(&mut self, remove_source: bool) {
+        let mut pending = match self.data.2.take() {
+            Some(pending) => pending,
+            None => return
+        };
+        if remove_source {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, pending.source);
+        }
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        let event = pointer::event::Motion::from_ptr(&mut pending.event);
+        let pointer = self.data.0.weak_reference();
+        self.data.1.on_motion(compositor, pointer, &event);
+    }
+}
+
+unsafe extern "C" fn flush_motion_idle(data: *mut libc::c_void) {
+    let wrapper = &mut *(data as *mut PointerWrapper);
+    wrapper.flush_motion(false);
+}
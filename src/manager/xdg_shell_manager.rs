@@ -6,6 +6,7 @@ use wlroots_sys::{wlr_xdg_surface, wlr_xdg_surface_role::*};
 
 use {compositor,
      shell::xdg_shell::{self, ShellState},
+     snapshot,
      surface,
      utils::Handleable};
 use super::xdg_shell_handler::XdgShell;
@@ -41,6 +42,7 @@ wayland_listener_static! {
                 }
             };
             let shell_surface = xdg_shell::Surface::new(data, state);
+            snapshot::track(|tracked| tracked.add_xdg_surface(shell_surface.weak_reference()));
 
             let (shell_surface_manager, surface_handler) =
                 match manager.surface_added {
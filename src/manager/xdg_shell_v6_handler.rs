@@ -8,6 +8,7 @@ use wlroots_sys::wlr_xdg_surface_v6;
 use {compositor,
      surface,
      shell::xdg_shell_v6::{self, SurfaceState},
+     trace,
      utils::Handleable};
 
 /// Handles events from the client XDG v6 shells.
@@ -32,6 +33,18 @@ pub trait Handler {
                     surface_handle: surface::Handle,
                     xdg_shell_v6_handle: xdg_shell_v6::Handle) {}
 
+    /// Called when the client stops answering pings, or starts answering
+    /// them again.
+    ///
+    /// `responsive` is false once a ping timed out. It's a good time to
+    /// indicate the view is not responding and offer to kill the client
+    /// (see `xdg_shell_v6::Surface::kill_client`).
+    fn responsiveness_changed(&mut self,
+                              compositor_handle: compositor::Handle,
+                              surface_handle: surface::Handle,
+                              xdg_shell_v6_handle: xdg_shell_v6::Handle,
+                              responsive: bool) {}
+
     /// Called when a new popup appears in the xdg tree.
     fn new_popup(&mut self,
                  compositor_handle: compositor::Handle,
@@ -115,6 +128,7 @@ wayland_listener!(pub(crate) XdgShellV6, (xdg_shell_v6::Surface, Option<Box<Hand
         Box::from_raw((*shell_state_ptr).shell);
     };
     commit_listener => commit_notify: |this: &mut XdgShellV6, _data: *mut libc::c_void,| unsafe {
+        trace::record(trace::Kind::Commit, Some(this.data.0.id()));
         let (ref mut shell_surface, ref mut manager) = match &mut this.data {
             (_, None) => return,
             (ss, Some(manager)) => (ss, manager)
@@ -132,6 +146,7 @@ wayland_listener!(pub(crate) XdgShellV6, (xdg_shell_v6::Surface, Option<Box<Hand
     ping_timeout_listener => ping_timeout_notify: |this: &mut XdgShellV6,
                                                    _data: *mut libc::c_void,|
     unsafe {
+        this.set_responsive(false);
         let (ref mut shell_surface, ref mut manager) = match &mut this.data {
             (_, None) => return,
             (ss, Some(manager)) => (ss, manager)
@@ -267,6 +282,7 @@ wayland_listener!(pub(crate) XdgShellV6, (xdg_shell_v6::Surface, Option<Box<Hand
     };
 
     map_listener => map_notify: |this: &mut XdgShellV6, _event: *mut libc::c_void,| unsafe {
+        trace::record(trace::Kind::Map, Some(this.data.0.id()));
         let (ref mut shell_surface, ref mut manager) = match &mut this.data {
             (_, None) => return,
             (ss, Some(manager)) => (ss, manager)
@@ -283,6 +299,7 @@ wayland_listener!(pub(crate) XdgShellV6, (xdg_shell_v6::Surface, Option<Box<Hand
     };
 
     unmap_listener => unmap_notify: |this: &mut XdgShellV6, _event: *mut libc::c_void,| unsafe {
+        trace::record(trace::Kind::Unmap, Some(this.data.0.id()));
         let (ref mut shell_surface, ref mut manager) = match &mut this.data {
             (_, None) => return,
             (ss, Some(manager)) => (ss, manager)
@@ -303,6 +320,28 @@ impl XdgShellV6 {
     pub(crate) fn surface_mut(&mut self) -> xdg_shell_v6::Handle {
         self.data.0.weak_reference()
     }
+
+    /// Record whether the client answers pings, telling the handler if that
+    /// changed.
+    pub(crate) unsafe fn set_responsive(&mut self, responsive: bool) {
+        let (ref mut shell_surface, ref mut manager) = self.data;
+        let state = (*shell_surface.as_ptr()).data as *mut SurfaceState;
+        if state.is_null() || (*state).responsive == responsive {
+            return
+        }
+        (*state).responsive = responsive;
+        wlr_log!(WLR_DEBUG, "Client responsive: {}", responsive);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        if let Some(manager) = manager.as_mut() {
+            manager.responsiveness_changed(compositor,
+                                           shell_surface.surface(),
+                                           shell_surface.weak_reference(),
+                                           responsive);
+        }
+    }
 }
 
 impl Drop for XdgShellV6 {
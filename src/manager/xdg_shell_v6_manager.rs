@@ -6,6 +6,7 @@ use wlroots_sys::{wlr_xdg_surface_v6, wlr_xdg_surface_v6_role::*};
 
 use {compositor,
      shell::xdg_shell_v6::{self, ShellState},
+     snapshot,
      surface,
      utils::Handleable};
 use super::xdg_shell_v6_handler::XdgShellV6;
@@ -40,6 +41,7 @@ wayland_listener_static! {
                 }
             };
             let shell_surface = xdg_shell_v6::Surface::new(data, state);
+            snapshot::track(|tracked| tracked.add_xdg_v6_surface(shell_surface.weak_reference()));
 
             let (shell_surface_handler, surface_handler) =
                 match manager.surface_added {
@@ -0,0 +1,276 @@
+//! Embedding another compositor as a view, e.g for sandboxed sessions or
+//! for developing a compositor inside of the running one.
+//!
+//! The child compositor is started with a connection of its own, passed in
+//! `WAYLAND_SOCKET`, and told to use the Wayland backend. Its virtual output
+//! then shows up as a toplevel of that connection, which `Nested` picks out
+//! of the new XDG surfaces:
+//!
+//! ```rust,no_run,ignore
+//! let mut nested = Nested::spawn(compositor, Command::new("sway"))?;
+//! // In the XDG shell manager.
+//! if state.nested.claim(&shell) {
+//!     state.nested.resize(Size::new(1280, 720));
+//! }
+//! // When rendering, with `area` where it's shown on the output.
+//! state.nested.render(&mut renderer, area);
+//! // When the pointer moves, to find where to send it.
+//! if let Some((surface, sx, sy)) = state.nested.surface_at(area, (x, y)) {
+//!     seat.pointer_notify_enter(surface, sx, sy);
+//! }
+//! ```
+//!
+//! Input goes to the child like to any other client, through the `Seat`.
+//! Give the view keyboard focus for the child to get key presses, including
+//! the key bindings of the parent it should handle itself.
+
+use std::{io, ptr, os::unix::io::RawFd, process::{Child, Command}};
+
+use libc;
+use wayland_sys::server::{wl_client, wl_display, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::wlr_xdg_surface_role::WLR_XDG_SURFACE_ROLE_TOPLEVEL;
+
+use {area::{Area, Origin, Size},
+     compositor::Compositor,
+     render::Renderer,
+     shell::xdg_shell,
+     surface,
+     utils::current_time};
+
+wayland_listener!(ClientWatcher, *mut wl_client, [
+    destroy_listener => destroy_notify: |this: &mut ClientWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        // The child disconnected, e.g because it exited.
+        this.data = ptr::null_mut();
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_init,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+    };
+]);
+
+/// A child process with a Wayland connection of its own, which it gets in
+/// `WAYLAND_SOCKET`.
+///
+/// Dropping it disconnects the child, and kills and reaps it.
+pub(crate) struct ChildClient {
+    pub(crate) child: Child,
+    watcher: Box<ClientWatcher>
+}
+
+impl ChildClient {
+    /// Start `command`, connected to the display through a socket pair.
+    pub(crate) unsafe fn spawn(display: *mut wl_display,
+                               command: &mut Command)
+                               -> io::Result<Self> {
+        let mut fds: [RawFd; 2] = [0; 2];
+        if libc::socketpair(libc::AF_UNIX,
+                            libc::SOCK_STREAM | libc::SOCK_CLOEXEC,
+                            0,
+                            fds.as_mut_ptr()) < 0 {
+            return Err(io::Error::last_os_error())
+        }
+        let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_create, display, fds[0]);
+        if client.is_null() {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+            return Err(io::Error::new(io::ErrorKind::Other, "Could not create a client"))
+        }
+        let mut watcher = ClientWatcher::new(client);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_client_add_destroy_listener,
+                      client,
+                      watcher.destroy_listener());
+        // Only the end of the child is inherited.
+        libc::fcntl(fds[1], libc::F_SETFD, 0);
+        let child = command.env("WAYLAND_SOCKET", fds[1].to_string()).spawn();
+        libc::close(fds[1]);
+        match child {
+            Ok(child) => Ok(ChildClient { child, watcher }),
+            Err(err) => {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_destroy, client);
+                Err(err)
+            }
+        }
+    }
+
+    /// Whether the child is still connected.
+    pub(crate) fn is_connected(&self) -> bool {
+        !self.watcher.data.is_null()
+    }
+
+    /// Whether the surface was made through the connection of the child.
+    pub(crate) fn owns(&self, surface: &surface::Handle) -> bool {
+        let client = self.watcher.data;
+        if client.is_null() {
+            return false
+        }
+        surface.run(|surface| unsafe {
+                        let resource = (*surface.as_ptr()).resource;
+                        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                      wl_resource_get_client,
+                                      resource as _)
+                        == client
+                    })
+               .unwrap_or(false)
+    }
+}
+
+impl Drop for ChildClient {
+    fn drop(&mut self) {
+        unsafe {
+            let client = self.watcher.data;
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.watcher.destroy_listener()).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_init,
+                          &mut (*self.watcher.destroy_listener()).link as *mut _ as _);
+            if !client.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_client_destroy, client);
+            }
+        }
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+/// A compositor running inside of this one.
+pub struct Nested {
+    client: ChildClient,
+    view: Option<xdg_shell::Handle>
+}
+
+impl Nested {
+    /// Start the child compositor with `command`, connected to this one
+    /// through a socket pair.
+    ///
+    /// `WAYLAND_SOCKET` and `WLR_BACKENDS` are set for the child, and
+    /// `WAYLAND_DISPLAY` removed so it can't connect another way.
+    pub fn spawn(compositor: &mut Compositor, mut command: Command) -> io::Result<Self> {
+        command.env("WLR_BACKENDS", "wayland").env_remove("WAYLAND_DISPLAY");
+        let client = unsafe { ChildClient::spawn(compositor.display as _, &mut command)? };
+        wlr_log!(WLR_INFO, "Started nested compositor with pid {}", client.child.id());
+        Ok(Nested { client,
+                    view: None })
+    }
+
+    /// Get the process id of the child compositor.
+    pub fn pid(&self) -> u32 {
+        self.client.child.id()
+    }
+
+    /// Whether the child compositor is still connected.
+    pub fn is_connected(&self) -> bool {
+        self.client.is_connected()
+    }
+
+    /// Take the new XDG surface as the view of the child compositor if it's
+    /// a toplevel of its connection.
+    ///
+    /// Returns whether it was taken. A new toplevel replaces the last one,
+    /// e.g when the child recreated its output.
+    pub fn claim(&mut self, shell: &xdg_shell::Handle) -> bool {
+        let owned = shell.run(|shell| {
+                                  shell.role() == WLR_XDG_SURFACE_ROLE_TOPLEVEL
+                                  && self.client.owns(&shell.surface())
+                              })
+                         .unwrap_or(false);
+        if owned {
+            self.view = Some(shell.clone());
+        }
+        owned
+    }
+
+    /// Get the toplevel that shows the output of the child compositor, once
+    /// it's there.
+    pub fn view(&self) -> Option<xdg_shell::Handle> {
+        self.view.clone().filter(|view| view.is_alive())
+    }
+
+    /// Ask the child compositor to resize its output.
+    pub fn resize(&self, size: Size) {
+        if let Some(view) = self.view() {
+            view.run(|shell| {
+                         if let Some(&mut xdg_shell::ShellState::TopLevel(ref mut toplevel)) =
+                             shell.state()
+                         {
+                             toplevel.set_size(size.width as u32, size.height as u32);
+                             toplevel.set_activated(true);
+                         }
+                     })
+                .ok();
+        }
+    }
+
+    /// Render the output of the child compositor into `area` of the output
+    /// being rendered, in output coordinates.
+    pub fn render(&self, renderer: &mut Renderer, area: Area) {
+        let view = match self.view() {
+            Some(view) => view,
+            None => return
+        };
+        let scale = f64::from(renderer.output.scale());
+        view.run(|shell| {
+            let geometry = shell.geometry();
+            shell.for_each_surface(|surface, sx, sy| {
+                surface.run(|surface| {
+                    let (width, height) = surface.current_state().size();
+                    let (x, y) = (area.origin.x + sx - geometry.origin.x,
+                                  area.origin.y + sy - geometry.origin.y);
+                    let render_box =
+                        Area::new(Origin::new((f64::from(x) * scale) as i32,
+                                              (f64::from(y) * scale) as i32),
+                                  Size::new((f64::from(width) * scale) as i32,
+                                            (f64::from(height) * scale) as i32));
+                    renderer.render_surface(surface, render_box);
+                    surface.send_frame_done(current_time());
+                }).ok();
+            })
+        }).ok();
+    }
+
+    /// Find the surface of the child compositor under a point, for sending
+    /// it input.
+    ///
+    /// `area` is where the view is shown and the point is in the same
+    /// coordinates. Returns the surface with the point in its coordinates,
+    /// or `None` if the point is outside of the view.
+    pub fn surface_at(&self,
+                      area: Area,
+                      (x, y): (f64, f64))
+                      -> Option<(surface::Handle, f64, f64)> {
+        let view = self.view()?;
+        let (x, y) = (x - f64::from(area.origin.x), y - f64::from(area.origin.y));
+        if x < 0.0 || y < 0.0 || x >= f64::from(area.size.width)
+           || y >= f64::from(area.size.height)
+        {
+            return None
+        }
+        view.run(|shell| {
+                     let geometry = shell.geometry();
+                     let (mut sub_x, mut sub_y) = (0.0, 0.0);
+                     shell.surface_at(x + f64::from(geometry.origin.x),
+                                      y + f64::from(geometry.origin.y),
+                                      &mut sub_x,
+                                      &mut sub_y)
+                          .map(|surface| (surface, sub_x, sub_y))
+                 })
+            .ok()
+            .and_then(|found| found)
+    }
+
+    /// Whether the child compositor exited, e.g to start it again.
+    pub fn has_exited(&mut self) -> bool {
+        match self.client.child.try_wait() {
+            Ok(Some(status)) => {
+                wlr_log!(WLR_INFO, "Nested compositor exited with {}", status);
+                true
+            }
+            Ok(None) => false,
+            Err(_) => true
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Occlusion culling for the render loop.
+//!
+//! Views that are completely covered by opaque parts of the views above them
+//! don't need to be drawn, and damage under an opaque view doesn't need to be
+//! repainted for the views below it. `Culling` works this out once per frame
+//! from the stacking order:
+//!
+//! ```rust,no_run,ignore
+//! let culled = state.culling.cull(&state.stack, output_layout_area, |view| view_shape(view));
+//! for view in culled.views() {
+//!     for area in culled.damage(view, &damage) {
+//!         renderer.render_scissor(area);
+//!         render_view(&mut renderer, view);
+//!     }
+//! }
+//! ```
+//!
+//! Culling can be switched off with `Culling::set_enabled`, which is useful to
+//! check whether a rendering glitch is caused by a client lying about its
+//! opaque region.
+
+use area::Area;
+use wm::{occlusion::{self, Shape, Visibility}, stacking::Stack};
+
+/// Per frame visibility of the views on an output.
+#[derive(Debug)]
+pub struct Culled<T> {
+    /// Ordered from bottom to top.
+    views: Vec<T>,
+    visibility: Option<Visibility<T>>,
+    skipped: usize
+}
+
+impl<T: Clone + PartialEq> Culled<T> {
+    /// The views that need to be drawn, from bottom to top.
+    pub fn views<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.views.iter()
+    }
+
+    /// The parts of `damage` that need to be repainted for `view`.
+    ///
+    /// With culling disabled this is `damage` itself.
+    pub fn damage(&self, view: &T, damage: &[Area]) -> Vec<Area> {
+        match self.visibility {
+            Some(ref visibility) => {
+                let visible = visibility.visible_area(view);
+                damage.iter()
+                      .flat_map(|&damage| {
+                                    visible.iter()
+                                           .filter_map(move |&visible| {
+                                                           occlusion::intersect(damage, visible)
+                                                       })
+                                })
+                      .collect()
+            }
+            None => damage.to_vec()
+        }
+    }
+
+    /// How many views were skipped because they couldn't be seen.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+/// Decides which views are drawn on an output.
+#[derive(Debug)]
+pub struct Culling {
+    enabled: bool
+}
+
+impl Default for Culling {
+    fn default() -> Self {
+        Culling { enabled: true }
+    }
+}
+
+impl Culling {
+    pub fn new() -> Self {
+        Culling::default()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn culling on or off. Defaults to on.
+    ///
+    /// When it's off every shown view is drawn and damage is not reduced.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled
+    }
+
+    /// Compute what needs to be drawn on the output at `output_area` in the
+    /// layout.
+    ///
+    /// `shape` returns `None` for views that aren't shown at all. Damage
+    /// passed to `Culled::damage` is in the same (layout) coordinates.
+    pub fn cull<T, F>(&self, stack: &Stack<T>, output_area: Area, mut shape: F) -> Culled<T>
+        where T: Clone + PartialEq,
+              F: FnMut(&T) -> Option<Shape>
+    {
+        if !self.enabled {
+            let views: Vec<T> = stack.iter().filter(|view| shape(view).is_some()).cloned().collect();
+            return Culled { skipped: stack.len() - views.len(),
+                            views,
+                            visibility: None }
+        }
+        let visibility = Visibility::compute(stack, &[output_area], shape);
+        let views: Vec<T> = visibility.iter_visible().cloned().collect();
+        Culled { skipped: stack.len() - views.len(),
+                 views,
+                 visibility: Some(visibility) }
+    }
+}
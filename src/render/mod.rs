@@ -3,8 +3,18 @@ mod renderer;
 #[cfg(feature = "unstable")]
 mod texture;
 #[cfg(feature = "unstable")]
+pub mod culling;
+#[cfg(feature = "unstable")]
 pub mod matrix;
 #[cfg(feature = "unstable")]
+pub(crate) mod offscreen;
+#[cfg(all(feature = "unstable", feature = "pipewire"))]
+pub mod screencast;
+#[cfg(feature = "unstable")]
+pub mod screensaver;
+#[cfg(feature = "unstable")]
+pub mod stereo;
+#[cfg(feature = "unstable")]
 mod image;
 #[cfg(feature = "unstable")]
 mod pixman_region;
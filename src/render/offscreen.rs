@@ -0,0 +1,188 @@
+//! A framebuffer to draw into instead of the output.
+//!
+//! wlroots has no render targets other than outputs yet, so this talks to
+//! the GLES2 context of the renderer directly. While an `Offscreen` is
+//! alive it's what the `Renderer` draws into, and dropping it goes back to
+//! drawing on the output, as if nothing happened.
+
+use libc::{c_int, c_uint, c_void};
+
+use render::Renderer;
+
+type GLenum = c_uint;
+type GLuint = c_uint;
+type GLint = c_int;
+type GLsizei = c_int;
+type GLboolean = u8;
+
+const GL_FRAMEBUFFER: GLenum = 0x8D40;
+const GL_FRAMEBUFFER_BINDING: GLenum = 0x8CA6;
+const GL_FRAMEBUFFER_COMPLETE: GLenum = 0x8CD5;
+const GL_COLOR_ATTACHMENT0: GLenum = 0x8CE0;
+const GL_TEXTURE_2D: GLenum = 0x0DE1;
+const GL_TEXTURE_BINDING_2D: GLenum = 0x8069;
+const GL_VIEWPORT: GLenum = 0x0BA2;
+const GL_SCISSOR_TEST: GLenum = 0x0C11;
+const GL_SCISSOR_BOX: GLenum = 0x0C10;
+const GL_RGBA: GLenum = 0x1908;
+const GL_UNSIGNED_BYTE: GLenum = 0x1401;
+
+#[link(name = "GLESv2")]
+extern "C" {
+    fn glGenFramebuffers(n: GLsizei, framebuffers: *mut GLuint);
+    fn glDeleteFramebuffers(n: GLsizei, framebuffers: *const GLuint);
+    fn glBindFramebuffer(target: GLenum, framebuffer: GLuint);
+    fn glCheckFramebufferStatus(target: GLenum) -> GLenum;
+    fn glFramebufferTexture2D(target: GLenum,
+                              attachment: GLenum,
+                              textarget: GLenum,
+                              texture: GLuint,
+                              level: GLint);
+    fn glGenTextures(n: GLsizei, textures: *mut GLuint);
+    fn glDeleteTextures(n: GLsizei, textures: *const GLuint);
+    fn glBindTexture(target: GLenum, texture: GLuint);
+    fn glTexImage2D(target: GLenum,
+                    level: GLint,
+                    internalformat: GLint,
+                    width: GLsizei,
+                    height: GLsizei,
+                    border: GLint,
+                    format: GLenum,
+                    type_: GLenum,
+                    pixels: *const c_void);
+    fn glGetIntegerv(pname: GLenum, data: *mut GLint);
+    fn glIsEnabled(cap: GLenum) -> GLboolean;
+    fn glEnable(cap: GLenum);
+    fn glDisable(cap: GLenum);
+    fn glScissor(x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+    fn glViewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei);
+    fn glReadPixels(x: GLint,
+                    y: GLint,
+                    width: GLsizei,
+                    height: GLsizei,
+                    format: GLenum,
+                    type_: GLenum,
+                    pixels: *mut c_void);
+    fn glFinish();
+}
+
+/// An offscreen framebuffer the `Renderer` draws into while it's alive.
+#[derive(Debug)]
+pub(crate) struct Offscreen {
+    width: i32,
+    height: i32,
+    framebuffer: GLuint,
+    texture: GLuint,
+    /// What to go back to when done.
+    previous_framebuffer: GLint,
+    previous_viewport: [GLint; 4],
+    previous_scissor: Option<[GLint; 4]>
+}
+
+impl Offscreen {
+    /// Make a framebuffer of the size and draw into it from now on.
+    ///
+    /// The scissor is off while drawing offscreen. Returns `None` if the
+    /// framebuffer couldn't be made, e.g because it's too large.
+    ///
+    /// The `Renderer` is only needed for its context to be current.
+    pub(crate) fn new(_renderer: &mut Renderer, width: i32, height: i32) -> Option<Self> {
+        if width <= 0 || height <= 0 {
+            return None
+        }
+        unsafe {
+            let mut offscreen = Offscreen { width,
+                                            height,
+                                            framebuffer: 0,
+                                            texture: 0,
+                                            previous_framebuffer: 0,
+                                            previous_viewport: [0; 4],
+                                            previous_scissor: None };
+            glGetIntegerv(GL_FRAMEBUFFER_BINDING, &mut offscreen.previous_framebuffer);
+            glGetIntegerv(GL_VIEWPORT, offscreen.previous_viewport.as_mut_ptr());
+            if glIsEnabled(GL_SCISSOR_TEST) != 0 {
+                let mut scissor = [0; 4];
+                glGetIntegerv(GL_SCISSOR_BOX, scissor.as_mut_ptr());
+                offscreen.previous_scissor = Some(scissor);
+                glDisable(GL_SCISSOR_TEST);
+            }
+            let mut previous_texture = 0;
+            glGetIntegerv(GL_TEXTURE_BINDING_2D, &mut previous_texture);
+            glGenTextures(1, &mut offscreen.texture);
+            glBindTexture(GL_TEXTURE_2D, offscreen.texture);
+            glTexImage2D(GL_TEXTURE_2D,
+                         0,
+                         GL_RGBA as GLint,
+                         width,
+                         height,
+                         0,
+                         GL_RGBA,
+                         GL_UNSIGNED_BYTE,
+                         ::std::ptr::null());
+            glBindTexture(GL_TEXTURE_2D, previous_texture as GLuint);
+            glGenFramebuffers(1, &mut offscreen.framebuffer);
+            glBindFramebuffer(GL_FRAMEBUFFER, offscreen.framebuffer);
+            glFramebufferTexture2D(GL_FRAMEBUFFER,
+                                   GL_COLOR_ATTACHMENT0,
+                                   GL_TEXTURE_2D,
+                                   offscreen.texture,
+                                   0);
+            // Dropping it restores what was bound before.
+            if glCheckFramebufferStatus(GL_FRAMEBUFFER) != GL_FRAMEBUFFER_COMPLETE {
+                wlr_log!(WLR_ERROR,
+                         "Could not make an offscreen framebuffer of {}x{}",
+                         width,
+                         height);
+                return None
+            }
+            glViewport(0, 0, width, height);
+            Some(offscreen)
+        }
+    }
+
+    /// Read back what was drawn, as `WL_SHM_FORMAT_ARGB8888` with a stride
+    /// of four bytes per pixel, top row first.
+    pub(crate) fn read_pixels(&self, data: &mut [u8]) -> bool {
+        let stride = self.width as usize * 4;
+        if data.len() < stride * self.height as usize {
+            return false
+        }
+        unsafe {
+            glFinish();
+            glReadPixels(0,
+                         0,
+                         self.width,
+                         self.height,
+                         GL_RGBA,
+                         GL_UNSIGNED_BYTE,
+                         data.as_mut_ptr() as *mut c_void);
+        }
+        // GL reads the bottom row first, and the bytes of ARGB8888 are in
+        // BGRA order.
+        let rows = self.height as usize;
+        for row in 0..rows / 2 {
+            let (top, bottom) = data.split_at_mut((rows - row - 1) * stride);
+            top[row * stride..(row + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+        for pixel in data[..stride * rows].chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+        true
+    }
+}
+
+impl Drop for Offscreen {
+    fn drop(&mut self) {
+        unsafe {
+            glBindFramebuffer(GL_FRAMEBUFFER, self.previous_framebuffer as GLuint);
+            let [x, y, width, height] = self.previous_viewport;
+            glViewport(x, y, width, height);
+            if let Some([x, y, width, height]) = self.previous_scissor {
+                glEnable(GL_SCISSOR_TEST);
+                glScissor(x, y, width, height);
+            }
+            glDeleteFramebuffers(1, &self.framebuffer);
+            glDeleteTextures(1, &self.texture);
+        }
+    }
+}
@@ -8,9 +8,11 @@ use wlroots_sys::{wl_shm_format, wlr_backend, wlr_backend_get_renderer,
                   wlr_render_ellipse_with_matrix, wlr_render_quad_with_matrix, wlr_render_rect,
                   wlr_render_texture, wlr_render_texture_with_matrix, wlr_renderer,
                   wlr_renderer_begin, wlr_renderer_clear, wlr_renderer_destroy, wlr_renderer_end,
-                  wlr_texture_from_pixels, wlr_texture_destroy, wlr_renderer_scissor};
+                  wlr_texture_from_pixels, wlr_texture_destroy, wlr_renderer_scissor,
+                  wlr_renderer_read_pixels};
 
-use {area::Area, output::Output, render::{PixmanRegion, texture::Texture}};
+use {area::Area, extensions::linux_dmabuf, output::Output,
+     render::{matrix, PixmanRegion, texture::Texture}, stats, surface::Surface};
 
 /// A generic interface for rendering to the screen.
 ///
@@ -43,10 +45,18 @@ impl GenericRenderer {
         GenericRenderer { renderer }
     }
 
+    /// Get the formats and modifiers of the dmabufs the renderer can make
+    /// textures of.
+    pub fn dmabuf_texture_formats(&self) -> Vec<linux_dmabuf::Format> {
+        unsafe { linux_dmabuf::formats(self.renderer) }
+    }
+
     /// Drops a texture that was created explicitly through the renderer.
     ///
     /// This must be done before rendering has begun, which is why this is here.
     pub fn drop_texture(&self, texture: Texture<'static>) {
+        let (width, height) = texture.size();
+        stats::freed(stats::Kind::Textures, texture_bytes(width, height));
         unsafe {
             wlr_texture_destroy(texture.as_ptr());
         }
@@ -175,6 +185,42 @@ impl<'output> Renderer<'output> {
         unsafe { wlr_renderer_scissor(self.renderer, area_ptr) }
     }
 
+    /// Reads pixels out of the buffer that is being rendered to, into `data`.
+    ///
+    /// The `width` x `height` rectangle at (`src_x`, `src_y`) is copied to
+    /// (`dst_x`, `dst_y`) in `data`, which has `stride` bytes per row.
+    ///
+    /// Returns false if the pixels could not be read or `data` is too small.
+    pub fn read_pixels(&mut self,
+                       format: wl_shm_format,
+                       stride: u32,
+                       width: u32,
+                       height: u32,
+                       src_x: u32,
+                       src_y: u32,
+                       dst_x: u32,
+                       dst_y: u32,
+                       data: &mut [u8])
+                       -> bool {
+        if (stride as usize) * ((dst_y + height) as usize) > data.len() {
+            return false
+        }
+        unsafe {
+            let mut flags = 0;
+            wlr_renderer_read_pixels(self.renderer,
+                                     format,
+                                     &mut flags,
+                                     stride,
+                                     width,
+                                     height,
+                                     src_x,
+                                     src_y,
+                                     dst_x,
+                                     dst_y,
+                                     data.as_mut_ptr() as *mut c_void)
+        }
+    }
+
     /// Renders a solid quad in the specified color.
     pub fn render_colored_quad(&mut self, color: [f32; 4], matrix: [f32; 9]) {
         unsafe { wlr_render_quad_with_matrix(self.renderer, color.as_ptr(), matrix.as_ptr()) }
@@ -189,6 +235,24 @@ impl<'output> Renderer<'output> {
     pub fn render_colored_rect(&mut self, area: Area, color: [f32; 4], matrix: [f32; 9]) {
         unsafe { wlr_render_rect(self.renderer, &area.into(), color.as_ptr(), matrix.as_ptr()) }
     }
+
+    /// Renders a surface into `area` of the output, in output buffer
+    /// coordinates, i.e already multiplied by the scale of the output.
+    ///
+    /// The buffer is turned the right way up by the inverse of its
+    /// `Surface::buffer_transform` and stretched over `area`, so buffers of
+    /// another scale than the output aren't distorted.
+    ///
+    /// Returns false if the surface has nothing to render.
+    pub fn render_surface(&mut self, surface: &Surface, area: Area) -> bool {
+        let projection = self.output.transform_matrix();
+        let matrix =
+            matrix::project_box(area, surface.buffer_transform().invert(), 0.0, projection);
+        match surface.texture() {
+            Some(texture) => self.render_texture_with_matrix(&texture, matrix),
+            None => false
+        }
+    }
 }
 
 impl<'output> Drop for Renderer<'output> {
@@ -216,6 +280,15 @@ unsafe fn create_texture_from_pixels(renderer: *mut wlr_renderer,
     if texture.is_null() {
         None
     } else {
-        Some(Texture::from_ptr(texture))
+        let texture = Texture::from_ptr(texture);
+        let (width, height) = texture.size();
+        stats::allocated(stats::Kind::Textures, texture_bytes(width, height));
+        Some(texture)
     }
 }
+
+/// Roughly how much memory a texture takes up, assuming 4 bytes a pixel
+/// whatever the format it was uploaded in.
+fn texture_bytes(width: c_int, height: c_int) -> usize {
+    (width.max(0) as usize) * (height.max(0) as usize) * 4
+}
@@ -0,0 +1,227 @@
+//! Damage driven frame production for PipeWire screencasts.
+//!
+//! xdg-desktop-portal expects the compositor to export what is being shared
+//! as a PipeWire video node. `Screencast` does the compositor side of that:
+//! it collects damage for an output (or a region of it, e.g the geometry of a
+//! window), and only when something in it changed reads back the pixels and
+//! hands them to a `Node`. `Node` is implemented on top of the PipeWire
+//! bindings of your choice, which only need to negotiate the format and queue
+//! the buffers they are given.
+//!
+//! Windows can also be cast on their own with `Source::Window`, which draws
+//! them offscreen with `capture::capture_view`.
+//!
+//! ```rust,no_run,ignore
+//! // In the output damage handler.
+//! screencast.damage(damaged_area);
+//! // At the end of the frame, before the `Renderer` is dropped.
+//! screencast.render(&mut renderer, utils::current_time());
+//! ```
+
+use std::time::Duration;
+
+use wlroots_sys::{wl_shm_format, wl_shm_format::WL_SHM_FORMAT_ARGB8888};
+
+use {area::{Area, IntersectionResult, Origin, Size},
+     capture,
+     render::Renderer,
+     stats,
+     utils::Id};
+
+/// How many damaged areas are kept before giving up and sending the
+/// whole frame.
+const MAX_DAMAGE: usize = 32;
+
+/// What part of the output is cast.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Source {
+    /// The whole output.
+    Output,
+    /// A region of the output, in output buffer coordinates.
+    Region(Area),
+    /// A window on its own, wherever it is. Damage anywhere on the output
+    /// produces a new frame.
+    Window(Id)
+}
+
+/// The consuming end of a screencast, usually a PipeWire stream.
+pub trait Node {
+    /// The size or format of the frames changed.
+    ///
+    /// Called before the first frame, and before a frame whose size differs
+    /// from the previous one.
+    fn format(&mut self, width: u32, height: u32, stride: u32, format: wl_shm_format);
+
+    /// A new frame is ready.
+    ///
+    /// `damage` is what changed since the previous frame, relative to the
+    /// frame.
+    fn frame(&mut self, data: &[u8], damage: &[Area], time: Duration);
+}
+
+/// Produces frames for a `Node` whenever the cast part of an output changes.
+pub struct Screencast {
+    source: Source,
+    node: Box<Node>,
+    min_interval: Option<Duration>,
+    last_frame: Option<Duration>,
+    damage: Vec<Area>,
+    full_damage: bool,
+    size: Option<Size>,
+    paused: bool,
+    buffer: stats::PixelBuffer
+}
+
+impl Screencast {
+    /// Start a screencast. The first frame is produced on the next call to
+    /// `render`, regardless of damage.
+    pub fn new(source: Source, node: Box<Node>) -> Self {
+        Screencast { source,
+                     node,
+                     min_interval: None,
+                     last_frame: None,
+                     damage: Vec::new(),
+                     full_damage: true,
+                     size: None,
+                     paused: false,
+                     buffer: stats::PixelBuffer::new() }
+    }
+
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Change what is cast, e.g because the window that is shared moved.
+    pub fn set_source(&mut self, source: Source) {
+        if self.source != source {
+            self.source = source;
+            self.full_damage = true;
+        }
+    }
+
+    /// Limit how many frames per second are produced. `None` (the default)
+    /// produces a frame for every damaged output frame.
+    pub fn set_max_framerate(&mut self, fps: Option<u32>) {
+        self.min_interval = fps.and_then(|fps| {
+                                             if fps == 0 {
+                                                 None
+                                             } else {
+                                                 Some(Duration::from_secs(1) / fps)
+                                             }
+                                         });
+    }
+
+    /// Pause or resume producing frames, e.g when the stream is paused by the
+    /// consumer. Damage keeps being collected while paused.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused
+    }
+
+    /// Record that an area of the output, in output buffer coordinates,
+    /// changed.
+    pub fn damage(&mut self, area: Area) {
+        if self.full_damage {
+            return
+        }
+        if self.damage.len() >= MAX_DAMAGE {
+            self.damage.clear();
+            self.full_damage = true;
+        } else {
+            self.damage.push(area)
+        }
+    }
+
+    /// Produce a frame if the cast region was damaged.
+    ///
+    /// Call this after rendering the output, before the `Renderer` is dropped.
+    /// `time` is the presentation time of the frame.
+    ///
+    /// Returns true if a frame was sent to the `Node`.
+    pub fn render(&mut self, renderer: &mut Renderer, time: Duration) -> bool {
+        if self.paused {
+            return false
+        }
+        if let (Some(min_interval), Some(last_frame)) = (self.min_interval, self.last_frame) {
+            if time < last_frame + min_interval {
+                return false
+            }
+        }
+        let (width, height) = renderer.output.size();
+        let output_area = Area::new(Origin::default(), Size::new(width, height));
+        let region = match self.source {
+            Source::Output => output_area,
+            Source::Region(region) => match region.intersection(output_area) {
+                IntersectionResult::Intersection(region) => region,
+                IntersectionResult::NoIntersection => return false
+            },
+            Source::Window(id) => return self.render_window(renderer, id, time)
+        };
+        let resized = self.size != Some(region.size);
+        let damage: Vec<Area> = if resized || self.full_damage {
+            vec![Area::new(Origin::default(), region.size)]
+        } else {
+            self.damage.iter()
+                .filter_map(|&area| match area.intersection(region) {
+                    IntersectionResult::Intersection(area) => {
+                        let origin = Origin::new(area.origin.x - region.origin.x,
+                                                 area.origin.y - region.origin.y);
+                        Some(Area::new(origin, area.size))
+                    }
+                    IntersectionResult::NoIntersection => None
+                })
+                .collect()
+        };
+        if damage.is_empty() {
+            // Nothing changed in the cast region, forget about the rest.
+            self.damage.clear();
+            return false
+        }
+        let (frame_width, frame_height) = (region.size.width as u32, region.size.height as u32);
+        let stride = frame_width * 4;
+        self.buffer.resize((stride * frame_height) as usize);
+        if !renderer.read_pixels(WL_SHM_FORMAT_ARGB8888,
+                                 stride,
+                                 frame_width,
+                                 frame_height,
+                                 region.origin.x as u32,
+                                 region.origin.y as u32,
+                                 0,
+                                 0,
+                                 &mut self.buffer) {
+            // Keep the damage, so it's sent with the next frame that can be
+            // read.
+            return false
+        }
+        self.damage.clear();
+        self.full_damage = false;
+        if resized {
+            self.node.format(frame_width, frame_height, stride, WL_SHM_FORMAT_ARGB8888);
+            self.size = Some(region.size);
+        }
+        self.node.frame(&self.buffer, &damage, time);
+        self.last_frame = Some(time);
+        true
+    }
+
+    fn render_window(&mut self, renderer: &mut Renderer, id: Id, time: Duration) -> bool {
+        if self.damage.is_empty() && !self.full_damage {
+            return false
+        }
+        let capture = match capture::window(id).and_then(|window| {
+                                                   capture::capture_view(renderer, &window)
+                                               }) {
+            Some(capture) => capture,
+            None => return false
+        };
+        self.damage.clear();
+        self.full_damage = false;
+        let size = Size::new(capture.width as i32, capture.height as i32);
+        if self.size != Some(size) {
+            self.node.format(capture.width, capture.height, capture.stride, WL_SHM_FORMAT_ARGB8888);
+            self.size = Some(size);
+        }
+        self.node.frame(&capture.data, &[Area::new(Origin::default(), size)], time);
+        self.last_frame = Some(time);
+        true
+    }
+}
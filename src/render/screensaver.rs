@@ -0,0 +1,175 @@
+//! A screensaver that takes over rendering while the seats are idle.
+//!
+//! Once no input has been seen for the configured timeout, `Screensaver::render`
+//! starts returning true after drawing the screensaver, and the compositor
+//! should skip its normal rendering for that frame:
+//!
+//! ```rust,no_run,ignore
+//! fn on_frame(&mut self, compositor_handle: compositor::Handle, output_handle: output::Handle) {
+//!     with_handles!([(compositor: {compositor_handle}), (output: {output_handle})] => {
+//!         let state: &mut State = compositor.downcast();
+//!         let renderer = compositor.renderer.as_mut().unwrap();
+//!         let mut renderer = renderer.render(output, None);
+//!         if state.screensaver.render(&mut renderer) {
+//!             return
+//!         }
+//!         // Render the views like usual.
+//!     }).unwrap();
+//! }
+//! ```
+//!
+//! Activity is picked up from the `bus::SeatActivity` events, so anything sent
+//! through a `Seat` wakes the outputs up again. Frames are scheduled on every
+//! output when the screensaver starts and stops, and continuously while it
+//! runs so it can be animated.
+
+use std::{cell::RefCell, ptr, rc::Rc, time::{Duration, Instant}};
+
+use libc;
+use wayland_sys::server::{wl_event_source, WAYLAND_SERVER_HANDLE};
+
+use {bus::{self, SeatActivity, Subscription},
+     compositor::Compositor,
+     render::Renderer,
+     snapshot,
+     utils::ToMs};
+
+/// Draws the screensaver on one output.
+///
+/// The `Duration` is how long the screensaver has been running, the output is
+/// available as `renderer.output`.
+pub type Draw = Box<FnMut(&mut Renderer, Duration)>;
+
+struct State {
+    timeout: Duration,
+    started: Option<Instant>,
+    timer: *mut wl_event_source
+}
+
+pub struct Screensaver {
+    state: Rc<RefCell<State>>,
+    draw: Draw,
+    subscription: Subscription
+}
+
+impl Screensaver {
+    /// Start the screensaver whenever the seats have been idle for `timeout`.
+    pub fn new<F>(compositor: &Compositor, timeout: Duration, draw: F) -> Self
+        where F: FnMut(&mut Renderer, Duration) + 'static
+    {
+        let state = Rc::new(RefCell::new(State { timeout,
+                                                 started: None,
+                                                 timer: ptr::null_mut() }));
+        unsafe {
+            let timer = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                      wl_event_loop_add_timer,
+                                      compositor.event_loop,
+                                      screensaver_timer_fired,
+                                      &*state as *const RefCell<State> as *mut _);
+            state.borrow_mut().timer = timer;
+        }
+        state.borrow().rearm();
+        let activity_state = state.clone();
+        let subscription = bus::subscribe(move |_: &SeatActivity| {
+                                              activity_state.borrow_mut().activity()
+                                          });
+        Screensaver { state,
+                      draw: Box::new(draw),
+                      subscription }
+    }
+
+    /// Whether the screensaver is currently running.
+    pub fn active(&self) -> bool {
+        self.state.borrow().started.is_some()
+    }
+
+    /// Reset the idle timeout, e.g for activity that doesn't go through a
+    /// `Seat`.
+    pub fn notify_activity(&mut self) {
+        self.state.borrow_mut().activity()
+    }
+
+    /// Change how long the seats need to be idle before the screensaver
+    /// starts.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        let mut state = self.state.borrow_mut();
+        state.timeout = timeout;
+        if state.started.is_none() {
+            state.rearm()
+        }
+    }
+
+    /// Start the screensaver right away.
+    pub fn start(&mut self) {
+        self.state.borrow_mut().start()
+    }
+
+    /// Draw the screensaver if it's running.
+    ///
+    /// Returns false, without drawing anything, if it isn't running.
+    pub fn render(&mut self, renderer: &mut Renderer) -> bool {
+        let started = match self.state.borrow().started {
+            Some(started) => started,
+            None => return false
+        };
+        (self.draw)(renderer, started.elapsed());
+        renderer.output.schedule_frame();
+        true
+    }
+}
+
+impl State {
+    fn activity(&mut self) {
+        if self.started.take().is_some() {
+            schedule_frames();
+        }
+        self.rearm()
+    }
+
+    fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+            schedule_frames();
+        }
+    }
+
+    fn rearm(&self) {
+        if self.timer.is_null() {
+            return
+        }
+        // A timeout of 0 would disarm the timer instead.
+        let ms = ::std::cmp::max(self.timeout.to_ms(), 1);
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_source_timer_update,
+                          self.timer,
+                          ms as libc::c_int);
+        }
+    }
+}
+
+impl Drop for Screensaver {
+    fn drop(&mut self) {
+        bus::unsubscribe(self.subscription);
+        let timer = self.state.borrow().timer;
+        if !timer.is_null() {
+            unsafe {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, timer);
+            }
+        }
+    }
+}
+
+fn schedule_frames() {
+    for output in unsafe { snapshot::outputs() } {
+        output.run(|output| output.schedule_frame()).ok();
+    }
+}
+
+unsafe extern "C" fn screensaver_timer_fired(data: *mut libc::c_void) -> libc::c_int {
+    let state = &*(data as *const RefCell<State>);
+    if let Ok(mut state) = state.try_borrow_mut() {
+        state.start()
+    }
+    0
+}
@@ -0,0 +1,104 @@
+//! Stereo rendering, for driving a head mounted display as a regular output.
+//!
+//! `Stereo` splits the output buffer into a left and a right eye viewport and
+//! calls your draw function once per eye, with a projection matrix that maps
+//! the usual output coordinates into that eye's half of the buffer. Once both
+//! eyes are drawn an optional `DistortionPass` runs, which is where lens
+//! distortion and chromatic aberration correction belong.
+//!
+//! The output is still driven by the normal backend, this does not lease the
+//! display away from the compositor.
+
+use area::{Area, Origin, Size};
+use output::Output;
+use render::{matrix, Renderer};
+
+/// One of the two eyes of a stereo view.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Eye {
+    Left,
+    Right
+}
+
+/// Where and how to draw the scene for one eye.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EyeView {
+    pub eye: Eye,
+    /// The part of the output buffer this eye is drawn to, in buffer
+    /// coordinates.
+    pub viewport: Area,
+    /// Use this in place of `Output::transform_matrix` when projecting
+    /// things for this eye.
+    pub projection: [f32; 9]
+}
+
+/// Post processing applied to the whole buffer after both eyes are drawn.
+pub trait DistortionPass {
+    fn apply(&mut self, renderer: &mut Renderer, views: &[EyeView; 2]);
+}
+
+/// Renders an output as two side by side per eye viewports.
+#[derive(Default)]
+pub struct Stereo {
+    eye_offset: f32,
+    distortion: Option<Box<DistortionPass>>
+}
+
+impl Stereo {
+    pub fn new() -> Self {
+        Stereo::default()
+    }
+
+    /// Shift the two eyes towards each other by `offset` pixels, to match the
+    /// distance between the lens centers of the headset.
+    ///
+    /// Defaults to 0.
+    pub fn set_eye_offset(&mut self, offset: f32) {
+        self.eye_offset = offset
+    }
+
+    /// Set the pass that runs after both eyes are drawn, replacing the
+    /// previous one.
+    pub fn set_distortion_pass<T>(&mut self, pass: T) -> Option<Box<DistortionPass>>
+        where T: Into<Option<Box<DistortionPass>>>
+    {
+        ::std::mem::replace(&mut self.distortion, pass.into())
+    }
+
+    /// Compute the viewports and projections for both eyes of the output.
+    pub fn views(&self, output: &Output) -> [EyeView; 2] {
+        let (width, height) = output.size();
+        let half = width / 2;
+        let shift = if width > 0 {
+            self.eye_offset * 2.0 / width as f32
+        } else {
+            0.0
+        };
+        let view = |eye, x, center: f32| {
+            let eye_matrix = matrix::multiply(matrix::translate(center, 0.0),
+                                              matrix::scale(0.5, 1.0));
+            EyeView { eye,
+                      viewport: Area::new(Origin::new(x, 0), Size::new(half, height)),
+                      projection: matrix::multiply(eye_matrix, output.transform_matrix()) }
+        };
+        [view(Eye::Left, 0, -0.5 + shift),
+         view(Eye::Right, half, 0.5 - shift)]
+    }
+
+    /// Draw the scene once per eye, then apply the distortion pass.
+    ///
+    /// Drawing is clipped to the eye's viewport while `draw` runs.
+    pub fn render<F>(&mut self, renderer: &mut Renderer, mut draw: F)
+        where F: FnMut(&mut Renderer, &EyeView)
+    {
+        let views = self.views(renderer.output);
+        for view in &views {
+            renderer.render_scissor(view.viewport);
+            draw(renderer, view);
+        }
+        renderer.render_scissor(None);
+        if let Some(distortion) = self.distortion.as_mut() {
+            distortion.apply(renderer, &views);
+        }
+    }
+}
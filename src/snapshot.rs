@@ -0,0 +1,400 @@
+//! Structured snapshots of the compositor state, meant for bug reports.
+//!
+//! A [`Snapshot`](./struct.Snapshot.html) is produced by
+//! `Compositor::dump_state`. It only contains owned data, so it can be kept
+//! around, printed, or (with the `serde` feature) serialized and sent over
+//! an IPC socket.
+//!
+//! If the compositor was built with `compositor::Builder::dump_state_on_sigusr1`
+//! the snapshot is also written to a file every time the process receives
+//! `SIGUSR1`.
+
+use std::fmt;
+
+use wlroots_sys::wlr_input_device_type::{self, *};
+
+use {area::{Area, Origin, Size},
+     compositor::{self, Compositor},
+     input::{self, Device},
+     output::{self, Output},
+     seat::{self, Capability, Seat},
+     shell::{layer_shell, xdg_shell, xdg_shell_v6},
+     surface,
+     trace,
+     utils::Id,
+     xwayland};
+
+/// Everything the compositor has created that should show up in a state dump.
+///
+/// Handles are only ever added here, dead ones are pruned when new ones are
+/// tracked and whenever a snapshot is taken.
+#[derive(Default)]
+pub(crate) struct Tracked {
+    outputs: Vec<output::Handle>,
+    xdg_surfaces: Vec<xdg_shell::Handle>,
+    xdg_v6_surfaces: Vec<xdg_shell_v6::Handle>,
+    layer_surfaces: Vec<layer_shell::Handle>,
+    xwayland_surfaces: Vec<xwayland::surface::Handle>,
+    seats: Vec<seat::Handle>,
+    devices: Vec<input::Handle>
+}
+
+/// A snapshot of the compositor state at one point in time.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Snapshot {
+    /// The name of the Wayland socket clients connect to.
+    pub socket_name: String,
+    /// The Wayland globals advertised by the compositor.
+    pub globals: Vec<&'static str>,
+    /// Whether an Xwayland server was started.
+    pub xwayland: bool,
+    /// Outputs that were accepted by the output manager.
+    pub outputs: Vec<OutputInfo>,
+    /// XDG shell surfaces, from both the stable and the v6 protocol.
+    pub surfaces: Vec<SurfaceInfo>,
+    /// Seats created with `Seat::create`.
+    pub seats: Vec<SeatInfo>,
+    /// Input devices that were accepted by the input manager.
+    pub devices: Vec<DeviceInfo>,
+    /// The most recent events, see the `trace` module.
+    pub trace: Vec<trace::Event>
+}
+
+/// Description of an `Output`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct OutputInfo {
+    pub id: Id,
+    pub name: String,
+    pub make: String,
+    pub model: String,
+    pub enabled: bool,
+    pub scale: f32,
+    /// Whether the output is part of an `output::Layout`.
+    pub in_layout: bool,
+    /// Position in layout coordinates and the effective resolution.
+    pub geometry: Area,
+    /// The current mode as `(width, height, refresh)`.
+    pub mode: Option<(i32, i32, i32)>,
+    /// All modes the output supports, in the same format.
+    pub modes: Vec<(i32, i32, i32)>
+}
+
+/// Description of an XDG shell surface.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SurfaceInfo {
+    pub id: Id,
+    /// Either `"xdg_shell"` or `"xdg_shell_v6"`.
+    pub shell: &'static str,
+    /// Either `"toplevel"`, `"popup"`, or `"none"`.
+    pub role: &'static str,
+    pub title: Option<String>,
+    pub app_id: Option<String>,
+    pub geometry: Area
+}
+
+/// Description of a `Seat`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SeatInfo {
+    pub id: Id,
+    pub name: Option<String>,
+    pub capabilities: Vec<&'static str>
+}
+
+/// Description of an input `Device`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceInfo {
+    pub id: Id,
+    pub name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    pub dev_type: &'static str,
+    pub vendor: u32,
+    pub product: u32,
+    pub output_name: Option<String>
+}
+
+impl Tracked {
+    pub(crate) fn add_output(&mut self, handle: output::Handle) {
+        self.prune();
+        self.outputs.push(handle)
+    }
+
+    pub(crate) fn add_xdg_surface(&mut self, handle: xdg_shell::Handle) {
+        self.prune();
+        self.xdg_surfaces.push(handle)
+    }
+
+    pub(crate) fn add_xdg_v6_surface(&mut self, handle: xdg_shell_v6::Handle) {
+        self.prune();
+        self.xdg_v6_surfaces.push(handle)
+    }
+
+    pub(crate) fn add_layer_surface(&mut self, handle: layer_shell::Handle) {
+        self.prune();
+        self.layer_surfaces.push(handle)
+    }
+
+    pub(crate) fn add_xwayland_surface(&mut self, handle: xwayland::surface::Handle) {
+        self.prune();
+        self.xwayland_surfaces.push(handle)
+    }
+
+    pub(crate) fn add_seat(&mut self, handle: seat::Handle) {
+        self.prune();
+        self.seats.push(handle)
+    }
+
+    pub(crate) fn add_device(&mut self, handle: input::Handle) {
+        self.prune();
+        self.devices.push(handle)
+    }
+
+    fn prune(&mut self) {
+        self.outputs.retain(|handle| handle.is_alive());
+        self.xdg_surfaces.retain(|handle| handle.is_alive());
+        self.xdg_v6_surfaces.retain(|handle| handle.is_alive());
+        self.layer_surfaces.retain(|handle| handle.is_alive());
+        self.xwayland_surfaces.retain(|handle| handle.is_alive());
+        self.seats.retain(|handle| handle.is_alive());
+        self.devices.retain(device_alive);
+    }
+}
+
+/// Track a newly created resource on the running compositor, if there is one.
+pub(crate) unsafe fn track<F: FnOnce(&mut Tracked)>(runner: F) {
+    if !compositor::COMPOSITOR_PTR.is_null() {
+        runner(&mut (*compositor::COMPOSITOR_PTR).tracked)
+    }
+}
+
+/// Handles to the outputs of the running compositor.
+pub(crate) unsafe fn outputs() -> Vec<output::Handle> {
+    if compositor::COMPOSITOR_PTR.is_null() {
+        return vec![]
+    }
+    let tracked = &mut (*compositor::COMPOSITOR_PTR).tracked;
+    tracked.prune();
+    tracked.outputs.clone()
+}
+
+/// Handles to the seats of the running compositor.
+pub(crate) unsafe fn seats() -> Vec<seat::Handle> {
+    if compositor::COMPOSITOR_PTR.is_null() {
+        return vec![]
+    }
+    let tracked = &mut (*compositor::COMPOSITOR_PTR).tracked;
+    tracked.prune();
+    tracked.seats.clone()
+}
+
+/// Handles to the stable and v6 XDG surfaces of the running compositor.
+pub(crate) unsafe fn xdg_surfaces() -> (Vec<xdg_shell::Handle>, Vec<xdg_shell_v6::Handle>) {
+    if compositor::COMPOSITOR_PTR.is_null() {
+        return (vec![], vec![])
+    }
+    let tracked = &mut (*compositor::COMPOSITOR_PTR).tracked;
+    tracked.prune();
+    (tracked.xdg_surfaces.clone(), tracked.xdg_v6_surfaces.clone())
+}
+
+/// Handles to the main surfaces of all the shell surfaces of the running
+/// compositor: XDG toplevels and popups, layer surfaces and Xwayland
+/// surfaces.
+pub(crate) unsafe fn surfaces() -> Vec<surface::Handle> {
+    if compositor::COMPOSITOR_PTR.is_null() {
+        return vec![]
+    }
+    let tracked = &mut (*compositor::COMPOSITOR_PTR).tracked;
+    tracked.prune();
+    // Xwayland surfaces have no surface until they are associated with one.
+    tracked.xdg_surfaces.iter()
+           .map(|handle| (*handle.as_ptr()).surface)
+           .chain(tracked.xdg_v6_surfaces.iter().map(|handle| (*handle.as_ptr()).surface))
+           .chain(tracked.layer_surfaces.iter().map(|handle| (*handle.as_ptr()).surface))
+           .chain(tracked.xwayland_surfaces.iter().map(|handle| (*handle.as_ptr()).surface))
+           .filter(|surface| !surface.is_null())
+           .map(|surface| surface::Handle::from_ptr(surface))
+           .collect()
+}
+
+impl Snapshot {
+    pub(crate) fn take(compositor: &mut Compositor) -> Self {
+        compositor.tracked.prune();
+        let tracked = &compositor.tracked;
+        let mut surfaces: Vec<SurfaceInfo> =
+            tracked.xdg_surfaces.iter()
+                .filter_map(|handle| handle.run(|surface| surface.info()).ok())
+                .collect();
+        surfaces.extend(tracked.xdg_v6_surfaces.iter()
+                        .filter_map(|handle| handle.run(|surface| surface.info()).ok()));
+        Snapshot { socket_name: compositor.socket_name().into(),
+                   globals: compositor.globals(),
+                   xwayland: compositor.xwayland_started(),
+                   outputs: tracked.outputs.iter()
+                       .filter_map(|handle| handle.run(|output| OutputInfo::new(output)).ok())
+                       .collect(),
+                   surfaces,
+                   seats: tracked.seats.iter()
+                       .filter_map(|handle| handle.run(|seat| SeatInfo::new(seat)).ok())
+                       .collect(),
+                   devices: tracked.devices.iter().filter_map(device_info).collect(),
+                   trace: trace::events() }
+    }
+}
+
+impl OutputInfo {
+    pub(crate) fn new(output: &Output) -> Self {
+        let (x, y) = output.layout_space_pos();
+        let (width, height) = output.effective_resolution();
+        let mode = |mode: output::Mode| {
+            let (width, height) = mode.dimensions();
+            (width, height, mode.refresh())
+        };
+        OutputInfo { id: output.id(),
+                     name: output.name(),
+                     make: output.make(),
+                     model: output.model(),
+                     enabled: output.enabled(),
+                     scale: output.scale(),
+                     in_layout: output.in_layout(),
+                     geometry: Area::new(Origin::new(x, y), Size::new(width, height)),
+                     mode: output.current_mode().map(mode),
+                     modes: output.modes().into_iter().map(mode).collect() }
+    }
+}
+
+impl SeatInfo {
+    fn new(seat: &mut Seat) -> Self {
+        let capabilities = seat.capabilities();
+        let mut names = vec![];
+        if capabilities.contains(Capability::Pointer) {
+            names.push("pointer")
+        }
+        if capabilities.contains(Capability::Keyboard) {
+            names.push("keyboard")
+        }
+        if capabilities.contains(Capability::Touch) {
+            names.push("touch")
+        }
+        SeatInfo { id: seat.id(),
+                   name: seat.name(),
+                   capabilities: names }
+    }
+}
+
+impl DeviceInfo {
+    pub(crate) fn new(device: &Device) -> Self {
+        DeviceInfo { id: device.id(),
+                     name: device.name(),
+                     dev_type: dev_type_name(device.dev_type()),
+                     vendor: device.vendor(),
+                     product: device.product(),
+                     output_name: device.output_name() }
+    }
+}
+
+fn dev_type_name(dev_type: wlr_input_device_type) -> &'static str {
+    match dev_type {
+        WLR_INPUT_DEVICE_KEYBOARD => "keyboard",
+        WLR_INPUT_DEVICE_POINTER => "pointer",
+        WLR_INPUT_DEVICE_TOUCH => "touch",
+        WLR_INPUT_DEVICE_TABLET_TOOL => "tablet_tool",
+        WLR_INPUT_DEVICE_TABLET_PAD => "tablet_pad",
+        WLR_INPUT_DEVICE_SWITCH => "switch"
+    }
+}
+
+fn device_alive(handle: &input::Handle) -> bool {
+    use input::Handle::*;
+    match *handle {
+        Keyboard(ref handle) => handle.is_alive(),
+        Pointer(ref handle) => handle.is_alive(),
+        Touch(ref handle) => handle.is_alive(),
+        TabletPad(ref handle) => handle.is_alive(),
+        TabletTool(ref handle) => handle.is_alive(),
+        Switch(ref handle) => handle.is_alive()
+    }
+}
+
+fn device_info(handle: &input::Handle) -> Option<DeviceInfo> {
+    use input::Handle::*;
+    match *handle {
+        Keyboard(ref handle) => handle.run(|dev| DeviceInfo::new(dev.input_device())),
+        Pointer(ref handle) => handle.run(|dev| DeviceInfo::new(dev.input_device())),
+        Touch(ref handle) => handle.run(|dev| DeviceInfo::new(dev.input_device())),
+        TabletPad(ref handle) => handle.run(|dev| DeviceInfo::new(dev.input_device())),
+        TabletTool(ref handle) => handle.run(|dev| DeviceInfo::new(dev.input_device())),
+        Switch(ref handle) => handle.run(|dev| DeviceInfo::new(dev.input_device()))
+    }.ok()
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "socket: {}", self.socket_name)?;
+        writeln!(f, "globals: {}", self.globals.join(", "))?;
+        writeln!(f, "xwayland: {}", if self.xwayland { "started" } else { "off" })?;
+        writeln!(f, "outputs:")?;
+        for output in &self.outputs {
+            let Area { origin, size } = output.geometry;
+            writeln!(f,
+                     "  [{}] {} ({} {}) {}x{}+{}+{} scale {}{}{}",
+                     output.id,
+                     output.name,
+                     output.make,
+                     output.model,
+                     size.width,
+                     size.height,
+                     origin.x,
+                     origin.y,
+                     output.scale,
+                     if output.enabled { "" } else { " disabled" },
+                     if output.in_layout { "" } else { " (not in layout)" })?;
+        }
+        writeln!(f, "surfaces:")?;
+        for surface in &self.surfaces {
+            let Area { origin, size } = surface.geometry;
+            writeln!(f,
+                     "  [{}] {} {} app_id={:?} title={:?} {}x{}+{}+{}",
+                     surface.id,
+                     surface.shell,
+                     surface.role,
+                     surface.app_id,
+                     surface.title,
+                     size.width,
+                     size.height,
+                     origin.x,
+                     origin.y)?;
+        }
+        writeln!(f, "seats:")?;
+        for seat in &self.seats {
+            writeln!(f,
+                     "  [{}] {} [{}]",
+                     seat.id,
+                     seat.name.as_ref().map(|s| s.as_str()).unwrap_or("<unnamed>"),
+                     seat.capabilities.join(", "))?;
+        }
+        writeln!(f, "devices:")?;
+        for device in &self.devices {
+            writeln!(f,
+                     "  [{}] {} {} ({:04x}:{:04x})",
+                     device.id,
+                     device.dev_type,
+                     device.name.as_ref().map(|s| s.as_str()).unwrap_or("<unnamed>"),
+                     device.vendor,
+                     device.product)?;
+        }
+        writeln!(f, "trace:")?;
+        for event in &self.trace {
+            let millis = event.time.as_secs() * 1000 + u64::from(event.time.subsec_millis());
+            match event.id {
+                Some(id) => writeln!(f, "  {} {:?} [{}]", millis, event.kind, id)?,
+                None => writeln!(f, "  {} {:?}", millis, event.kind)?
+            }
+        }
+        Ok(())
+    }
+}
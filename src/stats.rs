@@ -0,0 +1,165 @@
+//! Memory usage of the compositor.
+//!
+//! Most of the memory a compositor holds on to is pixels: textures it
+//! uploaded itself, buffers frames are read back into for screencasts and
+//! remote outputs, and whatever it keeps around for clients, like clipboard
+//! contents that outlive the client that copied them. wlroots-rs accounts
+//! for the textures created through a `GenericRenderer` or `Renderer` and
+//! the buffers it reads frames into. Memory the compositor itself manages
+//! can be accounted for with `allocated` and `freed`:
+//!
+//! ```rust,no_run,ignore
+//! stats::allocated(stats::Kind::Clipboard, contents.len());
+//! // When the selection is replaced.
+//! stats::freed(stats::Kind::Clipboard, contents.len());
+//! ```
+//!
+//! `trim` asks everything that holds on to memory it doesn't strictly need
+//! to let go of it. Caches take part by subscribing to the `Trim` event on
+//! the `bus`. With `compositor::Builder::trim_on_memory_pressure` this
+//! happens automatically when the kernel reports memory pressure.
+
+use std::{cell::Cell, fs::File, io::Read, ops::{Deref, DerefMut}};
+
+use bus;
+
+/// Where the file the kernel reports memory pressure in is.
+const MEMORY_PRESSURE_PATH: &str = "/proc/pressure/memory";
+
+/// What memory is used for.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Kind {
+    /// Textures uploaded to the GPU.
+    Textures,
+    /// Pixel buffers in system memory, e.g frames read back from an output.
+    Buffers,
+    /// Data kept around for clients, like clipboard contents.
+    Clipboard
+}
+
+/// How many bytes are in use, per `Kind`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct MemoryUsage {
+    pub textures: usize,
+    pub buffers: usize,
+    pub clipboard: usize
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.textures + self.buffers + self.clipboard
+    }
+
+    fn get_mut(&mut self, kind: Kind) -> &mut usize {
+        match kind {
+            Kind::Textures => &mut self.textures,
+            Kind::Buffers => &mut self.buffers,
+            Kind::Clipboard => &mut self.clipboard
+        }
+    }
+}
+
+/// Published on the bus by `trim`. Listeners should drop whatever they can
+/// rebuild later, and report it with `freed`.
+#[derive(Debug, Clone, Copy)]
+pub struct Trim {
+    /// The memory in use when trimming started.
+    pub usage: MemoryUsage
+}
+
+thread_local!(static USAGE: Cell<MemoryUsage> = Cell::new(MemoryUsage::default()));
+
+/// Get the memory currently in use.
+pub fn memory_usage() -> MemoryUsage {
+    USAGE.with(|usage| usage.get())
+}
+
+/// Account for `bytes` more being used for `kind`.
+pub fn allocated(kind: Kind, bytes: usize) {
+    USAGE.with(|usage| {
+        let mut current = usage.get();
+        *current.get_mut(kind) += bytes;
+        usage.set(current)
+    })
+}
+
+/// Account for `bytes` no longer being used for `kind`.
+pub fn freed(kind: Kind, bytes: usize) {
+    USAGE.with(|usage| {
+        let mut current = usage.get();
+        {
+            let used = current.get_mut(kind);
+            *used = used.saturating_sub(bytes);
+        }
+        usage.set(current)
+    })
+}
+
+/// Ask every cache to evict what it can.
+///
+/// Returns how many bytes were freed.
+pub fn trim() -> usize {
+    let before = memory_usage();
+    bus::publish(&Trim { usage: before });
+    let after = memory_usage();
+    let freed = before.total().saturating_sub(after.total());
+    wlr_log!(WLR_INFO, "Trimmed {} bytes, {} bytes still in use", freed, after.total());
+    freed
+}
+
+/// Get the share of the last 10 seconds in which some tasks were stalled
+/// waiting for memory, between 0 and 1.
+///
+/// `None` if the kernel doesn't report memory pressure.
+pub fn memory_pressure() -> Option<f64> {
+    let mut contents = String::new();
+    File::open(MEMORY_PRESSURE_PATH).ok()?
+                                    .read_to_string(&mut contents)
+                                    .ok()?;
+    // e.g "some avg10=0.00 avg60=0.00 avg300=0.00 total=0"
+    let some = contents.lines().find(|line| line.starts_with("some "))?;
+    let avg10 = some.split_whitespace()
+                    .find(|field| field.starts_with("avg10="))?;
+    avg10["avg10=".len()..].parse::<f64>().ok().map(|percent| percent / 100.0)
+}
+
+/// A pixel buffer in system memory that is accounted for as `Kind::Buffers`.
+#[derive(Debug, Default)]
+pub(crate) struct PixelBuffer {
+    data: Vec<u8>
+}
+
+impl PixelBuffer {
+    pub(crate) fn new() -> Self {
+        PixelBuffer::default()
+    }
+
+    pub(crate) fn resize(&mut self, len: usize) {
+        let capacity = self.data.capacity();
+        self.data.resize(len, 0);
+        let new_capacity = self.data.capacity();
+        if new_capacity > capacity {
+            allocated(Kind::Buffers, new_capacity - capacity)
+        }
+    }
+}
+
+impl Deref for PixelBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl DerefMut for PixelBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl Drop for PixelBuffer {
+    fn drop(&mut self) {
+        freed(Kind::Buffers, self.data.capacity())
+    }
+}
@@ -0,0 +1,116 @@
+//! A ring buffer of recent significant events.
+//!
+//! Full debug logging is too slow and too noisy to leave on, but when
+//! something goes wrong it's usually the last few events that explain it.
+//! wlroots-rs records input, commits, mapping and focus changes here as they
+//! happen, keeping only the most recent ones. The trace is part of every
+//! `snapshot::Snapshot`, so it ends up in state dumps and crash reports.
+//!
+//! Events are small and recording one doesn't allocate once the ring is
+//! full. Key events deliberately don't record which key was pressed, so
+//! passwords don't end up in bug reports.
+
+use std::{cell::RefCell, collections::VecDeque, time::Duration};
+
+use utils::{self, Id};
+
+/// How many events are kept by default.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// What happened.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Kind {
+    /// A key was pressed or released.
+    Key { pressed: bool },
+    /// A pointer button was pressed or released.
+    Button { button: u32, pressed: bool },
+    /// A surface committed new state.
+    Commit,
+    /// A surface was mapped.
+    Map,
+    /// A surface was unmapped.
+    Unmap,
+    /// Keyboard focus moved, as decided by a `wm::focus::Policy`.
+    Focus,
+    /// An output was added.
+    OutputAdded,
+    /// An output was removed.
+    OutputRemoved,
+    /// An event recorded by the compositor itself, see `record_custom`.
+    Custom(&'static str)
+}
+
+/// One recorded event.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Event {
+    /// When it happened, on the same clock as `utils::current_time`.
+    pub time: Duration,
+    pub kind: Kind,
+    /// The output or view it happened to, if there is one.
+    pub id: Option<Id>
+}
+
+struct Ring {
+    enabled: bool,
+    capacity: usize,
+    events: VecDeque<Event>
+}
+
+thread_local! {
+    static RING: RefCell<Ring> = RefCell::new(Ring { enabled: true,
+                                                     capacity: DEFAULT_CAPACITY,
+                                                     events: VecDeque::new() });
+}
+
+/// Record an event.
+pub(crate) fn record(kind: Kind, id: Option<Id>) {
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        if !ring.enabled || ring.capacity == 0 {
+            return
+        }
+        if ring.events.len() >= ring.capacity {
+            ring.events.pop_front();
+        }
+        ring.events.push_back(Event { time: utils::current_time(),
+                                      kind,
+                                      id });
+    })
+}
+
+/// Record an event of the compositor's own, e.g a keybinding that ran or a
+/// workspace switch.
+pub fn record_custom(name: &'static str, id: Option<Id>) {
+    record(Kind::Custom(name), id)
+}
+
+/// The recorded events, oldest first.
+pub fn events() -> Vec<Event> {
+    RING.with(|ring| ring.borrow().events.iter().cloned().collect())
+}
+
+/// Forget all recorded events.
+pub fn clear() {
+    RING.with(|ring| ring.borrow_mut().events.clear())
+}
+
+/// Turn recording on or off. It's on by default.
+pub fn set_enabled(enabled: bool) {
+    RING.with(|ring| ring.borrow_mut().enabled = enabled)
+}
+
+/// Change how many events are kept, dropping the oldest ones if there are
+/// more than that already.
+pub fn set_capacity(capacity: usize) {
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        ring.capacity = capacity;
+        while ring.events.len() > capacity {
+            ring.events.pop_front();
+        }
+        let additional = capacity.saturating_sub(ring.events.len());
+        ring.events.reserve(additional);
+    })
+}
@@ -1,10 +1,17 @@
 //! Wrapper for the `wlr_box` type.
 //! Note that we renamed it to `Area` to avoid conflicts with Rust's Box.
 
-use libc::{c_double, c_float, c_int};
+//!
+//! The geometry itself is plain Rust, so it's there without the `sys`
+//! feature too. Only converting from and to `wlr_box` and the transforms
+//! need wlroots.
 
-use wlroots_sys::{wl_output_transform, wlr_box, wlr_box_closest_point, wlr_box_contains_point,
-                  wlr_box_empty, wlr_box_intersection, wlr_box_rotated_bounds, wlr_box_transform};
+use std::os::raw::{c_double, c_int};
+#[cfg(feature = "sys")]
+use std::os::raw::c_float;
+
+#[cfg(feature = "sys")]
+use wlroots_sys::{wl_output_transform, wlr_box, wlr_box_rotated_bounds, wlr_box_transform};
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 /// Result of applying an intersection of two `Area`s.
@@ -16,6 +23,7 @@ pub enum IntersectionResult {
 }
 
 #[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Origin {
     pub x: c_int,
     pub y: c_int
@@ -34,6 +42,7 @@ impl Into<Area> for Origin {
 }
 
 #[derive(Default, Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Size {
     pub width: c_int,
     pub height: c_int
@@ -52,6 +61,7 @@ impl Into<Area> for Size {
 }
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 /// Generic geometry-like struct. Container an origin (x, y) point and bounds
 /// (width, height).
 pub struct Area {
@@ -59,6 +69,7 @@ pub struct Area {
     pub size: Size
 }
 
+#[cfg(feature = "sys")]
 impl Into<wlr_box> for Area {
     fn into(self) -> wlr_box {
         wlr_box { x: self.origin.x,
@@ -74,6 +85,7 @@ impl Area {
     }
 
     /// Construct an Area from a `wlr_box`.
+    #[cfg(feature = "sys")]
     pub fn from_box(wlr_box: wlr_box) -> Self {
         Area { origin: Origin { x: wlr_box.x,
                                 y: wlr_box.y },
@@ -92,46 +104,81 @@ impl Area {
         Area { origin, ..self }
     }
 
+    /// Gets the smallest area that contains both areas, including the space
+    /// between them.
+    pub fn union(self, other: Area) -> Area {
+        let x = self.origin.x.min(other.origin.x);
+        let y = self.origin.y.min(other.origin.y);
+        let right = (self.origin.x + self.size.width).max(other.origin.x + other.size.width);
+        let bottom = (self.origin.y + self.size.height).max(other.origin.y + other.size.height);
+        Area::new(Origin::new(x, y), Size::new(right - x, bottom - y))
+    }
+
     /// Finds the closest point within the box to the given point.
     /// If the (x, y) point lies outside of the box, then it finds the closest
     /// corner and returns that.
     ///
     /// Returned value is in form of (x, y).
+    ///
+    /// Same as `wlr_box_closest_point`.
     pub fn closest_point(self, x: c_double, y: c_double) -> (c_double, c_double) {
-        unsafe {
-            let (mut dest_x, mut dest_y) = (0.0, 0.0);
-            wlr_box_closest_point(&mut self.into(), x, y, &mut dest_x, &mut dest_y);
-            (dest_x, dest_y)
-        }
+        let clamp = |value: c_double, start: c_int, length: c_int| {
+            let (start, end) = (c_double::from(start), c_double::from(start + length));
+            if value < start {
+                start
+            } else if value > end {
+                end
+            } else {
+                value
+            }
+        };
+        (clamp(x, self.origin.x, self.size.width), clamp(y, self.origin.y, self.size.height))
     }
 
     /// Gets the intersection of the two areas.
-    pub fn intersection(self, other_box: Area) -> IntersectionResult {
-        unsafe {
-            let res = Area::default();
-            let is_empty = wlr_box_intersection(&mut res.into(), &self.into(), &other_box.into());
-            if is_empty {
-                IntersectionResult::NoIntersection
-            } else {
-                IntersectionResult::Intersection(res)
-            }
+    ///
+    /// Same as `wlr_box_intersection`: areas that only touch, or where
+    /// either one is empty, don't intersect.
+    pub fn intersection(self, other: Area) -> IntersectionResult {
+        if self.is_empty() || other.is_empty() {
+            return IntersectionResult::NoIntersection
+        }
+        let x = self.origin.x.max(other.origin.x);
+        let y = self.origin.y.max(other.origin.y);
+        let right = (self.origin.x + self.size.width).min(other.origin.x + other.size.width);
+        let bottom = (self.origin.y + self.size.height).min(other.origin.y + other.size.height);
+        let res = Area::new(Origin::new(x, y), Size::new(right - x, bottom - y));
+        if res.is_empty() {
+            IntersectionResult::NoIntersection
+        } else {
+            IntersectionResult::Intersection(res)
         }
     }
 
     /// Determines if the box contains the given point.
+    ///
+    /// Same as `wlr_box_contains_point`: the right and bottom edges are
+    /// outside of the box.
     pub fn contains_point(self, x: c_double, y: c_double) -> bool {
-        unsafe { wlr_box_contains_point(&mut self.into(), x, y) }
+        if self.is_empty() {
+            return false
+        }
+        let (left, top) = (c_double::from(self.origin.x), c_double::from(self.origin.y));
+        let right = c_double::from(self.origin.x + self.size.width);
+        let bottom = c_double::from(self.origin.y + self.size.height);
+        x >= left && x < right && y >= top && y < bottom
     }
 
     /// Determines if the box is empty (e.g if the bounds give it an area of 0).
     pub fn is_empty(self) -> bool {
-        unsafe { wlr_box_empty(&mut self.into()) }
+        self.size.width <= 0 || self.size.height <= 0
     }
 
     /// Transforms the box coordinates and bounds according to the
     /// output transformation.
     ///
     /// e.g: If it's `WL_OUTPUT_TRANSFORM_90` then it will flip the Area 90° clockwise.
+    #[cfg(feature = "sys")]
     pub fn transform(self, transform: wl_output_transform, width: c_int, height: c_int) -> Area {
         unsafe {
             let res = Area::default();
@@ -141,6 +188,7 @@ impl Area {
     }
 
     /// Creates the smallest box that contains the box rotated about its center.
+    #[cfg(feature = "sys")]
     pub fn rotated_bounds(self, rotation: c_float) -> Area {
         unsafe {
             let dest = Area::default();
@@ -0,0 +1,103 @@
+//! Pointer barriers, lines that a `Cursor` can't be moved across.
+
+/// Which way a barrier runs through the layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    /// A line at a fixed x coordinate, spanning from `start` to `end` in y.
+    Vertical { x: f64 },
+    /// A line at a fixed y coordinate, spanning from `start` to `end` in x.
+    Horizontal { y: f64 }
+}
+
+/// A line in layout coordinates that relative cursor motion can't cross.
+///
+/// Barriers only apply to `Cursor::move_relative`. Warping the cursor
+/// ignores them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Barrier {
+    orientation: Orientation,
+    start: f64,
+    end: f64,
+    resistance: f64,
+    /// How far the cursor has pushed against a sticky barrier.
+    pressure: f64
+}
+
+impl Barrier {
+    /// A barrier along `x` from `y1` to `y2`.
+    pub fn vertical(x: f64, y1: f64, y2: f64) -> Self {
+        Barrier::new(Orientation::Vertical { x }, y1, y2)
+    }
+
+    /// A barrier along `y` from `x1` to `x2`.
+    pub fn horizontal(y: f64, x1: f64, x2: f64) -> Self {
+        Barrier::new(Orientation::Horizontal { y }, x1, x2)
+    }
+
+    fn new(orientation: Orientation, start: f64, end: f64) -> Self {
+        Barrier { orientation,
+                  start: start.min(end),
+                  end: start.max(end),
+                  resistance: 0.0,
+                  pressure: 0.0 }
+    }
+
+    /// Make the barrier sticky: it lets the cursor through once it has been
+    /// pushed against for `resistance` units, e.g to make the edge between
+    /// two outputs easier to hit.
+    ///
+    /// A resistance of zero (the default) makes the barrier solid.
+    pub fn sticky(self, resistance: f64) -> Self {
+        Barrier { resistance, ..self }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Constrain a move by `delta` from `from` so it does not cross this
+    /// barrier. Returns the (possibly) shortened delta.
+    fn constrain(&mut self, from: (f64, f64), delta: (f64, f64)) -> (f64, f64) {
+        let (line, along, pos, delta_across, delta_along) = match self.orientation {
+            Orientation::Vertical { x } => (x, from.1, from.0, delta.0, delta.1),
+            Orientation::Horizontal { y } => (y, from.0, from.1, delta.1, delta.0)
+        };
+        let target = pos + delta_across;
+        // The line itself belongs to the side with the larger coordinates.
+        let crosses = (pos < line && target >= line) || (pos >= line && target < line);
+        if !crosses || delta_across == 0.0 {
+            self.pressure = 0.0;
+            return delta
+        }
+        let fraction = (line - pos) / delta_across;
+        let crossing = along + delta_along * fraction;
+        if crossing < self.start || crossing > self.end {
+            return delta
+        }
+        let overshoot = (target - line).abs();
+        if self.resistance > 0.0 {
+            self.pressure += overshoot;
+            if self.pressure >= self.resistance {
+                self.pressure = 0.0;
+                return delta
+            }
+        }
+        let stop = if pos < line { line - 0.001 } else { line };
+        let delta_across = stop - pos;
+        match self.orientation {
+            Orientation::Vertical { .. } => (delta_across, delta.1),
+            Orientation::Horizontal { .. } => (delta.0, delta_across)
+        }
+    }
+}
+
+/// Constrain a move by `delta` from `from` against all of the barriers.
+pub(crate) fn constrain(barriers: &mut [Barrier],
+                        from: (f64, f64),
+                        mut delta: (f64, f64))
+                        -> (f64, f64) {
+    for barrier in barriers {
+        delta = barrier.constrain(from, delta);
+    }
+    delta
+}
@@ -0,0 +1,78 @@
+//! Remapping the buttons of pointer devices before their events reach the
+//! `cursor::Handler`.
+
+use input::pointer::event::{BTN_LEFT, BTN_RIGHT};
+
+/// What a button is turned into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapping {
+    /// Act like another button, e.g `pointer::event::BTN_MIDDLE`.
+    Button(u32),
+    /// Act like a key, given as an evdev keycode.
+    ///
+    /// The press and release are given to
+    /// `cursor::Handler::on_pointer_button_mapped`, which decides how to
+    /// send the key, e.g with `Seat::keyboard_notify_key`.
+    Key(u32),
+    /// Run a compositor action, identified by a number of the compositor's
+    /// choosing.
+    ///
+    /// Like keys, these go to `cursor::Handler::on_pointer_button_mapped`.
+    Action(u32),
+    /// Ignore the button.
+    Disabled
+}
+
+/// How the buttons of one device are remapped.
+///
+/// Set it with `Cursor::set_button_map`. Buttons without a mapping act like
+/// themselves.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ButtonMap {
+    mappings: Vec<(u32, Mapping)>,
+    middle_emulation: bool
+}
+
+impl ButtonMap {
+    pub fn new() -> Self {
+        ButtonMap::default()
+    }
+
+    /// Turn `button` into `mapping`, replacing any earlier mapping of it.
+    pub fn map(mut self, button: u32, mapping: Mapping) -> Self {
+        self.mappings.retain(|&(mapped, _)| mapped != button);
+        self.mappings.push((button, mapping));
+        self
+    }
+
+    /// Swap the left and right buttons, e.g for left handed use.
+    pub fn swap_left_right(self) -> Self {
+        self.map(BTN_LEFT, Mapping::Button(BTN_RIGHT))
+            .map(BTN_RIGHT, Mapping::Button(BTN_LEFT))
+    }
+
+    /// Press the middle button by pressing left and right together, e.g to
+    /// paste the primary selection on touchpads or mice without a middle
+    /// button.
+    ///
+    /// Like libinput does it, the first of the two buttons is held back
+    /// for a short time to see whether the other one follows. This applies
+    /// after remapping.
+    pub fn middle_emulation(self, middle_emulation: bool) -> Self {
+        ButtonMap { middle_emulation, ..self }
+    }
+
+    /// Get what `button` is turned into.
+    pub fn get(&self, button: u32) -> Mapping {
+        self.mappings
+            .iter()
+            .find(|&&(mapped, _)| mapped == button)
+            .map(|&(_, mapping)| mapping)
+            .unwrap_or(Mapping::Button(button))
+    }
+
+    /// Whether pressing left and right together acts as the middle button.
+    pub fn has_middle_emulation(&self) -> bool {
+        self.middle_emulation
+    }
+}
@@ -1,29 +1,61 @@
 //! Wrapper for wlr_cursor
 
-use std::{fmt, panic, ptr, cell::Cell, rc::{Rc, Weak}};
+use std::{fmt, panic, ptr, cell::{Cell, RefCell}, rc::{Rc, Weak}, time::Duration};
 
 use libc;
-use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+use wayland_sys::server::{wl_event_source, WAYLAND_SERVER_HANDLE};
 use wayland_sys::server::signal::wl_signal_add;
-use wlroots_sys::{wlr_cursor, wlr_cursor_absolute_to_layout_coords,
+use wlroots_sys::{wlr_button_state, wlr_cursor, wlr_cursor_absolute_to_layout_coords,
                   wlr_cursor_attach_input_device, wlr_cursor_attach_output_layout,
                   wlr_cursor_create, wlr_cursor_destroy, wlr_cursor_detach_input_device,
                   wlr_cursor_map_input_to_output, wlr_cursor_map_input_to_region,
                   wlr_cursor_map_to_output, wlr_cursor_map_to_region, wlr_cursor_move,
                   wlr_cursor_set_image, wlr_cursor_set_surface, wlr_cursor_warp,
-                  wlr_cursor_warp_absolute};
+                  wlr_cursor_warp_absolute, wlr_event_pointer_axis, wlr_event_pointer_button,
+                  wlr_xcursor_manager_set_cursor_image};
 
 use {area::Area,
      compositor,
-     input::{self, pointer, tablet_tool, touch},
+     input::{self, pointer::{self, event::{BTN_LEFT, BTN_MIDDLE, BTN_RIGHT}}, tablet_tool,
+             touch},
      output::{self, Output, layout::Layout},
-     surface::Surface,
-     cursor::xcursor,
-     utils::{HandleErr, HandleResult, Handleable}};
+     surface::{self, Surface},
+     cursor::{barrier::{self, Barrier}, button_map::{ButtonMap, Mapping}, xcursor},
+     utils::{edges::Edges, HandleErr, HandleResult, Handleable, ToMs, safe_as_cstring}};
 
 #[derive(Debug)]
 pub(crate) struct CursorState {
     output_layout: Option<output::layout::Handle>,
+    /// The theme owned by this cursor, if it was given its own.
+    ///
+    /// Each seat has its own cursor, so keeping the theme here lets two seats
+    /// show different themes (or sizes) at the same time.
+    xcursor_manager: Option<xcursor::Manager>,
+    /// The name of the last image set with `set_named_image`.
+    image_name: Option<String>,
+    /// Used to count consecutive clicks of the same button.
+    clicks: ClickCounter,
+    /// Lines relative motion can't cross.
+    barriers: Vec<Barrier>,
+    /// The last client surface set with `set_surface`, so it can be restored
+    /// when the cursor is shown again.
+    surface: Option<(surface::Handle, i32, i32)>,
+    /// Whether the cursor was hidden with `hide`.
+    hidden: bool,
+    /// Whether the cursor was hidden automatically, by inactivity or typing.
+    auto_hidden: bool,
+    /// How long the cursor can be inactive before it is hidden.
+    hide_timeout: Option<Duration>,
+    /// Timer that hides the cursor after `hide_timeout`.
+    hide_timer: *mut wl_event_source,
+    /// Whether the cursor is hidden when `notify_typing` is called.
+    hide_while_typing: bool,
+    /// Devices whose scroll direction is inverted.
+    natural_scroll: Vec<Box<input::DeviceWatcher>>,
+    /// How the buttons of each device are remapped.
+    button_maps: Vec<(Box<input::DeviceWatcher>, ButtonMap)>,
+    /// The state of middle button emulation.
+    middle_emulation: MiddleEmulation,
     /// A counter that will always have a strong count of 1.
     ///
     /// Once the cursor is destroyed, this will signal to the `cursor::Handle`s that
@@ -33,6 +65,87 @@ pub(crate) struct CursorState {
     cursor: *mut Cursor
 }
 
+/// Counts consecutive clicks of the same button that are close together in
+/// both time and space.
+#[derive(Debug)]
+struct ClickCounter {
+    interval: Duration,
+    distance: f64,
+    last_button: u32,
+    last_time_msec: u32,
+    last_coords: (f64, f64),
+    count: u32
+}
+
+impl Default for ClickCounter {
+    fn default() -> Self {
+        ClickCounter { interval: Duration::from_millis(400),
+                       distance: 4.0,
+                       last_button: 0,
+                       last_time_msec: 0,
+                       last_coords: (0.0, 0.0),
+                       count: 0 }
+    }
+}
+
+impl ClickCounter {
+    /// Update the count for a button event, returning the count for it.
+    fn button(&mut self,
+              button: u32,
+              state: wlr_button_state,
+              time_msec: u32,
+              coords: (f64, f64))
+              -> u32 {
+        if state != wlr_button_state::WLR_BUTTON_PRESSED {
+            return if button == self.last_button { self.count.max(1) } else { 1 }
+        }
+        let elapsed = Duration::from_millis(time_msec.wrapping_sub(self.last_time_msec) as u64);
+        let (dx, dy) = (coords.0 - self.last_coords.0, coords.1 - self.last_coords.1);
+        let close = (dx * dx + dy * dy).sqrt() <= self.distance;
+        if self.count > 0 && button == self.last_button && elapsed <= self.interval && close {
+            self.count += 1;
+        } else {
+            self.count = 1;
+        }
+        self.last_button = button;
+        self.last_time_msec = time_msec;
+        self.last_coords = coords;
+        self.count
+    }
+}
+
+/// How long the first of left and right is held back to see whether the
+/// other one follows, making it a middle click.
+const MIDDLE_EMULATION_TIMEOUT_MS: libc::c_int = 50;
+
+/// Turns pressing left and right together into the middle button.
+#[derive(Debug)]
+struct MiddleEmulation {
+    /// The press of left or right that is held back, with the device it
+    /// came from.
+    ///
+    /// Only one press is held back at a time, a press of another device
+    /// lets it through.
+    pending: Option<(Box<input::DeviceWatcher>, wlr_event_pointer_button)>,
+    /// Timer that lets the held back press through.
+    timer: *mut wl_event_source,
+    /// The devices whose left and right are acting as the middle button,
+    /// with the buttons they still hold.
+    active: Vec<(Box<input::DeviceWatcher>, Vec<u32>)>
+}
+
+impl Default for MiddleEmulation {
+    fn default() -> Self {
+        MiddleEmulation { pending: None,
+                          timer: ptr::null_mut(),
+                          active: Vec::new() }
+    }
+}
+
+/// Every cursor that is alive, so their themes can be reloaded when the
+/// scale of an output changes.
+thread_local!(static CURSORS: RefCell<Vec<*mut wlr_cursor>> = RefCell::new(Vec::new()));
+
 // NOTE We can't use `utils::Handle` because we own the cursor.
 // So this is special cased, just like `output::Layout`.
 #[derive(Debug, Clone)]
@@ -61,6 +174,16 @@ pub trait Handler {
                          cursor_handle: Handle,
                          event: &pointer::event::Button) {}
 
+    /// Callback that is triggered when a button remapped to a key or an
+    /// action with `Cursor::set_button_map` is pressed or released.
+    ///
+    /// `event` is the event of the original button.
+    fn on_pointer_button_mapped(&mut self,
+                                compositor_handle: compositor::Handle,
+                                cursor_handle: Handle,
+                                event: &pointer::event::Button,
+                                mapping: Mapping) {}
+
     fn on_pointer_axis(&mut self,
                        compositor_handle: compositor::Handle,
                        cursor_handle: Handle,
@@ -115,7 +238,8 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
     pointer_motion_listener => pointer_motion_notify: |this: &mut Cursor, event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
         let event = pointer::event::Motion::from_ptr(event as _);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
@@ -133,7 +257,8 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
         let event = pointer::event::AbsoluteMotion::from_ptr(event as _);
-        let cursor = Cursor::from_ptr(cursor_ptr);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -148,24 +273,51 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
     pointer_button_listener => pointer_button_notify: |this: &mut Cursor, event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
-        let event = pointer::event::Button::from_ptr(event as _);
-        let compositor = match compositor::handle() {
-            Some(handle) => handle,
-            None => return
-        };
-
-        cursor_handler.on_pointer_button(compositor,
-                                         cursor.weak_reference(),
-                                         &event);
-
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
+        // Remapped on a copy, so other listeners of the wlr_cursor still get
+        // the event the device sent.
+        let mut event = *(event as *mut wlr_event_pointer_button);
+        let (mapping, middle_emulation) =
+            cursor.state()
+                  .button_maps
+                  .iter()
+                  .find(|&&(ref watcher, _)| watcher.is(event.device))
+                  .map(|&(_, ref map)| (map.get(event.button), map.has_middle_emulation()))
+                  .unwrap_or((Mapping::Button(event.button), false));
+        match (mapping, compositor::handle()) {
+            (Mapping::Key(_), Some(compositor)) | (Mapping::Action(_), Some(compositor)) => {
+                let event = pointer::event::Button::from_ptr(&mut event);
+                cursor_handler.on_pointer_button_mapped(compositor,
+                                                        cursor.weak_reference(),
+                                                        &event,
+                                                        mapping);
+            }
+            _ => {}
+        }
         Box::into_raw(cursor);
+        if let Mapping::Button(button) = mapping {
+            event.button = button;
+            if middle_emulation {
+                emulate_middle_button(cursor_ptr, &mut event)
+            } else {
+                dispatch_button(cursor_ptr, &mut event)
+            }
+        }
     };
     pointer_axis_listener => pointer_axis_notify: |this: &mut Cursor, event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
-        let event = pointer::event::Axis::from_ptr(event as _);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
+        // Inverted on a copy, so other listeners of the wlr_cursor still
+        // get the event the device sent.
+        let mut event = *(event as *mut wlr_event_pointer_axis);
+        if cursor.state().natural_scroll.iter().any(|watcher| watcher.is(event.device)) {
+            event.delta = -event.delta;
+            event.delta_discrete = -event.delta_discrete;
+        }
+        let event = pointer::event::Axis::from_ptr(&mut event);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -245,7 +397,8 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
                                                            event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
         let event = tablet_tool::event::Axis::from_ptr(event as _);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
@@ -262,7 +415,8 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
                                                                      event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
         let event = tablet_tool::event::Proximity::from_ptr(event as _);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
@@ -279,7 +433,8 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
                                                          event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
         let event = tablet_tool::event::Tip::from_ptr(event as _);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
@@ -296,7 +451,8 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
                                                                event: *mut libc::c_void,|
     unsafe {
         let (cursor_ptr, ref mut cursor_handler, _) = this.data;
-        let cursor = Cursor::from_ptr(cursor_ptr);
+        let mut cursor = Cursor::from_ptr(cursor_ptr);
+        cursor.pointer_activity();
         let event = tablet_tool::event::Button::from_ptr(event as _);
         let compositor = match compositor::handle() {
             Some(handle) => handle,
@@ -353,8 +509,22 @@ impl Cursor {
             let handle = Rc::downgrade(&counter);
             let state = Box::new(CursorState { counter,
                                                cursor: Box::into_raw(cursor),
-                                               output_layout: None });
+                                               output_layout: None,
+                                               xcursor_manager: None,
+                                               image_name: None,
+                                               clicks: ClickCounter::default(),
+                                               barriers: Vec::new(),
+                                               surface: None,
+                                               hidden: false,
+                                               auto_hidden: false,
+                                               hide_timeout: None,
+                                               hide_timer: ptr::null_mut(),
+                                               hide_while_typing: false,
+                                               natural_scroll: Vec::new(),
+                                               button_maps: Vec::new(),
+                                               middle_emulation: MiddleEmulation::default() });
             (*cursor_ptr).data = Box::into_raw(state) as *mut libc::c_void;
+            CURSORS.with(|cursors| cursors.borrow_mut().push(cursor_ptr));
             Handle { cursor: cursor_ptr,
                            handle }
         }
@@ -372,6 +542,11 @@ impl Cursor {
         self.data.0
     }
 
+    /// Gets the state stored in the wlr_cursor's data pointer.
+    unsafe fn state(&self) -> &mut CursorState {
+        &mut *((*self.data.0).data as *mut CursorState)
+    }
+
     /// Get a weak reference to this `Cursor`.
     pub fn weak_reference(&self) -> Handle {
         unsafe {
@@ -391,6 +566,7 @@ impl Cursor {
             (*self.data.0).data = Box::into_raw(data) as *mut libc::c_void;
             wlr_cursor_attach_output_layout(self.data.0, output_layout.as_ptr());
         }
+        self.reload_xcursor_theme();
     }
 
     pub fn deattach_output_layout(&mut self) {
@@ -438,10 +614,29 @@ impl Cursor {
         }
     }
 
+    /// Warp the cursor to the center of the area, in layout coordinates,
+    /// unless it is already inside of it.
+    ///
+    /// This is useful to move the cursor along with keyboard focus when a
+    /// view is focused with a keybinding.
+    ///
+    /// Returns true if the cursor is inside the area afterwards.
+    pub fn warp_into(&mut self, area: Area) -> bool {
+        let (x, y) = self.coords();
+        if area.contains_point(x, y) {
+            return true
+        }
+        let center_x = area.origin.x as f64 + area.size.width as f64 / 2.0;
+        let center_y = area.origin.y as f64 + area.size.height as f64 / 2.0;
+        self.warp(None, center_x, center_y)
+    }
+
     /// Move the cursor in the direction of the given x and y coordinates.
     ///
     /// `dev` may be passed to respect device mapping constraints. If `dev` is None,
     /// device mapping constraints will be ignored.
+    ///
+    /// The motion stops at any barrier added with `add_barrier`.
     pub fn move_relative<'this, O>(&'this mut self,
                                    dev: O,
                                    delta_x: f64,
@@ -450,12 +645,31 @@ impl Cursor {
     {
         self.assert_layout();
         unsafe {
+            let coords = self.coords();
+            let (delta_x, delta_y) = barrier::constrain(&mut self.state().barriers,
+                                                        coords,
+                                                        (delta_x, delta_y));
             let dev_ptr = dev.into().map(|dev| dev.as_ptr())
                              .unwrap_or(ptr::null_mut());
             wlr_cursor_move(self.data.0, dev_ptr, delta_x, delta_y)
         }
     }
 
+    /// Add a barrier that relative motion of this cursor can't cross.
+    pub fn add_barrier(&mut self, barrier: Barrier) {
+        unsafe { self.state().barriers.push(barrier) }
+    }
+
+    /// Get the barriers that constrain this cursor.
+    pub fn barriers(&self) -> &[Barrier] {
+        unsafe { &self.state().barriers }
+    }
+
+    /// Remove all of the barriers on this cursor.
+    pub fn clear_barriers(&mut self) {
+        unsafe { self.state().barriers.clear() }
+    }
+
     //TODO USE IMAGE
     /// Sets the image of the cursor to the image.
     pub fn set_cursor_image(&mut self, image: &xcursor::Image) {
@@ -476,6 +690,124 @@ impl Cursor {
         }
     }
 
+    /// Give this cursor its own xcursor theme.
+    ///
+    /// When there are multiple seats each one should have its own `Cursor`.
+    /// Every cursor is rendered on every output it is over at the same time:
+    /// outputs only have one hardware cursor plane, so any additional cursors
+    /// fall back to being drawn in software. Call
+    /// `Output::render_software_cursors` while rendering a frame so those show up.
+    ///
+    /// The theme is loaded at every scale factor of the outputs in the attached
+    /// layout, and loaded again whenever an output changes its scale, so the
+    /// cursor stays crisp on every output. The current image is switched over
+    /// to the new theme right away. Returns the previous theme, if there was
+    /// one.
+    pub fn set_xcursor_manager(&mut self,
+                               manager: xcursor::Manager)
+                               -> Option<xcursor::Manager> {
+        let previous = unsafe {
+            let state = self.state();
+            let previous = state.xcursor_manager.take();
+            state.xcursor_manager = Some(manager);
+            previous
+        };
+        self.reload_xcursor_theme();
+        previous
+    }
+
+    /// Give this cursor its own xcursor theme, by name and base size.
+    ///
+    /// `None` picks the default theme. This is a shorthand for creating an
+    /// `xcursor::Manager` and passing it to `set_xcursor_manager`.
+    ///
+    /// Returns false, leaving the current theme in place, if the theme could
+    /// not be created.
+    pub fn set_xcursor_theme<T: Into<Option<String>>>(&mut self, name: T, size: u32) -> bool {
+        match xcursor::Manager::create(name, size) {
+            Some(manager) => {
+                self.set_xcursor_manager(manager);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Load the theme of this cursor at the scale of every output in the
+    /// attached layout, and set the current named image again.
+    ///
+    /// This happens automatically when this cursor is attached to a layout,
+    /// when an output is added to a layout and when any output changes its
+    /// scale.
+    pub fn reload_xcursor_theme(&mut self) {
+        unsafe { reload_xcursor_theme(self.data.0) }
+    }
+
+    /// Get the xcursor theme owned by this cursor, if it has one.
+    pub fn xcursor_manager(&self) -> Option<&xcursor::Manager> {
+        unsafe { self.state().xcursor_manager.as_ref() }
+    }
+
+    /// Set the image of the cursor to the named image from the theme owned by
+    /// this cursor.
+    ///
+    /// Returns false if this cursor does not have its own theme.
+    pub fn set_named_image<T: Into<String>>(&mut self, name: T) -> bool {
+        unsafe {
+            let state = self.state();
+            let name = name.into();
+            state.image_name = Some(name.clone());
+            state.surface = None;
+            match state.xcursor_manager.as_mut() {
+                Some(manager) => {
+                    if !state.hidden && !state.auto_hidden {
+                        manager.set_cursor_image(name, self);
+                    }
+                    true
+                }
+                None => false
+            }
+        }
+    }
+
+    /// Show the image for resizing from the edges, e.g from a hit-test with
+    /// `Edges::near` or `wm::input_region::InputRegions::hit`, or `left_ptr`
+    /// if there are no edges.
+    ///
+    /// Meant to be called on every motion, it does nothing if the image is
+    /// already shown. Returns false if there is no xcursor theme to set it
+    /// from.
+    pub fn set_resize_image(&mut self, edges: Edges) -> bool {
+        let name = edges.cursor_name().unwrap_or("left_ptr");
+        unsafe {
+            let state = self.state();
+            if state.image_name.as_ref().map(|current| current.as_str()) == Some(name) {
+                return state.xcursor_manager.is_some()
+            }
+        }
+        self.set_named_image(name)
+    }
+
+    /// Get the name of the last image set with `set_named_image`.
+    pub fn image_name(&self) -> Option<String> {
+        unsafe { self.state().image_name.clone() }
+    }
+
+    /// Set how close together clicks of the same button must be to count as a
+    /// double (or triple, ...) click.
+    ///
+    /// `interval` is the longest time between presses and `distance` is how
+    /// far the cursor may move between them, in layout coordinates.
+    ///
+    /// Defaults to 400ms and 4 units.
+    pub fn set_click_thresholds(&mut self, interval: Duration, distance: f64) {
+        unsafe {
+            let clicks = &mut self.state().clicks;
+            clicks.interval = interval;
+            clicks.distance = distance;
+        }
+    }
+
     /// Set the cursor surface. The surface can be committed to update the cursor
     /// image. The surface position is substracted from the hotspot.
     ///
@@ -484,13 +816,147 @@ impl Cursor {
         where O: Into<Option<&'this Surface>>
     {
         unsafe {
-            let surface_ptr = surface.into()
-                                     .map(|surface| surface.as_ptr())
+            let surface = surface.into();
+            let state = self.state();
+            state.image_name = None;
+            state.surface = surface.map(|surface| (surface.weak_reference(), hotspot_x, hotspot_y));
+            if state.hidden || state.auto_hidden {
+                return
+            }
+            let surface_ptr = surface.map(|surface| surface.as_ptr())
                                      .unwrap_or(ptr::null_mut());
             wlr_cursor_set_surface(self.data.0, surface_ptr, hotspot_x, hotspot_y)
         }
     }
 
+    /// Hide the cursor on every output until `show` is called.
+    ///
+    /// Images set while the cursor is hidden are remembered and shown by `show`.
+    pub fn hide(&mut self) {
+        unsafe { self.state().hidden = true }
+        self.update_visibility();
+    }
+
+    /// Show the cursor again after it was hidden.
+    ///
+    /// This restores the last named image or client surface. Images set
+    /// directly with `set_cursor_image` can't be restored, so set it again
+    /// after calling this.
+    pub fn show(&mut self) {
+        unsafe {
+            let state = self.state();
+            state.hidden = false;
+            state.auto_hidden = false;
+        }
+        self.update_visibility();
+        self.rearm_hide_timer();
+    }
+
+    /// Whether the cursor is currently shown.
+    pub fn visible(&self) -> bool {
+        unsafe { !self.state().hidden && !self.state().auto_hidden }
+    }
+
+    /// Automatically hide the cursor once there has been no pointer or tablet
+    /// activity on it for `timeout`. It is shown again on the next activity.
+    ///
+    /// `None` disables hiding on inactivity. A timeout of zero hides the
+    /// cursor right away, like the idle timeouts of `idle::Manager` do.
+    pub fn set_hide_timeout(&mut self, timeout: Option<Duration>) {
+        unsafe {
+            let state = self.state();
+            state.hide_timeout = timeout;
+            if state.hide_timer.is_null() && timeout.is_some() {
+                let compositor = match compositor::handle() {
+                    Some(_) => &*compositor::COMPOSITOR_PTR,
+                    None => {
+                        wlr_log!(WLR_ERROR, "Can't set a hide timeout before the compositor runs");
+                        return
+                    }
+                };
+                state.hide_timer = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                                 wl_event_loop_add_timer,
+                                                 compositor.event_loop,
+                                                 hide_timer_fired,
+                                                 self.data.0 as *mut _);
+            }
+        }
+        self.rearm_hide_timer();
+    }
+
+    /// Hide the cursor whenever `notify_typing` is called, until it is moved.
+    pub fn set_hide_while_typing(&mut self, hide_while_typing: bool) {
+        unsafe { self.state().hide_while_typing = hide_while_typing }
+    }
+
+    /// Tell the cursor that the user is typing.
+    ///
+    /// Call this from a keyboard handler. If hiding while typing is enabled
+    /// the cursor is hidden until it is moved again.
+    pub fn notify_typing(&mut self) {
+        unsafe {
+            let state = self.state();
+            if !state.hide_while_typing || state.auto_hidden {
+                return
+            }
+            state.auto_hidden = true;
+        }
+        self.update_visibility();
+    }
+
+    /// There was pointer activity, so show an automatically hidden cursor.
+    fn pointer_activity(&mut self) {
+        let was_hidden = unsafe {
+            let state = self.state();
+            let was_hidden = state.auto_hidden;
+            state.auto_hidden = false;
+            was_hidden
+        };
+        if was_hidden {
+            self.update_visibility();
+        }
+        self.rearm_hide_timer();
+    }
+
+    fn rearm_hide_timer(&mut self) {
+        unsafe {
+            let state = self.state();
+            if state.hide_timer.is_null() {
+                return
+            }
+            // A timeout of 0 disarms the timer, so it's only used for `None`.
+            let ms = state.hide_timeout
+                          .map(|timeout| ::std::cmp::max(timeout.to_ms(), 1) as libc::c_int)
+                          .unwrap_or(0);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_event_source_timer_update,
+                          state.hide_timer,
+                          ms);
+        }
+    }
+
+    /// Apply the current visibility to the wlr_cursor.
+    fn update_visibility(&mut self) {
+        unsafe {
+            let state = self.state();
+            if state.hidden || state.auto_hidden {
+                wlr_cursor_set_surface(self.data.0, ptr::null_mut(), 0, 0);
+                return
+            }
+            if let Some(name) = state.image_name.clone() {
+                if let Some(manager) = state.xcursor_manager.as_mut() {
+                    manager.set_cursor_image(name, self);
+                }
+            } else if let Some((ref surface, hotspot_x, hotspot_y)) = state.surface {
+                let cursor_ptr = self.data.0;
+                surface.run(|surface| {
+                           wlr_cursor_set_surface(cursor_ptr, surface.as_ptr(), hotspot_x, hotspot_y)
+                       })
+                       .ok();
+            }
+        }
+    }
+
     /// Attaches this input device to this cursor. The input device must be one of:
     ///
     /// - WLR_INPUT_DEVICE_POINTER
@@ -510,7 +976,67 @@ impl Cursor {
 
     /// Deattaches the input device from this cursor.
     pub fn deattach_input_device(&mut self, dev: &input::Device) {
-        unsafe { wlr_cursor_detach_input_device(self.data.0, dev.as_ptr()) }
+        unsafe {
+            self.state().natural_scroll.retain(|watcher| !watcher.is(dev.as_ptr()));
+            self.state().button_maps.retain(|&(ref watcher, _)| !watcher.is(dev.as_ptr()));
+            let emulation = &mut self.state().middle_emulation;
+            let pending_device = emulation.pending
+                                          .as_ref()
+                                          .map(|&(ref watcher, _)| watcher.is(dev.as_ptr()));
+            if pending_device == Some(true) {
+                emulation.pending = None;
+            }
+            emulation.active.retain(|&(ref watcher, _)| !watcher.is(dev.as_ptr()));
+            wlr_cursor_detach_input_device(self.data.0, dev.as_ptr())
+        }
+    }
+
+    /// Invert the scroll direction of the device, so content follows the
+    /// fingers like on a touchscreen.
+    ///
+    /// This is for backends that can't configure the device itself, e.g
+    /// nested in another compositor. The axis events the `Handler` gets are
+    /// already inverted.
+    pub fn set_natural_scroll(&mut self, dev: &input::Device, natural_scroll: bool) {
+        unsafe {
+            let devices = &mut self.state().natural_scroll;
+            devices.retain(|watcher| !watcher.is_dead() && !watcher.is(dev.as_ptr()));
+            if natural_scroll {
+                devices.push(input::DeviceWatcher::watch(dev.as_ptr()));
+            }
+        }
+    }
+
+    /// Whether the scroll direction of the device is inverted.
+    pub fn natural_scroll(&self, dev: &input::Device) -> bool {
+        unsafe { self.state().natural_scroll.iter().any(|watcher| watcher.is(dev.as_ptr())) }
+    }
+
+    /// Remap the buttons of the device, or stop remapping them with `None`.
+    ///
+    /// This can be changed at any time, but a button held while the map
+    /// changes is released as what the new map turns it into.
+    pub fn set_button_map<T: Into<Option<ButtonMap>>>(&mut self,
+                                                      dev: &input::Device,
+                                                      map: T) {
+        unsafe {
+            let maps = &mut self.state().button_maps;
+            maps.retain(|&(ref watcher, _)| !watcher.is_dead() && !watcher.is(dev.as_ptr()));
+            if let Some(map) = map.into() {
+                maps.push((input::DeviceWatcher::watch(dev.as_ptr()), map));
+            }
+        }
+    }
+
+    /// Get how the buttons of the device are remapped, if they are.
+    pub fn button_map(&self, dev: &input::Device) -> Option<ButtonMap> {
+        unsafe {
+            self.state()
+                .button_maps
+                .iter()
+                .find(|&&(ref watcher, _)| watcher.is(dev.as_ptr()))
+                .map(|&(_, ref map)| map.clone())
+        }
     }
 
     /// Attaches this cursor to the given output, which must be among the outputs in
@@ -672,7 +1198,16 @@ impl Drop for Cursor {
                           wl_list_remove,
                           &mut (*self.tablet_tool_button_listener()).link as *mut _ as _);
             let data = Box::from_raw((*cursor_ptr).data as *mut CursorState);
+            if !data.hide_timer.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, data.hide_timer);
+            }
+            if !data.middle_emulation.timer.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_event_source_remove,
+                              data.middle_emulation.timer);
+            }
             let _ = Box::from_raw(data.cursor);
+            CURSORS.with(|cursors| cursors.borrow_mut().retain(|&cursor| cursor != cursor_ptr));
             assert_eq!(Rc::strong_count(&data.counter),
                        1,
                        "Cursor had more than 1 reference count");
@@ -682,6 +1217,192 @@ impl Drop for Cursor {
     }
 }
 
+/// Load the xcursor theme of every cursor at the current output scales.
+///
+/// Called when the scale of an output changes.
+pub(crate) fn reload_xcursor_themes() {
+    let cursors = CURSORS.with(|cursors| cursors.borrow().clone());
+    for cursor_ptr in cursors {
+        unsafe { reload_xcursor_theme(cursor_ptr) }
+    }
+}
+
+/// Works on the state directly, so it also works while the cursor is
+/// borrowed, e.g when the scale was changed from a cursor callback.
+unsafe fn reload_xcursor_theme(cursor_ptr: *mut wlr_cursor) {
+    let state = (*cursor_ptr).data as *mut CursorState;
+    if state.is_null() {
+        return
+    }
+    let state = &mut *state;
+    let manager = match state.xcursor_manager.as_ref() {
+        Some(manager) => manager,
+        None => return
+    };
+    let scales = state.output_layout
+                      .as_ref()
+                      .map(|layout| layout.output_scales())
+                      .unwrap_or_default();
+    if scales.is_empty() {
+        manager.load(1.0);
+    }
+    for scale in scales {
+        manager.load(scale);
+    }
+    if state.hidden || state.auto_hidden {
+        return
+    }
+    if let Some(name) = state.image_name.clone() {
+        let name = safe_as_cstring(name);
+        wlr_xcursor_manager_set_cursor_image(manager.as_ptr(), name.as_ptr(), cursor_ptr);
+    }
+}
+
+/// Hides the cursor when it has been inactive for its hide timeout.
+unsafe extern "C" fn hide_timer_fired(data: *mut libc::c_void) -> libc::c_int {
+    let cursor_ptr = data as *mut wlr_cursor;
+    let state = (*cursor_ptr).data as *mut CursorState;
+    if state.is_null() || (*state).auto_hidden {
+        return 0
+    }
+    (*state).auto_hidden = true;
+    let mut cursor = Cursor::from_ptr(cursor_ptr);
+    cursor.update_visibility();
+    Box::into_raw(cursor);
+    0
+}
+
+/// Count the clicks of a button event and give it to the handler.
+///
+/// Takes the raw cursor so it can also be used from the middle emulation
+/// timer.
+unsafe fn dispatch_button(cursor_ptr: *mut wlr_cursor, event: *mut wlr_event_pointer_button) {
+    let mut cursor = Cursor::from_ptr(cursor_ptr);
+    let event = pointer::event::Button::from_ptr(event);
+    let click_count = cursor.state().clicks.button(event.button(),
+                                                   event.state(),
+                                                   event.time_msec(),
+                                                   cursor.coords());
+    let event = event.with_click_count(click_count);
+    if let Some(compositor) = compositor::handle() {
+        let cursor_handle = cursor.weak_reference();
+        cursor.data.1.on_pointer_button(compositor, cursor_handle, &event);
+    }
+    Box::into_raw(cursor);
+}
+
+/// Turn left and right pressed together into the middle button, holding
+/// back the first of them until it's clear whether the other one follows.
+///
+/// Only buttons of the same device are put together.
+unsafe fn emulate_middle_button(cursor_ptr: *mut wlr_cursor,
+                                event: *mut wlr_event_pointer_button) {
+    let state = &mut *((*cursor_ptr).data as *mut CursorState);
+    let device = (*event).device;
+    let button = (*event).button;
+    let pressed = (*event).state == wlr_button_state::WLR_BUTTON_PRESSED;
+    let pending_button = match state.middle_emulation.pending {
+        Some((ref watcher, pending)) if watcher.is(device) => Some(pending.button),
+        Some(_) => {
+            // Another device doesn't follow up on the press.
+            flush_middle_emulation(cursor_ptr);
+            None
+        }
+        None => None
+    };
+    if button != BTN_LEFT && button != BTN_RIGHT {
+        flush_middle_emulation(cursor_ptr);
+        return dispatch_button(cursor_ptr, event)
+    }
+    let emulation = &mut state.middle_emulation;
+    emulation.active.retain(|&(ref watcher, _)| !watcher.is_dead());
+    if let Some(index) = emulation.active.iter().position(|&(ref watcher, _)| watcher.is(device)) {
+        let held = &mut emulation.active[index].1;
+        if pressed {
+            held.push(button);
+            return
+        }
+        held.retain(|&held| held != button);
+        if held.is_empty() {
+            emulation.active.remove(index);
+            (*event).button = BTN_MIDDLE;
+            dispatch_button(cursor_ptr, event);
+        }
+        return
+    }
+    match pending_button {
+        Some(pending_button) if pressed && pending_button != button => {
+            // The other button followed in time, so both are the middle one.
+            let (watcher, mut pending) = emulation.pending.take().unwrap();
+            emulation.active.push((watcher, vec![pending_button, button]));
+            disarm_middle_emulation(state);
+            pending.button = BTN_MIDDLE;
+            pending.time_msec = (*event).time_msec;
+            dispatch_button(cursor_ptr, &mut pending);
+        }
+        Some(_) => {
+            flush_middle_emulation(cursor_ptr);
+            dispatch_button(cursor_ptr, event);
+        }
+        None if pressed => {
+            emulation.pending = Some((input::DeviceWatcher::watch(device), *event));
+            arm_middle_emulation(cursor_ptr, state);
+        }
+        None => dispatch_button(cursor_ptr, event)
+    }
+}
+
+/// Let a held back press of left or right through, unless its device was
+/// destroyed in the meantime.
+unsafe fn flush_middle_emulation(cursor_ptr: *mut wlr_cursor) {
+    let state = &mut *((*cursor_ptr).data as *mut CursorState);
+    disarm_middle_emulation(state);
+    if let Some((watcher, mut pending)) = state.middle_emulation.pending.take() {
+        if !watcher.is_dead() {
+            dispatch_button(cursor_ptr, &mut pending);
+        }
+    }
+}
+
+unsafe fn arm_middle_emulation(cursor_ptr: *mut wlr_cursor, state: &mut CursorState) {
+    if state.middle_emulation.timer.is_null() {
+        if compositor::COMPOSITOR_PTR.is_null() {
+            // Without an event loop the press can't be held back.
+            return flush_middle_emulation(cursor_ptr)
+        }
+        state.middle_emulation.timer = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                                     wl_event_loop_add_timer,
+                                                     (*compositor::COMPOSITOR_PTR).event_loop,
+                                                     middle_emulation_timer_fired,
+                                                     cursor_ptr as *mut _);
+    }
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_event_source_timer_update,
+                  state.middle_emulation.timer,
+                  MIDDLE_EMULATION_TIMEOUT_MS);
+}
+
+unsafe fn disarm_middle_emulation(state: &mut CursorState) {
+    if !state.middle_emulation.timer.is_null() {
+        // A timeout of 0 disarms the timer.
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_event_source_timer_update,
+                      state.middle_emulation.timer,
+                      0);
+    }
+}
+
+/// The other button didn't follow in time, so the held back press is a
+/// normal one.
+unsafe extern "C" fn middle_emulation_timer_fired(data: *mut libc::c_void) -> libc::c_int {
+    let cursor_ptr = data as *mut wlr_cursor;
+    if (*cursor_ptr).data.is_null() {
+        return 0
+    }
+    flush_middle_emulation(cursor_ptr);
+    0
+}
+
 impl Handle {
     /// Constructs a `cursor::Handle` that is always invalid. Calling `run` on this
     /// will always fail.
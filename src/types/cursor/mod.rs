@@ -1,8 +1,16 @@
 #[cfg(feature = "unstable")]
+mod barrier;
+#[cfg(feature = "unstable")]
+mod button_map;
+#[cfg(feature = "unstable")]
 mod cursor;
 pub mod xcursor;
 #[cfg(feature = "unstable")]
 pub(crate) mod xcursor_manager;
 
+#[cfg(feature = "unstable")]
+pub use self::barrier::{Barrier, Orientation as BarrierOrientation};
+#[cfg(feature = "unstable")]
+pub use self::button_map::{ButtonMap, Mapping as ButtonMapping};
 #[cfg(feature = "unstable")]
 pub use self::cursor::*;
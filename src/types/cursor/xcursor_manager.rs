@@ -114,6 +114,10 @@ impl Manager {
         }
     }
 
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_xcursor_manager {
+        self.manager
+    }
+
     /// Set a `Cursor`'s cursor image to the specified cursor name for all scale
     /// factors. The `Cursor` will take over from this point and ensure the correct
     /// cursor is used on each output, assuming an `OutputLayout` is attached to
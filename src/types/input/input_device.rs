@@ -1,11 +1,12 @@
-use std::{cell::Cell, rc::Weak};
+use std::{cell::Cell, ptr, rc::Weak};
 
-use libc::{c_double, c_uint};
+use libc::{self, c_double, c_uint};
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
 use wlroots_sys::{wlr_input_device, wlr_input_device_pointer, wlr_input_device_type,
                   wlr_input_device_type::*};
 
 use {input::{keyboard, pointer, switch, touch, tablet_pad, tablet_tool},
-     utils::c_to_rust_string};
+     utils::{c_to_rust_string, Id}};
 pub(crate) use manager::input_manager::Manager;
 
 /// A handle to an input device.
@@ -20,6 +21,7 @@ pub enum Handle {
 
 pub(crate) struct InputState {
     pub(crate) handle: Weak<Cell<bool>>,
+    pub(crate) id: Id,
     pub(crate) device: Device
 }
 
@@ -43,6 +45,25 @@ impl Device {
         Device { device: self.device }
     }
 
+    /// Get the stable identifier of this input device.
+    ///
+    /// Unlike the name this is never reused, even when the same device is
+    /// plugged in again.
+    pub fn id(&self) -> Id {
+        unsafe {
+            let dev = self.dev_union();
+            let data = match self.dev_type() {
+                WLR_INPUT_DEVICE_KEYBOARD => (*dev.keyboard).data,
+                WLR_INPUT_DEVICE_POINTER => (*dev.pointer).data,
+                WLR_INPUT_DEVICE_TOUCH => (*dev.touch).data,
+                WLR_INPUT_DEVICE_TABLET_TOOL => (*dev.tablet).data,
+                WLR_INPUT_DEVICE_TABLET_PAD => (*dev.tablet_pad).data,
+                WLR_INPUT_DEVICE_SWITCH => (*dev.lid_switch).data
+            };
+            (*(data as *mut InputState)).id
+        }
+    }
+
     pub fn vendor(&self) -> c_uint {
         unsafe { (*self.device).vendor }
     }
@@ -115,3 +136,55 @@ impl Device {
         self.device
     }
 }
+
+wayland_listener!(pub(crate) DeviceWatcher, *mut wlr_input_device, [
+    destroy_listener => destroy_notify: |this: &mut DeviceWatcher, _data: *mut libc::c_void,|
+    unsafe {
+        this.data = ptr::null_mut();
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_init,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+    };
+]);
+
+impl DeviceWatcher {
+    /// Keep track of a device something was set for, so the setting isn't
+    /// applied to another device that is later created at the same address.
+    pub(crate) unsafe fn watch(device: *mut wlr_input_device) -> Box<DeviceWatcher> {
+        let mut watcher = DeviceWatcher::new(device);
+        wl_signal_add(&mut (*device).events.destroy as *mut _ as _,
+                      watcher.destroy_listener() as *mut _ as _);
+        watcher
+    }
+
+    /// Whether this watches `device`, which must not be null.
+    pub(crate) fn is(&self, device: *mut wlr_input_device) -> bool {
+        self.data == device
+    }
+
+    /// Whether the device was destroyed, so the watcher can be dropped.
+    pub(crate) fn is_dead(&self) -> bool {
+        self.data.is_null()
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*self.destroy_listener()).link as *mut _ as _);
+        }
+    }
+}
+
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Device {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ::serde::Serialize::serialize(&::snapshot::DeviceInfo::new(self), serializer)
+    }
+}
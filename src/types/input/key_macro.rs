@@ -0,0 +1,290 @@
+//! Recording key presses and playing them back.
+//!
+//! A `Recorder` is fed the key events of a keyboard and keeps the sequences
+//! it recorded by name, optionally saving them through a `Storage` so they
+//! survive a restart. A `Player` types them again on a virtual keyboard,
+//! with the same timing, so they go through the keyboard handlers like any
+//! other input. This is handy for automating repetitive input, and for
+//! reproducing input related bugs.
+//!
+//! ```rust,no_run,ignore
+//! // In the keyboard handler, for keyboards other than the player's.
+//! state.recorder.record(&key_event);
+//! // Bound to some key combination.
+//! if let Some(events) = state.recorder.get("greeting") {
+//!     state.player.play(&events);
+//! }
+//! ```
+
+use std::{collections::VecDeque, mem, ptr, time::Duration};
+
+use libc;
+use wayland_sys::server::{wl_event_source, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_event_keyboard_key, wlr_input_device_type::WLR_INPUT_DEVICE_KEYBOARD,
+                  wlr_key_state::*, wlr_keyboard_notify_key};
+
+use {compositor::{self, Compositor},
+     input::{self, keyboard},
+     utils::{current_time, Handleable, ToMs}};
+
+/// A recorded key press or release.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct KeyEvent {
+    /// The evdev keycode of the key.
+    pub keycode: u32,
+    pub pressed: bool,
+    /// How long after the previous event this one happened.
+    pub delay: Duration
+}
+
+/// Keeps recorded macros beyond the lifetime of the compositor, e.g in a
+/// file.
+pub trait Storage {
+    fn save(&mut self, name: &str, events: &[KeyEvent]);
+
+    fn load(&mut self, name: &str) -> Option<Vec<KeyEvent>>;
+
+    fn remove(&mut self, name: &str);
+}
+
+/// Records sequences of key events and keeps them by name.
+#[derive(Default)]
+pub struct Recorder {
+    recording: Option<(String, Vec<KeyEvent>, Option<Duration>)>,
+    macros: Vec<(String, Vec<KeyEvent>)>,
+    storage: Option<Box<Storage>>
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Recorder::default()
+    }
+
+    /// Save recorded macros to `storage`, and look up the macros it has
+    /// when they're not in memory.
+    pub fn set_storage(&mut self, storage: Box<Storage>) {
+        self.storage = Some(storage)
+    }
+
+    /// Start recording a macro, throwing away the one being recorded.
+    pub fn start(&mut self, name: &str) {
+        self.recording = Some((name.into(), Vec::new(), None));
+    }
+
+    /// Whether a macro is being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Record a key event, if a macro is being recorded.
+    ///
+    /// Don't pass the events of the `Player`'s keyboard, or playing a macro
+    /// while recording records it again.
+    pub fn record(&mut self, event: &keyboard::event::Key) {
+        if let Some((_, ref mut events, ref mut last_time)) = self.recording {
+            let time = event.time_msec();
+            let delay = match *last_time {
+                Some(last_time) if time > last_time => time - last_time,
+                _ => Duration::from_millis(0)
+            };
+            *last_time = Some(time);
+            events.push(KeyEvent { keycode: event.keycode(),
+                                   pressed: event.key_state() == WLR_KEY_PRESSED,
+                                   delay })
+        }
+    }
+
+    /// Stop recording and keep the macro, replacing an earlier one with the
+    /// same name.
+    ///
+    /// Keys still held down are released at the end of it, so playing it
+    /// back leaves no key stuck.
+    pub fn stop(&mut self) {
+        let (name, mut events, _) = match self.recording.take() {
+            Some(recording) => recording,
+            None => return
+        };
+        let mut held: Vec<u32> = Vec::new();
+        for event in &events {
+            if event.pressed {
+                held.push(event.keycode)
+            } else {
+                held.retain(|&keycode| keycode != event.keycode)
+            }
+        }
+        events.extend(held.into_iter().map(|keycode| {
+                                               KeyEvent { keycode,
+                                                          pressed: false,
+                                                          delay: Duration::from_millis(0) }
+                                           }));
+        if let Some(ref mut storage) = self.storage {
+            storage.save(&name, &events)
+        }
+        self.macros.retain(|&(ref other, _)| *other != name);
+        self.macros.push((name, events));
+    }
+
+    /// Throw away the macro being recorded.
+    pub fn cancel(&mut self) {
+        self.recording = None
+    }
+
+    /// Get the macro with the name, from memory or else from the storage.
+    pub fn get(&mut self, name: &str) -> Option<Vec<KeyEvent>> {
+        let recorded = self.macros
+                           .iter()
+                           .find(|&&(ref other, _)| other == name)
+                           .map(|&(_, ref events)| events.clone());
+        match (recorded, &mut self.storage) {
+            (Some(events), _) => Some(events),
+            (None, &mut Some(ref mut storage)) => storage.load(name),
+            (None, &mut None) => None
+        }
+    }
+
+    /// Forget the macro with the name, also in the storage.
+    pub fn remove(&mut self, name: &str) {
+        self.macros.retain(|&(ref other, _)| other != name);
+        if let Some(ref mut storage) = self.storage {
+            storage.remove(name)
+        }
+    }
+}
+
+struct PlayerState {
+    keyboard: keyboard::Handle,
+    queue: VecDeque<KeyEvent>,
+    /// Keys the player pressed and didn't release yet.
+    held: Vec<u32>,
+    timer: *mut wl_event_source
+}
+
+/// Plays macros back on a virtual keyboard.
+pub struct Player {
+    state: Box<PlayerState>
+}
+
+impl Player {
+    /// Create the virtual keyboard macros are played on.
+    ///
+    /// It's announced to the input manager like any other keyboard, which
+    /// has to set it up (e.g its keymap) the same way. Returns `None` if
+    /// the backend doesn't support virtual input devices, see
+    /// `Compositor::create_virtual_input_device`.
+    pub fn new(compositor: &mut Compositor) -> Option<Self> {
+        let keyboard = match compositor.create_virtual_input_device(WLR_INPUT_DEVICE_KEYBOARD) {
+            Some(input::Handle::Keyboard(keyboard)) => keyboard,
+            _ => return None
+        };
+        Some(Player { state: Box::new(PlayerState { keyboard,
+                                                    queue: VecDeque::new(),
+                                                    held: Vec::new(),
+                                                    timer: ptr::null_mut() }) })
+    }
+
+    /// Get the keyboard macros are played on.
+    pub fn keyboard(&self) -> keyboard::Handle {
+        self.state.keyboard.clone()
+    }
+
+    /// Play the events after any that are still being played.
+    pub fn play(&mut self, events: &[KeyEvent]) {
+        let idle = self.state.queue.is_empty();
+        self.state.queue.extend(events.iter().cloned());
+        if idle {
+            unsafe { advance(&mut *self.state) }
+        }
+    }
+
+    /// Whether a macro is being played.
+    pub fn is_playing(&self) -> bool {
+        !self.state.queue.is_empty()
+    }
+
+    /// Stop playing, releasing the keys the macro held down.
+    pub fn stop(&mut self) {
+        unsafe {
+            let state = &mut *self.state;
+            state.queue.clear();
+            disarm(state);
+            for keycode in mem::replace(&mut state.held, Vec::new()) {
+                send_key(state, keycode, false)
+            }
+        }
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.stop();
+        if !self.state.timer.is_null() {
+            unsafe {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, self.state.timer);
+            }
+        }
+    }
+}
+
+/// Send the events that are due and wait for the next one.
+unsafe fn advance(state: &mut PlayerState) {
+    loop {
+        let delay = match state.queue.front_mut() {
+            Some(event) => mem::replace(&mut event.delay, Duration::from_millis(0)),
+            None => return
+        };
+        let delay_ms = delay.to_ms();
+        if delay_ms > 0 && arm(state, delay_ms) {
+            return
+        }
+        if let Some(event) = state.queue.pop_front() {
+            send_key(state, event.keycode, event.pressed)
+        }
+    }
+}
+
+/// Fire the timer in `delay_ms`. Returns false if there's no event loop to
+/// wait in.
+unsafe fn arm(state: &mut PlayerState, delay_ms: u32) -> bool {
+    if state.timer.is_null() {
+        if compositor::COMPOSITOR_PTR.is_null() {
+            return false
+        }
+        state.timer = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                    wl_event_loop_add_timer,
+                                    (*compositor::COMPOSITOR_PTR).event_loop,
+                                    timer_fired,
+                                    state as *mut PlayerState as *mut _);
+    }
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_event_source_timer_update,
+                  state.timer,
+                  delay_ms as libc::c_int);
+    true
+}
+
+unsafe fn disarm(state: &mut PlayerState) {
+    if !state.timer.is_null() {
+        // A timeout of 0 disarms the timer.
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_timer_update, state.timer, 0);
+    }
+}
+
+unsafe fn send_key(state: &mut PlayerState, keycode: u32, pressed: bool) {
+    if pressed {
+        state.held.push(keycode)
+    } else {
+        state.held.retain(|&held| held != keycode)
+    }
+    let mut event: wlr_event_keyboard_key = mem::zeroed();
+    event.time_msec = current_time().to_ms();
+    event.keycode = keycode;
+    event.update_state = true;
+    event.state = if pressed { WLR_KEY_PRESSED } else { WLR_KEY_RELEASED };
+    let _ = state.keyboard.run(|keyboard| wlr_keyboard_notify_key(keyboard.as_ptr(), &mut event));
+}
+
+unsafe extern "C" fn timer_fired(data: *mut libc::c_void) -> libc::c_int {
+    advance(&mut *(data as *mut PlayerState));
+    0
+}
@@ -1,5 +1,5 @@
 //! TODO Documentation
-use std::{fmt, cell::Cell, rc::Rc};
+use std::{env, fmt, cell::Cell, rc::Rc};
 
 use wlroots_sys::{wlr_input_device, wlr_keyboard, wlr_keyboard_led, wlr_keyboard_led_update,
                   wlr_keyboard_get_modifiers, wlr_keyboard_modifier, wlr_keyboard_modifiers,
@@ -10,7 +10,7 @@ use xkbcommon::xkb::{self, Keycode, Keymap, LedIndex, ModIndex};
 use xkbcommon::xkb::ffi::{xkb_keymap, xkb_state};
 
 use {input::{self, InputState},
-     utils::{self, Handleable, HandleErr, HandleResult}};
+     utils::{self, Handleable, HandleErr, HandleResult, Id}};
 pub use manager::keyboard_handler::*;
 pub use events::key_events as event;
 
@@ -89,6 +89,7 @@ impl Keyboard {
                 let liveliness = Rc::new(Cell::new(false));
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState { handle,
+                                                  id: Id::next(),
                                                   device: input::Device::from_ptr(device) });
                 (*keyboard).data = Box::into_raw(state) as *mut _;
                 Some(Keyboard { liveliness,
@@ -119,6 +120,38 @@ impl Keyboard {
         }
     }
 
+    /// Switch to the XKB layout and variant, e.g "ru" and "phonetic".
+    ///
+    /// The rules, model and options are taken from the `XKB_DEFAULT_*`
+    /// environment variables, like for new keyboards. Returns false if no
+    /// keymap could be compiled for the layout.
+    pub fn set_layout(&mut self, layout: &str, variant: &str) -> bool {
+        let rules = env::var("XKB_DEFAULT_RULES").unwrap_or_default();
+        let model = env::var("XKB_DEFAULT_MODEL").unwrap_or_default();
+        let options = env::var("XKB_DEFAULT_OPTIONS").ok();
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_names(&context,
+                                                 rules.as_str(),
+                                                 model.as_str(),
+                                                 layout,
+                                                 variant,
+                                                 options,
+                                                 xkb::KEYMAP_COMPILE_NO_FLAGS);
+        match keymap {
+            Some(keymap) => {
+                self.set_keymap(&keymap);
+                true
+            }
+            None => {
+                wlr_log!(WLR_ERROR,
+                         "Could not compile keymap for layout {:?} ({:?})",
+                         layout,
+                         variant);
+                false
+            }
+        }
+    }
+
     /// Get the XKB keymap associated with this Keyboard.
     pub fn get_keymap(&mut self) -> Option<Keymap> {
         unsafe {
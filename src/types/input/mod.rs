@@ -1,4 +1,5 @@
 mod input_device;
+pub mod key_macro;
 pub mod keyboard;
 pub mod pointer;
 pub mod switch;
@@ -4,7 +4,7 @@ use std::{cell::Cell, rc::Rc};
 
 use {
     input::{self, InputState},
-    utils::{self, Handleable, HandleErr, HandleResult}};
+    utils::{self, Handleable, HandleErr, HandleResult, Id}};
 use wlroots_sys::{wlr_input_device, wlr_switch};
 pub use manager::switch_handler::*;
 pub use events::switch_events as event;
@@ -45,6 +45,7 @@ impl Switch {
                 let liveliness = Rc::new(Cell::new(false));
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState { handle,
+                                                  id: Id::next(),
                                                   device: input::Device::from_ptr(device) });
                 (*switch).data = Box::into_raw(state) as *mut _;
                 Some(Switch { liveliness,
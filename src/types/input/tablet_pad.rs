@@ -4,7 +4,7 @@ use std::{cell::Cell, rc::Rc};
 use wlroots_sys::{wlr_input_device, wlr_tablet_pad};
 
 use {input::{self, InputState},
-     utils::{self, Handleable, HandleErr, HandleResult}};
+     utils::{self, Handleable, HandleErr, HandleResult, Id}};
 pub use manager::tablet_pad_handler::*;
 pub use events::tablet_pad_events as event;
 
@@ -44,6 +44,7 @@ impl TabletPad {
                 let liveliness = Rc::new(Cell::new(false));
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState { handle,
+                                                  id: Id::next(),
                                                   device: input::Device::from_ptr(device) });
                 (*pad).data = Box::into_raw(state) as *mut _;
                 Some(TabletPad { liveliness,
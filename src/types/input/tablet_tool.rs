@@ -4,7 +4,7 @@ use std::{cell::Cell, rc::Rc};
 use wlroots_sys::{wlr_input_device, wlr_tablet, wlr_tablet_tool_axes};
 
 use {input::{self, InputState},
-     utils::{self, Handleable, HandleErr, HandleResult}};
+     utils::{self, Handleable, HandleErr, HandleResult, Id}};
 pub use manager::tablet_tool_handler::*;
 pub use events::tablet_tool_events as event;
 
@@ -61,6 +61,7 @@ impl TabletTool {
                 let liveliness = Rc::new(Cell::new(false));
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState { handle,
+                                                  id: Id::next(),
                                                   device: input::Device::from_ptr(device) });
                 (*tool).data = Box::into_raw(state) as *mut _;
                 Some(TabletTool { liveliness,
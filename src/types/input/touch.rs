@@ -5,7 +5,7 @@ use std::{cell::Cell, rc::Rc};
 use wlroots_sys::{wlr_input_device, wlr_touch};
 
 use {input::{self, InputState},
-     utils::{self, Handleable, HandleErr, HandleResult}};
+     utils::{self, Handleable, HandleErr, HandleResult, Id}};
 pub use manager::touch_handler::*;
 pub use events::touch_events as event;
 
@@ -45,6 +45,7 @@ impl Touch {
                 let liveliness = Rc::new(Cell::new(false));
                 let handle = Rc::downgrade(&liveliness);
                 let state = Box::new(InputState { handle,
+                                                  id: Id::next(),
                                                   device: input::Device::from_ptr(device) });
                 (*touch).data = Box::into_raw(state) as *mut _;
                 Some(Touch { liveliness,
@@ -1,10 +1,11 @@
+#[cfg(feature = "sys")]
 pub mod cursor;
 
 #[cfg(feature = "unstable")]
 pub mod input;
 #[cfg(feature = "unstable")]
 pub mod output;
-#[cfg(feature = "unstable")]
+#[cfg(any(feature = "unstable", feature = "mock"))]
 pub mod area;
 #[cfg(feature = "unstable")]
 pub mod seat;
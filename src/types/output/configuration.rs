@@ -0,0 +1,299 @@
+//! Checking and applying output configurations as a whole.
+//!
+//! Configurations proposed by the user (e.g over IPC, or from a settings
+//! tool) often change several outputs at once. Applying the changes one
+//! output at a time makes every output flicker through modesets, only to
+//! find out halfway through that one of the changes can't work.
+//!
+//! `test_configuration` checks every change up front, without touching the
+//! outputs, and `apply_configuration` only applies a configuration that
+//! passed, putting the outputs it already changed back the way they were
+//! if the backend still rejects a change.
+//!
+//! Test commits (`wlr_output_test`) only arrived with the atomic output
+//! state of later wlroots releases, and the wlroots 0.3.0 this builds
+//! against has no way to ask the backend about a change without applying
+//! it. So the test is
+//! best effort: modes are checked against the modes the outputs advertise,
+//! scales against being positive, and positions against overlapping. What
+//! the backend still rejects is caught while applying, and rolled back.
+
+use std::{error::Error as StdError, fmt};
+
+use libc::c_float;
+use wlroots_sys::wl_output_transform::{WL_OUTPUT_TRANSFORM_270, WL_OUTPUT_TRANSFORM_90,
+                                       WL_OUTPUT_TRANSFORM_FLIPPED_270,
+                                       WL_OUTPUT_TRANSFORM_FLIPPED_90};
+
+use {area::{Area, Origin, Size},
+     output::{self, layout, Output, Transform}};
+
+/// How far off the refresh rate of a requested mode may be, in mHz.
+const REFRESH_TOLERANCE: i32 = 1000;
+
+/// A change to one output.
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub output: output::Handle,
+    pub enabled: bool,
+    /// The resolution and refresh rate (in mHz, or 0 for any).
+    pub mode: Option<(Size, i32)>,
+    pub scale: Option<c_float>,
+    pub transform: Option<Transform>,
+    /// Where the output goes in the layout.
+    pub position: Option<Origin>
+}
+
+impl Change {
+    /// A change that leaves the output as it is, but enabled.
+    pub fn new(output: output::Handle) -> Self {
+        Change { output,
+                 enabled: true,
+                 mode: None,
+                 scale: None,
+                 transform: None,
+                 position: None }
+    }
+}
+
+/// Why a configuration was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The output of the change at this index is gone, or it's borrowed.
+    Unavailable(usize),
+    /// The output doesn't support the mode.
+    UnsupportedMode { output: String, size: Size, refresh: i32 },
+    /// The scale is not a positive number.
+    InvalidScale { output: String, scale: c_float },
+    /// The two outputs would overlap in the layout.
+    Overlapping(String, String),
+    /// The backend rejected the change to the output. The outputs were put
+    /// back the way they were.
+    Failed(String)
+}
+
+/// The state of an output, to go back to if applying fails.
+struct Saved {
+    enabled: bool,
+    mode: Option<(Size, i32)>,
+    scale: c_float,
+    transform: Transform,
+    /// Where the output was in the layout. Moving it back does nothing if
+    /// it isn't in one.
+    position: Origin
+}
+
+/// Check that the changes can be applied together, without changing
+/// anything.
+///
+/// Outputs that aren't part of the changes are left out of the overlap
+/// check, include a `Change::new` for them if they should be considered.
+pub fn test_configuration(changes: &[Change]) -> Result<(), Error> {
+    let mut areas: Vec<(String, Area)> = Vec::new();
+    for (index, change) in changes.iter().enumerate() {
+        let (name, area) = change.output
+            .run(|output| test_change(output, change))
+            .map_err(|_| Error::Unavailable(index))??;
+        if !change.enabled {
+            continue
+        }
+        if let Some(area) = area {
+            if let Some(&(ref other, _)) = areas.iter().find(|&&(_, other)| overlap(area, other)) {
+                return Err(Error::Overlapping(other.clone(), name))
+            }
+            areas.push((name, area));
+        }
+    }
+    Ok(())
+}
+
+/// Test the changes, and apply them if they passed.
+///
+/// Outputs are moved in the `layout` if the change has a position. If
+/// applying fails, the outputs that were already changed get their mode,
+/// scale, transform and position in the `layout` back.
+pub fn apply_configuration(layout: &mut layout::Layout, changes: &[Change]) -> Result<(), Error> {
+    test_configuration(changes)?;
+    let mut applied: Vec<(output::Handle, Saved)> = Vec::new();
+    for (index, change) in changes.iter().enumerate() {
+        let result = change.output
+            .run(|output| {
+                     let saved = save(output);
+                     (apply_change(output, change), saved, output.name())
+                 })
+            .map_err(|_| Error::Unavailable(index));
+        let (ok, saved, name) = match result {
+            Ok(result) => result,
+            Err(err) => {
+                restore_all(layout, applied);
+                return Err(err)
+            }
+        };
+        applied.push((change.output.clone(), saved));
+        if !ok {
+            wlr_log!(WLR_ERROR, "Output {} rejected the configuration", name);
+            restore_all(layout, applied);
+            return Err(Error::Failed(name))
+        }
+        if let (true, Some(position)) = (change.enabled, change.position) {
+            let _ = change.output.run(|output| layout.move_output(output, position));
+        }
+    }
+    Ok(())
+}
+
+/// Check the change, returning the name of the output and the area it
+/// would take up in the layout, if it has a position.
+fn test_change(output: &mut Output, change: &Change) -> Result<(String, Option<Area>), Error> {
+    let name = output.name();
+    let size = match change.mode {
+        Some((size, refresh)) => {
+            let modes = output.modes();
+            let supported = if modes.is_empty() {
+                // Nested and headless outputs take any mode.
+                size.width > 0 && size.height > 0
+            } else {
+                modes.iter().any(|mode| {
+                    let (width, height) = mode.dimensions();
+                    (width, height) == (size.width, size.height)
+                        && (refresh == 0 || (mode.refresh() - refresh).abs() <= REFRESH_TOLERANCE)
+                })
+            };
+            if change.enabled && !supported {
+                return Err(Error::UnsupportedMode { output: name, size, refresh })
+            }
+            size
+        }
+        None => {
+            let (width, height) = output.size();
+            Size::new(width, height)
+        }
+    };
+    let scale = change.scale.unwrap_or_else(|| output.scale());
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(Error::InvalidScale { output: name, scale })
+    }
+    let transform = change.transform.unwrap_or_else(|| output.get_transform());
+    let area = change.position.map(|origin| Area::new(origin, logical_size(size, scale, transform)));
+    Ok((name, area))
+}
+
+fn apply_change(output: &mut Output, change: &Change) -> bool {
+    if !change.enabled {
+        return output.enable(false)
+    }
+    if !output.enabled() && !output.enable(true) {
+        return false
+    }
+    if let Some((size, refresh)) = change.mode {
+        if !set_mode(output, size, refresh) {
+            return false
+        }
+    }
+    if let Some(transform) = change.transform {
+        output.transform(transform);
+    }
+    if let Some(scale) = change.scale {
+        output.set_scale(scale);
+    }
+    true
+}
+
+fn set_mode(output: &mut Output, size: Size, refresh: i32) -> bool {
+    let mode = {
+        let modes = output.modes();
+        if modes.is_empty() {
+            None
+        } else {
+            let mut matching: Vec<_> = modes.into_iter()
+                .filter(|mode| mode.dimensions() == (size.width, size.height))
+                .collect();
+            // Prefer the closest refresh rate, or the highest if any will do.
+            matching.sort_by_key(|mode| if refresh == 0 {
+                                            -mode.refresh()
+                                        } else {
+                                            (mode.refresh() - refresh).abs()
+                                        });
+            match matching.into_iter().next() {
+                Some(mode) => Some(unsafe { output::Mode::new(mode.as_ptr()) }),
+                None => return false
+            }
+        }
+    };
+    match mode {
+        Some(mode) => output.set_mode(mode),
+        None => output.set_custom_mode(size, refresh)
+    }
+}
+
+fn save(output: &mut Output) -> Saved {
+    Saved { enabled: output.enabled(),
+            mode: output.current_mode().map(|mode| {
+                let (width, height) = mode.dimensions();
+                (Size::new(width, height), mode.refresh())
+            }),
+            scale: output.scale(),
+            transform: output.get_transform(),
+            position: {
+                let (x, y) = output.layout_space_pos();
+                Origin::new(x, y)
+            } }
+}
+
+fn restore_all(layout: &mut layout::Layout, applied: Vec<(output::Handle, Saved)>) {
+    for (handle, saved) in applied.into_iter().rev() {
+        let _ = handle.run(|output| {
+            if let Some((size, refresh)) = saved.mode {
+                set_mode(output, size, refresh);
+            }
+            output.transform(saved.transform);
+            output.set_scale(saved.scale);
+            output.enable(saved.enabled);
+            layout.move_output(output, saved.position);
+        });
+    }
+}
+
+/// The size of the output in the layout.
+fn logical_size(size: Size, scale: c_float, transform: Transform) -> Size {
+    let (width, height) = match transform {
+        WL_OUTPUT_TRANSFORM_90 | WL_OUTPUT_TRANSFORM_270 | WL_OUTPUT_TRANSFORM_FLIPPED_90 |
+        WL_OUTPUT_TRANSFORM_FLIPPED_270 => (size.height, size.width),
+        _ => (size.width, size.height)
+    };
+    Size::new((width as c_float / scale).round() as i32,
+              (height as c_float / scale).round() as i32)
+}
+
+fn overlap(a: Area, b: Area) -> bool {
+    a.origin.x < b.origin.x + b.size.width && b.origin.x < a.origin.x + a.size.width &&
+    a.origin.y < b.origin.y + b.size.height && b.origin.y < a.origin.y + a.size.height
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Error::*;
+        match *self {
+            Unavailable(index) => write!(f, "output of change {} is unavailable", index),
+            UnsupportedMode { ref output, size, refresh } => {
+                write!(f, "{} does not support {}x{}@{}mHz", output, size.width, size.height, refresh)
+            }
+            InvalidScale { ref output, scale } => write!(f, "invalid scale {} for {}", scale, output),
+            Overlapping(ref a, ref b) => write!(f, "{} and {} overlap", a, b),
+            Failed(ref output) => write!(f, "{} rejected the configuration", output)
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        use self::Error::*;
+        match *self {
+            Unavailable(_) => "Output is unavailable",
+            UnsupportedMode { .. } => "Output does not support the mode",
+            InvalidScale { .. } => "Invalid output scale",
+            Overlapping(..) => "Outputs overlap",
+            Failed(_) => "Output rejected the configuration"
+        }
+    }
+}
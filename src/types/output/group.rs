@@ -0,0 +1,200 @@
+//! Groups of outputs that are used as one canvas, e.g a video wall.
+//!
+//! An `OutputGroup` places its outputs in the `output::layout::Layout` at
+//! fixed offsets from the origin of the group. Leaving a gap between
+//! neighbouring outputs as wide as their bezels makes the picture line up
+//! across them: what would be drawn behind a bezel is not shown, like on a
+//! window frame.
+//!
+//! ```rust,no_run,ignore
+//! // Two 1920x1080 screens side by side, with 40 pixels worth of bezels
+//! // between them.
+//! let mut wall = OutputGroup::new("wall", Origin::new(0, 0));
+//! wall.add(left, Origin::new(0, 0));
+//! wall.add(right, Origin::new(1960, 0));
+//! wall.arrange(layout);
+//! let canvas = wall.area(layout);
+//! ```
+//!
+//! Views are fullscreened onto the whole group with
+//! `wm::fullscreen::Target::Group`. The cursor doesn't cross the gaps by
+//! itself, since they aren't part of the layout, use
+//! `OutputGroup::skip_bezels` before moving it.
+
+use area::{Area, Origin};
+use cursor::Cursor;
+use input;
+use output::{self, layout::Layout};
+
+#[derive(Debug, Clone)]
+struct Member {
+    output: output::Handle,
+    /// Where the top left corner of the output is on the canvas.
+    offset: Origin
+}
+
+/// Outputs that show one picture together.
+#[derive(Debug, Clone)]
+pub struct OutputGroup {
+    name: String,
+    origin: Origin,
+    members: Vec<Member>
+}
+
+impl OutputGroup {
+    /// Start an empty group whose canvas starts at `origin` in the layout.
+    pub fn new<S: Into<String>>(name: S, origin: Origin) -> Self {
+        OutputGroup { name: name.into(),
+                      origin,
+                      members: Vec::new() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn origin(&self) -> Origin {
+        self.origin
+    }
+
+    /// Move the canvas in the layout. Call `arrange` to move the outputs.
+    pub fn set_origin(&mut self, origin: Origin) {
+        self.origin = origin
+    }
+
+    /// Put the output on the canvas, or move it if it's there already.
+    ///
+    /// `offset` is where its top left corner is, relative to the origin of
+    /// the group. Call `arrange` to move it there.
+    pub fn add(&mut self, output: output::Handle, offset: Origin) {
+        self.remove(&output);
+        self.members.push(Member { output, offset })
+    }
+
+    pub fn remove(&mut self, output: &output::Handle) {
+        self.members.retain(|member| member.output != *output)
+    }
+
+    pub fn contains(&self, output: &output::Handle) -> bool {
+        self.members.iter().any(|member| member.output == *output)
+    }
+
+    pub fn outputs(&self) -> Vec<output::Handle> {
+        self.members.iter().map(|member| member.output.clone()).collect()
+    }
+
+    /// Move the outputs to their place in the layout.
+    pub fn arrange(&self, layout: &mut Layout) {
+        for member in &self.members {
+            let origin = Origin::new(self.origin.x + member.offset.x,
+                                     self.origin.y + member.offset.y);
+            member.output.run(|output| layout.move_output(output, origin)).ok();
+        }
+    }
+
+    /// Get where the outputs of the group are in the layout.
+    ///
+    /// Outputs that aren't in the layout are left out.
+    pub fn output_areas(&self, layout: &mut Layout) -> Vec<(output::Handle, Area)> {
+        let outputs = layout.outputs();
+        self.members
+            .iter()
+            .filter(|member| outputs.iter().any(|&(ref output, _)| *output == member.output))
+            .filter_map(|member| {
+                            member.output
+                                  .run(|output| layout.get_box(output))
+                                  .ok()
+                                  .map(|area| (member.output.clone(), area))
+                        })
+            .collect()
+    }
+
+    /// Get the canvas of the group in layout coordinates, i.e the area its
+    /// outputs span, bezels included.
+    pub fn area(&self, layout: &mut Layout) -> Option<Area> {
+        let areas: Vec<Area> = self.output_areas(layout)
+                                   .into_iter()
+                                   .map(|(_, area)| area)
+                                   .collect();
+        bounds(&areas)
+    }
+
+    /// Confine the cursor to the canvas of the group.
+    pub fn map_cursor(&self, cursor: &mut Cursor, layout: &mut Layout) {
+        if let Some(area) = self.area(layout) {
+            cursor.map_to_region(area)
+        }
+    }
+
+    /// Map an absolute input device, e.g a touch frame around a video wall,
+    /// to the canvas of the group.
+    ///
+    /// The device must be attached to the cursor.
+    pub fn map_input(&self, cursor: &mut Cursor, dev: &input::Device, layout: &mut Layout) {
+        if let Some(area) = self.area(layout) {
+            cursor.map_input_to_region(dev, area)
+        }
+    }
+
+    /// Carry the cursor across a bezel it moves onto, to the next output in
+    /// the direction it moves.
+    ///
+    /// `from` is where the cursor is and `to` where it would be moved. Returns
+    /// where it should be moved instead, which is `to` unless that's in a gap
+    /// between the outputs of the group.
+    pub fn skip_bezels(&self,
+                       layout: &mut Layout,
+                       from: (f64, f64),
+                       to: (f64, f64))
+                       -> (f64, f64) {
+        let areas: Vec<Area> = self.output_areas(layout)
+                                   .into_iter()
+                                   .map(|(_, area)| area)
+                                   .collect();
+        let (x, y) = to;
+        let (dx, dy) = (x - from.0, y - from.1);
+        let canvas = match bounds(&areas) {
+            Some(canvas) => canvas,
+            None => return to
+        };
+        if !canvas.contains_point(x, y) || areas.iter().any(|area| area.contains_point(x, y)) {
+            return to
+        }
+        let mut closest: Option<(f64, f64)> = None;
+        for area in areas {
+            let (left, top) = (f64::from(area.origin.x), f64::from(area.origin.y));
+            let right = left + f64::from(area.size.width);
+            let bottom = top + f64::from(area.size.height);
+            let in_row = y >= top && y < bottom;
+            let in_column = x >= left && x < right;
+            let candidate = if dx > 0.0 && in_row && x < left {
+                (left, y)
+            } else if dx < 0.0 && in_row && x >= right {
+                (right - 1.0, y)
+            } else if dy > 0.0 && in_column && y < top {
+                (x, top)
+            } else if dy < 0.0 && in_column && y >= bottom {
+                (x, bottom - 1.0)
+            } else {
+                continue
+            };
+            let distance = |(cx, cy): (f64, f64)| (cx - x).abs() + (cy - y).abs();
+            if closest.map(|point| distance(candidate) < distance(point)).unwrap_or(true) {
+                closest = Some(candidate);
+            }
+        }
+        closest.unwrap_or(to)
+    }
+
+    /// Forget outputs that were destroyed.
+    pub fn prune(&mut self) {
+        self.members.retain(|member| member.output.is_alive())
+    }
+}
+
+/// Get the smallest area that contains all of the areas.
+fn bounds(areas: &[Area]) -> Option<Area> {
+    let mut iter = areas.iter().cloned();
+    let first = iter.next()?;
+    Some(iter.fold(first, Area::union))
+}
@@ -14,6 +14,7 @@ use wlroots_sys::{wlr_output_effective_resolution, wlr_output_layout, wlr_output
 
 use {area::{Area, Origin},
      compositor,
+     cursor,
      output,
      utils::{HandleErr, HandleResult, Handleable}};
 
@@ -205,6 +206,7 @@ impl Layout {
     pub fn add(&mut self, output: &mut output::Output, origin: Origin) {
         let (x, y) = (origin.x, origin.y);
         unsafe { wlr_output_layout_add(self.data.0, output.as_ptr(), x, y) }
+        cursor::reload_xcursor_themes();
     }
 
     /// Adds an output to the layout, automatically positioning it with
@@ -216,6 +218,7 @@ impl Layout {
             wlr_output_layout_add_auto(self.data.0, output.as_ptr());
             wlr_log!(WLR_DEBUG, "Added {:?} to {:?}", output, self);
         }
+        cursor::reload_xcursor_themes();
     }
 
     /// Moves the output to the given coordinates.
@@ -383,6 +386,22 @@ impl Handle {
         }
     }
 
+    /// The scales of the outputs in the layout, read without borrowing it.
+    ///
+    /// Empty if the layout was destroyed.
+    pub(crate) fn output_scales(&self) -> Vec<f32> {
+        let mut scales = vec![];
+        if self.handle.upgrade().is_none() {
+            return scales
+        }
+        unsafe {
+            wl_list_for_each!((*self.layout).outputs, link, (pos: wlr_output_layout_output) => {
+                scales.push((*(*pos).output).scale)
+            });
+        }
+        scales
+    }
+
     /// Upgrades the `Handle` to a reference
     /// to the backing `output::layout::Layout`.
     ///
@@ -1,13 +1,17 @@
 mod output;
+pub mod configuration;
+pub mod group;
 pub mod layout;
 mod mode;
 mod cursor;
 mod damage;
+mod scale;
 
 pub use self::cursor::*;
 pub use self::damage::*;
 pub use self::output::*;
 pub use self::mode::*;
+pub use self::scale::*;
 
 pub mod manager {
     //! Output resources are managed by the output resource manager.
@@ -41,3 +41,17 @@ impl<'output> Mode<'output> {
         unsafe { (*self.output_mode).refresh }
     }
 }
+
+
+#[cfg(feature = "serde")]
+impl<'output> ::serde::Serialize for Mode<'output> {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let (width, height) = self.dimensions();
+        let mut state = serializer.serialize_struct("Mode", 3)?;
+        state.serialize_field("width", &width)?;
+        state.serialize_field("height", &height)?;
+        state.serialize_field("refresh", &self.refresh())?;
+        state.end()
+    }
+}
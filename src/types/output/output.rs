@@ -15,7 +15,7 @@ use wlroots_sys::{timespec, wl_list, wl_output_subpixel, wl_output_transform, wl
                   wlr_output_swap_buffers, wlr_output_transformed_resolution};
 
 use {area::{Origin, Size},
-     utils::{self, HandleErr, HandleResult, Handleable, c_to_rust_string},
+     utils::{self, HandleErr, HandleResult, Handleable, Id, c_to_rust_string},
      output::{self, layout},
      render::PixmanRegion};
 pub use manager::output_handler::*;
@@ -28,8 +28,14 @@ pub type Transform = wl_output_transform;
 pub(crate) struct OutputState {
     pub(crate) output: *mut UserOutput,
     handle: Weak<Cell<bool>>,
+    id: Id,
     damage: *mut wlr_output_damage,
-    layout_handle: Option<layout::Handle>
+    layout_handle: Option<layout::Handle>,
+    /// The arguments of the last `set_gamma`, restored after the session was
+    /// inactive.
+    gamma: Option<(usize, u16, u16, u16)>,
+    /// Whether clients may export the contents of the output as dmabufs.
+    pub(crate) dmabuf_export: bool
 }
 
 #[derive(Debug)]
@@ -79,8 +85,11 @@ impl Output {
         let damage = ManuallyDrop::new(output::Damage::new(output));
         let state = Box::new(OutputState { output: ptr::null_mut(),
                                            handle,
+                                           id: Id::next(),
                                            damage: damage.as_ptr(),
-                                           layout_handle: None });
+                                           layout_handle: None,
+                                           gamma: None,
+                                           dmabuf_export: true });
         (*output).data = Box::into_raw(state) as *mut _;
         Output { liveliness,
                  damage,
@@ -150,6 +159,14 @@ impl Output {
         }
     }
 
+    /// Determines if the output was added to an `output::Layout`.
+    pub(crate) fn in_layout(&self) -> bool {
+        unsafe {
+            let data = (*self.output).data as *mut OutputState;
+            !data.is_null() && (*data).layout_handle.is_some()
+        }
+    }
+
     /// Sets the best modesetting for an output.
     ///
     /// NOTE You _cannot_ call this when the output will be removed.
@@ -184,6 +201,14 @@ impl Output {
         unsafe { wlr_output_set_custom_mode(self.output, size.width, size.height, refresh) }
     }
 
+    /// Gets the stable identifier of this output.
+    ///
+    /// Unlike the name this is never reused, even when an output with
+    /// the same name is hotplugged again.
+    pub fn id(&self) -> Id {
+        unsafe { (*((*self.output).data as *mut OutputState)).id }
+    }
+
     /// Gets the name of the output in UTF-8.
     pub fn name(&self) -> String {
         unsafe {
@@ -350,6 +375,12 @@ impl Output {
         unsafe { ((*self.output).phys_width, (*self.output).phys_height) }
     }
 
+    /// Suggest a scale for this output, based on its current mode and the
+    /// physical size from its EDID. See `output::suggested_scale`.
+    pub fn suggested_scale(&self, fractional: bool) -> c_float {
+        output::suggested_scale(self.size(), self.physical_size(), fractional)
+    }
+
     /// Computes the transformed output resolution
     pub fn transformed_resolution(&self) -> (c_int, c_int) {
         unsafe {
@@ -398,7 +429,47 @@ impl Output {
 
     /// Sets the gamma based on the size.
     pub fn set_gamma(&mut self, size: usize, mut r: u16, mut g: u16, mut b: u16) -> bool {
-        unsafe { wlr_output_set_gamma(self.output, size, &mut r, &mut g, &mut b) }
+        unsafe {
+            let user_data = self.user_data();
+            if !user_data.is_null() {
+                (*user_data).gamma = Some((size, r, g, b));
+            }
+            wlr_output_set_gamma(self.output, size, &mut r, &mut g, &mut b)
+        }
+    }
+
+    /// Set the gamma from the last `set_gamma` again, e.g because the
+    /// session was inactive and the hardware lost it.
+    pub(crate) fn restore_gamma(&mut self) {
+        unsafe {
+            let user_data = self.user_data();
+            if user_data.is_null() {
+                return
+            }
+            if let Some((size, mut r, mut g, mut b)) = (*user_data).gamma {
+                wlr_output_set_gamma(self.output, size, &mut r, &mut g, &mut b);
+            }
+        }
+    }
+
+    /// Allow or forbid clients to capture this output with the export-dmabuf
+    /// protocol. Outputs may be exported by default.
+    pub fn set_dmabuf_export_allowed(&mut self, allowed: bool) {
+        unsafe {
+            let user_data = self.user_data();
+            if !user_data.is_null() {
+                (*user_data).dmabuf_export = allowed;
+            }
+        }
+    }
+
+    /// Determines if clients may capture this output with the export-dmabuf
+    /// protocol.
+    pub fn dmabuf_export_allowed(&self) -> bool {
+        unsafe {
+            let user_data = (*self.output).data as *mut OutputState;
+            user_data.is_null() || (*user_data).dmabuf_export
+        }
     }
 
     /// Get the gamma size.
@@ -421,6 +492,14 @@ impl Output {
     }
 }
 
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Output {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ::serde::Serialize::serialize(&::snapshot::OutputInfo::new(self), serializer)
+    }
+}
+
 impl Drop for Output {
     fn drop(&mut self) {
         // NOTE
@@ -0,0 +1,74 @@
+//! Picking a sensible default scale for an output.
+
+use libc::c_float;
+
+/// The pixel density, in pixels per inch, that is drawn at scale 1 on
+/// monitors on a desk.
+pub const DESKTOP_REFERENCE_DPI: f64 = 96.0;
+
+/// The pixel density that is drawn at scale 1 on laptop panels and other
+/// small screens, which are looked at from closer by.
+pub const LAPTOP_REFERENCE_DPI: f64 = 125.0;
+
+/// Screens with a smaller diagonal than this, in inches, are treated as
+/// laptop panels.
+const LAPTOP_MAX_DIAGONAL: f64 = 18.0;
+
+/// The suggested scale never leaves less logical space than this, so e.g a
+/// small 4K panel doesn't end up with a desktop the size of a phone.
+const MIN_LOGICAL_WIDTH: f64 = 1024.0;
+const MIN_LOGICAL_HEIGHT: f64 = 640.0;
+
+const MAX_SCALE: f64 = 4.0;
+
+/// Suggest a scale for an output from its resolution, in pixels, and its
+/// physical size as reported by its EDID, in millimeters.
+///
+/// The scale is chosen so text comes out at about the size it would on a
+/// 96 DPI monitor, taking into account that laptop panels are looked at from
+/// closer by. With `fractional` it's rounded to a quarter, otherwise to a
+/// whole number, and it is never less than 1.
+///
+/// Falls back to 1 when the physical size is missing or obviously made up,
+/// which is common for projectors, TVs and virtual outputs.
+pub fn suggested_scale(resolution: (i32, i32), physical_size: (i32, i32), fractional: bool) -> c_float {
+    let (width, height) = (resolution.0 as f64, resolution.1 as f64);
+    let (phys_width, phys_height) = (physical_size.0 as f64, physical_size.1 as f64);
+    if width <= 0.0 || height <= 0.0 || !plausible_physical_size(physical_size) {
+        return 1.0
+    }
+    let horizontal_dpi = width / (phys_width / 25.4);
+    let vertical_dpi = height / (phys_height / 25.4);
+    // Rotated or squashed sizes mean the EDID can't be trusted.
+    let ratio = horizontal_dpi / vertical_dpi;
+    if ratio < 0.8 || ratio > 1.25 {
+        return 1.0
+    }
+    let dpi = (horizontal_dpi + vertical_dpi) / 2.0;
+    let diagonal = (phys_width * phys_width + phys_height * phys_height).sqrt() / 25.4;
+    let reference = if diagonal < LAPTOP_MAX_DIAGONAL {
+        LAPTOP_REFERENCE_DPI
+    } else {
+        DESKTOP_REFERENCE_DPI
+    };
+    let max_scale = (width / MIN_LOGICAL_WIDTH).min(height / MIN_LOGICAL_HEIGHT)
+                                               .min(MAX_SCALE);
+    let step = if fractional { 0.25 } else { 1.0 };
+    let mut scale = ((dpi / reference) / step).round() * step;
+    while scale > step && scale > max_scale {
+        scale -= step;
+    }
+    scale.max(1.0) as c_float
+}
+
+/// Determines if a physical size from an EDID looks like a real size.
+///
+/// Sizes of zero mean it's unknown, and some screens put the aspect ratio
+/// in the size fields instead (e.g 16x9 cm).
+fn plausible_physical_size((width, height): (i32, i32)) -> bool {
+    const ASPECT_RATIOS: [(i32, i32); 2] = [(160, 90), (160, 100)];
+    if width < 100 || height < 60 {
+        return false
+    }
+    !ASPECT_RATIOS.iter().any(|&(w, h)| (w, h) == (width, height) || (h, w) == (width, height))
+}
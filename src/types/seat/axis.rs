@@ -0,0 +1,108 @@
+//! Combining the axis events that arrive together into one notification.
+//!
+//! High resolution mice and touchpads can send several axis events for what
+//! is one scroll on screen. Sending each of them on its own makes clients
+//! redraw for every one, so they are summed up until the compositor is done
+//! with the events it read in one go.
+
+use std::{ptr, time::Duration};
+
+use libc;
+use wayland_sys::server::{wl_event_source, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_axis_orientation, wlr_axis_source, wlr_seat, wlr_seat_pointer_notify_axis};
+
+use {compositor,
+     seat::{Capability, Seat, SeatState},
+     utils::ToMs};
+
+/// Axis motion that hasn't been sent yet.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PendingAxis {
+    time_msec: u32,
+    orientation: wlr_axis_orientation,
+    value: f64,
+    value_discrete: i32,
+    source: wlr_axis_source
+}
+
+impl Seat {
+    /// Like `pointer_notify_axis`, but axis events on the same axis from the
+    /// same source are summed up and sent as one, once the compositor has
+    /// handled all the events that are ready.
+    pub fn pointer_notify_axis_batched(&self,
+                                       time: Duration,
+                                       orientation: wlr_axis_orientation,
+                                       value: f64,
+                                       value_discrete: i32,
+                                       source: wlr_axis_source) {
+        self.notify_activity(Capability::Pointer);
+        unsafe {
+            let seat_ptr = self.as_ptr();
+            let state = self.state();
+            let time_msec = time.to_ms();
+            // Events from another source aren't the same scroll, so they
+            // can't be combined with what is pending.
+            if state.pending_axis.iter().any(|pending| pending.source != source) {
+                flush(seat_ptr, state);
+            }
+            match state.pending_axis
+                       .iter_mut()
+                       .find(|pending| pending.orientation == orientation) {
+                Some(pending) => {
+                    pending.time_msec = time_msec;
+                    pending.value += value;
+                    pending.value_discrete += value_discrete;
+                }
+                None => {
+                    state.pending_axis.push(PendingAxis { time_msec,
+                                                          orientation,
+                                                          value,
+                                                          value_discrete,
+                                                          source })
+                }
+            }
+            if compositor::COMPOSITOR_PTR.is_null() {
+                // Without an event loop there is nothing to wait for.
+                flush(seat_ptr, state);
+            } else if state.axis_flush.is_null() {
+                state.axis_flush = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                                 wl_event_loop_add_idle,
+                                                 (*compositor::COMPOSITOR_PTR).event_loop,
+                                                 flush_axis,
+                                                 seat_ptr as *mut _);
+            }
+        }
+    }
+}
+
+/// Stop waiting to send the pending axis events, e.g because the seat is
+/// being destroyed.
+pub(super) unsafe fn cancel_flush(state: &mut SeatState) {
+    if !state.axis_flush.is_null() {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, state.axis_flush);
+        state.axis_flush = ptr::null_mut::<wl_event_source>();
+    }
+    state.pending_axis.clear();
+}
+
+unsafe fn flush(seat_ptr: *mut wlr_seat, state: &mut SeatState) {
+    for pending in state.pending_axis.drain(..) {
+        wlr_seat_pointer_notify_axis(seat_ptr,
+                                     pending.time_msec,
+                                     pending.orientation,
+                                     pending.value,
+                                     pending.value_discrete,
+                                     pending.source);
+    }
+}
+
+unsafe extern "C" fn flush_axis(data: *mut libc::c_void) {
+    let seat_ptr = data as *mut wlr_seat;
+    let state = (*seat_ptr).data as *mut SeatState;
+    if state.is_null() {
+        return
+    }
+    // Idle sources only fire once.
+    (*state).axis_flush = ptr::null_mut();
+    flush(seat_ptr, &mut *state);
+}
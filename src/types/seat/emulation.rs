@@ -0,0 +1,163 @@
+//! Translation of touch and tablet tool input into pointer input, for
+//! clients that only bind wl_pointer.
+
+use std::time::Duration;
+
+use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+use wlroots_sys::{wlr_seat_client_for_wl_client, wlr_tablet_tool_tip_state,
+                  wlr_button_state::*};
+
+use {input::{self, pointer::event::BTN_LEFT},
+     seat::{Seat, TouchId},
+     surface::Surface};
+
+impl Seat {
+    /// Enable or disable translating touch into pointer events.
+    ///
+    /// When enabled, the `touch_notify_*_emulated` methods send a single touch
+    /// point as pointer enter, motion and left button events to surfaces whose
+    /// client never bound wl_touch. Other surfaces get regular touch events.
+    pub fn set_touch_emulation(&mut self, enabled: bool) {
+        unsafe {
+            let state = self.state();
+            state.touch_emulation = enabled;
+            if !enabled {
+                state.emulated_touch = None;
+            }
+        }
+    }
+
+    /// Whether touch is being translated into pointer events.
+    pub fn touch_emulation(&self) -> bool {
+        unsafe { self.state().touch_emulation }
+    }
+
+    /// Determines if the client of the surface has bound wl_touch on this seat.
+    pub fn surface_accepts_touch(&self, surface: &mut Surface) -> bool {
+        unsafe {
+            let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_resource_get_client,
+                                       (*surface.as_ptr()).resource as _);
+            let seat_client = wlr_seat_client_for_wl_client(self.as_ptr(), client as _);
+            if seat_client.is_null() {
+                return false
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_length,
+                          &mut (*seat_client).touches as *mut _ as _) > 0
+        }
+    }
+
+    /// Like `Seat::touch_notify_down`, but presses the left pointer button
+    /// instead if the surface can't receive touch and emulation is enabled.
+    ///
+    /// Only one touch point is emulated at a time, any others are ignored
+    /// while it is down.
+    pub fn touch_notify_down_emulated(&mut self,
+                                      surface: &mut Surface,
+                                      time: Duration,
+                                      touch_id: TouchId,
+                                      sx: f64,
+                                      sy: f64)
+                                      -> u32 {
+        unsafe {
+            if !self.state().touch_emulation || self.surface_accepts_touch(surface) {
+                return self.touch_notify_down(surface, time, touch_id, sx, sy)
+            }
+            if self.state().emulated_touch.is_some() {
+                return 0
+            }
+            self.state().emulated_touch = Some(touch_id);
+        }
+        self.pointer_notify_enter(surface, sx, sy);
+        self.pointer_notify_motion(time, sx, sy);
+        self.pointer_notify_button(time, BTN_LEFT, WLR_BUTTON_PRESSED as u32)
+    }
+
+    /// Like `Seat::touch_notify_motion`, but moves the pointer instead if the
+    /// touch point is being emulated.
+    pub fn touch_notify_motion_emulated(&mut self,
+                                        time: Duration,
+                                        touch_id: TouchId,
+                                        sx: f64,
+                                        sy: f64) {
+        if unsafe { self.state().emulated_touch } == Some(touch_id) {
+            self.pointer_notify_motion(time, sx, sy)
+        } else {
+            self.touch_notify_motion(time, touch_id, sx, sy)
+        }
+    }
+
+    /// Like `Seat::touch_notify_up`, but releases the left pointer button
+    /// instead if the touch point is being emulated.
+    pub fn touch_notify_up_emulated(&mut self, time: Duration, touch_id: TouchId) {
+        if unsafe { self.state().emulated_touch } == Some(touch_id) {
+            unsafe { self.state().emulated_touch = None };
+            self.pointer_notify_button(time, BTN_LEFT, WLR_BUTTON_RELEASED as u32);
+        } else {
+            self.touch_notify_up(time, touch_id)
+        }
+    }
+
+    /// Enable or disable moving the pointer with the given tablet tool.
+    ///
+    /// This is for clients that don't implement tablet-v2: the tool moves the
+    /// pointer and the tip acts as the left button. Pressure, tilt and the
+    /// other axes are dropped.
+    pub fn set_tablet_pointer_emulation(&mut self, tool: &input::Device, enabled: bool) {
+        unsafe {
+            let tools = &mut self.state().emulated_tools;
+            tools.retain(|watcher| !watcher.is_dead() && !watcher.is(tool.as_ptr()));
+            if enabled {
+                tools.push(input::DeviceWatcher::watch(tool.as_ptr()));
+            }
+        }
+    }
+
+    /// Whether the tablet tool moves the pointer.
+    pub fn tablet_pointer_emulation(&self, tool: &input::Device) -> bool {
+        unsafe { self.state().emulated_tools.iter().any(|watcher| watcher.is(tool.as_ptr())) }
+    }
+
+    /// Move the pointer over the surface because the tablet tool moved.
+    ///
+    /// Coordinates are surface-local.
+    ///
+    /// Returns false, without sending anything, if emulation is disabled for
+    /// the tool.
+    pub fn tablet_tool_notify_motion_emulated(&mut self,
+                                              tool: &input::Device,
+                                              surface: &mut Surface,
+                                              time: Duration,
+                                              sx: f64,
+                                              sy: f64)
+                                              -> bool {
+        if !self.tablet_pointer_emulation(tool) {
+            return false
+        }
+        self.pointer_notify_enter(surface, sx, sy);
+        self.pointer_notify_motion(time, sx, sy);
+        true
+    }
+
+    /// Press or release the left pointer button because the tablet tool tip
+    /// touched or left the tablet.
+    ///
+    /// Returns false, without sending anything, if emulation is disabled for
+    /// the tool.
+    pub fn tablet_tool_notify_tip_emulated(&mut self,
+                                           tool: &input::Device,
+                                           time: Duration,
+                                           state: wlr_tablet_tool_tip_state)
+                                           -> bool {
+        if !self.tablet_pointer_emulation(tool) {
+            return false
+        }
+        let button_state = match state {
+            wlr_tablet_tool_tip_state::WLR_TABLET_TOOL_TIP_DOWN => WLR_BUTTON_PRESSED,
+            wlr_tablet_tool_tip_state::WLR_TABLET_TOOL_TIP_UP => WLR_BUTTON_RELEASED
+        };
+        self.pointer_notify_button(time, BTN_LEFT, button_state as u32);
+        true
+    }
+}
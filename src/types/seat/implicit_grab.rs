@@ -0,0 +1,95 @@
+//! Keeping pointer focus on a surface while a button is held on it.
+//!
+//! When a button is pressed on a surface, clients expect to get all motion
+//! until the last button is released, even if the cursor leaves the surface,
+//! e.g to drag a scrollbar or to select text past the edge of the window.
+//! This is called an implicit grab.
+
+use std::time::Duration;
+
+use wlroots_sys::wlr_button_state::*;
+
+use {seat::Seat,
+     surface::{self, Surface}};
+
+impl Seat {
+    /// Move the pointer to `(lx, ly)` in layout coordinates.
+    ///
+    /// `focus` is the surface under the cursor, with the position of the
+    /// cursor on it in surface-local coordinates, and gets pointer focus.
+    ///
+    /// While a button that was pressed on a surface is held, motion keeps
+    /// going to that surface instead, with coordinates relative to it, and
+    /// `focus` is ignored. Focus moves to the surface under the cursor on
+    /// the first motion after the last button is released.
+    pub fn pointer_notify_motion_at(&self,
+                                    time: Duration,
+                                    lx: f64,
+                                    ly: f64,
+                                    focus: Option<(&mut Surface, f64, f64)>) {
+        if let Some((origin_x, origin_y)) = self.implicit_grab_origin() {
+            self.pointer_notify_motion(time, lx - origin_x, ly - origin_y);
+            return
+        }
+        match focus {
+            Some((surface, sx, sy)) => {
+                unsafe { self.state().pointer_origin = Some((lx - sx, ly - sy)) }
+                self.pointer_notify_enter(surface, sx, sy);
+                self.pointer_notify_motion(time, sx, sy);
+            }
+            None => {
+                unsafe { self.state().pointer_origin = None }
+                self.pointer_clear_focus();
+            }
+        }
+    }
+
+    /// Get the surface that holds the pointer because a button was pressed
+    /// on it, if there is one.
+    pub fn implicit_grab_surface(&self) -> Option<surface::Handle> {
+        unsafe {
+            self.state()
+                .implicit_grab
+                .as_ref()
+                .filter(|&&(ref surface, _)| surface.is_alive())
+                .map(|&(ref surface, _)| surface.clone())
+        }
+    }
+
+    /// Start or end the implicit grab after a button was pressed or
+    /// released.
+    pub(super) fn update_implicit_grab(&self, state: u32) {
+        unsafe {
+            let seat = self.as_ptr();
+            let grab_state = self.state();
+            if (*seat).pointer_state.button_count == 0 {
+                grab_state.implicit_grab = None;
+                return
+            }
+            if state != WLR_BUTTON_PRESSED as u32 || grab_state.implicit_grab.is_some() {
+                return
+            }
+            let focused = (*seat).pointer_state.focused_surface;
+            if let (false, Some(origin)) = (focused.is_null(), grab_state.pointer_origin) {
+                grab_state.implicit_grab = Some((surface::Handle::from_ptr(focused), origin));
+            }
+        }
+    }
+
+    /// The layout position of the surface holding the implicit grab, if
+    /// there is one and it's still alive.
+    fn implicit_grab_origin(&self) -> Option<(f64, f64)> {
+        unsafe {
+            let state = self.state();
+            let alive = match state.implicit_grab {
+                Some((ref surface, _)) => surface.is_alive(),
+                None => return None
+            };
+            if !alive {
+                state.implicit_grab = None;
+                return None
+            }
+            state.implicit_grab.as_ref().map(|&(_, origin)| origin)
+        }
+    }
+}
@@ -1,4 +1,8 @@
+mod axis;
 pub mod drag_icon;
+mod emulation;
+mod implicit_grab;
+mod primary_selection;
 mod seat_client;
 mod seat;
 pub mod grab;
@@ -0,0 +1,152 @@
+//! Reading and replacing the primary selection of a seat.
+//!
+//! The selection is offered by a client (or the compositor) in a number of
+//! mime types. Whoever wants it asks for one of them and gets it written to
+//! a file descriptor, usually the write end of a pipe.
+//!
+//! wlroots 0.3.0 only implements the GTK primary selection protocol, so
+//! this is the selection of the clients that bound
+//! `compositor::Builder::gtk_primary_selection_manager`.
+
+use std::{io, mem, ffi::CStr, os::unix::io::RawFd};
+
+use libc;
+use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+use wlroots_sys::{wlr_gtk_primary_selection_source, wlr_gtk_primary_selection_source_finish,
+                  wlr_gtk_primary_selection_source_init, wlr_seat_set_gtk_primary_selection};
+
+use {seat::Seat, utils::safe_as_cstring};
+
+/// The mime types text set by the compositor is offered as, in the order
+/// of preference.
+const TEXT_MIME_TYPES: [&str; 5] =
+    ["text/plain;charset=utf-8", "text/plain", "UTF8_STRING", "TEXT", "STRING"];
+
+/// A primary selection owned by the compositor.
+#[repr(C)]
+struct TextSource {
+    /// wlroots only knows about this part, so it must come first.
+    source: wlr_gtk_primary_selection_source,
+    text: String
+}
+
+impl Seat {
+    /// Get the mime types the primary selection is offered as.
+    ///
+    /// Empty if there is no primary selection.
+    pub fn primary_selection_mime_types(&self) -> Vec<String> {
+        unsafe {
+            let source = (*self.as_ptr()).primary_selection_source;
+            if source.is_null() {
+                return Vec::new()
+            }
+            let mime_types = &(*source).mime_types;
+            let count = mime_types.size / mem::size_of::<*mut libc::c_char>();
+            let data = mime_types.data as *mut *mut libc::c_char;
+            (0..count).map(|i| CStr::from_ptr(*data.add(i)).to_string_lossy().into_owned())
+                      .collect()
+        }
+    }
+
+    /// Ask the owner of the primary selection to write it to `fd`, in the
+    /// given mime type.
+    ///
+    /// This takes ownership of `fd`: it's closed once the selection was
+    /// written. Returns false, closing `fd` right away, if there is no
+    /// primary selection.
+    pub fn request_primary_selection(&self, mime_type: &str, fd: RawFd) -> bool {
+        unsafe {
+            let source = (*self.as_ptr()).primary_selection_source;
+            if source.is_null() {
+                libc::close(fd);
+                return false
+            }
+            match (*source).send {
+                Some(send) => {
+                    let mime_type = safe_as_cstring(mime_type);
+                    send(source, mime_type.as_ptr(), fd);
+                    true
+                }
+                None => {
+                    libc::close(fd);
+                    false
+                }
+            }
+        }
+    }
+
+    /// Make `text` the primary selection, so it's what the focused client
+    /// pastes on a middle click.
+    ///
+    /// The text is written out in one go when a client asks for it, so this
+    /// is meant for short text like a word or a line.
+    pub fn set_primary_selection_text<T: Into<String>>(&mut self, text: T) {
+        unsafe {
+            let mut source = Box::new(TextSource { source: mem::zeroed(),
+                                                   text: text.into() });
+            wlr_gtk_primary_selection_source_init(&mut source.source);
+            source.source.send = Some(text_source_send);
+            source.source.cancel = Some(text_source_cancel);
+            // wlroots frees the array and the strings in it when the source
+            // is finished.
+            let size = TEXT_MIME_TYPES.len() * mem::size_of::<*mut libc::c_char>();
+            let data = libc::malloc(size) as *mut *mut libc::c_char;
+            if data.is_null() {
+                return
+            }
+            for (i, mime_type) in TEXT_MIME_TYPES.iter().enumerate() {
+                *data.add(i) = libc::strdup(safe_as_cstring(*mime_type).as_ptr());
+            }
+            source.source.mime_types.data = data as *mut _;
+            source.source.mime_types.size = size;
+            source.source.mime_types.alloc = size;
+            let source = Box::into_raw(source);
+            wlr_seat_set_gtk_primary_selection(self.as_ptr(),
+                                               &mut (*source).source,
+                                               self.next_serial());
+        }
+    }
+
+    /// Clear the primary selection, so there is nothing to paste.
+    pub fn clear_primary_selection(&mut self) {
+        unsafe {
+            wlr_seat_set_gtk_primary_selection(self.as_ptr(),
+                                               ::std::ptr::null_mut(),
+                                               self.next_serial())
+        }
+    }
+
+    unsafe fn next_serial(&self) -> u32 {
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_display_next_serial,
+                      (*self.as_ptr()).display as _)
+    }
+}
+
+unsafe extern "C" fn text_source_send(source: *mut wlr_gtk_primary_selection_source,
+                                      _mime_type: *const libc::c_char,
+                                      fd: libc::c_int) {
+    // Every mime type it's offered as is plain text.
+    let text = (*(source as *mut TextSource)).text.as_bytes();
+    let mut written = 0;
+    while written < text.len() {
+        let result = libc::write(fd,
+                                 text[written..].as_ptr() as *const libc::c_void,
+                                 text.len() - written);
+        if result < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue
+            }
+            wlr_log!(WLR_ERROR, "Could not write the primary selection");
+            break
+        }
+        written += result as usize;
+    }
+    libc::close(fd);
+}
+
+/// Called by wlroots once the source was replaced by another selection.
+unsafe extern "C" fn text_source_cancel(source: *mut wlr_gtk_primary_selection_source) {
+    wlr_gtk_primary_selection_source_finish(source);
+    Box::from_raw(source as *mut TextSource);
+}
@@ -3,11 +3,11 @@
 //!
 //! TODO This module could really use some examples, as the API surface is huge.
 
-use std::{fmt, panic, ptr, cell::Cell, rc::{Rc, Weak}, time::Duration};
+use std::{fmt, panic, ptr, cell::{Cell, RefCell}, rc::{Rc, Weak}, time::Duration};
 
 use libc;
-use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
-use wlroots_sys::{wlr_axis_orientation, wlr_seat, wlr_seat_create, wlr_seat_destroy,
+use wayland_sys::server::{signal::wl_signal_add, wl_event_source, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_client, wlr_axis_orientation, wlr_seat, wlr_seat_create, wlr_seat_destroy,
                   wlr_seat_get_keyboard, wlr_seat_keyboard_clear_focus,
                   wlr_seat_keyboard_end_grab, wlr_seat_keyboard_enter, wlr_seat_keyboard_has_grab,
                   wlr_seat_keyboard_notify_enter, wlr_seat_keyboard_notify_key,
@@ -26,25 +26,43 @@ use wlroots_sys::{wlr_axis_orientation, wlr_seat, wlr_seat_create, wlr_seat_dest
                   wlr_seat_touch_num_points, wlr_seat_touch_point_clear_focus,
                   wlr_seat_touch_point_focus, wlr_seat_touch_send_down,
                   wlr_seat_touch_send_motion, wlr_seat_touch_send_up, wlr_seat_touch_start_grab,
-                  wlr_axis_source, wlr_drag_icon};
+                  wlr_axis_source, wlr_drag_icon, wlr_surface};
 pub use wlroots_sys::wayland_server::protocol::wl_seat::Capability;
 use xkbcommon::xkb::Keycode;
 
-use {compositor::{self, Compositor},
+use {bus,
+     compositor::{self, Compositor},
      input::{self, keyboard},
      surface::{self, Surface},
-     seat::{self, grab, touch_point::{TouchId, TouchPoint}, drag_icon::{self, DragIcon}},
-     utils::{ToMs, HandleErr, HandleResult, c_to_rust_string, safe_as_cstring}};
+     seat::{self, axis, grab, touch_point::{TouchId, TouchPoint}, drag_icon::{self, DragIcon}},
+     utils::{ToMs, HandleErr, HandleResult, Id, c_to_rust_string, safe_as_cstring}};
 pub use events::seat_events as event;
 
-struct SeatState {
+pub(super) struct SeatState {
     /// A counter that will always have a strong count of 1.
     ///
     /// Once the seat is destroyed, this will signal to the `seat::Handle`s that
     /// they cannot be upgraded.
     counter: Rc<Cell<bool>>,
     /// A raw pointer to the Seat on the heap.
-    seat: *mut Seat
+    seat: *mut Seat,
+    id: Id,
+    /// Whether touch is translated to pointer events for clients without wl_touch.
+    pub(super) touch_emulation: bool,
+    /// The touch point currently being translated into pointer events.
+    pub(super) emulated_touch: Option<TouchId>,
+    /// Tablet tools whose motion is translated into pointer events.
+    pub(super) emulated_tools: Vec<Box<input::DeviceWatcher>>,
+    /// Where the top left of the surface with pointer focus is in the
+    /// layout, as of the last `pointer_notify_motion_at`.
+    pub(super) pointer_origin: Option<(f64, f64)>,
+    /// The surface that got the first button press, with its layout
+    /// position, while any button is held.
+    pub(super) implicit_grab: Option<(surface::Handle, (f64, f64))>,
+    /// Axis motion waiting to be sent as one event, per orientation.
+    pub(super) pending_axis: Vec<axis::PendingAxis>,
+    /// The idle source that sends the pending axis motion, if it's queued.
+    pub(super) axis_flush: *mut wl_event_source
 }
 
 #[derive(Debug, Clone)]
@@ -110,6 +128,19 @@ pub trait Handler {
                              compositor_handle: compositor::Handle,
                              seat_handle: Handle) {}
 
+    /// There was input activity on this seat.
+    ///
+    /// This is triggered by the `*_notify_*` input methods on `Seat`, so idle
+    /// tracking and "wake on input" behaviour can be hooked in one place.
+    /// `source` is the device class that caused the activity.
+    ///
+    /// The seat is borrowed while this runs, so `seat_handle` can't be
+    /// upgraded until the callback returns.
+    fn activity(&mut self,
+                compositor_handle: compositor::Handle,
+                seat_handle: Handle,
+                source: Capability) {}
+
     /// The seat is being destroyed.
     fn destroy(&mut self,
                compositor_handle: compositor::Handle,
@@ -124,11 +155,12 @@ pub trait Handler {
     }
 }
 
-wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
+wayland_listener!(pub Seat, (*mut wlr_seat, RefCell<Box<Handler>>), [
     pointer_grab_begin_listener => pointer_grab_begin_notify: |this: &mut Seat,
                                                                event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -146,7 +178,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     pointer_grab_end_listener => pointer_grab_end_notify: |this: &mut Seat,
     event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -163,7 +196,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     keyboard_grab_begin_listener => keyboard_grab_begin_notify: |this: &mut Seat,
     event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -180,7 +214,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     keyboard_grab_end_listener => keyboard_grab_end_notify: |this: &mut Seat,
     event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -197,7 +232,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     touch_grab_begin_listener => touch_grab_begin_notify: |this: &mut Seat,
     event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -214,7 +250,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     touch_grab_end_listener => touch_grab_end_notify: |this: &mut Seat,
     event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -231,7 +268,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     request_set_cursor_listener => request_set_cursor_notify: |this: &mut Seat,
     event_ptr: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -248,7 +286,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     };
     set_selection_listener => selection_notify: |this: &mut Seat, _event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -262,7 +301,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     set_primary_selection_listener => primary_selection_notify: |this: &mut Seat,
     _event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -275,7 +315,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     };
     new_drag_icon_listener => new_drag_icon_notify: |this: &mut Seat, data: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let data = data as *mut wlr_drag_icon;
         let compositor = match compositor::handle() {
             Some(handle) => handle,
@@ -307,7 +348,8 @@ wayland_listener!(pub Seat, (*mut wlr_seat, Box<Handler>), [
     };
     destroy_listener => destroy_notify: |this: &mut Seat, _event: *mut libc::c_void,|
     unsafe {
-        let (seat_ptr, ref mut handler) = this.data;
+        let seat_ptr = this.data.0;
+        let mut handler = this.data.1.borrow_mut();
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -334,7 +376,7 @@ impl Seat {
             if seat.is_null() {
                 panic!("Could not allocate a wlr_seat");
             }
-            let mut res = Seat::new((seat, handler));
+            let mut res = Seat::new((seat, RefCell::new(handler)));
             wl_signal_add(&mut (*seat).events.pointer_grab_begin as *mut _ as _,
                           res.pointer_grab_begin_listener() as *mut _ as _);
             wl_signal_add(&mut (*seat).events.pointer_grab_end as *mut _ as _,
@@ -360,9 +402,19 @@ impl Seat {
             let counter = Rc::new(Cell::new(false));
             let handle = Rc::downgrade(&counter);
             let state = Box::new(SeatState { counter,
-                                             seat: Box::into_raw(res) });
+                                             seat: Box::into_raw(res),
+                                             id: Id::next(),
+                                             touch_emulation: false,
+                                             emulated_touch: None,
+                                             emulated_tools: Vec::new(),
+                                             pointer_origin: None,
+                                             implicit_grab: None,
+                                             pending_axis: Vec::new(),
+                                             axis_flush: ptr::null_mut() });
             (*seat).data = Box::into_raw(state) as *mut libc::c_void;
-            Handle { seat: seat, handle }
+            let handle = Handle { seat: seat, handle };
+            compositor.tracked.add_seat(handle.clone());
+            handle
         }
     }
 
@@ -384,6 +436,14 @@ impl Seat {
         }
     }
 
+    /// Get the stable identifier of this seat.
+    ///
+    /// Unlike the name this is never reused, even when a seat with the same
+    /// name is created again.
+    pub fn id(&self) -> Id {
+        unsafe { self.state().id }
+    }
+
     /// Get the name of the seat.
     pub fn name(&self) -> Option<String> {
         unsafe {
@@ -509,6 +569,7 @@ impl Seat {
     ///
     /// Pass surface-local coordinates where the pointer motion occurred.
     pub fn pointer_notify_motion(&self, time: Duration, sx: f64, sy: f64) {
+        self.notify_activity(Capability::Pointer);
         unsafe { wlr_seat_pointer_notify_motion(self.data.0, time.to_ms(), sx, sy) }
     }
 
@@ -517,8 +578,16 @@ impl Seat {
     /// Notify the seat that a button has been pressed.
     ///
     /// Returns the serial of the button press or zero if no button press was sent.
+    ///
+    /// The first press starts an implicit grab for `pointer_notify_motion_at`
+    /// on the surface with pointer focus, the last release ends it.
     pub fn pointer_notify_button(&self, time: Duration, button: u32, state: u32) -> u32 {
-        unsafe { wlr_seat_pointer_notify_button(self.data.0, time.to_ms(), button, state) }
+        self.notify_activity(Capability::Pointer);
+        let serial = unsafe {
+            wlr_seat_pointer_notify_button(self.data.0, time.to_ms(), button, state)
+        };
+        self.update_implicit_grab(state);
+        serial
     }
 
     /// Notify the seat of an axis event.
@@ -528,6 +597,7 @@ impl Seat {
                                value: f64,
                                value_discrete: i32,
                                source: wlr_axis_source) {
+        self.notify_activity(Capability::Pointer);
         unsafe { wlr_seat_pointer_notify_axis(self.data.0, time.to_ms(), orientation, value, value_discrete, source) }
     }
 
@@ -633,6 +703,7 @@ impl Seat {
     ///
     /// Defers to any keyboard grabs.
     pub fn keyboard_notify_modifiers(&self, modifiers: &mut keyboard::Modifiers) {
+        self.notify_activity(Capability::Keyboard);
         let mut mods = (*modifiers).into();
         unsafe { wlr_seat_keyboard_notify_modifiers(self.data.0, &mut mods) }
     }
@@ -643,6 +714,7 @@ impl Seat {
     ///
     /// Defers to any keyboard grabs.
     pub fn keyboard_notify_key(&self, time: Duration, key: u32, state: u32) {
+        self.notify_activity(Capability::Keyboard);
         unsafe { wlr_seat_keyboard_notify_key(self.data.0, time.to_ms(), key, state) }
     }
 
@@ -770,6 +842,7 @@ impl Seat {
                              sx: f64,
                              sy: f64)
                              -> u32 {
+        self.notify_activity(Capability::Touch);
         unsafe {
             wlr_seat_touch_notify_down(self.data.0,
                                        surface.as_ptr(),
@@ -783,6 +856,7 @@ impl Seat {
     /// Notify the seat that the touch point given by `touch_id` is up. Defers to any
     /// grab of the touch device.
     pub fn touch_notify_up(&self, time: Duration, touch_id: TouchId) {
+        self.notify_activity(Capability::Touch);
         unsafe { wlr_seat_touch_notify_up(self.data.0, time.to_ms(), touch_id.into()) }
     }
 
@@ -793,9 +867,72 @@ impl Seat {
     /// The seat should be notified of touch motion even if the surface is
     /// not the owner of the touch point for processing by grabs.
     pub fn touch_notify_motion(&self, time: Duration, touch_id: TouchId, sx: f64, sy: f64) {
+        self.notify_activity(Capability::Touch);
         unsafe { wlr_seat_touch_notify_motion(self.data.0, time.to_ms(), touch_id.into(), sx, sy) }
     }
 
+    /// Gets the state stored in the wlr_seat's data pointer.
+    pub(super) unsafe fn state(&self) -> &mut SeatState {
+        &mut *((*self.data.0).data as *mut SeatState)
+    }
+
+    /// Determines if input may be sent to the surface.
+    ///
+    /// This is false when another client has taken exclusive input with the
+    /// input inhibitor protocol, e.g because it locked the screen. Check it
+    /// before giving a surface keyboard or pointer focus.
+    pub fn may_receive_input(&self, surface: &Surface) -> bool {
+        unsafe {
+            let inhibitor = inhibiting_client();
+            inhibitor.is_null() || surface_client(surface.as_ptr()) == inhibitor
+        }
+    }
+
+    /// Take the keyboard and pointer focus away from surfaces that may not
+    /// receive input while a client has exclusive input.
+    ///
+    /// This is done for every seat when a client takes exclusive input.
+    pub fn enforce_input_inhibitor(&mut self) {
+        unsafe {
+            let inhibitor = inhibiting_client();
+            if inhibitor.is_null() {
+                return
+            }
+            let keyboard_focus = (*self.data.0).keyboard_state.focused_surface;
+            if !keyboard_focus.is_null() && surface_client(keyboard_focus) != inhibitor {
+                wlr_seat_keyboard_clear_focus(self.data.0)
+            }
+            let pointer_focus = (*self.data.0).pointer_state.focused_surface;
+            if !pointer_focus.is_null() && surface_client(pointer_focus) != inhibitor {
+                wlr_seat_pointer_clear_focus(self.data.0)
+            }
+        }
+    }
+
+    /// Tell the handler there was input activity on this seat.
+    ///
+    /// Activity caused by input sent from one of the seat handler's own
+    /// callbacks isn't reported to it, as the handler is already borrowed.
+    pub(super) fn notify_activity(&self, source: Capability) {
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        let seat = self.weak_reference();
+        unsafe {
+            if !compositor::COMPOSITOR_PTR.is_null() {
+                if let Some(ref mut idle) = (*compositor::COMPOSITOR_PTR).idle_manager {
+                    idle.notify_activity(self);
+                }
+            }
+        }
+        if let Ok(mut handler) = self.data.1.try_borrow_mut() {
+            handler.activity(compositor, seat, source);
+        }
+        bus::publish(&bus::SeatActivity { seat: self.name(),
+                                          source });
+    }
+
     pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_seat {
         self.data.0
     }
@@ -807,11 +944,29 @@ impl fmt::Debug for Seat {
     }
 }
 
+/// The client that has exclusive input, or null if there is none.
+unsafe fn inhibiting_client() -> *mut wl_client {
+    if compositor::COMPOSITOR_PTR.is_null() {
+        return ptr::null_mut()
+    }
+    match (*compositor::COMPOSITOR_PTR).input_inhibit_manager {
+        Some(ref manager) => manager.active_client(),
+        None => ptr::null_mut()
+    }
+}
+
+unsafe fn surface_client(surface: *mut wlr_surface) -> *mut wl_client {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_resource_get_client,
+                  (*surface).resource as _) as *mut wl_client
+}
+
 impl Drop for Seat {
     fn drop(&mut self) {
         let seat_ptr = self.data.0;
         unsafe {
-            let data = Box::from_raw((*seat_ptr).data as *mut SeatState);
+            let mut data = Box::from_raw((*seat_ptr).data as *mut SeatState);
+            axis::cancel_flush(&mut data);
             let mut manager = Box::from_raw(data.seat);
             assert_eq!(Rc::strong_count(&data.counter),
                        1,
@@ -880,6 +1035,10 @@ impl Handle {
         Handle { seat, handle }
     }
 
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_seat {
+        self.seat
+    }
+
     /// Upgrades the seat handle to a reference to the backing `Seat`.
     ///
     /// # Unsafety
@@ -942,6 +1101,13 @@ impl Handle {
         }
     }
 
+    /// Determines if the handle is alive or not.
+    ///
+    /// This does not check if it's already being borrowed.
+    pub fn is_alive(&self) -> bool {
+        self.handle.upgrade().map(|_| true).unwrap_or(false)
+    }
+
     /// Destroy the seat that this handle refers to.
     ///
     /// This will invalidate the other handles.
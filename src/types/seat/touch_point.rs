@@ -17,6 +17,13 @@ impl Into<i32> for TouchId {
     }
 }
 
+impl TouchId {
+    /// Wrap the touch id of a touch event.
+    pub(crate) fn from_raw(touch_id: i32) -> Self {
+        TouchId(touch_id)
+    }
+}
+
 impl TouchPoint {
     /// Get the touch id associated for this point.
     pub fn touch_id(&self) -> TouchId {
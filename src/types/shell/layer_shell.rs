@@ -0,0 +1,305 @@
+//! Surfaces of the wlr-layer-shell protocol.
+//!
+//! Layer surfaces are used for desktop elements that are anchored to the
+//! edges of an output, such as panels, bars, notifications, wallpapers and
+//! lock screens. Each surface is put in one of the four layers, which are
+//! drawn below and above the regular views.
+
+use std::{cell::Cell, rc::{Rc, Weak}, ptr};
+
+use libc::c_void;
+use wlroots_sys::{wlr_layer_surface, wlr_layer_surface_close, wlr_layer_surface_configure,
+                  wlr_layer_surface_for_each_surface, wlr_layer_surface_state,
+                  wlr_layer_surface_surface_at, wlr_surface, zwlr_layer_surface_v1_anchor};
+pub use wlroots_sys::zwlr_layer_shell_v1_layer as Layer;
+
+use {output,
+     surface,
+     utils::{self, HandleErr, HandleResult, Handleable, Id, c_to_rust_string}};
+pub use manager::layer_shell_handler::*;
+pub(crate) use manager::layer_shell_manager::Manager;
+
+pub mod manager {
+    //! Layer shell resources are managed by the layer shell resource manager.
+    //!
+    //! To manage layer surfaces from clients implement a function with
+    //! [`NewSurface`](./type.NewSurface.html) as the signature.
+    //!
+    //! Pass that function to the [`layer_shell::Builder`](./struct.Builder.html)
+    //! which is then passed to the `compositor::Builder`.
+    pub use manager::layer_shell_manager::*;
+}
+
+pub type Handle = utils::Handle<(), wlr_layer_surface, Surface>;
+
+bitflags! {
+    /// The edges of the output a layer surface is anchored to.
+    pub struct Anchor: u32 {
+        const ZWLR_LAYER_SURFACE_V1_ANCHOR_TOP =
+            zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_TOP as u32;
+        const ZWLR_LAYER_SURFACE_V1_ANCHOR_BOTTOM =
+            zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_BOTTOM as u32;
+        const ZWLR_LAYER_SURFACE_V1_ANCHOR_LEFT =
+            zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_LEFT as u32;
+        const ZWLR_LAYER_SURFACE_V1_ANCHOR_RIGHT =
+            zwlr_layer_surface_v1_anchor::ZWLR_LAYER_SURFACE_V1_ANCHOR_RIGHT as u32;
+    }
+}
+
+/// The distance a layer surface keeps from the edges it is anchored to.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Margin {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32
+}
+
+/// Used internally to reclaim a handle from just a *mut wlr_layer_surface.
+pub(crate) struct SurfaceState {
+    /// Pointer to the backing storage.
+    pub(crate) shell: *mut LayerShell,
+    handle: Weak<Cell<bool>>,
+    id: Id
+}
+
+#[derive(Debug)]
+pub struct Surface {
+    liveliness: Rc<Cell<bool>>,
+    layer_surface: *mut wlr_layer_surface
+}
+
+impl Surface {
+    pub(crate) unsafe fn new(layer_surface: *mut wlr_layer_surface) -> Self {
+        let liveliness = Rc::new(Cell::new(false));
+        let state = Box::new(SurfaceState { shell: ptr::null_mut(),
+                                            handle: Rc::downgrade(&liveliness),
+                                            id: Id::next() });
+        (*layer_surface).data = Box::into_raw(state) as *mut _;
+        Surface { liveliness,
+                  layer_surface }
+    }
+
+    /// Gets the surface used by this layer surface.
+    pub fn surface(&mut self) -> surface::Handle {
+        unsafe {
+            let surface = (*self.layer_surface).surface;
+            if surface.is_null() {
+                panic!("layer surface had a null surface!")
+            }
+            surface::Handle::from_ptr(surface)
+        }
+    }
+
+    /// Get the stable identifier of this layer surface.
+    pub fn id(&self) -> Id {
+        unsafe { (*((*self.layer_surface).data as *mut SurfaceState)).id }
+    }
+
+    /// Get the output this layer surface is on.
+    ///
+    /// This is `None` if the client left it up to the compositor, until
+    /// `set_output` is called.
+    pub fn output(&self) -> Option<output::Handle> {
+        unsafe {
+            let output = (*self.layer_surface).output;
+            if output.is_null() || (*output).data.is_null() {
+                None
+            } else {
+                Some(output::Handle::from_ptr(output))
+            }
+        }
+    }
+
+    /// Put the layer surface on an output.
+    ///
+    /// This must be done in the `NewSurface` callback when the client did
+    /// not choose an output itself, otherwise the surface can't be
+    /// configured.
+    pub fn set_output(&mut self, output: &output::Handle) {
+        unsafe { (*self.layer_surface).output = output.as_ptr() }
+    }
+
+    /// Get the namespace the client gave this layer surface, e.g "panel" or
+    /// "wallpaper".
+    pub fn namespace(&self) -> Option<String> {
+        unsafe { c_to_rust_string((*self.layer_surface).namespace) }
+    }
+
+    /// Get the layer this surface is drawn in.
+    pub fn layer(&self) -> Layer {
+        unsafe { (*self.layer_surface).layer }
+    }
+
+    /// Determines if this layer surface has been configured or not.
+    pub fn configured(&self) -> bool {
+        unsafe { (*self.layer_surface).configured }
+    }
+
+    /// Determines if this layer surface is mapped.
+    pub fn mapped(&self) -> bool {
+        unsafe { (*self.layer_surface).mapped }
+    }
+
+    /// Determines if this layer surface was closed by the compositor.
+    pub fn closed(&self) -> bool {
+        unsafe { (*self.layer_surface).closed }
+    }
+
+    pub fn configure_serial(&self) -> u32 {
+        unsafe { (*self.layer_surface).configure_serial }
+    }
+
+    /// Get the pending client state.
+    pub fn client_pending_state(&self) -> wlr_layer_surface_state {
+        unsafe { (*self.layer_surface).client_pending }
+    }
+
+    /// Get the pending server state.
+    pub fn server_pending_state(&self) -> wlr_layer_surface_state {
+        unsafe { (*self.layer_surface).server_pending }
+    }
+
+    /// Get the current state.
+    pub fn current_state(&self) -> wlr_layer_surface_state {
+        unsafe { (*self.layer_surface).current }
+    }
+
+    /// Get the edges of the output the surface is anchored to.
+    pub fn anchor(&self) -> Anchor {
+        unsafe { Anchor::from_bits_truncate((*self.layer_surface).current.anchor) }
+    }
+
+    /// Get the size of the area the surface reserves along the edge it is
+    /// anchored to, that views should not overlap.
+    ///
+    /// Zero means the surface doesn't reserve any space but is moved out of
+    /// the way of other surfaces, and -1 means it doesn't care about them.
+    pub fn exclusive_zone(&self) -> i32 {
+        unsafe { (*self.layer_surface).current.exclusive_zone }
+    }
+
+    /// Get the distance the surface keeps from the edges it is anchored to.
+    pub fn margin(&self) -> Margin {
+        unsafe {
+            let margin = (*self.layer_surface).current.margin;
+            Margin { top: margin.top,
+                     right: margin.right,
+                     bottom: margin.bottom,
+                     left: margin.left }
+        }
+    }
+
+    /// Determines if the surface wants keyboard focus.
+    pub fn keyboard_interactive(&self) -> bool {
+        unsafe { (*self.layer_surface).current.keyboard_interactive }
+    }
+
+    /// Get the size the client asked for.
+    ///
+    /// A dimension is zero if the client wants the surface to be stretched
+    /// between the edges it is anchored to in that direction.
+    pub fn desired_size(&self) -> (u32, u32) {
+        unsafe {
+            let state = (*self.layer_surface).current;
+            (state.desired_width, state.desired_height)
+        }
+    }
+
+    /// Tell the client the size the surface should be.
+    pub fn configure(&mut self, width: u32, height: u32) {
+        unsafe { wlr_layer_surface_configure(self.layer_surface, width, height) }
+    }
+
+    /// Tell the client the surface was closed, e.g because its output went
+    /// away. The client should destroy it.
+    pub fn close(&mut self) {
+        unsafe { wlr_layer_surface_close(self.layer_surface) }
+    }
+
+    /// Find a surface within this surface at the surface-local coordinates.
+    ///
+    /// Returns the surface and coordinates in the topmost surface coordinate
+    /// system or None if no surface is found at that location.
+    pub fn surface_at(&mut self,
+                      sx: f64,
+                      sy: f64,
+                      sub_sx: &mut f64,
+                      sub_sy: &mut f64)
+                      -> Option<surface::Handle> {
+        unsafe {
+            let sub_surface =
+                wlr_layer_surface_surface_at(self.layer_surface, sx, sy, sub_sx, sub_sy);
+            if sub_surface.is_null() {
+                None
+            } else {
+                Some(surface::Handle::from_ptr(sub_surface))
+            }
+        }
+    }
+
+    pub fn for_each_surface<F>(&self, mut iterator: F)
+            where F: FnMut(surface::Handle, i32, i32) {
+        let mut iterator_ref: &mut FnMut(surface::Handle, i32, i32) = &mut iterator;
+        unsafe {
+            unsafe extern "C" fn c_iterator(wlr_surface: *mut wlr_surface, sx: i32, sy: i32, data: *mut c_void) {
+                let iterator_fn = &mut *(data as *mut &mut FnMut(surface::Handle, i32, i32));
+                let surface = surface::Handle::from_ptr(wlr_surface);
+                iterator_fn(surface, sx, sy);
+            }
+            let iterator_ptr: *mut c_void = &mut iterator_ref as *mut _ as *mut c_void;
+            wlr_layer_surface_for_each_surface(self.layer_surface, Some(c_iterator), iterator_ptr);
+        }
+    }
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        if Rc::strong_count(&self.liveliness) == 1 {
+            wlr_log!(WLR_DEBUG, "Dropped layer surface {:p}", self.layer_surface);
+            let weak_count = Rc::weak_count(&self.liveliness);
+            if weak_count > 0 {
+                wlr_log!(WLR_DEBUG,
+                         "Still {} weak pointers to layer surface {:p}",
+                         weak_count,
+                         self.layer_surface);
+            }
+        } else {
+            return
+        }
+        unsafe {
+            let _ = Box::from_raw((*self.layer_surface).data as *mut SurfaceState);
+        }
+    }
+}
+
+impl Handleable<(), wlr_layer_surface> for Surface {
+    #[doc(hidden)]
+    unsafe fn from_ptr(layer_surface: *mut wlr_layer_surface) -> Self {
+        let data = &mut *((*layer_surface).data as *mut SurfaceState);
+        let liveliness = data.handle.upgrade().unwrap();
+        Surface { liveliness,
+                  layer_surface }
+    }
+
+    #[doc(hidden)]
+    unsafe fn as_ptr(&self) -> *mut wlr_layer_surface {
+        self.layer_surface
+    }
+
+    #[doc(hidden)]
+    unsafe fn from_handle(handle: &Handle) -> HandleResult<Self> {
+        let liveliness = handle.handle
+            .upgrade()
+            .ok_or_else(|| HandleErr::AlreadyDropped)?;
+        Ok(Surface { liveliness,
+                     layer_surface: handle.ptr })
+    }
+
+    fn weak_reference(&self) -> Handle {
+        Handle { ptr: self.layer_surface,
+                 handle: Rc::downgrade(&self.liveliness),
+                 data: (),
+                 _marker: std::marker::PhantomData }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod xdg_shell_v6;
 pub mod xdg_shell;
+pub mod layer_shell;
@@ -1,8 +1,17 @@
-//! TODO Documentation
+//! Surfaces of the stable XDG shell protocol.
+//!
+//! This is the `xdg_wm_base` global most clients use today. The older
+//! `zxdg_shell_v6` lives in `xdg_shell_v6` and works the same way, so a
+//! compositor can offer both by passing a manager builder for each to the
+//! `compositor::Builder` (see `xdg_shell_manager`).
+//!
+//! Shell surfaces are either a `TopLevel` (a regular window) or a `Popup`
+//! (menus, tooltips), which the `Surface::state` tells apart.
 
 use std::{cell::Cell, rc::{Rc, Weak}, panic, ptr};
 
-use libc::c_void;
+use libc::{self, c_void};
+use wayland_sys::server::WAYLAND_SERVER_HANDLE;
 use wlroots_sys::{wlr_xdg_popup, wlr_xdg_surface, wlr_xdg_surface_ping,
                   wlr_xdg_surface_role, wlr_xdg_toplevel_send_close,
                   wlr_xdg_popup_destroy, wlr_xdg_surface_surface_at,
@@ -12,14 +21,21 @@ use wlroots_sys::{wlr_xdg_popup, wlr_xdg_surface, wlr_xdg_surface_ping,
                   wlr_xdg_toplevel_state, wlr_xdg_surface_for_each_surface, wlr_surface};
 
 
-use {area::Area,
+use {area::{Area, Size},
      seat,
+     snapshot::SurfaceInfo,
      surface,
-     utils::{self, HandleErr, HandleResult, Handleable, c_to_rust_string}};
+     utils::{self, HandleErr, HandleResult, Handleable, Id, c_to_rust_string}};
 pub use manager::xdg_shell_handler::*;
 pub(crate) use manager::xdg_shell_manager::Manager;
 pub use events::xdg_shell_events as event;
 
+/// Checks whether the client answered its last ping.
+pub(crate) unsafe fn pong_received(shell_surface: *mut wlr_xdg_surface) -> bool {
+    let client = (*shell_surface).client;
+    !client.is_null() && (*client).ping_serial == 0
+}
+
 pub mod manager {
     //! XDG shell resources are managed by the XDG shell resource manager.
     //!
@@ -43,6 +59,9 @@ pub(crate) struct SurfaceState {
     /// Pointer to the backing storage.
     pub(crate) shell: *mut XdgShell,
     handle: Weak<Cell<bool>>,
+    id: Id,
+    /// Cleared when a ping times out, set again once the client answers.
+    pub(crate) responsive: bool,
     shell_state: Option<ShellState>
 }
 
@@ -96,6 +115,8 @@ impl Surface {
         let shell_state =
             Box::new(SurfaceState { shell: ptr::null_mut(),
                                     handle: Rc::downgrade(&liveliness),
+                                    id: Id::next(),
+                                    responsive: true,
                                     shell_state: match state {
                                         None => None,
                                         Some(ref state) => Some(state.clone())
@@ -117,6 +138,14 @@ impl Surface {
         }
     }
 
+    /// Get the stable identifier of this XDG surface.
+    ///
+    /// The identifier is never reused, so it can be used to refer to
+    /// this view from outside of the compositor.
+    pub fn id(&self) -> Id {
+        unsafe { (*((*self.shell_surface).data as *mut SurfaceState)).id }
+    }
+
     /// Get the role of this XDG surface.
     pub fn role(&self) -> wlr_xdg_surface_role {
         unsafe { (*self.shell_surface).role }
@@ -165,6 +194,38 @@ impl Surface {
         }
     }
 
+    /// Determines if the client answered the last ping in time.
+    ///
+    /// Pings are sent periodically if `compositor::Builder::ping_interval` is
+    /// set, otherwise this only changes when `ping` is called.
+    pub fn is_responsive(&self) -> bool {
+        unsafe { (*((*self.shell_surface).data as *mut SurfaceState)).responsive }
+    }
+
+    /// Get the process id of the client.
+    pub fn client_pid(&self) -> Option<libc::pid_t> {
+        unsafe {
+            let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_resource_get_client,
+                                       (*self.shell_surface).resource as _);
+            utils::client_pid(client as _)
+        }
+    }
+
+    /// Kill the process of the client with `SIGKILL`, e.g after the user
+    /// confirmed they want to force quit an unresponsive client.
+    ///
+    /// Returns false if the process could not be found or signalled.
+    pub fn kill_client(&self) -> bool {
+        match self.client_pid() {
+            Some(pid) if pid != unsafe { libc::getpid() } => {
+                wlr_log!(WLR_INFO, "Killing client with pid {}", pid);
+                unsafe { libc::kill(pid, libc::SIGKILL) == 0 }
+            }
+            _ => false
+        }
+    }
+
     /// Find a surface within this surface at the surface-local coordinates.
     ///
     /// Returns the popup and coordinates in the topmost surface coordinate system
@@ -199,6 +260,31 @@ impl Surface {
             wlr_xdg_surface_for_each_surface(self.shell_surface, Some(c_iterator), iterator_ptr);
         }
     }
+
+    /// Describe the surface for a state dump, see `Compositor::dump_state`.
+    pub(crate) fn info(&self) -> SurfaceInfo {
+        let (role, title, app_id) = match self.state {
+            Some(ShellState::TopLevel(ref toplevel)) => {
+                ("toplevel", Some(toplevel.title()), Some(toplevel.app_id()))
+            }
+            Some(ShellState::Popup(_)) => ("popup", None, None),
+            None => ("none", None, None)
+        };
+        SurfaceInfo { id: self.id(),
+                      shell: "xdg_shell",
+                      role,
+                      title,
+                      app_id,
+                      geometry: self.geometry() }
+    }
+}
+
+
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for Surface {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ::serde::Serialize::serialize(&self.info(), serializer)
+    }
 }
 
 impl Drop for Surface {
@@ -310,6 +396,22 @@ impl TopLevel {
         unsafe { (*self.toplevel).current }
     }
 
+    /// Get the smallest size the client accepts, with zero for dimensions
+    /// it doesn't limit.
+    pub fn min_size(&self) -> Size {
+        let state = self.current_state();
+        Size::new(state.min_width as i32, state.min_height as i32)
+    }
+
+    /// Get the largest size the client accepts, with zero for dimensions
+    /// it doesn't limit.
+    ///
+    /// Use `wm::size_limits::SizeLimits` to keep configures within them.
+    pub fn max_size(&self) -> Size {
+        let state = self.current_state();
+        Size::new(state.max_width as i32, state.max_height as i32)
+    }
+
     /// Request that this toplevel surface be the given size.
     ///
     /// Returns the associated configure serial.
@@ -1,9 +1,16 @@
-//! TODO Documentation
+//! Subsurfaces are surfaces placed relative to a parent surface, e.g the
+//! video of a media player with its controls drawn on top of it.
+//!
+//! A surface announces its new subsurfaces through
+//! `surface::Handler::new_subsurface`, and the `Handler` returned from there
+//! is told when the subsurface is moved, restacked among its siblings, or
+//! destroyed. Moves and restacks only happen when the parent commits, so
+//! that's when the handler hears about them.
 
 use std::{cell::Cell, rc::Rc};
 
 use libc;
-use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
 use wlroots_sys::wlr_subsurface;
 
 use {compositor,
@@ -18,13 +25,68 @@ pub trait Handler {
                   compositor_handle: compositor::Handle,
                   subsurface_handle: Handle,
                   surface_handle: surface::Handle) {}
+
+    /// The subsurface was moved to a new position relative to its parent.
+    fn on_move(&mut self,
+               compositor_handle: compositor::Handle,
+               subsurface_handle: Handle,
+               x: i32,
+               y: i32) {}
+
+    /// The subsurface moved up or down the stack of subsurfaces of its
+    /// parent, because it was restacked or a sibling was added or removed.
+    ///
+    /// `index` is its new index in `surface::Surface::subsurfaces` of the
+    /// parent.
+    fn on_restack(&mut self,
+                  compositor_handle: compositor::Handle,
+                  subsurface_handle: Handle,
+                  index: usize) {}
 }
 
-wayland_listener!(pub(crate) InternalSubsurface, (Subsurface, Box<Handler>), [
+/// Where the subsurface was the last time the parent committed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Placement {
+    position: (i32, i32),
+    index: Option<usize>
+}
+
+impl Placement {
+    pub(crate) unsafe fn of(subsurface: *mut wlr_subsurface) -> Self {
+        Placement { position: ((*subsurface).current.x, (*subsurface).current.y),
+                    index: stacking_index(subsurface) }
+    }
+}
+
+wayland_listener!(pub(crate) InternalSubsurface, (Subsurface, Box<Handler>, Placement), [
+    parent_commit_listener => parent_commit_notify: |this: &mut InternalSubsurface,
+                                                     _data: *mut libc::c_void,|
+    unsafe {
+        let (ref mut subsurface, ref mut manager, ref mut placement) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        let previous = *placement;
+        *placement = Placement::of(subsurface.as_ptr());
+        if placement.position != previous.position {
+            let (x, y) = placement.position;
+            manager.on_move(compositor.clone(), subsurface.weak_reference(), x, y);
+        }
+        if let (Some(index), true) = (placement.index, placement.index != previous.index) {
+            manager.on_restack(compositor, subsurface.weak_reference(), index);
+        }
+    };
+    parent_destroy_listener => parent_destroy_notify: |this: &mut InternalSubsurface,
+                                                       _data: *mut libc::c_void,|
+    unsafe {
+        // The signals go away with the parent.
+        this.unlink_parent();
+    };
     on_destroy_listener => on_destroy_notify: |this: &mut InternalSubsurface,
                                                data: *mut libc::c_void,|
     unsafe {
-        let (ref mut subsurface, ref mut manager) = this.data;
+        let (ref mut subsurface, ref mut manager, _) = this.data;
         let subsurface_ptr = data as *mut wlr_subsurface;
         let surface = surface::Handle::from_ptr((*subsurface_ptr).surface);
         let compositor = match compositor::handle() {
@@ -73,18 +135,35 @@ impl Subsurface {
     pub fn cached_state<'surface>(&'surface self) -> Option<surface::State<'surface>> {
         unsafe {
             if (*self.subsurface).has_cache {
-                None
-            } else {
                 Some(surface::State::new((*self.subsurface).cached))
+            } else {
+                None
             }
         }
     }
 
+    /// Get the position of the sub surface relative to its parent.
+    ///
+    /// A new position only takes effect when the parent commits.
+    pub fn position(&self) -> (i32, i32) {
+        unsafe { ((*self.subsurface).current.x, (*self.subsurface).current.y) }
+    }
+
+    /// Get the index of the sub surface among the subsurfaces of its parent,
+    /// or `None` if the parent is gone.
+    pub fn stacking_index(&self) -> Option<usize> {
+        unsafe { stacking_index(self.subsurface) }
+    }
+
     /// Determine if the sub surface has a cached state.
     pub fn has_cache(&self) -> bool {
         unsafe { (*self.subsurface).has_cache }
     }
 
+    /// Determines if the sub surface is in synchronized mode, where its
+    /// state is only applied when the parent commits.
+    ///
+    /// In desynchronized mode its commits are applied right away.
     pub fn synchronized(&self) -> bool {
         unsafe { (*self.subsurface).synchronized }
     }
@@ -127,12 +206,56 @@ impl Handleable<(), wlr_subsurface> for Subsurface {
     }
 }
 
+impl InternalSubsurface {
+    /// Listen for the parent committing, to tell the handler about moves and
+    /// restacks.
+    pub(crate) unsafe fn watch_parent(&mut self, subsurface: *mut wlr_subsurface) {
+        let parent = (*subsurface).parent;
+        if parent.is_null() {
+            return
+        }
+        wl_signal_add(&mut (*parent).events.commit as *mut _ as _,
+                      self.parent_commit_listener() as _);
+        wl_signal_add(&mut (*parent).events.destroy as *mut _ as _,
+                      self.parent_destroy_listener() as _);
+    }
+
+    unsafe fn unlink_parent(&mut self) {
+        for listener in &[self.parent_commit_listener(), self.parent_destroy_listener()] {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (**listener).link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_init,
+                          &mut (**listener).link as *mut _ as _);
+        }
+    }
+}
+
 impl Drop for InternalSubsurface {
     fn drop(&mut self) {
         unsafe {
+            self.unlink_parent();
             ffi_dispatch!(WAYLAND_SERVER_HANDLE,
                           wl_list_remove,
                           &mut (*self.on_destroy_listener()).link as *mut _ as _);
         }
     }
 }
+
+/// The index of the subsurface among the subsurfaces of its parent.
+unsafe fn stacking_index(subsurface: *mut wlr_subsurface) -> Option<usize> {
+    let parent = (*subsurface).parent;
+    if parent.is_null() {
+        return None
+    }
+    let mut index = 0;
+    let mut found = None;
+    wl_list_for_each!((*parent).subsurfaces, parent_link, (sibling: wlr_subsurface) => {
+        if sibling == subsurface {
+            found = Some(index);
+        }
+        index += 1;
+    });
+    found
+}
@@ -5,10 +5,17 @@ use std::{panic, ptr, cell::Cell, rc::{Rc, Weak}, time::Duration};
 
 use wayland_sys::server::WAYLAND_SERVER_HANDLE;
 use wayland_sys::server::signal::wl_signal_add;
-use wlroots_sys::{timespec, wlr_subsurface, wlr_surface, wlr_surface_get_root_surface,
-                  wlr_surface_has_buffer, wlr_surface_point_accepts_input, wlr_surface_send_enter,
+use wlroots_sys::{timespec, wl_output_transform, wlr_subsurface, wlr_surface,
+                  wlr_surface_get_root_surface, wlr_surface_has_buffer,
+                  wlr_surface_point_accepts_input, wlr_surface_send_enter,
                   wlr_surface_send_frame_done, wlr_surface_send_leave, wlr_surface_surface_at,
-                  wlr_surface_is_xdg_surface, wlr_surface_get_texture};
+                  wlr_surface_is_xdg_surface, wlr_surface_is_xdg_surface_v6,
+                  wlr_surface_is_layer_surface, wlr_surface_is_xwayland_surface,
+                  wlr_surface_is_subsurface, wlr_surface_get_texture,
+                  wlr_xdg_surface_from_wlr_surface, wlr_xdg_surface_v6_from_wlr_surface,
+                  wlr_layer_surface_from_wlr_surface, wlr_xwayland_surface_from_wlr_surface,
+                  wlr_subsurface_from_wlr_surface,
+                  wlr_xdg_surface_role::*, wlr_xdg_surface_v6_role::*};
 
 use {compositor,
      surface::{self,
@@ -16,7 +23,14 @@ use {compositor,
                subsurface_manager::SubsurfaceManager},
      output::Output,
      render::Texture,
-     utils::{self, Handleable, HandleErr, HandleResult, c_to_rust_string}};
+     shell::{layer_shell, xdg_shell, xdg_shell_v6},
+     utils::{self, Handleable, HandleErr, HandleResult, c_to_rust_string},
+     xwayland};
+
+/// The name wlroots gives the role of cursor surfaces.
+const CURSOR_ROLE: &str = "wl_pointer-cursor";
+/// The name wlroots gives the role of drag and drop icons.
+const DRAG_ICON_ROLE: &str = "wl_data_device-icon";
 
 pub type Handle = utils::Handle<Weak<Box<SubsurfaceManager>>,
                                 wlr_surface,
@@ -41,6 +55,67 @@ pub trait Handler {
 
 impl Handler for () {}
 
+/// What a surface is used for.
+///
+/// A surface gets its role when it's first used for something, and keeps it
+/// until it's destroyed. Toplevels and popups of both the stable and the v6
+/// XDG shell are `XdgToplevel` and `XdgPopup`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Role {
+    XdgToplevel,
+    XdgPopup,
+    /// A layer shell surface, e.g a panel or a wallpaper.
+    Layer,
+    /// A window of an X11 client.
+    Xwayland,
+    /// The image of the cursor, set by the client the pointer is over.
+    Cursor,
+    /// The icon under the cursor during drag and drop.
+    DragIcon,
+    Subsurface,
+    /// The surface has no role yet, or it's an XDG surface that's not a
+    /// toplevel or popup yet.
+    None
+}
+
+/// What to do with a surface that was just created, see
+/// `ManagerHandler::new_surface`.
+pub enum NewSurfaceAction {
+    /// Keep the surface, with a handler for its events.
+    Accept(Box<Handler>),
+    /// Refuse the surface. The client is disconnected with a
+    /// `REJECTED_SURFACE_ERROR` protocol error on the surface.
+    Reject
+}
+
+/// The code of the protocol error posted on a `wl_surface` that was
+/// rejected with `NewSurfaceAction::Reject`.
+///
+/// `wl_surface` doesn't define an error for this, so it's kept clear of the
+/// codes it does define.
+pub const REJECTED_SURFACE_ERROR: u32 = 0x100;
+
+/// Decides what happens to every surface when it's created, see
+/// `compositor::Builder::surface_manager`.
+///
+/// This sees all surfaces, including the ones that never get a shell role
+/// (e.g cursors and drag icons), so it's the place to keep track of them or
+/// to limit how many surfaces a client can have.
+pub trait ManagerHandler {
+    /// A client created a surface. It doesn't have a role yet.
+    ///
+    /// If the surface gets a shell role later on, and the shell manager
+    /// returns a `surface::Handler` of its own, that one replaces the one
+    /// returned here.
+    fn new_surface(&mut self,
+                   compositor_handle: compositor::Handle,
+                   surface_handle: Handle)
+                   -> NewSurfaceAction {
+        let _ = (compositor_handle, surface_handle);
+        NewSurfaceAction::Accept(Box::new(()))
+    }
+}
+
 wayland_listener!(pub(crate) InternalSurface, (Surface, Box<Handler>), [
     on_commit_listener => on_commit_notify: |this: &mut InternalSurface, _data: *mut libc::c_void,|
     unsafe {
@@ -64,9 +139,13 @@ wayland_listener!(pub(crate) InternalSurface, (Surface, Box<Handler>), [
         if let Some(subsurface_handler) = manager.new_subsurface(compositor,
                                                                 surface.weak_reference(),
                                                                 subsurface.weak_reference()) {
-            let mut internal_subsurface = InternalSubsurface::new((subsurface, subsurface_handler));
+            let placement = subsurface::Placement::of(subsurface_ptr);
+            let mut internal_subsurface = InternalSubsurface::new((subsurface,
+                                                                   subsurface_handler,
+                                                                   placement));
             wl_signal_add(&mut (*subsurface_ptr).events.destroy as *mut _ as _,
                           internal_subsurface.on_destroy_listener() as _);
+            internal_subsurface.watch_parent(subsurface_ptr);
             (*subsurface_ptr).data = Box::into_raw(internal_subsurface) as *mut _;
         }
     };
@@ -150,6 +229,22 @@ impl Surface {
                   surface }
     }
 
+    /// Undo `Surface::new` for a surface that was rejected before anything
+    /// else was set up for it.
+    ///
+    /// The `wlr_surface` is left without any state of the bindings, so it
+    /// can be destroyed by wlroots at any point after this.
+    pub(crate) unsafe fn discard(self) {
+        let surface = self.surface;
+        let manager = &*self.subsurfaces_manager as *const Box<SubsurfaceManager>
+            as *mut Box<SubsurfaceManager>;
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*(*manager).subsurface_created_listener()).link as *mut _ as _);
+        drop(self);
+        (*surface).data = ptr::null_mut();
+    }
+
     /// Create the subsurface manager and ensures theat the listeners are
     /// set up correctly to listen for subsurface creation and deletion.
     fn create_manager(surface: *mut wlr_surface) -> Box<SubsurfaceManager> {
@@ -206,9 +301,159 @@ impl Surface {
         }
     }
 
+    /// Get the transform the client already applied to its buffer, e.g
+    /// because it renders rotated for an output that is rotated.
+    ///
+    /// The buffer has to be transformed by the inverse of this to show the
+    /// surface the right way up, see `Renderer::render_surface`.
+    pub fn buffer_transform(&self) -> wl_output_transform {
+        unsafe { (*self.surface).current.transform }
+    }
+
+    /// Get the scale the client rendered its buffer at, i.e how many buffer
+    /// pixels there are to a surface coordinate.
+    ///
+    /// This is independent of the scale of the output the surface is shown
+    /// on, the renderer stretches the buffer to make up the difference.
+    pub fn buffer_scale(&self) -> i32 {
+        unsafe { (*self.surface).current.scale }
+    }
+
     /// Get the lifetime bound role (if one exists) for this surface.
+    #[deprecated(note = "use `role_name` for the name, or `role_kind` for what the surface is \
+                         used for")]
     pub fn role(&self) -> Option<String> {
-        unsafe { c_to_rust_string((*(*self.surface).role).name) }
+        self.role_name()
+    }
+
+    /// Get the role of the surface.
+    ///
+    /// Use `xdg_surface`, `layer_surface` etc. to get to the surface the role
+    /// belongs to.
+    pub fn role_kind(&self) -> Role {
+        unsafe {
+            let surface = self.surface;
+            if (*surface).role.is_null() {
+                return Role::None
+            }
+            if wlr_surface_is_xdg_surface(surface) {
+                match (*wlr_xdg_surface_from_wlr_surface(surface)).role {
+                    WLR_XDG_SURFACE_ROLE_TOPLEVEL => Role::XdgToplevel,
+                    WLR_XDG_SURFACE_ROLE_POPUP => Role::XdgPopup,
+                    WLR_XDG_SURFACE_ROLE_NONE => Role::None
+                }
+            } else if wlr_surface_is_xdg_surface_v6(surface) {
+                match (*wlr_xdg_surface_v6_from_wlr_surface(surface)).role {
+                    WLR_XDG_SURFACE_V6_ROLE_TOPLEVEL => Role::XdgToplevel,
+                    WLR_XDG_SURFACE_V6_ROLE_POPUP => Role::XdgPopup,
+                    WLR_XDG_SURFACE_V6_ROLE_NONE => Role::None
+                }
+            } else if wlr_surface_is_layer_surface(surface) {
+                Role::Layer
+            } else if wlr_surface_is_xwayland_surface(surface) {
+                Role::Xwayland
+            } else if wlr_surface_is_subsurface(surface) {
+                Role::Subsurface
+            } else {
+                match self.role_name().as_ref().map(String::as_str) {
+                    Some(CURSOR_ROLE) => Role::Cursor,
+                    Some(DRAG_ICON_ROLE) => Role::DragIcon,
+                    _ => Role::None
+                }
+            }
+        }
+    }
+
+    /// Get the name wlroots gives the role of this surface, if it has one.
+    pub fn role_name(&self) -> Option<String> {
+        unsafe {
+            let role = (*self.surface).role;
+            if role.is_null() {
+                None
+            } else {
+                c_to_rust_string((*role).name)
+            }
+        }
+    }
+
+    /// Get the stable XDG shell surface this is the surface of, if it's one
+    /// the XDG shell manager knows about.
+    pub fn xdg_surface(&self) -> Option<xdg_shell::Handle> {
+        unsafe {
+            if !wlr_surface_is_xdg_surface(self.surface) {
+                return None
+            }
+            let xdg_surface = wlr_xdg_surface_from_wlr_surface(self.surface);
+            if (*xdg_surface).data.is_null() {
+                None
+            } else {
+                Some(xdg_shell::Handle::from_ptr(xdg_surface))
+            }
+        }
+    }
+
+    /// Get the XDG shell v6 surface this is the surface of, if it's one the
+    /// XDG shell v6 manager knows about.
+    pub fn xdg_v6_surface(&self) -> Option<xdg_shell_v6::Handle> {
+        unsafe {
+            if !wlr_surface_is_xdg_surface_v6(self.surface) {
+                return None
+            }
+            let xdg_surface = wlr_xdg_surface_v6_from_wlr_surface(self.surface);
+            if (*xdg_surface).data.is_null() {
+                None
+            } else {
+                Some(xdg_shell_v6::Handle::from_ptr(xdg_surface))
+            }
+        }
+    }
+
+    /// Get the layer surface this is the surface of, if it's one the layer
+    /// shell manager knows about.
+    pub fn layer_surface(&self) -> Option<layer_shell::Handle> {
+        unsafe {
+            if !wlr_surface_is_layer_surface(self.surface) {
+                return None
+            }
+            let layer_surface = wlr_layer_surface_from_wlr_surface(self.surface);
+            if (*layer_surface).data.is_null() {
+                None
+            } else {
+                Some(layer_shell::Handle::from_ptr(layer_surface))
+            }
+        }
+    }
+
+    /// Get the XWayland surface this is the surface of, if it's one the
+    /// XWayland manager knows about.
+    pub fn xwayland_surface(&self) -> Option<xwayland::surface::Handle> {
+        unsafe {
+            if !wlr_surface_is_xwayland_surface(self.surface) {
+                return None
+            }
+            let xwayland_surface = wlr_xwayland_surface_from_wlr_surface(self.surface);
+            if (*xwayland_surface).data.is_null() {
+                None
+            } else {
+                Some(xwayland::surface::Handle::from_ptr(xwayland_surface))
+            }
+        }
+    }
+
+    /// Get the subsurface this is the surface of, if the parent's handler
+    /// kept track of it.
+    pub fn subsurface(&self) -> Option<subsurface::Handle> {
+        unsafe {
+            if !wlr_surface_is_subsurface(self.surface) {
+                return None
+            }
+            let subsurface = wlr_subsurface_from_wlr_surface(self.surface);
+            if (*subsurface).data.is_null() {
+                None
+            } else {
+                Some(subsurface::Handle::from_ptr(subsurface))
+            }
+        }
     }
 
     /// Whether or not this surface currently has an attached buffer.
@@ -228,6 +473,16 @@ impl Surface {
         unsafe { wlr_surface_point_accepts_input(self.surface, sx, sy) }
     }
 
+    /// Get the process id of the client that created the surface.
+    pub fn client_pid(&self) -> Option<libc::pid_t> {
+        unsafe {
+            let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_resource_get_client,
+                                       (*self.surface).resource as _);
+            utils::client_pid(client as _)
+        }
+    }
+
     /// Determines if this surface is an XDG surface.
     ///
     /// This is really only useful for getting the parent of popups from stable XDG
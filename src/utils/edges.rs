@@ -4,6 +4,9 @@
 
 use wlroots_sys::wlr_edges;
 
+#[cfg(feature = "unstable")]
+use area::Area;
+
 bitflags! {
     /// A bit flag representing which edge was affected by an event.
     pub struct Edges: u32 {
@@ -14,3 +17,57 @@ bitflags! {
         const WLR_EDGE_RIGHT = wlr_edges::WLR_EDGE_RIGHT as u32;
     }
 }
+
+impl Edges {
+    /// Get the edges of `area` that `(x, y)` is within `distance` of, in the
+    /// same coordinates as `area`.
+    ///
+    /// Near a corner both of its edges are returned. Points further away from
+    /// the area than `distance` aren't near any edge.
+    #[cfg(feature = "unstable")]
+    pub fn near(area: Area, x: f64, y: f64, distance: f64) -> Edges {
+        let (left, top) = (area.origin.x as f64, area.origin.y as f64);
+        let right = left + area.size.width as f64;
+        let bottom = top + area.size.height as f64;
+        let mut edges = Edges::empty();
+        if x < left - distance || x >= right + distance || y < top - distance
+           || y >= bottom + distance
+        {
+            return edges
+        }
+        if x < left + distance {
+            edges |= Edges::WLR_EDGE_LEFT;
+        } else if x >= right - distance {
+            edges |= Edges::WLR_EDGE_RIGHT;
+        }
+        if y < top + distance {
+            edges |= Edges::WLR_EDGE_TOP;
+        } else if y >= bottom - distance {
+            edges |= Edges::WLR_EDGE_BOTTOM;
+        }
+        edges
+    }
+
+    /// Get the name of the xcursor image for resizing from these edges, e.g
+    /// `top_left_corner` or `right_side`.
+    ///
+    /// `None` if there are no edges, or edges that can't be resized from
+    /// together.
+    pub fn cursor_name(self) -> Option<&'static str> {
+        let top = self.contains(Edges::WLR_EDGE_TOP);
+        let bottom = self.contains(Edges::WLR_EDGE_BOTTOM);
+        let left = self.contains(Edges::WLR_EDGE_LEFT);
+        let right = self.contains(Edges::WLR_EDGE_RIGHT);
+        match (top, bottom, left, right) {
+            (true, false, true, false) => Some("top_left_corner"),
+            (true, false, false, true) => Some("top_right_corner"),
+            (false, true, true, false) => Some("bottom_left_corner"),
+            (false, true, false, true) => Some("bottom_right_corner"),
+            (true, false, false, false) => Some("top_side"),
+            (false, true, false, false) => Some("bottom_side"),
+            (false, false, true, false) => Some("left_side"),
+            (false, false, false, true) => Some("right_side"),
+            _ => None
+        }
+    }
+}
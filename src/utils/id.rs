@@ -0,0 +1,43 @@
+//! Numeric identifiers that stay stable for the lifetime of the compositor.
+//!
+//! Pointers and names are not good enough to refer to objects from outside
+//! of the process: pointers get reused once an object is freed and names
+//! (e.g output names) are not guaranteed to be unique. An `Id` is handed out
+//! exactly once, so external tools can use it to refer to an object
+//! unambiguously.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+
+static NEXT_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// A unique identifier for an object managed by the compositor.
+///
+/// IDs are never reused, even after the object they refer to is destroyed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Id(u64);
+
+impl Id {
+    /// Allocate a fresh identifier.
+    pub fn next() -> Self {
+        Id(NEXT_ID.fetch_add(1, Ordering::Relaxed) as u64 + 1)
+    }
+
+    /// Reconstruct an identifier from the raw number, e.g one that was
+    /// received back from an external tool.
+    pub fn from_raw(id: u64) -> Self {
+        Id(id)
+    }
+
+    /// Get the raw number backing this identifier.
+    pub fn as_raw(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -5,18 +5,38 @@
 //!
 //! To log using this system please utilize the [`wlr_log!`](../../macro.wlr_log.html) macro.
 
+#[cfg(feature = "sys")]
 use libc::c_char;
+#[cfg(feature = "sys")]
 use vsprintf::vsprintf;
-use wlroots_sys::{wlr_log_importance, __va_list_tag, wlr_log_init, _wlr_log};
+#[cfg(feature = "sys")]
+use wlroots_sys::{wlr_log_importance, __va_list_tag};
+#[cfg(not(feature = "mock"))]
+use wlroots_sys::{wlr_log_init, _wlr_log};
 
+#[cfg(feature = "sys")]
 use utils::c_to_rust_string;
 
+use std::{cell::RefCell, collections::VecDeque};
+#[cfg(not(feature = "mock"))]
 use std::ffi::CString;
 
 // Export these so it can be used in `wlr_log!`.
 pub use self::wlr_log_importance::{WLR_SILENT, WLR_ERROR, WLR_INFO,
                                    WLR_DEBUG};
 
+/// The levels of `wlroots_sys::wlr_log_importance`, for when wlroots isn't
+/// linked.
+#[cfg(not(feature = "sys"))]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum wlr_log_importance {
+    WLR_SILENT = 0,
+    WLR_ERROR = 1,
+    WLR_INFO = 2,
+    WLR_DEBUG = 3
+}
+
 /// How verbose you want the logging. Lower levels prints more.
 pub type LogVerbosity = wlr_log_importance;
 
@@ -28,11 +48,19 @@ pub type LogCallback = fn(verbosity: LogVerbosity, message: String);
 
 static mut RUST_LOGGING_FN: LogCallback = dummy_callback;
 
+/// How many of the most recent log messages are kept for crash reports.
+const RECENT_MESSAGES: usize = 64;
+
+thread_local! {
+    static RECENT: RefCell<VecDeque<String>> = RefCell::new(VecDeque::new());
+}
+
 /// Initialize wlroots logging at a certain level of verbosity with
 /// an optional callback that will be called for every log.
 ///
 /// To log using this system, use the
 /// [`wlr_log!`](../../macro.wlr_log.html) macro.
+#[cfg(not(feature = "mock"))]
 pub fn init_logging<F>(verbosity: LogVerbosity, callback: F)
 where F: Into<Option<LogCallback>>
 {
@@ -47,20 +75,59 @@ where F: Into<Option<LogCallback>>
     }
 }
 
+/// Without wlroots only the callback is set up, `verbosity` is ignored.
+#[cfg(feature = "mock")]
+pub fn init_logging<F>(_verbosity: LogVerbosity, callback: F)
+where F: Into<Option<LogCallback>>
+{
+    if let Some(callback) = callback.into() {
+        unsafe { RUST_LOGGING_FN = callback }
+    }
+}
+
 /// Dummy callback to fill in RUST_LOGGING_FN when it's not in use.
 fn dummy_callback(_: LogVerbosity, _: String) {}
 
 /// Real hook into the logging callback, calls the real user-supplied callback
 /// with nice Rust inputs.
+#[cfg(feature = "sys")]
+#[cfg_attr(feature = "mock", allow(dead_code))]
 unsafe extern "C" fn log_callback(importance: wlr_log_importance,
                                   fmt: *const c_char,
                                   va_list: *mut __va_list_tag) {
     let message = vsprintf(fmt, va_list).unwrap_or_else(|_| {
         c_to_rust_string(fmt).unwrap_or_else(|| "".into())
     });
+    record(importance, message)
+}
+
+/// Used by `wlr_log!` with the `mock` feature, which logs without going
+/// through wlroots.
+#[cfg(feature = "mock")]
+#[doc(hidden)]
+pub fn mock_log(importance: wlr_log_importance, message: String) {
+    unsafe { record(importance, message) }
+}
+
+unsafe fn record(importance: wlr_log_importance, message: String) {
+    RECENT.with(|recent| {
+        let mut recent = recent.borrow_mut();
+        if recent.len() == RECENT_MESSAGES {
+            recent.pop_front();
+        }
+        recent.push_back(message.clone());
+    });
     RUST_LOGGING_FN(importance, message);
 }
 
+/// The most recent log messages, oldest first.
+///
+/// Messages are only recorded when logging was initialized with a callback,
+/// otherwise wlroots writes them straight to standard error.
+pub fn recent_messages() -> Vec<String> {
+    RECENT.with(|recent| recent.borrow().iter().cloned().collect())
+}
+
 pub struct Logger;
 
 static LOGGER: Logger = Logger;
@@ -113,11 +180,16 @@ impl log::Log for Logger {
                 (Some(file), None) => format!("[{}] {}", file, record.args()),
                 (None, _) => format!("{}", record.args()),
             };
-            let msg = CString::new(formatted_msg)
-                .expect("Could not convert log message to CString");
-
-            unsafe {
-                _wlr_log(wlr_level, msg.as_ptr());
+            #[cfg(feature = "mock")]
+            unsafe { self::record(wlr_level, formatted_msg) };
+            #[cfg(not(feature = "mock"))]
+            {
+                let msg = CString::new(formatted_msg)
+                    .expect("Could not convert log message to CString");
+
+                unsafe {
+                    _wlr_log(wlr_level, msg.as_ptr());
+                }
             }
         }
     }
@@ -0,0 +1,183 @@
+//! A resource that behaves like a wlroots resource, without wlroots.
+//!
+//! Only available with the `mock` feature. The borrow tracking of handles
+//! (the liveliness flag, upgrading and the lock that is held while a
+//! resource is borrowed) is the same for every resource, so it can be
+//! exercised with this one in unit tests, including under Miri which can't
+//! call into C. With the feature `wlr_log!` doesn't call into wlroots
+//! either, and without the default features wlroots isn't linked at all.
+//!
+//! ```rust,no_run,ignore
+//! let resource = mock::Resource::create();
+//! let handle = resource.weak_reference();
+//! handle.run(|resource| {
+//!     assert_eq!(handle.run(|_| ()), Err(HandleErr::AlreadyBorrowed));
+//! }).unwrap();
+//! mock::Resource::destroy(resource);
+//! assert_eq!(handle.run(|_| ()), Err(HandleErr::AlreadyDropped));
+//! ```
+
+use std::{cell::Cell, marker::PhantomData, ptr, rc::{Rc, Weak}};
+
+use std::os::raw::c_void;
+
+use utils::{self, HandleErr, HandleResult, Handleable, Id};
+
+/// Stands in for the C struct of a wlroots resource, which keeps the state
+/// of the wrapper in its `data` pointer.
+#[derive(Debug)]
+#[allow(non_camel_case_types)]
+pub struct wlr_mock_resource {
+    pub data: *mut c_void
+}
+
+/// What the wrappers of real resources keep in `data`.
+struct ResourceState {
+    handle: Weak<Cell<bool>>,
+    id: Id
+}
+
+pub type Handle = utils::Handle<(), wlr_mock_resource, Resource>;
+
+#[derive(Debug)]
+pub struct Resource {
+    liveliness: Rc<Cell<bool>>,
+    resource: *mut wlr_mock_resource
+}
+
+impl Resource {
+    /// Create a resource, like wlroots does when e.g an output is plugged
+    /// in. The returned value owns the resource until it's destroyed.
+    pub fn create() -> Self {
+        let liveliness = Rc::new(Cell::new(false));
+        let state = Box::new(ResourceState { handle: Rc::downgrade(&liveliness),
+                                             id: Id::next() });
+        let resource = Box::into_raw(Box::new(wlr_mock_resource { data: ptr::null_mut() }));
+        unsafe {
+            (*resource).data = Box::into_raw(state) as *mut _;
+        }
+        Resource { liveliness,
+                   resource }
+    }
+
+    /// Destroy the resource, like wlroots does when e.g an output is
+    /// unplugged. Handles to it can't be upgraded anymore.
+    ///
+    /// # Panics
+    /// Panics if this isn't the value returned by `create`, or the resource
+    /// is borrowed.
+    pub fn destroy(resource: Resource) {
+        assert_eq!(Rc::strong_count(&resource.liveliness), 1,
+                   "Destroyed a resource that is still in use");
+        let ptr = resource.resource;
+        drop(resource);
+        unsafe {
+            drop(Box::from_raw((*ptr).data as *mut ResourceState));
+            drop(Box::from_raw(ptr));
+        }
+    }
+
+    pub fn id(&self) -> Id {
+        unsafe { (*((*self.resource).data as *mut ResourceState)).id }
+    }
+
+    /// Mark the resource as borrowed or not, like the compositor does while
+    /// it's passed to a callback.
+    pub fn set_lock(&self, val: bool) {
+        self.liveliness.set(val)
+    }
+}
+
+impl Handleable<(), wlr_mock_resource> for Resource {
+    #[doc(hidden)]
+    unsafe fn from_ptr(resource: *mut wlr_mock_resource) -> Self {
+        let state = &mut *((*resource).data as *mut ResourceState);
+        let liveliness = state.handle.upgrade().unwrap();
+        Resource { liveliness,
+                   resource }
+    }
+
+    #[doc(hidden)]
+    unsafe fn as_ptr(&self) -> *mut wlr_mock_resource {
+        self.resource
+    }
+
+    #[doc(hidden)]
+    unsafe fn from_handle(handle: &Handle) -> HandleResult<Self> {
+        let liveliness = handle.handle
+            .upgrade()
+            .ok_or(HandleErr::AlreadyDropped)?;
+        Ok(Resource { liveliness,
+                      resource: handle.ptr })
+    }
+
+    fn weak_reference(&self) -> Handle {
+        Handle { ptr: self.resource,
+                 handle: Rc::downgrade(&self.liveliness),
+                 data: (),
+                 _marker: PhantomData }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use utils::{log, HandleErr, Handleable};
+
+    use super::Resource;
+
+    #[test]
+    fn handle_runs_while_alive() {
+        let resource = Resource::create();
+        let id = resource.id();
+        let handle = resource.weak_reference();
+        assert!(handle.is_alive());
+        assert_eq!(handle.run(|resource| resource.id()), Ok(id));
+        assert!(!handle.is_borrowed());
+        Resource::destroy(resource);
+    }
+
+    #[test]
+    fn handle_is_locked_while_borrowed() {
+        let resource = Resource::create();
+        let handle = resource.weak_reference();
+        handle.run(|_| {
+                  assert!(handle.is_borrowed());
+                  assert_eq!(handle.run(|_| ()), Err(HandleErr::AlreadyBorrowed));
+              })
+              .unwrap();
+        assert_eq!(handle.run(|_| ()), Ok(()));
+        Resource::destroy(resource);
+    }
+
+    #[test]
+    fn handle_is_dropped_with_resource() {
+        let resource = Resource::create();
+        let handle = resource.weak_reference();
+        Resource::destroy(resource);
+        assert!(!handle.is_alive());
+        assert!(!handle.is_borrowed());
+        assert_eq!(handle.run(|_| ()), Err(HandleErr::AlreadyDropped));
+    }
+
+    #[test]
+    fn handle_is_unlocked_after_panic() {
+        let resource = Resource::create();
+        let handle = resource.weak_reference();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                                             handle.run(|_| panic!("callback panicked"))
+                                         }));
+        assert!(result.is_err());
+        assert!(!handle.is_borrowed());
+        assert_eq!(handle.run(|_| ()), Ok(()));
+        Resource::destroy(resource);
+    }
+
+    #[test]
+    fn log_is_recorded_without_wlroots() {
+        wlr_log!(WLR_INFO, "mock resource {}", 42);
+        let recent = log::recent_messages();
+        assert!(recent.last().unwrap().ends_with("mock resource 42"));
+    }
+}
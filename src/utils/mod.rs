@@ -1,18 +1,57 @@
 //! Utilities for use within wlroots-rs that are not directly related to Wayland or compositors.
+//!
+//! Without the `sys` feature only the handles, the ids and the
+//! logging are left, which is what the `mock` feature tests.
 
+#[cfg(feature = "sys")]
 pub mod edges;
+pub mod id;
 pub mod log;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "sys")]
 pub mod region;
 
 // Rust specific utilities that don't wrap a wlroots utility.
+#[cfg(feature = "sys")]
 mod time;
+#[cfg(feature = "sys")]
 mod string;
 mod handle;
 
+#[cfg(feature = "sys")]
 pub use self::time::{current_time, ToMs};
 pub use self::handle::*;
+pub use self::id::Id;
+#[cfg(feature = "sys")]
 pub(crate) use self::string::{c_to_rust_string, safe_as_cstring};
 
+#[cfg(feature = "sys")]
+use libc;
+#[cfg(feature = "sys")]
+use wayland_sys::server::WAYLAND_SERVER_HANDLE;
+#[cfg(feature = "sys")]
+use wlroots_sys::wl_client;
+
+/// Get the process id of a client, if it's known.
+///
+/// The client may be null, e.g when there's no active client, which is
+/// `None` as well.
+#[cfg(feature = "sys")]
+pub(crate) unsafe fn client_pid(client: *mut wl_client) -> Option<libc::pid_t> {
+    if client.is_null() {
+        return None
+    }
+    let (mut pid, mut uid, mut gid) = (0, 0, 0);
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_client_get_credentials,
+                  client as _,
+                  &mut pid,
+                  &mut uid,
+                  &mut gid);
+    if pid > 0 { Some(pid) } else { None }
+}
+
 
 /// Handle unwinding from a panic, used in conjunction with
 /// `::std::panic::catch_unwind`.
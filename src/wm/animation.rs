@@ -0,0 +1,269 @@
+//! Animations, and animated view geometry changes.
+//!
+//! An `Animation` interpolates between two values over time. It doesn't run
+//! by itself: ask it for its `value` at the time of every frame, and keep
+//! scheduling frames while any animation `is_running`.
+//!
+//! `Transitions` uses them for the common case of views changing geometry,
+//! e.g when a tiling layout is rearranged. Clients take a while to draw
+//! themselves at the new size after they were configured, so instead of
+//! jumping the view is drawn at an interpolated geometry, with whatever
+//! buffer the client last committed scaled to fit, until both the animation
+//! is over and the client acknowledged the configure:
+//!
+//! ```rust,no_run,ignore
+//! // When arranging the views.
+//! let serial = toplevel.set_size(new.size.width as u32, new.size.height as u32);
+//! state.transitions.configure(view.clone(), old, new, serial, utils::current_time());
+//!
+//! // On commit.
+//! state.transitions.committed(&view, shell_surface.configure_serial());
+//!
+//! // When rendering.
+//! let now = utils::current_time();
+//! let area = state.transitions.area(&view, now).unwrap_or(view_area);
+//! if state.transitions.is_running(now) {
+//!     output.schedule_frame();
+//! }
+//! ```
+
+use std::time::Duration;
+
+use area::{Area, Origin, Size};
+
+/// How long geometry changes are animated for by default, in milliseconds.
+pub const DEFAULT_DURATION_MS: u64 = 150;
+
+/// How the progress of an animation is spread over its duration.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Start fast and slow down towards the end.
+    EaseOut,
+    /// Start and end slowly.
+    EaseInOut
+}
+
+impl Easing {
+    /// Map the fraction of the duration that passed, between 0 and 1, to the
+    /// fraction of the way between the values.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.max(0.0).min(1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::EaseOut
+    }
+}
+
+/// A value that can be animated.
+pub trait Interpolate: Copy {
+    /// The value `t` of the way from `self` to `other`.
+    fn interpolate(self, other: Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for i32 {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        (self as f64).interpolate(other as f64, t).round() as i32
+    }
+}
+
+impl Interpolate for Origin {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        Origin::new(self.x.interpolate(other.x, t), self.y.interpolate(other.y, t))
+    }
+}
+
+impl Interpolate for Size {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        Size::new(self.width.interpolate(other.width, t),
+                  self.height.interpolate(other.height, t))
+    }
+}
+
+impl Interpolate for Area {
+    fn interpolate(self, other: Self, t: f64) -> Self {
+        Area::new(self.origin.interpolate(other.origin, t),
+                  self.size.interpolate(other.size, t))
+    }
+}
+
+/// A value changing from one to another over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Animation<V> {
+    from: V,
+    to: V,
+    start: Duration,
+    duration: Duration,
+    easing: Easing
+}
+
+impl<V: Interpolate> Animation<V> {
+    /// Animate from `from` to `to`, starting at `start` (e.g
+    /// `utils::current_time()`).
+    pub fn new(from: V, to: V, start: Duration, duration: Duration) -> Self {
+        Animation { from,
+                    to,
+                    start,
+                    duration,
+                    easing: Easing::default() }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// The value the animation ends at.
+    pub fn target(&self) -> V {
+        self.to
+    }
+
+    /// How far along the animation is at `time`, between 0 and 1.
+    pub fn progress(&self, time: Duration) -> f64 {
+        if self.duration == Duration::from_secs(0) || time >= self.start + self.duration {
+            return 1.0
+        }
+        if time <= self.start {
+            return 0.0
+        }
+        let elapsed = time - self.start;
+        as_secs_f64(elapsed) / as_secs_f64(self.duration)
+    }
+
+    /// The value at `time`.
+    pub fn value(&self, time: Duration) -> V {
+        self.from.interpolate(self.to, self.easing.apply(self.progress(time)))
+    }
+
+    /// Whether the animation hasn't reached its end yet at `time`.
+    pub fn is_running(&self, time: Duration) -> bool {
+        self.progress(time) < 1.0
+    }
+
+    /// Animate to a new value from wherever this animation is at `time`, so
+    /// interrupting an animation doesn't make the value jump.
+    pub fn retarget(&mut self, to: V, time: Duration) {
+        self.from = self.value(time);
+        self.to = to;
+        self.start = time;
+    }
+}
+
+fn as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+struct Transition<T> {
+    view: T,
+    animation: Animation<Area>,
+    /// The configure the client has to acknowledge before the view is drawn
+    /// as usual again.
+    serial: u32,
+    acked: bool
+}
+
+/// Animated geometry changes of views.
+pub struct Transitions<T> {
+    duration: Duration,
+    easing: Easing,
+    transitions: Vec<Transition<T>>
+}
+
+impl<T> Default for Transitions<T> {
+    fn default() -> Self {
+        Transitions { duration: Duration::from_millis(DEFAULT_DURATION_MS),
+                      easing: Easing::default(),
+                      transitions: Vec::new() }
+    }
+}
+
+impl<T: PartialEq> Transitions<T> {
+    pub fn new() -> Self {
+        Transitions::default()
+    }
+
+    /// Set how long geometry changes take. With zero views jump to their new
+    /// geometry right away, with the old buffer stretched until the client
+    /// caught up.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration
+    }
+
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = easing
+    }
+
+    /// The view was configured to move from `old` to `new` with the
+    /// configure `serial`, at `time`.
+    ///
+    /// If the view was already changing geometry the animation continues
+    /// from where it is now.
+    pub fn configure(&mut self, view: T, old: Area, new: Area, serial: u32, time: Duration) {
+        if let Some(transition) = self.transitions.iter_mut().find(|t| t.view == view) {
+            transition.animation.retarget(new, time);
+            transition.serial = serial;
+            transition.acked = false;
+            return
+        }
+        let animation = Animation::new(old, new, time, self.duration).with_easing(self.easing);
+        self.transitions.push(Transition { view,
+                                           animation,
+                                           serial,
+                                           acked: false });
+    }
+
+    /// The view committed after acknowledging the configure with
+    /// `configure_serial`.
+    pub fn committed(&mut self, view: &T, configure_serial: u32) {
+        for transition in self.transitions.iter_mut().filter(|t| t.view == *view) {
+            // Serials wrap around, compare them the way wlroots does.
+            if (configure_serial.wrapping_sub(transition.serial) as i32) >= 0 {
+                transition.acked = true;
+            }
+        }
+    }
+
+    /// The geometry to draw the view at, at `time`.
+    ///
+    /// `None` if the view isn't changing geometry, it should be drawn as
+    /// usual then.
+    pub fn area(&self, view: &T, time: Duration) -> Option<Area> {
+        self.transitions.iter()
+            .find(|t| t.view == *view)
+            .map(|t| t.animation.value(time))
+    }
+
+    /// Whether any view is still changing geometry at `time`, and frames
+    /// should keep being scheduled.
+    ///
+    /// Finished transitions are dropped.
+    pub fn is_running(&mut self, time: Duration) -> bool {
+        self.transitions.retain(|t| !t.acked || t.animation.is_running(time));
+        self.transitions.iter().any(|t| t.animation.is_running(time))
+    }
+
+    /// Stop animating the view, e.g because it was unmapped.
+    pub fn remove(&mut self, view: &T) {
+        self.transitions.retain(|t| t.view != *view)
+    }
+}
@@ -0,0 +1,141 @@
+//! Focus policy.
+//!
+//! `Policy` turns pointer motion and button presses into focus changes for
+//! the three common focus modes. It doesn't do any hit-testing itself: pass
+//! it the view under the cursor (e.g found by walking
+//! `stacking::Stack::iter_top_down`) and apply the `Intent`s it returns.
+//!
+//! Every focus change is also published on the bus as a `bus::FocusChanged`.
+
+use wlroots_sys::wlr_button_state;
+
+use {bus::{self, FocusChanged}, trace};
+
+/// How keyboard focus follows the pointer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Mode {
+    /// Focus only changes when a view is clicked.
+    ClickToFocus,
+    /// Focus follows the pointer, and is cleared when the pointer is over
+    /// no view at all.
+    FocusFollowsMouse,
+    /// Focus follows the pointer, but stays on the last view when the
+    /// pointer moves over the background.
+    SloppyFocus
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::ClickToFocus
+    }
+}
+
+/// A focus change the compositor should apply.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Intent<T> {
+    /// Give keyboard focus to the view.
+    ///
+    /// If `raise` is true the view should also be raised.
+    Focus { view: T, raise: bool },
+    /// Clear keyboard focus.
+    Clear
+}
+
+/// Decides when focus changes, according to a `Mode`.
+#[derive(Debug)]
+pub struct Policy<T> {
+    mode: Mode,
+    raise_on_focus: bool,
+    focused: Option<T>
+}
+
+impl<T: Clone + PartialEq + 'static> Policy<T> {
+    pub fn new(mode: Mode) -> Self {
+        Policy { mode,
+                 raise_on_focus: true,
+                 focused: None }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode
+    }
+
+    /// Whether views are raised when they are focused by the pointer.
+    ///
+    /// Defaults to true. Clicking a view always raises it.
+    pub fn set_raise_on_focus(&mut self, raise_on_focus: bool) {
+        self.raise_on_focus = raise_on_focus
+    }
+
+    /// The view the policy believes is focused.
+    pub fn focused(&self) -> Option<&T> {
+        self.focused.as_ref()
+    }
+
+    /// Tell the policy focus was changed by something else, e.g a keybinding
+    /// or a new view being mapped.
+    pub fn set_focused(&mut self, view: Option<T>) {
+        if self.focused != view {
+            self.change_focus(view)
+        }
+    }
+
+    /// The pointer moved, and is now over `under`.
+    pub fn pointer_motion(&mut self, under: Option<T>) -> Option<Intent<T>> {
+        match (self.mode, under) {
+            (Mode::ClickToFocus, _) => None,
+            (Mode::FocusFollowsMouse, None) => {
+                if self.focused.is_some() {
+                    self.change_focus(None);
+                    Some(Intent::Clear)
+                } else {
+                    None
+                }
+            }
+            (Mode::SloppyFocus, None) => None,
+            (_, Some(view)) => {
+                let raise = self.raise_on_focus;
+                self.focus(view, raise)
+            }
+        }
+    }
+
+    /// A pointer button changed state while the pointer was over `under`.
+    pub fn pointer_button(&mut self,
+                          under: Option<T>,
+                          state: wlr_button_state)
+                          -> Option<Intent<T>> {
+        if state != wlr_button_state::WLR_BUTTON_PRESSED {
+            return None
+        }
+        match under {
+            // Clicking the focused view still raises it.
+            Some(ref view) if self.focused.as_ref() == Some(view) => {
+                Some(Intent::Focus { view: view.clone(),
+                                     raise: true })
+            }
+            Some(view) => self.focus(view, true),
+            None => None
+        }
+    }
+
+    fn focus(&mut self, view: T, raise: bool) -> Option<Intent<T>> {
+        if self.focused.as_ref() == Some(&view) {
+            return None
+        }
+        self.change_focus(Some(view.clone()));
+        Some(Intent::Focus { view, raise })
+    }
+
+    fn change_focus(&mut self, view: Option<T>) {
+        let previous = ::std::mem::replace(&mut self.focused, view);
+        trace::record(trace::Kind::Focus, None);
+        bus::publish(&FocusChanged { previous,
+                                     focused: self.focused.clone() });
+    }
+}
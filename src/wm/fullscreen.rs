@@ -0,0 +1,498 @@
+//! Fullscreen on a chosen output, or spanned across the whole layout.
+//!
+//! A client asking to be fullscreen may name an output, otherwise the
+//! compositor picks one, usually the one the view is on. A view can also be
+//! spanned across every output, or across a group of them that is used as
+//! one canvas, e.g a video wall showing one big picture. `Fullscreen`
+//! remembers where each fullscreen view goes, the geometry to give it back
+//! afterwards, and moves it to `Layer::Fullscreen` of the `Stack`, above
+//! panels but below overlays such as lock screens:
+//!
+//! ```rust,no_run,ignore
+//! let target = Target::Output(event.output().unwrap_or(current_output));
+//! for (view, area) in state.fullscreen.enter(view.clone(), target, geometry, &mut state.stack) {
+//!     // Another view was fullscreen there, unset it and configure it to `area`.
+//! }
+//! let layout_area = layout.get_box(None);
+//! if let Some(area) = state.fullscreen.area(&view, |output| output_area(output), layout_area) {
+//!     // Configure the view to `area.size` and move it to `area.origin`.
+//! }
+//! ```
+//!
+//! The areas change when outputs are moved or resized, so views should be
+//! configured again then. Views fullscreen on an output that goes away
+//! leave fullscreen, see `Fullscreen::output_removed`.
+//!
+//! Views can also be fullscreen in a window, e.g a game in a tile: the
+//! client is told it's fullscreen, so it hides its decorations and menus,
+//! but the view keeps its frame and geometry:
+//!
+//! ```rust,no_run,ignore
+//! state.fullscreen.set_windowed(&view, true, &mut state.stack);
+//! // In the fullscreen request handler.
+//! if state.fullscreen.request_windowed(&view, event.fullscreen()) {
+//!     toplevel.set_fullscreen(event.fullscreen());
+//!     return
+//! }
+//! // Whenever the view is configured, e.g resized by the layout.
+//! toplevel.set_fullscreen(state.fullscreen.reports_fullscreen(&view));
+//! ```
+
+use area::Area;
+use wm::{history::Alive,
+         stacking::{Layer, Stack}};
+
+/// Where a view is fullscreen.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Target<K> {
+    /// Covering one output.
+    Output(K),
+    /// Covering a group of outputs used as one canvas, e.g the outputs of
+    /// an `output::group::OutputGroup`.
+    Group(Vec<K>),
+    /// Covering the extents of the whole layout.
+    Span
+}
+
+#[derive(Debug, Clone)]
+struct Entry<K, T> {
+    view: T,
+    target: Target<K>,
+    /// The geometry it had before it was made fullscreen.
+    restore: Area,
+    /// The layer to put it back in.
+    layer: Layer
+}
+
+/// The views that are fullscreen, and where.
+#[derive(Debug, Clone)]
+pub struct Fullscreen<K, T> {
+    entries: Vec<Entry<K, T>>,
+    confine_cursor: bool,
+    /// The views that are only ever fullscreen in their window.
+    windowed: Vec<T>,
+    /// The windowed views whose client is told they are fullscreen.
+    faked: Vec<T>
+}
+
+impl<K, T> Default for Fullscreen<K, T> {
+    fn default() -> Self {
+        Fullscreen { entries: Vec::new(),
+                     confine_cursor: false,
+                     windowed: Vec::new(),
+                     faked: Vec::new() }
+    }
+}
+
+impl<K, T> Fullscreen<K, T>
+    where K: Clone + PartialEq,
+          T: Clone + PartialEq + Alive
+{
+    pub fn new() -> Self {
+        Fullscreen::default()
+    }
+
+    /// Keep the cursor inside a fullscreen view while it has focus, see
+    /// `Fullscreen::confine`. Off by default.
+    pub fn set_confine_cursor(&mut self, confine_cursor: bool) {
+        self.confine_cursor = confine_cursor
+    }
+
+    /// Make the view fullscreen on the target. `geometry` is the geometry it
+    /// has now, which it gets back when it leaves fullscreen.
+    ///
+    /// Only one view is fullscreen on an output, and a spanned view covers
+    /// all of them. Returns the views that had to leave fullscreen for it,
+    /// with the geometry to give them back.
+    pub fn enter(&mut self,
+                 view: T,
+                 target: Target<K>,
+                 geometry: Area,
+                 stack: &mut Stack<T>)
+                 -> Vec<(T, Area)> {
+        // Going from one target to another keeps the geometry from before.
+        let (restore, layer) = match self.entry(&view) {
+            Some(entry) => (entry.restore, entry.layer),
+            None => (geometry, stack.layer(&view).unwrap_or_default())
+        };
+        self.entries.retain(|entry| entry.view != view);
+        self.faked.retain(|other| *other != view);
+        let displaced: Vec<T> = self.entries
+                                    .iter()
+                                    .filter(|entry| overlaps(&entry.target, &target))
+                                    .map(|entry| entry.view.clone())
+                                    .collect();
+        let displaced = displaced.iter()
+                                 .filter_map(|view| {
+                                                 self.exit(view, stack)
+                                                     .map(|area| (view.clone(), area))
+                                             })
+                                 .collect();
+        self.entries.push(Entry { view: view.clone(),
+                                  target,
+                                  restore,
+                                  layer });
+        stack.set_layer(&view, Layer::Fullscreen);
+        displaced
+    }
+
+    /// Take the view out of fullscreen, putting it back in the layer it was
+    /// in.
+    ///
+    /// Returns the geometry it had before it was made fullscreen.
+    pub fn exit(&mut self, view: &T, stack: &mut Stack<T>) -> Option<Area> {
+        let index = self.entries.iter().position(|entry| entry.view == *view)?;
+        let entry = self.entries.remove(index);
+        stack.set_layer(view, entry.layer);
+        Some(entry.restore)
+    }
+
+    pub fn is_fullscreen(&self, view: &T) -> bool {
+        self.entry(view).is_some()
+    }
+
+    /// Keep the view in its window when it's made fullscreen, or not.
+    ///
+    /// A view that is fullscreen is taken out of it when this is switched
+    /// on, and the geometry to give it back is returned. Its client is still
+    /// told it's fullscreen. When this is switched off, views that asked to
+    /// be fullscreen in the meantime (see `Fullscreen::is_faked`) should be
+    /// made fullscreen for real with `Fullscreen::enter`.
+    pub fn set_windowed(&mut self, view: &T, windowed: bool, stack: &mut Stack<T>) -> Option<Area> {
+        self.windowed.retain(|other| other != view);
+        if !windowed {
+            return None
+        }
+        self.windowed.push(view.clone());
+        let restore = self.exit(view, stack)?;
+        self.faked.push(view.clone());
+        Some(restore)
+    }
+
+    pub fn is_windowed(&self, view: &T) -> bool {
+        self.windowed.contains(view)
+    }
+
+    /// Handle a request of the client of the view to be fullscreen, or to
+    /// stop being fullscreen, if the view is kept in its window.
+    ///
+    /// Returns false if it isn't, and the request should be handled as
+    /// usual. Otherwise only tell the client whether it's fullscreen, and
+    /// leave its geometry alone.
+    pub fn request_windowed(&mut self, view: &T, fullscreen: bool) -> bool {
+        if !self.is_windowed(view) {
+            return false
+        }
+        self.faked.retain(|other| other != view);
+        if fullscreen {
+            self.faked.push(view.clone());
+        }
+        true
+    }
+
+    /// Whether the client of the view is told it's fullscreen while the view
+    /// is kept in its window.
+    pub fn is_faked(&self, view: &T) -> bool {
+        self.faked.contains(view)
+    }
+
+    /// Whether to tell the client of the view it's fullscreen when it's
+    /// configured, be it for real or in its window.
+    pub fn reports_fullscreen(&self, view: &T) -> bool {
+        self.is_fullscreen(view) || self.is_faked(view)
+    }
+
+    /// Get where the view is fullscreen.
+    pub fn target(&self, view: &T) -> Option<&Target<K>> {
+        self.entry(view).map(|entry| &entry.target)
+    }
+
+    /// Get the area the fullscreen view should cover, in layout coordinates.
+    ///
+    /// `output_area` gives the area of an output in the layout, `layout` the
+    /// extents of the whole layout (e.g `output::layout::Layout::get_box`
+    /// without a reference). A group covers the area its outputs span,
+    /// including the bezels between them. Returns `None` if the view isn't
+    /// fullscreen, or none of its outputs are in the layout.
+    pub fn area<F>(&self, view: &T, mut output_area: F, layout: Area) -> Option<Area>
+        where F: FnMut(&K) -> Option<Area>
+    {
+        match self.entry(view)?.target {
+            Target::Output(ref output) => output_area(output),
+            Target::Group(ref outputs) => {
+                outputs.iter()
+                       .filter_map(|output| output_area(output))
+                       .fold(None, |canvas: Option<Area>, area| {
+                           Some(canvas.map(|canvas| canvas.union(area)).unwrap_or(area))
+                       })
+            }
+            Target::Span => Some(layout)
+        }
+    }
+
+    /// Get the fullscreen view that covers the output, if any. Everything
+    /// below it in the stack doesn't need to be rendered there.
+    pub fn view_on(&self, output: &K) -> Option<&T> {
+        self.entries
+            .iter()
+            .find(|entry| match entry.target {
+                      Target::Output(ref other) => other == output,
+                      Target::Group(ref outputs) => outputs.contains(output),
+                      Target::Span => true
+                  })
+            .map(|entry| &entry.view)
+    }
+
+    /// Keep the cursor inside the area of the focused view, if it's
+    /// fullscreen and the cursor is confined.
+    ///
+    /// `area` is where the view is, e.g from `Fullscreen::area`. Returns
+    /// where the cursor should be moved to.
+    pub fn confine(&self, focused: Option<&T>, area: Area, (x, y): (f64, f64)) -> (f64, f64) {
+        match focused {
+            Some(view) if self.confine_cursor && self.is_fullscreen(view) => {
+                // The right and bottom edges are outside of the area.
+                let max_x = f64::from(area.origin.x + area.size.width) - 1.0;
+                let max_y = f64::from(area.origin.y + area.size.height) - 1.0;
+                (x.max(f64::from(area.origin.x)).min(max_x),
+                 y.max(f64::from(area.origin.y)).min(max_y))
+            }
+            _ => (x, y)
+        }
+    }
+
+    /// Take the views fullscreen on the output out of fullscreen, e.g
+    /// because it was unplugged.
+    ///
+    /// Returns them with the geometry to give them back. Views spanned
+    /// across the layout or a group stay fullscreen, and should be
+    /// configured to their new area.
+    pub fn output_removed(&mut self, output: &K, stack: &mut Stack<T>) -> Vec<(T, Area)> {
+        let views: Vec<T> = self.entries
+                                .iter()
+                                .filter(|entry| entry.target == Target::Output(output.clone()))
+                                .map(|entry| entry.view.clone())
+                                .collect();
+        views.into_iter()
+             .filter_map(|view| self.exit(&view, stack).map(|area| (view, area)))
+             .collect()
+    }
+
+    /// Forget views that were destroyed.
+    pub fn prune(&mut self) {
+        self.entries.retain(|entry| entry.view.is_alive());
+        self.windowed.retain(|view| view.is_alive());
+        self.faked.retain(|view| view.is_alive())
+    }
+
+    fn entry(&self, view: &T) -> Option<&Entry<K, T>> {
+        self.entries.iter().find(|entry| entry.view == *view)
+    }
+}
+
+/// Whether views fullscreen on the two targets would cover each other.
+fn overlaps<K: PartialEq>(a: &Target<K>, b: &Target<K>) -> bool {
+    match (a, b) {
+        (&Target::Output(ref a), &Target::Output(ref b)) => a == b,
+        (&Target::Output(ref output), &Target::Group(ref group))
+        | (&Target::Group(ref group), &Target::Output(ref output)) => group.contains(output),
+        (&Target::Group(ref a), &Target::Group(ref b)) => a.iter().any(|output| b.contains(output)),
+        _ => true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use area::{Origin, Size};
+
+    use super::*;
+    use wm::View;
+
+    const LEFT: &str = "DP-1";
+    const RIGHT: &str = "HDMI-A-1";
+    const UNPLUGGED: &str = "DP-2";
+
+    const VIDEO: View = View(1, true);
+    const GAME: View = View(2, true);
+    const SLIDES: View = View(3, true);
+
+    fn area(x: i32, y: i32, width: i32, height: i32) -> Area {
+        Area::new(Origin::new(x, y), Size::new(width, height))
+    }
+
+    fn output_area(output: &&'static str) -> Option<Area> {
+        match *output {
+            LEFT => Some(area(0, 0, 1920, 1080)),
+            RIGHT => Some(area(1920, 0, 1280, 1024)),
+            _ => None
+        }
+    }
+
+    fn layout() -> Area {
+        area(0, 0, 3200, 1080)
+    }
+
+    fn stack() -> Stack<View> {
+        let mut stack = Stack::new();
+        stack.insert(VIDEO, Layer::Normal);
+        stack.insert(GAME, Layer::Normal);
+        stack.insert(SLIDES, Layer::Top);
+        stack
+    }
+
+    #[test]
+    fn enter_and_exit() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let geometry = area(10, 20, 640, 480);
+        assert!(fullscreen.enter(VIDEO, Target::Output(LEFT), geometry, &mut stack)
+                          .is_empty());
+        assert!(fullscreen.is_fullscreen(&VIDEO));
+        assert!(fullscreen.reports_fullscreen(&VIDEO));
+        assert_eq!(fullscreen.target(&VIDEO), Some(&Target::Output(LEFT)));
+        assert_eq!(stack.layer(&VIDEO), Some(Layer::Fullscreen));
+        assert_eq!(stack.top(), Some(&VIDEO));
+
+        assert_eq!(fullscreen.exit(&VIDEO, &mut stack), Some(geometry));
+        assert!(!fullscreen.is_fullscreen(&VIDEO));
+        assert_eq!(stack.layer(&VIDEO), Some(Layer::Normal));
+        assert_eq!(fullscreen.exit(&VIDEO, &mut stack), None);
+    }
+
+    #[test]
+    fn changing_the_target_keeps_the_geometry_from_before() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let geometry = area(10, 20, 640, 480);
+        fullscreen.enter(VIDEO, Target::Output(LEFT), geometry, &mut stack);
+        fullscreen.enter(VIDEO, Target::Span, area(0, 0, 1920, 1080), &mut stack);
+        assert_eq!(fullscreen.target(&VIDEO), Some(&Target::Span));
+        assert_eq!(fullscreen.exit(&VIDEO, &mut stack), Some(geometry));
+        assert_eq!(stack.layer(&VIDEO), Some(Layer::Normal));
+    }
+
+    #[test]
+    fn one_view_is_fullscreen_per_output() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let video_geometry = area(10, 20, 640, 480);
+        let game_geometry = area(100, 100, 800, 600);
+        fullscreen.enter(VIDEO, Target::Output(LEFT), video_geometry, &mut stack);
+        assert!(fullscreen.enter(GAME, Target::Output(RIGHT), game_geometry, &mut stack)
+                          .is_empty());
+
+        // A group covering the left output displaces the video.
+        let group = Target::Group(vec![LEFT, UNPLUGGED]);
+        assert_eq!(fullscreen.enter(SLIDES, group, area(0, 0, 100, 30), &mut stack),
+                   vec![(VIDEO, video_geometry)]);
+        assert_eq!(stack.layer(&VIDEO), Some(Layer::Normal));
+        assert!(fullscreen.is_fullscreen(&GAME));
+
+        // Spanning displaces everything.
+        assert_eq!(fullscreen.enter(VIDEO, Target::Span, video_geometry, &mut stack),
+                   vec![(GAME, game_geometry), (SLIDES, area(0, 0, 100, 30))]);
+        assert_eq!(stack.layer(&SLIDES), Some(Layer::Top));
+        assert_eq!(fullscreen.view_on(&RIGHT), Some(&VIDEO));
+    }
+
+    #[test]
+    fn area_of_the_target() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        assert_eq!(fullscreen.area(&VIDEO, output_area, layout()), None);
+        fullscreen.enter(VIDEO, Target::Output(RIGHT), area(0, 0, 1, 1), &mut stack);
+        assert_eq!(fullscreen.area(&VIDEO, output_area, layout()),
+                   Some(area(1920, 0, 1280, 1024)));
+        fullscreen.enter(VIDEO, Target::Output(UNPLUGGED), area(0, 0, 1, 1), &mut stack);
+        assert_eq!(fullscreen.area(&VIDEO, output_area, layout()), None);
+        fullscreen.enter(VIDEO, Target::Span, area(0, 0, 1, 1), &mut stack);
+        assert_eq!(fullscreen.area(&VIDEO, output_area, layout()), Some(layout()));
+    }
+
+    #[test]
+    fn groups_cover_the_outputs_and_the_bezels_between() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let group = Target::Group(vec![RIGHT, UNPLUGGED, LEFT]);
+        fullscreen.enter(VIDEO, group, area(0, 0, 1, 1), &mut stack);
+        assert_eq!(fullscreen.area(&VIDEO, output_area, layout()),
+                   Some(area(0, 0, 3200, 1080)));
+        assert_eq!(fullscreen.view_on(&LEFT), Some(&VIDEO));
+        assert_eq!(fullscreen.view_on(&UNPLUGGED), Some(&VIDEO));
+        fullscreen.enter(VIDEO, Target::Group(vec![UNPLUGGED]), area(0, 0, 1, 1), &mut stack);
+        assert_eq!(fullscreen.area(&VIDEO, output_area, layout()), None);
+        assert_eq!(fullscreen.view_on(&LEFT), None);
+    }
+
+    #[test]
+    fn windowed_views_only_tell_the_client() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let geometry = area(100, 100, 800, 600);
+        assert!(!fullscreen.request_windowed(&GAME, true));
+        fullscreen.enter(GAME, Target::Output(LEFT), geometry, &mut stack);
+
+        // Taken out of fullscreen, but the client still thinks it is.
+        assert_eq!(fullscreen.set_windowed(&GAME, true, &mut stack), Some(geometry));
+        assert!(fullscreen.is_windowed(&GAME));
+        assert!(!fullscreen.is_fullscreen(&GAME));
+        assert!(fullscreen.is_faked(&GAME));
+        assert!(fullscreen.reports_fullscreen(&GAME));
+        assert_eq!(stack.layer(&GAME), Some(Layer::Normal));
+
+        assert!(fullscreen.request_windowed(&GAME, false));
+        assert!(!fullscreen.reports_fullscreen(&GAME));
+        assert!(fullscreen.request_windowed(&GAME, true));
+        assert!(fullscreen.is_faked(&GAME));
+
+        assert_eq!(fullscreen.set_windowed(&GAME, false, &mut stack), None);
+        assert!(!fullscreen.is_windowed(&GAME));
+        assert!(!fullscreen.request_windowed(&GAME, true));
+        // Entering for real stops faking it.
+        fullscreen.enter(GAME, Target::Output(LEFT), geometry, &mut stack);
+        assert!(!fullscreen.is_faked(&GAME));
+        assert!(fullscreen.reports_fullscreen(&GAME));
+    }
+
+    #[test]
+    fn confine_keeps_the_cursor_on_the_focused_view() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let right = area(1920, 0, 1280, 1024);
+        fullscreen.enter(VIDEO, Target::Output(RIGHT), area(0, 0, 1, 1), &mut stack);
+        assert_eq!(fullscreen.confine(Some(&VIDEO), right, (100.0, 2000.0)), (100.0, 2000.0));
+        fullscreen.set_confine_cursor(true);
+        assert_eq!(fullscreen.confine(Some(&VIDEO), right, (100.0, 2000.0)),
+                   (1920.0, 1023.0));
+        assert_eq!(fullscreen.confine(Some(&VIDEO), right, (2000.5, 10.5)), (2000.5, 10.5));
+        assert_eq!(fullscreen.confine(Some(&GAME), right, (100.0, 2000.0)), (100.0, 2000.0));
+        assert_eq!(fullscreen.confine(None, right, (100.0, 2000.0)), (100.0, 2000.0));
+    }
+
+    #[test]
+    fn output_removed_only_exits_views_on_that_output() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let geometry = area(10, 20, 640, 480);
+        fullscreen.enter(VIDEO, Target::Output(LEFT), geometry, &mut stack);
+        fullscreen.enter(GAME, Target::Group(vec![RIGHT, UNPLUGGED]), geometry, &mut stack);
+        assert!(fullscreen.output_removed(&UNPLUGGED, &mut stack).is_empty());
+        assert_eq!(fullscreen.output_removed(&LEFT, &mut stack), vec![(VIDEO, geometry)]);
+        assert_eq!(stack.layer(&VIDEO), Some(Layer::Normal));
+        assert!(fullscreen.is_fullscreen(&GAME));
+    }
+
+    #[test]
+    fn destroyed_views_are_forgotten() {
+        let mut stack = stack();
+        let mut fullscreen = Fullscreen::new();
+        let dead = View(4, false);
+        fullscreen.enter(dead, Target::Output(LEFT), area(0, 0, 1, 1), &mut stack);
+        fullscreen.set_windowed(&dead, true, &mut stack);
+        fullscreen.prune();
+        assert!(!fullscreen.is_fullscreen(&dead));
+        assert!(!fullscreen.is_windowed(&dead));
+        assert!(!fullscreen.is_faked(&dead));
+        assert_eq!(fullscreen.view_on(&LEFT), None);
+    }
+}
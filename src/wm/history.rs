@@ -0,0 +1,213 @@
+//! Keyboard focus history.
+//!
+//! A `History` remembers the order views were focused in, most recently used
+//! first. It's what "focus the previous window" and alt-tab are built on:
+//! call `History::focus` every time focus changes, and walk `History::iter`
+//! to get the views in most recently used order.
+//!
+//! Most compositors keep one history per seat, or per workspace and seat.
+//! `Histories` keeps a `History` for each key of your choosing.
+//!
+//! Views that were destroyed are dropped from the history automatically,
+//! there is no need to remove them from every history by hand.
+
+use utils::{self, Handleable};
+
+/// A view that knows whether it still exists.
+///
+/// Implemented for all handles, e.g `xdg_shell::Handle`.
+pub trait Alive {
+    fn is_alive(&self) -> bool;
+}
+
+impl<D: Clone, T, W: Handleable<D, T>> Alive for utils::Handle<D, T, W> {
+    fn is_alive(&self) -> bool {
+        utils::Handle::is_alive(self)
+    }
+}
+
+/// Focus history of a seat, most recently used first.
+#[derive(Debug, Clone)]
+pub struct History<T> {
+    views: Vec<T>
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History { views: Vec::new() }
+    }
+}
+
+impl<T: Clone + PartialEq + Alive> History<T> {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    /// Record that the view got focus, moving it to the front.
+    pub fn focus(&mut self, view: T) {
+        self.prune();
+        self.views.retain(|other| *other != view);
+        self.views.insert(0, view);
+    }
+
+    /// Remove a view from the history, e.g because it was minimized and
+    /// shouldn't be focused by `focus_previous`.
+    pub fn remove(&mut self, view: &T) {
+        self.views.retain(|other| other != view)
+    }
+
+    /// The most recently focused view.
+    pub fn focused(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    /// The view that was focused before the current one.
+    pub fn previous(&self) -> Option<&T> {
+        self.iter().nth(1)
+    }
+
+    /// Move focus back to the previous view, making the current one the
+    /// previous one.
+    ///
+    /// Returns the view that should be focused now.
+    pub fn focus_previous(&mut self) -> Option<T> {
+        self.prune();
+        if self.views.len() < 2 {
+            return None
+        }
+        self.views.swap(0, 1);
+        self.views.first().cloned()
+    }
+
+    /// Iterate over the views, most recently used first.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.views.iter().filter(|view| view.is_alive())
+    }
+
+    /// The number of views in the history.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop the views that were destroyed.
+    pub fn prune(&mut self) {
+        self.views.retain(|view| view.is_alive())
+    }
+}
+
+/// One `History` per key, e.g per seat or per workspace.
+#[derive(Debug, Clone)]
+pub struct Histories<K, T> {
+    histories: Vec<(K, History<T>)>
+}
+
+impl<K, T> Default for Histories<K, T> {
+    fn default() -> Self {
+        Histories { histories: Vec::new() }
+    }
+}
+
+impl<K: PartialEq, T: Clone + PartialEq + Alive> Histories<K, T> {
+    pub fn new() -> Self {
+        Histories::default()
+    }
+
+    /// Get the history for the key, if anything was focused in it yet.
+    pub fn get(&self, key: &K) -> Option<&History<T>> {
+        self.histories.iter()
+            .find(|&&(ref other, _)| other == key)
+            .map(|&(_, ref history)| history)
+    }
+
+    /// Get the history for the key, creating it if needed.
+    pub fn get_mut(&mut self, key: K) -> &mut History<T> {
+        let index = match self.histories.iter().position(|&(ref other, _)| *other == key) {
+            Some(index) => index,
+            None => {
+                self.histories.push((key, History::new()));
+                self.histories.len() - 1
+            }
+        };
+        &mut self.histories[index].1
+    }
+
+    /// Remove the view from all histories.
+    pub fn remove_view(&mut self, view: &T) {
+        for &mut (_, ref mut history) in &mut self.histories {
+            history.remove(view)
+        }
+    }
+
+    /// Forget the history for the key, e.g because the workspace was removed.
+    pub fn remove(&mut self, key: &K) -> Option<History<T>> {
+        self.histories.iter()
+            .position(|&(ref other, _)| other == key)
+            .map(|index| self.histories.remove(index).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wm::View;
+
+    const TERMINAL: View = View(1, true);
+    const EDITOR: View = View(2, true);
+    const BROWSER: View = View(3, true);
+    const CLOSED: View = View(4, false);
+
+    #[test]
+    fn most_recently_focused_first() {
+        let mut history = History::new();
+        history.focus(TERMINAL);
+        history.focus(EDITOR);
+        history.focus(BROWSER);
+        history.focus(TERMINAL);
+        assert_eq!(history.iter().cloned().collect::<Vec<_>>(), vec![TERMINAL, BROWSER, EDITOR]);
+        assert_eq!(history.focused(), Some(&TERMINAL));
+        assert_eq!(history.previous(), Some(&BROWSER));
+    }
+
+    #[test]
+    fn focus_previous_swaps_the_first_two() {
+        let mut history = History::new();
+        assert_eq!(history.focus_previous(), None);
+        history.focus(TERMINAL);
+        assert_eq!(history.focus_previous(), None);
+        history.focus(EDITOR);
+        assert_eq!(history.focus_previous(), Some(TERMINAL));
+        assert_eq!(history.focus_previous(), Some(EDITOR));
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn destroyed_views_are_skipped() {
+        let mut history = History::new();
+        history.focus(TERMINAL);
+        history.focus(CLOSED);
+        assert_eq!(history.focused(), Some(&TERMINAL));
+        assert_eq!(history.len(), 1);
+        history.focus(EDITOR);
+        assert_eq!(history.previous(), Some(&TERMINAL));
+        history.remove(&TERMINAL);
+        assert!(history.previous().is_none());
+    }
+
+    #[test]
+    fn histories_per_key() {
+        let mut histories = Histories::new();
+        histories.get_mut("seat0").focus(TERMINAL);
+        histories.get_mut("seat1").focus(EDITOR);
+        histories.get_mut("seat1").focus(TERMINAL);
+        histories.remove_view(&TERMINAL);
+        assert!(histories.get(&"seat0").unwrap().is_empty());
+        assert_eq!(histories.get(&"seat1").unwrap().focused(), Some(&EDITOR));
+        assert!(histories.remove(&"seat1").is_some());
+        assert!(histories.get(&"seat1").is_none());
+        assert!(histories.get(&"seat2").is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Placing the candidate windows of input methods next to the text caret.
+//!
+//! Input methods show their candidates in popup surfaces, which the
+//! compositor places itself. Text inputs report where their caret is, as a
+//! rectangle relative to their surface. `CaretTracker` keeps the one of the
+//! focused text input, and `place` puts a popup right below it, or above it
+//! when there's no room left on the output:
+//!
+//! ```rust,no_run,ignore
+//! // When a text input is enabled, and when it reports a new rectangle.
+//! state.caret.focus(Some(view.clone()));
+//! state.caret.set_rectangle(&view, rectangle);
+//! // When laying out the popups of the input method.
+//! if let Some(caret) = state.caret.layout_rectangle(view_origin) {
+//!     let area = input_popup::place(caret, popup_size, &output_areas);
+//! }
+//! ```
+
+use area::{Area, IntersectionResult, Origin, Size};
+
+/// Tracks the caret of the text input that has focus.
+#[derive(Debug)]
+pub struct CaretTracker<T> {
+    focused: Option<(T, Option<Area>)>
+}
+
+impl<T> Default for CaretTracker<T> {
+    fn default() -> Self {
+        CaretTracker { focused: None }
+    }
+}
+
+impl<T: Clone + PartialEq> CaretTracker<T> {
+    pub fn new() -> Self {
+        CaretTracker::default()
+    }
+
+    /// Set the view whose text input has focus, or none.
+    ///
+    /// The caret is forgotten when the focus moves to another view.
+    pub fn focus(&mut self, view: Option<T>) {
+        let unchanged = match (&self.focused, &view) {
+            (&Some((ref focused, _)), &Some(ref view)) => focused == view,
+            _ => false
+        };
+        if !unchanged {
+            self.focused = view.map(|view| (view, None));
+        }
+    }
+
+    /// Get the view whose text input has focus.
+    pub fn focused(&self) -> Option<&T> {
+        self.focused.as_ref().map(|&(ref view, _)| view)
+    }
+
+    /// Record the caret the text input of the view reported, relative to
+    /// the view.
+    ///
+    /// Ignored unless the view has focus, as only its caret matters.
+    pub fn set_rectangle(&mut self, view: &T, rectangle: Area) {
+        if let Some((ref focused, ref mut caret)) = self.focused {
+            if focused == view {
+                *caret = Some(rectangle);
+            }
+        }
+    }
+
+    /// Forget the view, e.g because it was destroyed.
+    pub fn remove(&mut self, view: &T) {
+        if self.focused().map(|focused| focused == view).unwrap_or(false) {
+            self.focused = None;
+        }
+    }
+
+    /// Get the caret relative to the focused view.
+    ///
+    /// `None` if nothing has focus or its text input didn't report a caret,
+    /// in which case popups usually go below the whole view.
+    pub fn rectangle(&self) -> Option<Area> {
+        self.focused.as_ref().and_then(|&(_, caret)| caret)
+    }
+
+    /// Get the caret in layout coordinates, for the focused view at
+    /// `view_origin`.
+    pub fn layout_rectangle(&self, view_origin: Origin) -> Option<Area> {
+        self.rectangle().map(|caret| {
+                                 let origin = Origin::new(view_origin.x + caret.origin.x,
+                                                          view_origin.y + caret.origin.y);
+                                 caret.with_origin(origin)
+                             })
+    }
+}
+
+/// Place a popup of `size` next to `caret`, both in layout coordinates.
+///
+/// The popup goes below the caret, starting at its left edge. It's kept on
+/// the output the caret is on, taken from `outputs`, moving it above the
+/// caret if it doesn't fit below and shifting it sideways if it doesn't fit
+/// to the right.
+pub fn place(caret: Area, size: Size, outputs: &[Area]) -> Area {
+    let below = Origin::new(caret.origin.x, caret.origin.y + caret.size.height);
+    let output = match caret_output(caret, outputs) {
+        Some(output) => output,
+        None => return Area::new(below, size)
+    };
+    let (left, top) = (output.origin.x, output.origin.y);
+    let right = left + output.size.width;
+    let bottom = top + output.size.height;
+    let mut y = below.y;
+    if y + size.height > bottom && caret.origin.y - size.height >= top {
+        y = caret.origin.y - size.height;
+    }
+    // Popups too large for the output stick to its top left corner.
+    let x = below.x.min(right - size.width).max(left);
+    let y = y.min(bottom - size.height).max(top);
+    Area::new(Origin::new(x, y), size)
+}
+
+/// The output the caret is on, or the one it overlaps the most.
+fn caret_output(caret: Area, outputs: &[Area]) -> Option<Area> {
+    let (x, y) = (caret.origin.x as f64, caret.origin.y as f64);
+    if let Some(&output) = outputs.iter().find(|output| output.contains_point(x, y)) {
+        return Some(output)
+    }
+    outputs.iter()
+           .filter_map(|&output| match caret.intersection(output) {
+                           IntersectionResult::Intersection(overlap) => {
+                               Some((overlap.size.width * overlap.size.height, output))
+                           }
+                           IntersectionResult::NoIntersection => None
+                       })
+           .max_by_key(|&(overlap, _)| overlap)
+           .map(|(_, output)| output)
+}
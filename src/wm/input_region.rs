@@ -0,0 +1,171 @@
+//! Overriding where views take pointer input.
+//!
+//! By default a view takes input inside its geometry. Client side decorated
+//! windows usually draw no border to grab, so compositors give them an
+//! invisible border around their geometry that resizes the view instead.
+//! Other views should take less input than they cover, e.g a dock whose
+//! shadow shouldn't block clicks to the windows below it.
+//!
+//! Hit-test with `InputRegions::view_at`, passing the views from top to
+//! bottom (e.g from `Stack::iter_top_down`), so both the border and the
+//! shrunk regions are respected:
+//!
+//! ```rust,no_run,ignore
+//! let views = stack.iter_top_down().map(|view| (view, view.geometry()));
+//! match regions.view_at(views, cursor.coords()) {
+//!     Some((view, Hit::Surface { sx, sy })) => focus(view, sx, sy),
+//!     Some((view, Hit::Border(edges))) => start_resize(view, edges),
+//!     None => {}
+//! }
+//! ```
+
+use area::Area;
+use utils::edges::Edges;
+
+/// How far the input region of a view reaches past each side of its
+/// geometry, in layout pixels. Negative values shrink it instead.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct Margins {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32
+}
+
+impl Margins {
+    /// The same margin on all sides.
+    pub fn uniform(margin: i32) -> Self {
+        Margins { top: margin,
+                  bottom: margin,
+                  left: margin,
+                  right: margin }
+    }
+
+    /// The input region of a view with the geometry `area`.
+    pub fn apply(self, area: Area) -> Area {
+        let mut area = area;
+        area.origin.x -= self.left;
+        area.origin.y -= self.top;
+        area.size.width = (area.size.width + self.left + self.right).max(0);
+        area.size.height = (area.size.height + self.top + self.bottom).max(0);
+        area
+    }
+}
+
+/// What a point in the input region of a view is over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hit {
+    /// The view itself, at surface-local coordinates.
+    Surface { sx: f64, sy: f64 },
+    /// The border added around the view, at the edges it resizes.
+    Border(Edges)
+}
+
+impl Hit {
+    /// Get the edges the hit resizes from, none for the view itself.
+    ///
+    /// Pass them to `Cursor::set_resize_image` to show the matching image.
+    pub fn edges(&self) -> Edges {
+        match *self {
+            Hit::Surface { .. } => Edges::empty(),
+            Hit::Border(edges) => edges
+        }
+    }
+}
+
+/// The input region overrides of a set of views.
+#[derive(Debug)]
+pub struct InputRegions<T> {
+    default: Margins,
+    overrides: Vec<(T, Margins)>
+}
+
+impl<T> Default for InputRegions<T> {
+    fn default() -> Self {
+        InputRegions { default: Margins::default(),
+                       overrides: Vec::new() }
+    }
+}
+
+impl<T: Clone + PartialEq> InputRegions<T> {
+    pub fn new() -> Self {
+        InputRegions::default()
+    }
+
+    /// Set the margins of views without an override of their own.
+    ///
+    /// None by default, so views take input exactly inside their geometry.
+    pub fn set_default(&mut self, margins: Margins) {
+        self.default = margins;
+    }
+
+    /// Override the margins of the view.
+    pub fn set(&mut self, view: T, margins: Margins) {
+        self.remove(&view);
+        self.overrides.push((view, margins));
+    }
+
+    /// Go back to the default margins for the view, e.g because it was
+    /// destroyed.
+    pub fn remove(&mut self, view: &T) {
+        self.overrides.retain(|&(ref overridden, _)| overridden != view);
+    }
+
+    /// Get the margins the view takes input with.
+    pub fn margins(&self, view: &T) -> Margins {
+        self.overrides
+            .iter()
+            .find(|&&(ref overridden, _)| overridden == view)
+            .map(|&(_, margins)| margins)
+            .unwrap_or(self.default)
+    }
+
+    /// Hit-test the view with the geometry `geometry` at `(x, y)`, both in
+    /// layout coordinates.
+    pub fn hit(&self, view: &T, geometry: Area, (x, y): (f64, f64)) -> Option<Hit> {
+        let margins = self.margins(view);
+        if !margins.apply(geometry).contains_point(x, y) {
+            return None
+        }
+        let (left, top) = (geometry.origin.x as f64, geometry.origin.y as f64);
+        let right = left + geometry.size.width as f64;
+        let bottom = top + geometry.size.height as f64;
+        if x >= left && x < right && y >= top && y < bottom {
+            return Some(Hit::Surface { sx: x - left,
+                                       sy: y - top })
+        }
+        // Along an edge, the part as long as the border is thick next to a
+        // corner resizes from the corner, so corners aren't tiny targets.
+        let corner_x = margins.left.max(margins.right).max(0) as f64;
+        let corner_y = margins.top.max(margins.bottom).max(0) as f64;
+        let beside = x < left || x >= right;
+        let above_or_below = y < top || y >= bottom;
+        let mut edges = Edges::empty();
+        if x < left || (above_or_below && x < left + corner_x) {
+            edges |= Edges::WLR_EDGE_LEFT;
+        } else if x >= right || (above_or_below && x >= right - corner_x) {
+            edges |= Edges::WLR_EDGE_RIGHT;
+        }
+        if y < top || (beside && y < top + corner_y) {
+            edges |= Edges::WLR_EDGE_TOP;
+        } else if y >= bottom || (beside && y >= bottom - corner_y) {
+            edges |= Edges::WLR_EDGE_BOTTOM;
+        }
+        Some(Hit::Border(edges))
+    }
+
+    /// Find the topmost view at `(x, y)` in layout coordinates and what it
+    /// is hit at.
+    ///
+    /// `views` are the views with their geometry, from top to bottom.
+    pub fn view_at<'a, I>(&self, views: I, point: (f64, f64)) -> Option<(T, Hit)>
+        where I: IntoIterator<Item = (&'a T, Area)>,
+              T: 'a
+    {
+        views.into_iter()
+             .filter_map(|(view, geometry)| {
+                             self.hit(view, geometry, point).map(|hit| (view.clone(), hit))
+                         })
+             .next()
+    }
+}
@@ -0,0 +1,168 @@
+//! Interactive moves, with snapping to the edges and corners of an output.
+//!
+//! Start a `Move` when a client asks to be moved (the `move_request` of the
+//! XDG shell handlers) or a view is dragged with a modifier held, and feed it
+//! the cursor position on every motion event. It tells you where the view
+//! goes and, if snapping is enabled, whether dropping it right now would tile
+//! it. Show a preview of the `Snap::area` while a snap is pending, and tile
+//! the view when the button is released:
+//!
+//! ```rust,no_run,ignore
+//! // On motion.
+//! let update = state.moving.motion(cursor.coords(), output_area);
+//! view.move_to(update.origin);
+//! state.snap_preview = update.snap.map(|snap| snap.area(output_area));
+//! // On button release.
+//! if let Some(snap) = state.moving.finish(cursor.coords(), output_area) {
+//!     view.tile(snap.area(output_area));
+//! }
+//! ```
+
+use area::{Area, Origin, Size};
+
+/// Where a view can be tiled by dragging it into an edge or a corner.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Snap {
+    /// The whole output, from the top edge.
+    Maximize,
+    LeftHalf,
+    RightHalf,
+    TopLeftQuarter,
+    TopRightQuarter,
+    BottomLeftQuarter,
+    BottomRightQuarter
+}
+
+impl Snap {
+    /// The area the view should take up on the output at `output`.
+    pub fn area(self, output: Area) -> Area {
+        let Area { origin, size } = output;
+        let half_width = size.width / 2;
+        let half_height = size.height / 2;
+        let (x, y, width, height) = match self {
+            Snap::Maximize => (0, 0, size.width, size.height),
+            Snap::LeftHalf => (0, 0, half_width, size.height),
+            Snap::RightHalf => (half_width, 0, size.width - half_width, size.height),
+            Snap::TopLeftQuarter => (0, 0, half_width, half_height),
+            Snap::TopRightQuarter => (half_width, 0, size.width - half_width, half_height),
+            Snap::BottomLeftQuarter => (0, half_height, half_width, size.height - half_height),
+            Snap::BottomRightQuarter => {
+                (half_width, half_height, size.width - half_width, size.height - half_height)
+            }
+        };
+        Area::new(Origin::new(origin.x + x, origin.y + y), Size::new(width, height))
+    }
+}
+
+/// The regions along the edges of an output that trigger snapping.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SnapZones {
+    /// How far from an edge, in layout pixels, the cursor has to be to snap
+    /// to that edge.
+    pub edge: i32,
+    /// How far along an edge from a corner the cursor has to be to snap to
+    /// a quarter instead of a half.
+    pub corner: i32,
+    /// Whether the top edge maximizes. If not, only its corners snap.
+    pub top_maximizes: bool
+}
+
+impl Default for SnapZones {
+    fn default() -> Self {
+        SnapZones { edge: 8,
+                    corner: 64,
+                    top_maximizes: true }
+    }
+}
+
+impl SnapZones {
+    /// The snap the cursor at (`x`, `y`) would trigger on the output at
+    /// `output`, if any.
+    pub fn detect(&self, x: f64, y: f64, output: Area) -> Option<Snap> {
+        let Area { origin, size } = output;
+        let (x, y) = (x - origin.x as f64, y - origin.y as f64);
+        let (width, height) = (size.width as f64, size.height as f64);
+        if x < 0.0 || y < 0.0 || x >= width || y >= height {
+            return None
+        }
+        let (edge, corner) = (self.edge as f64, self.corner as f64);
+        let left = x < edge;
+        let right = x >= width - edge;
+        let top = y < edge;
+        let bottom = y >= height - edge;
+        let near_left = x < corner;
+        let near_right = x >= width - corner;
+        let near_top = y < corner;
+        let near_bottom = y >= height - corner;
+        match () {
+            _ if (left && near_top) || (top && near_left) => Some(Snap::TopLeftQuarter),
+            _ if (right && near_top) || (top && near_right) => Some(Snap::TopRightQuarter),
+            _ if (left && near_bottom) || (bottom && near_left) => {
+                Some(Snap::BottomLeftQuarter)
+            }
+            _ if (right && near_bottom) || (bottom && near_right) => {
+                Some(Snap::BottomRightQuarter)
+            }
+            _ if left => Some(Snap::LeftHalf),
+            _ if right => Some(Snap::RightHalf),
+            _ if top && self.top_maximizes => Some(Snap::Maximize),
+            _ => None
+        }
+    }
+}
+
+/// Where a dragged view goes after the cursor moved.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MoveUpdate {
+    /// The new position of the view, in layout coordinates.
+    pub origin: Origin,
+    /// The snap that would happen if the view was dropped now.
+    pub snap: Option<Snap>
+}
+
+/// An interactive move of a view with the cursor.
+#[derive(Debug, Clone)]
+pub struct Move {
+    /// Where the cursor grabbed the view, relative to the view.
+    grab: (f64, f64),
+    zones: Option<SnapZones>,
+    snap: Option<Snap>
+}
+
+impl Move {
+    /// Start moving the view at `view_origin`, with the cursor at
+    /// `cursor`. Both are in layout coordinates.
+    pub fn new(view_origin: Origin, cursor: (f64, f64)) -> Self {
+        Move { grab: (cursor.0 - view_origin.x as f64, cursor.1 - view_origin.y as f64),
+               zones: None,
+               snap: None }
+    }
+
+    /// Snap the view when it's dragged into the zones. Snapping is off by
+    /// default.
+    pub fn with_snapping(mut self, zones: SnapZones) -> Self {
+        self.zones = Some(zones);
+        self
+    }
+
+    /// The snap that would happen if the view was dropped now.
+    pub fn pending_snap(&self) -> Option<Snap> {
+        self.snap
+    }
+
+    /// The cursor moved to `cursor`, on the output at `output`.
+    pub fn motion(&mut self, cursor: (f64, f64), output: Area) -> MoveUpdate {
+        let (x, y) = cursor;
+        self.snap = self.zones.and_then(|zones| zones.detect(x, y, output));
+        MoveUpdate { origin: Origin::new((x - self.grab.0).round() as i32,
+                                         (y - self.grab.1).round() as i32),
+                     snap: self.snap }
+    }
+
+    /// The view was dropped with the cursor at `cursor`.
+    ///
+    /// Returns the snap to apply, if the view was dropped in a snap zone.
+    pub fn finish(mut self, cursor: (f64, f64), output: Area) -> Option<Snap> {
+        self.motion(cursor, output).snap
+    }
+}
@@ -0,0 +1,371 @@
+//! Keybindings, grouped in modes.
+//!
+//! Like the modes of sway or vi, a mode is a table of its own bindings:
+//! a "resize" mode can bind the arrow keys without them doing anything in
+//! the default mode. `Modes` keeps a stack of the modes that were entered,
+//! only the bindings of the mode on top of it are looked up:
+//!
+//! ```rust,no_run,ignore
+//! let mut modes = Modes::new();
+//! modes.bindings_mut(DEFAULT_MODE)
+//!      .bind(keysyms::KEY_r, Modifier::WLR_MODIFIER_LOGO, Action::Resize);
+//! modes.bindings_mut("resize").bind(keysyms::KEY_Left, Modifier::empty(), Action::Shrink);
+//! modes.bindings_mut("resize").bind(keysyms::KEY_Escape, Modifier::empty(), Action::Leave);
+//! // In the keyboard handler, for key presses.
+//! match modes.lookup(keysym, modifiers).cloned() {
+//!     Some(Action::Resize) => {
+//!         modes.push("resize");
+//!     }
+//!     Some(Action::Leave) => {
+//!         modes.pop();
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! Every change of the mode is published as a `bus::ModeChanged` event, for
+//! status bars (e.g `river_status::ZManagerV1::set_mode`) and IPC. An
+//! `Indicator` shows on the outputs that a mode other than the default one
+//! is active.
+
+use {area::{Area, Origin, Size},
+     bus,
+     input::keyboard::{Key, Modifier},
+     render::Renderer};
+
+/// The name of the mode at the bottom of the stack, which is never left.
+pub const DEFAULT_MODE: &str = "default";
+
+/// The bindings of one mode.
+#[derive(Debug, Clone)]
+pub struct Bindings<A> {
+    bindings: Vec<(Key, Modifier, A)>
+}
+
+impl<A> Default for Bindings<A> {
+    fn default() -> Self {
+        Bindings { bindings: Vec::new() }
+    }
+}
+
+impl<A> Bindings<A> {
+    pub fn new() -> Self {
+        Bindings::default()
+    }
+
+    /// Bind the key combination to the action, replacing what it was bound
+    /// to before.
+    pub fn bind(&mut self, key: Key, modifiers: Modifier, action: A) {
+        self.unbind(key, modifiers);
+        self.bindings.push((key, modifiers, action));
+    }
+
+    /// Remove the binding of the key combination, returning its action.
+    pub fn unbind(&mut self, key: Key, modifiers: Modifier) -> Option<A> {
+        let index = self.bindings
+                        .iter()
+                        .position(|&(k, m, _)| k == key && m == modifiers)?;
+        Some(self.bindings.remove(index).2)
+    }
+
+    /// Get the action the key combination is bound to.
+    pub fn get(&self, key: Key, modifiers: Modifier) -> Option<&A> {
+        self.bindings
+            .iter()
+            .find(|&&(k, m, _)| k == key && m == modifiers)
+            .map(|&(_, _, ref action)| action)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+/// The binding tables of the modes, and the stack of entered modes.
+#[derive(Debug)]
+pub struct Modes<A> {
+    modes: Vec<(String, Bindings<A>)>,
+    /// Never empty, the default mode is at the bottom.
+    stack: Vec<String>
+}
+
+impl<A> Default for Modes<A> {
+    fn default() -> Self {
+        Modes { modes: vec![(DEFAULT_MODE.into(), Bindings::new())],
+                stack: vec![DEFAULT_MODE.into()] }
+    }
+}
+
+impl<A> Modes<A> {
+    /// Start in the default mode, without any bindings.
+    pub fn new() -> Self {
+        Modes::default()
+    }
+
+    /// Get the bindings of the mode, if it has any.
+    pub fn bindings(&self, mode: &str) -> Option<&Bindings<A>> {
+        self.modes
+            .iter()
+            .find(|&&(ref name, _)| name == mode)
+            .map(|&(_, ref bindings)| bindings)
+    }
+
+    /// Get the bindings of the mode, creating the mode if it doesn't exist.
+    pub fn bindings_mut(&mut self, mode: &str) -> &mut Bindings<A> {
+        let index = match self.modes.iter().position(|&(ref name, _)| name == mode) {
+            Some(index) => index,
+            None => {
+                self.modes.push((mode.into(), Bindings::new()));
+                self.modes.len() - 1
+            }
+        };
+        &mut self.modes[index].1
+    }
+
+    /// Get the names of the modes that have bindings.
+    pub fn names(&self) -> Vec<&str> {
+        self.modes.iter().map(|&(ref name, _)| name.as_str()).collect()
+    }
+
+    /// Get the name of the active mode.
+    pub fn current(&self) -> &str {
+        self.stack.last().map(String::as_str).unwrap_or(DEFAULT_MODE)
+    }
+
+    /// Get the entered modes, starting with the default mode.
+    pub fn stack(&self) -> &[String] {
+        &self.stack
+    }
+
+    /// Enter a mode on top of the active one.
+    ///
+    /// Returns false if the mode doesn't exist, or is active already.
+    pub fn push(&mut self, mode: &str) -> bool {
+        if self.bindings(mode).is_none() || self.current() == mode {
+            return false
+        }
+        let previous = self.current().to_owned();
+        self.stack.push(mode.into());
+        self.changed(previous);
+        true
+    }
+
+    /// Leave the active mode, going back to the one entered before it.
+    ///
+    /// Returns the mode that was left, the default mode is never left.
+    pub fn pop(&mut self) -> Option<String> {
+        if self.stack.len() <= 1 {
+            return None
+        }
+        let left = self.stack.pop();
+        if let Some(ref left) = left {
+            self.changed(left.clone());
+        }
+        left
+    }
+
+    /// Leave every mode, going back to the default one.
+    pub fn reset(&mut self) {
+        if self.stack.len() <= 1 {
+            return
+        }
+        let previous = self.current().to_owned();
+        self.stack.truncate(1);
+        self.changed(previous);
+    }
+
+    /// Look up the action of a key combination in the active mode.
+    pub fn lookup(&self, key: Key, modifiers: Modifier) -> Option<&A> {
+        self.bindings(self.current()).and_then(|bindings| bindings.get(key, modifiers))
+    }
+
+    fn changed(&self, previous: String) {
+        bus::publish(&bus::ModeChanged { previous,
+                                         mode: self.current().to_owned(),
+                                         depth: self.stack.len() - 1 });
+    }
+}
+
+/// Where on the output the indicator is shown.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight
+}
+
+/// Shows a colored box in a corner of the outputs while a mode other than
+/// the default one is active.
+///
+/// Compositors that can render text draw the name of the mode on top of it,
+/// at `Indicator::area`.
+#[derive(Debug, Clone)]
+pub struct Indicator {
+    pub corner: Corner,
+    pub size: Size,
+    /// The distance to the edges of the output.
+    pub margin: i32,
+    pub color: [f32; 4],
+    /// Colors of particular modes, instead of `color`.
+    pub mode_colors: Vec<(String, [f32; 4])>
+}
+
+impl Default for Indicator {
+    fn default() -> Self {
+        Indicator { corner: Corner::TopRight,
+                    size: Size::new(120, 24),
+                    margin: 8,
+                    color: [0.8, 0.3, 0.1, 0.9],
+                    mode_colors: Vec::new() }
+    }
+}
+
+impl Indicator {
+    /// Get where the indicator is drawn on an output of the size, in
+    /// logical output coordinates, i.e before the scale of the output.
+    pub fn area(&self, output_size: Size) -> Area {
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => self.margin,
+            Corner::TopRight | Corner::BottomRight => {
+                output_size.width - self.size.width - self.margin
+            }
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => self.margin,
+            Corner::BottomLeft | Corner::BottomRight => {
+                output_size.height - self.size.height - self.margin
+            }
+        };
+        Area::new(Origin::new(x, y), self.size)
+    }
+
+    /// Draw the indicator if the active mode isn't the default one.
+    ///
+    /// Returns where it was drawn, in output buffer pixels, i.e `area`
+    /// multiplied by the scale of the output. Call it after drawing the
+    /// views, so it's on top of them.
+    pub fn render<A>(&self, renderer: &mut Renderer, modes: &Modes<A>) -> Option<Area> {
+        let mode = modes.current();
+        if mode == DEFAULT_MODE {
+            return None
+        }
+        let color = self.mode_colors
+                        .iter()
+                        .find(|&&(ref name, _)| name == mode)
+                        .map(|&(_, color)| color)
+                        .unwrap_or(self.color);
+        let (width, height) = renderer.output.effective_resolution();
+        let area = self.area(Size::new(width, height));
+        let scale = renderer.output.scale();
+        let scaled = |value: i32| (value as f32 * scale).round() as i32;
+        let area = Area::new(Origin::new(scaled(area.origin.x), scaled(area.origin.y)),
+                             Size::new(scaled(area.size.width), scaled(area.size.height)));
+        let transform_matrix = renderer.output.transform_matrix();
+        renderer.render_colored_rect(area, color, transform_matrix);
+        Some(area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use xkbcommon::xkb::keysyms::{KEY_Escape, KEY_Left, KEY_r};
+
+    use bus;
+    use input::keyboard::Modifier;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Action {
+        Resize,
+        Shrink,
+        Leave
+    }
+
+    fn modes() -> Modes<Action> {
+        let mut modes = Modes::new();
+        modes.bindings_mut(DEFAULT_MODE).bind(KEY_r, Modifier::WLR_MODIFIER_LOGO, Action::Resize);
+        modes.bindings_mut("resize").bind(KEY_Left, Modifier::empty(), Action::Shrink);
+        modes.bindings_mut("resize").bind(KEY_Escape, Modifier::empty(), Action::Leave);
+        modes
+    }
+
+    #[test]
+    fn lookup_uses_the_active_mode() {
+        let mut modes = modes();
+        assert_eq!(modes.lookup(KEY_r, Modifier::WLR_MODIFIER_LOGO), Some(&Action::Resize));
+        assert_eq!(modes.lookup(KEY_Left, Modifier::empty()), None);
+        assert!(modes.push("resize"));
+        assert_eq!(modes.lookup(KEY_r, Modifier::WLR_MODIFIER_LOGO), None);
+        assert_eq!(modes.lookup(KEY_Left, Modifier::empty()), Some(&Action::Shrink));
+        assert_eq!(modes.lookup(KEY_Left, Modifier::WLR_MODIFIER_SHIFT), None);
+    }
+
+    #[test]
+    fn push_and_pop_keep_a_stack() {
+        let mut modes = modes();
+        modes.bindings_mut("move");
+        assert!(modes.push("resize"));
+        assert!(!modes.push("resize"));
+        assert!(!modes.push("missing"));
+        assert!(modes.push("move"));
+        assert_eq!(modes.stack(), &[DEFAULT_MODE, "resize", "move"]);
+        assert_eq!(modes.pop(), Some("move".to_owned()));
+        assert_eq!(modes.current(), "resize");
+        assert_eq!(modes.pop(), Some("resize".to_owned()));
+        assert_eq!(modes.pop(), None);
+        assert_eq!(modes.current(), DEFAULT_MODE);
+    }
+
+    #[test]
+    fn reset_goes_back_to_the_default_mode() {
+        let mut modes = modes();
+        modes.bindings_mut("move");
+        modes.push("resize");
+        modes.push("move");
+        modes.reset();
+        assert_eq!(modes.stack(), &[DEFAULT_MODE]);
+    }
+
+    #[test]
+    fn changes_are_published() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let seen = changes.clone();
+        let subscription = bus::subscribe(move |event: &bus::ModeChanged| {
+                                              seen.borrow_mut().push(event.clone())
+                                          });
+        let mut modes = modes();
+        modes.push("resize");
+        modes.pop();
+        modes.pop();
+        bus::unsubscribe(subscription);
+        let changes = changes.borrow();
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].previous.as_str(), changes[0].mode.as_str(), changes[0].depth),
+                   (DEFAULT_MODE, "resize", 1));
+        assert_eq!((changes[1].previous.as_str(), changes[1].mode.as_str(), changes[1].depth),
+                   ("resize", DEFAULT_MODE, 0));
+    }
+
+    #[test]
+    fn bind_replaces_the_earlier_action() {
+        let mut bindings = Bindings::new();
+        bindings.bind(KEY_r, Modifier::empty(), Action::Resize);
+        bindings.bind(KEY_r, Modifier::empty(), Action::Leave);
+        assert_eq!(bindings.get(KEY_r, Modifier::empty()), Some(&Action::Leave));
+        assert_eq!(bindings.unbind(KEY_r, Modifier::empty()), Some(Action::Leave));
+        assert!(bindings.is_empty());
+    }
+
+    #[test]
+    fn indicator_is_placed_in_its_corner() {
+        let indicator = Indicator { corner: Corner::BottomRight,
+                                    ..Indicator::default() };
+        let area = indicator.area(Size::new(1920, 1080));
+        assert_eq!(area.origin, Origin::new(1920 - 120 - 8, 1080 - 24 - 8));
+        assert_eq!(area.size, indicator.size);
+    }
+}
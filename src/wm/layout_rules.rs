@@ -0,0 +1,103 @@
+//! Keyboard layouts per application.
+//!
+//! People typing in more than one language often want a layout tied to an
+//! application, e.g a Russian layout in one terminal and the default one
+//! everywhere else. `LayoutRules` maps app ids to layouts and works out
+//! which layout to switch to when the focus moves, e.g on the
+//! `bus::FocusChanged` events of `wm::focus::Policy`:
+//!
+//! ```rust,no_run,ignore
+//! let mut rules = LayoutRules::new(Layout::new("us", ""));
+//! rules.set("org.gnome.Terminal", Layout::new("ru", "phonetic"));
+//! // Before keyboard focus moves to the view, so it gets the new keymap.
+//! if let Some(layout) = rules.focus_changed(Some(&app_id)) {
+//!     keyboard.set_layout(&layout.layout, &layout.variant);
+//! }
+//! ```
+
+/// An XKB layout, e.g "de" with the variant "nodeadkeys".
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Layout {
+    pub layout: String,
+    /// The variant of the layout, empty for the default one.
+    pub variant: String
+}
+
+impl Layout {
+    pub fn new<L: Into<String>, V: Into<String>>(layout: L, variant: V) -> Self {
+        Layout { layout: layout.into(),
+                 variant: variant.into() }
+    }
+}
+
+/// Which layout is used for which app id.
+#[derive(Debug)]
+pub struct LayoutRules {
+    default: Layout,
+    rules: Vec<(String, Layout)>,
+    current: Option<Layout>
+}
+
+impl LayoutRules {
+    /// `default` is used for applications without a rule, and when nothing
+    /// has focus.
+    pub fn new(default: Layout) -> Self {
+        LayoutRules { default,
+                      rules: Vec::new(),
+                      current: None }
+    }
+
+    pub fn default_layout(&self) -> &Layout {
+        &self.default
+    }
+
+    pub fn set_default(&mut self, default: Layout) {
+        self.default = default;
+    }
+
+    /// Use `layout` for the application with the app id, replacing any
+    /// earlier rule for it.
+    pub fn set<S: Into<String>>(&mut self, app_id: S, layout: Layout) {
+        let app_id = app_id.into();
+        self.remove(&app_id);
+        self.rules.push((app_id, layout));
+    }
+
+    /// Go back to the default layout for the application.
+    pub fn remove(&mut self, app_id: &str) {
+        self.rules.retain(|&(ref ruled, _)| ruled != app_id);
+    }
+
+    /// Get the layout for the application with the app id, or for nothing
+    /// having focus.
+    pub fn layout_for(&self, app_id: Option<&str>) -> &Layout {
+        app_id.and_then(|app_id| {
+                            self.rules
+                                .iter()
+                                .find(|&&(ref ruled, _)| ruled == app_id)
+                                .map(|&(_, ref layout)| layout)
+                        })
+              .unwrap_or(&self.default)
+    }
+
+    /// The focus moved to the application with the app id, or to nothing.
+    ///
+    /// Returns the layout the keyboards should switch to, or `None` if they
+    /// already use the right one.
+    pub fn focus_changed(&mut self, app_id: Option<&str>) -> Option<Layout> {
+        let layout = self.layout_for(app_id).clone();
+        if self.current.as_ref() == Some(&layout) {
+            return None
+        }
+        self.current = Some(layout.clone());
+        Some(layout)
+    }
+
+    /// Forget which layout the keyboards use, e.g because a keyboard was
+    /// added with the default keymap, so the next focus change switches
+    /// again.
+    pub fn reset(&mut self) {
+        self.current = None;
+    }
+}
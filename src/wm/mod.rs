@@ -0,0 +1,49 @@
+//! Building blocks for window management.
+//!
+//! wlroots leaves policy, such as which window is on top or which one gets
+//! focus, entirely to the compositor. The types in here implement the policy
+//! that most compositors end up needing.
+//!
+//! They don't hold on to any wlroots resources themselves. Instead they are
+//! generic over the type used to refer to a view, which will usually be a
+//! shell surface handle (e.g `xdg_shell::Handle`).
+//!
+//! The modules that are plain Rust are built with the `mock` feature as
+//! well, so their tests run without wlroots.
+
+pub mod animation;
+#[cfg(feature = "unstable")]
+pub mod focus;
+pub mod fullscreen;
+pub mod history;
+pub mod input_popup;
+#[cfg(feature = "unstable")]
+pub mod input_region;
+pub mod interactive;
+#[cfg(feature = "unstable")]
+pub mod keybinding;
+pub mod layout_rules;
+pub mod occlusion;
+#[cfg(feature = "unstable")]
+pub mod osk;
+pub mod scratchpad;
+#[cfg(feature = "unstable")]
+pub mod size_limits;
+pub mod stacking;
+#[cfg(feature = "unstable")]
+pub mod switcher;
+pub mod throttle;
+pub mod workspaces;
+
+/// A view for the tests of the modules in here, with an id and whether it's
+/// still alive.
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct View(u32, bool);
+
+#[cfg(test)]
+impl history::Alive for View {
+    fn is_alive(&self) -> bool {
+        self.1
+    }
+}
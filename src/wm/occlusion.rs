@@ -0,0 +1,294 @@
+//! Which parts of the views are actually visible.
+//!
+//! `Visibility` walks a `stacking::Stack` from the top down and subtracts the
+//! opaque parts of every view from the views below it. Everything is done in
+//! layout coordinates with plain rectangles, so it doesn't need a renderer or
+//! any wlroots resources and can be recomputed whenever the stack or the
+//! geometry of a view changes.
+
+use area::{Area, IntersectionResult, Origin, Size};
+use wm::stacking::Stack;
+
+/// The geometry of a view, as far as occlusion is concerned.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Shape {
+    /// Where the view is, in layout coordinates.
+    pub area: Area,
+    /// The parts of the view that are fully opaque, in layout coordinates.
+    ///
+    /// Usually the opaque region the client set on its surface, moved to
+    /// where the view is.
+    pub opaque: Vec<Area>
+}
+
+impl Shape {
+    /// A view with no opaque parts, which never hides anything below it.
+    pub fn transparent(area: Area) -> Self {
+        Shape { area,
+                opaque: Vec::new() }
+    }
+
+    /// A view that is opaque everywhere.
+    pub fn opaque(area: Area) -> Self {
+        Shape { area,
+                opaque: vec![area] }
+    }
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    view: T,
+    visible: Vec<Area>
+}
+
+/// The visible parts of every view in a stack.
+#[derive(Debug)]
+pub struct Visibility<T> {
+    /// Ordered from top to bottom.
+    entries: Vec<Entry<T>>
+}
+
+impl<T> Default for Visibility<T> {
+    fn default() -> Self {
+        Visibility { entries: Vec::new() }
+    }
+}
+
+impl<T: Clone + PartialEq> Visibility<T> {
+    /// Compute what is visible of the views in `stack`.
+    ///
+    /// `screens` are the areas of the outputs in the layout, anything outside
+    /// of them is not visible. `shape` returns `None` for views that aren't
+    /// shown at all (e.g unmapped or minimized views).
+    pub fn compute<F>(stack: &Stack<T>, screens: &[Area], mut shape: F) -> Self
+        where F: FnMut(&T) -> Option<Shape>
+    {
+        let mut covered: Vec<Area> = Vec::new();
+        let mut entries = Vec::with_capacity(stack.len());
+        for view in stack.iter_top_down() {
+            let shape = shape(view);
+            let visible = match shape {
+                Some(ref shape) => {
+                    let on_screen = screens.iter()
+                                           .filter_map(|&screen| intersect(shape.area, screen))
+                                           .collect();
+                    subtract_all(on_screen, &covered)
+                }
+                None => Vec::new()
+            };
+            if let Some(shape) = shape {
+                covered.extend(shape.opaque
+                                    .iter()
+                                    .filter_map(|&opaque| intersect(opaque, shape.area)));
+            }
+            entries.push(Entry { view: view.clone(),
+                                 visible });
+        }
+        Visibility { entries }
+    }
+
+    /// Whether any part of the view can be seen.
+    ///
+    /// Views that weren't in the stack are not visible.
+    pub fn is_visible(&self, view: &T) -> bool {
+        !self.visible_area(view).is_empty()
+    }
+
+    /// The parts of the view that can be seen, in layout coordinates.
+    pub fn visible_area(&self, view: &T) -> &[Area] {
+        self.entries.iter()
+            .find(|entry| entry.view == *view)
+            .map(|entry| entry.visible.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Forget a view, e.g because it was destroyed.
+    ///
+    /// What it covered is only uncovered by computing the visibility again.
+    pub fn remove(&mut self, view: &T) {
+        self.entries.retain(|entry| entry.view != *view)
+    }
+
+    /// Iterate over the views that can be seen, from bottom to top.
+    pub fn iter_visible<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.entries.iter()
+            .rev()
+            .filter(|entry| !entry.visible.is_empty())
+            .map(|entry| &entry.view)
+    }
+}
+
+/// The intersection of two areas, if they overlap.
+pub fn intersect(a: Area, b: Area) -> Option<Area> {
+    match a.intersection(b) {
+        IntersectionResult::Intersection(area) => Some(area),
+        IntersectionResult::NoIntersection => None
+    }
+}
+
+/// The parts of `area` that are not covered by `hole`, as at most four
+/// non overlapping areas.
+pub fn subtract(area: Area, hole: Area) -> Vec<Area> {
+    let hole = match intersect(area, hole) {
+        Some(hole) => hole,
+        None => return vec![area]
+    };
+    let (left, top) = (area.origin.x, area.origin.y);
+    let (right, bottom) = (left + area.size.width, top + area.size.height);
+    let (hole_left, hole_top) = (hole.origin.x, hole.origin.y);
+    let (hole_right, hole_bottom) = (hole_left + hole.size.width, hole_top + hole.size.height);
+    let rect = |x1, y1, x2, y2| Area::new(Origin::new(x1, y1), Size::new(x2 - x1, y2 - y1));
+    let mut rest = Vec::with_capacity(4);
+    if top < hole_top {
+        rest.push(rect(left, top, right, hole_top));
+    }
+    if hole_bottom < bottom {
+        rest.push(rect(left, hole_bottom, right, bottom));
+    }
+    if left < hole_left {
+        rest.push(rect(left, hole_top, hole_left, hole_bottom));
+    }
+    if hole_right < right {
+        rest.push(rect(hole_right, hole_top, right, hole_bottom));
+    }
+    rest
+}
+
+/// The parts of `areas` that are not covered by any of `holes`.
+pub fn subtract_all(areas: Vec<Area>, holes: &[Area]) -> Vec<Area> {
+    holes.iter().fold(areas, |areas, &hole| {
+        areas.into_iter()
+             .flat_map(|area| subtract(area, hole))
+             .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wm::stacking::Layer;
+
+    use super::*;
+
+    fn area(x: i32, y: i32, width: i32, height: i32) -> Area {
+        Area::new(Origin::new(x, y), Size::new(width, height))
+    }
+
+    fn size(areas: &[Area]) -> i32 {
+        areas.iter().map(|area| area.size.width * area.size.height).sum()
+    }
+
+    /// No two of the areas overlap.
+    fn disjoint(areas: &[Area]) -> bool {
+        areas.iter().enumerate().all(|(index, &a)| {
+            areas[index + 1..].iter().all(|&b| intersect(a, b).is_none())
+        })
+    }
+
+    #[test]
+    fn subtract_outside_hole() {
+        let window = area(0, 0, 100, 100);
+        assert_eq!(subtract(window, area(200, 0, 50, 50)), vec![window]);
+        // Touching edges don't overlap.
+        assert_eq!(subtract(window, area(100, 0, 50, 50)), vec![window]);
+    }
+
+    #[test]
+    fn subtract_covering_hole() {
+        assert!(subtract(area(10, 10, 50, 50), area(0, 0, 100, 100)).is_empty());
+        assert!(subtract(area(0, 0, 100, 100), area(0, 0, 100, 100)).is_empty());
+    }
+
+    #[test]
+    fn subtract_hole_in_the_middle() {
+        let rest = subtract(area(0, 0, 100, 100), area(25, 25, 50, 50));
+        assert_eq!(rest,
+                   vec![area(0, 0, 100, 25),
+                        area(0, 75, 100, 25),
+                        area(0, 25, 25, 50),
+                        area(75, 25, 25, 50)]);
+        assert_eq!(size(&rest), 100 * 100 - 50 * 50);
+        assert!(disjoint(&rest));
+    }
+
+    #[test]
+    fn subtract_hole_over_an_edge() {
+        // Only the part of the hole inside the area counts.
+        assert_eq!(subtract(area(0, 0, 100, 100), area(50, -20, 100, 200)),
+                   vec![area(0, 0, 50, 100)]);
+        assert_eq!(subtract(area(0, 0, 100, 100), area(-10, 80, 50, 50)),
+                   vec![area(0, 0, 100, 80), area(40, 80, 60, 20)]);
+    }
+
+    #[test]
+    fn subtract_all_holes() {
+        let rest = subtract_all(vec![area(0, 0, 100, 100)],
+                                &[area(0, 0, 50, 50), area(25, 25, 50, 50), area(90, 0, 50, 200)]);
+        // The first hole, the part of the second one outside of the first,
+        // and the part of the third one inside the area.
+        assert_eq!(size(&rest), 100 * 100 - 2500 - 1875 - 1000);
+        assert!(disjoint(&rest));
+        assert!(rest.iter().all(|&rest| {
+            intersect(rest, area(0, 0, 50, 50)).is_none()
+            && intersect(rest, area(25, 25, 50, 50)).is_none()
+            && intersect(rest, area(90, 0, 50, 200)).is_none()
+        }));
+        assert!(subtract_all(Vec::new(), &[area(0, 0, 10, 10)]).is_empty());
+        assert_eq!(subtract_all(vec![area(0, 0, 10, 10)], &[]), vec![area(0, 0, 10, 10)]);
+    }
+
+    #[test]
+    fn views_covered_by_opaque_views_are_not_visible() {
+        let screen = area(0, 0, 1000, 1000);
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Normal);
+        stack.insert(2, Layer::Normal);
+        stack.insert(3, Layer::Top);
+        let visibility = Visibility::compute(&stack, &[screen], |&view| {
+            Some(match view {
+                     1 => Shape::opaque(area(100, 100, 200, 200)),
+                     2 => Shape::opaque(area(0, 0, 500, 500)),
+                     _ => Shape::transparent(area(0, 0, 1000, 1000))
+                 })
+        });
+        assert!(!visibility.is_visible(&1));
+        assert!(visibility.is_visible(&2));
+        // Transparent views don't hide anything.
+        assert_eq!(visibility.visible_area(&2), &[area(0, 0, 500, 500)]);
+        assert_eq!(visibility.iter_visible().collect::<Vec<_>>(), vec![&2, &3]);
+    }
+
+    #[test]
+    fn only_the_opaque_region_covers() {
+        let screen = area(0, 0, 1000, 1000);
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Normal);
+        stack.insert(2, Layer::Normal);
+        let visibility = Visibility::compute(&stack, &[screen], |&view| {
+            Some(match view {
+                     1 => Shape::opaque(area(0, 0, 100, 100)),
+                     // Opaque only in its left half.
+                     _ => Shape { area: area(0, 0, 100, 100),
+                                  opaque: vec![area(0, 0, 50, 100)] }
+                 })
+        });
+        assert_eq!(visibility.visible_area(&1), &[area(50, 0, 50, 100)]);
+    }
+
+    #[test]
+    fn views_off_screen_or_unshown_are_not_visible() {
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Normal);
+        stack.insert(2, Layer::Normal);
+        stack.insert(3, Layer::Normal);
+        let visibility = Visibility::compute(&stack, &[area(0, 0, 100, 100)], |&view| match view {
+            1 => Some(Shape::opaque(area(200, 0, 100, 100))),
+            2 => Some(Shape::opaque(area(50, 50, 100, 100))),
+            _ => None
+        });
+        assert!(!visibility.is_visible(&1));
+        // Clipped to the screen.
+        assert_eq!(visibility.visible_area(&2), &[area(50, 50, 50, 50)]);
+        assert!(!visibility.is_visible(&3));
+        assert!(!visibility.is_visible(&4));
+    }
+}
@@ -0,0 +1,195 @@
+//! Deciding when to show an on-screen keyboard.
+//!
+//! Touch devices need a keyboard on screen while a text field has focus, but
+//! only while no hardware keyboard is around to type on, e.g when a
+//! convertible is folded into tablet mode. `OskPolicy` follows the focus of
+//! text inputs, the tablet mode switch and the capabilities of the seat, and
+//! tells an `OnScreenKeyboard` whenever it should be shown or hidden:
+//!
+//! ```rust,no_run,ignore
+//! let mut osk = OskPolicy::new(Box::new(Launcher::new(Command::new("squeekboard"))));
+//! // When a text input is enabled or disabled.
+//! osk.set_text_input_focused(true);
+//! // In the switch handler.
+//! osk.switch_toggled(&event);
+//! // When keyboards are added or removed.
+//! osk.set_capabilities(seat.capabilities());
+//! ```
+
+use std::process::{Child, Command};
+
+use wlroots_sys::{wlr_switch_state::*, wlr_switch_type::WLR_SWITCH_TYPE_TABLET_MODE};
+
+use {input::switch, seat::Capability};
+
+/// The on-screen keyboard that is shown and hidden, e.g a client that is
+/// told over a custom protocol or an external program.
+pub trait OnScreenKeyboard {
+    fn show(&mut self);
+
+    fn hide(&mut self);
+}
+
+/// Shows an on-screen keyboard by starting an external program, and hides
+/// it by stopping the program again.
+#[derive(Debug)]
+pub struct Launcher {
+    command: Command,
+    child: Option<Child>
+}
+
+impl Launcher {
+    pub fn new(command: Command) -> Self {
+        Launcher { command,
+                   child: None }
+    }
+}
+
+impl OnScreenKeyboard for Launcher {
+    fn show(&mut self) {
+        if self.child.is_some() {
+            return
+        }
+        match self.command.spawn() {
+            Ok(child) => self.child = Some(child),
+            Err(err) => wlr_log!(WLR_ERROR, "Could not start on-screen keyboard: {}", err)
+        }
+    }
+
+    fn hide(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+impl Drop for Launcher {
+    fn drop(&mut self) {
+        self.hide()
+    }
+}
+
+/// When the on-screen keyboard is shown.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Mode {
+    /// Whenever a text input has focus, while in tablet mode or without a
+    /// hardware keyboard.
+    Auto,
+    /// Whenever a text input has focus.
+    Always,
+    /// Never.
+    Never
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Auto
+    }
+}
+
+/// Shows and hides an on-screen keyboard as text inputs get focus.
+pub struct OskPolicy {
+    osk: Box<OnScreenKeyboard>,
+    mode: Mode,
+    text_input_focused: bool,
+    tablet_mode: bool,
+    hardware_keyboard: bool,
+    dismissed: bool,
+    shown: bool
+}
+
+impl OskPolicy {
+    /// Start with no text input focused, outside of tablet mode and without a
+    /// hardware keyboard.
+    pub fn new(osk: Box<OnScreenKeyboard>) -> Self {
+        OskPolicy { osk,
+                    mode: Mode::default(),
+                    text_input_focused: false,
+                    tablet_mode: false,
+                    hardware_keyboard: false,
+                    dismissed: false,
+                    shown: false }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.update()
+    }
+
+    /// Whether the on-screen keyboard is shown.
+    pub fn shown(&self) -> bool {
+        self.shown
+    }
+
+    /// Record whether a text input has focus, i.e whether one was enabled or
+    /// disabled.
+    pub fn set_text_input_focused(&mut self, focused: bool) {
+        if self.text_input_focused != focused {
+            self.text_input_focused = focused;
+            self.dismissed = false;
+            self.update()
+        }
+    }
+
+    /// Record whether the device is in tablet mode.
+    pub fn set_tablet_mode(&mut self, tablet_mode: bool) {
+        self.tablet_mode = tablet_mode;
+        self.update()
+    }
+
+    /// Follow the tablet mode switch. Events of other switches are ignored.
+    pub fn switch_toggled(&mut self, event: &switch::event::Toggle) {
+        if event.switch_type() != WLR_SWITCH_TYPE_TABLET_MODE {
+            return
+        }
+        let tablet_mode = match event.switch_state() {
+            WLR_SWITCH_STATE_ON => true,
+            WLR_SWITCH_STATE_OFF => false,
+            WLR_SWITCH_STATE_TOGGLE => !self.tablet_mode
+        };
+        self.set_tablet_mode(tablet_mode)
+    }
+
+    /// Record the capabilities of the seat, which tell whether there's a
+    /// hardware keyboard.
+    pub fn set_capabilities(&mut self, capabilities: Capability) {
+        self.hardware_keyboard = capabilities.contains(Capability::Keyboard);
+        self.update()
+    }
+
+    /// Hide the on-screen keyboard because the user closed it, until a text
+    /// input gets focus again.
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+        self.update()
+    }
+
+    fn wanted(&self) -> bool {
+        if !self.text_input_focused || self.dismissed {
+            return false
+        }
+        match self.mode {
+            Mode::Auto => self.tablet_mode || !self.hardware_keyboard,
+            Mode::Always => true,
+            Mode::Never => false
+        }
+    }
+
+    fn update(&mut self) {
+        let wanted = self.wanted();
+        if wanted == self.shown {
+            return
+        }
+        self.shown = wanted;
+        if wanted {
+            self.osk.show()
+        } else {
+            self.osk.hide()
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! A scratchpad for views that are hidden but kept alive.
+//!
+//! Moving a view to the scratchpad takes it off every output: it's removed
+//! from the `Stack`, so it's neither drawn nor found by hit-testing, and from
+//! the focus `History`, so it isn't focused by accident. The client keeps the
+//! view around and doesn't notice. Showing it again puts it on top of the
+//! focused output, centered at the size it had when it was hidden:
+//!
+//! ```rust,no_run,ignore
+//! // Bound to e.g Logo+Shift+minus.
+//! let next = state.scratchpad.add(view, size, &mut state.stack, &mut state.history);
+//! // Bound to e.g Logo+minus, shows the next hidden view or hides the shown one.
+//! if let Some((view, area)) = state.scratchpad.toggle(output_area,
+//!                                                      |view| view_size(view),
+//!                                                      &mut state.stack,
+//!                                                      &mut state.history) {
+//!     // Move and resize the view to `area`, then give it keyboard focus.
+//! }
+//! ```
+//!
+//! While a view is hidden, the compositor should not send it frame events or
+//! count it as visible on any output, e.g for `wm::occlusion`.
+
+use {area::{Area, Origin, Size},
+     wm::{history::{Alive, History},
+          stacking::{Layer, Stack}}};
+
+#[derive(Debug, Clone)]
+struct Entry<T> {
+    view: T,
+    /// The size the view had when it was hidden last.
+    size: Size,
+    /// The layer to put it back in.
+    layer: Layer,
+    hidden: bool
+}
+
+/// The views moved to the scratchpad, hidden or shown.
+#[derive(Debug, Clone)]
+pub struct Scratchpad<T> {
+    /// Ordered by when they were hidden, least recently first.
+    entries: Vec<Entry<T>>
+}
+
+impl<T> Default for Scratchpad<T> {
+    fn default() -> Self {
+        Scratchpad { entries: Vec::new() }
+    }
+}
+
+impl<T: Clone + PartialEq + Alive> Scratchpad<T> {
+    pub fn new() -> Self {
+        Scratchpad::default()
+    }
+
+    /// Move the view to the scratchpad and hide it. `size` is the size it
+    /// gets when it's shown again.
+    ///
+    /// Returns the view that should get focus instead, if any.
+    pub fn add(&mut self,
+               view: T,
+               size: Size,
+               stack: &mut Stack<T>,
+               history: &mut History<T>)
+               -> Option<T> {
+        let layer = stack.layer(&view).unwrap_or_default();
+        self.entries.retain(|entry| entry.view != view);
+        self.entries.push(Entry { view: view.clone(),
+                                  size,
+                                  layer,
+                                  hidden: false });
+        self.hide(&view, size, stack, history)
+    }
+
+    /// Take the view out of the scratchpad, leaving it where it is.
+    ///
+    /// A hidden view is not shown again by this, use `show` first.
+    pub fn remove(&mut self, view: &T) {
+        self.entries.retain(|entry| entry.view != *view)
+    }
+
+    /// Whether the view was moved to the scratchpad, hidden or not.
+    pub fn contains(&self, view: &T) -> bool {
+        self.entries.iter().any(|entry| entry.view == *view)
+    }
+
+    pub fn is_hidden(&self, view: &T) -> bool {
+        self.entries.iter().any(|entry| entry.view == *view && entry.hidden)
+    }
+
+    /// Get the hidden views, in the order `toggle` shows them.
+    pub fn hidden<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.entries.iter().filter(|entry| entry.hidden).map(|entry| &entry.view)
+    }
+
+    /// Hide a view of the scratchpad that is shown, remembering its size.
+    ///
+    /// Returns the view that should get focus instead, if any.
+    pub fn hide(&mut self,
+                view: &T,
+                size: Size,
+                stack: &mut Stack<T>,
+                history: &mut History<T>)
+                -> Option<T> {
+        let index = self.entries.iter().position(|entry| entry.view == *view)?;
+        let mut entry = self.entries.remove(index);
+        if let Some(layer) = stack.layer(view) {
+            entry.layer = layer;
+        }
+        entry.size = size;
+        entry.hidden = true;
+        // Shown again after the views that were hidden before it.
+        self.entries.push(entry);
+        stack.remove(view);
+        history.remove(view);
+        history.focused().cloned()
+    }
+
+    /// Show a hidden view on top of the output, at the size it was hidden
+    /// with.
+    ///
+    /// Returns where the view should be placed, centered on the output and
+    /// no larger than it. The caller should give it keyboard focus.
+    pub fn show(&mut self,
+                view: &T,
+                output: Area,
+                stack: &mut Stack<T>,
+                history: &mut History<T>)
+                -> Option<Area> {
+        let entry = self.entries
+                        .iter_mut()
+                        .find(|entry| entry.view == *view && entry.hidden)?;
+        entry.hidden = false;
+        stack.insert(view.clone(), entry.layer);
+        history.focus(view.clone());
+        Some(center(entry.size, output))
+    }
+
+    /// Hide the focused view if it's a shown view of the scratchpad, or
+    /// else show the hidden view that was hidden first.
+    ///
+    /// `size` gives the current size of the view that is hidden. Returns
+    /// the view that should be focused, with where it should be placed if
+    /// it was just shown.
+    pub fn toggle<F>(&mut self,
+                     output: Area,
+                     size: F,
+                     stack: &mut Stack<T>,
+                     history: &mut History<T>)
+                     -> Option<(T, Option<Area>)>
+        where F: FnOnce(&T) -> Size
+    {
+        self.prune();
+        let focused = history.focused().cloned();
+        if let Some(focused) = focused {
+            if self.contains(&focused) && !self.is_hidden(&focused) {
+                let size = size(&focused);
+                return self.hide(&focused, size, stack, history).map(|next| (next, None))
+            }
+        }
+        let view = self.hidden().next().cloned()?;
+        let area = self.show(&view, output, stack, history);
+        Some((view, area))
+    }
+
+    /// Forget views that were destroyed.
+    pub fn prune(&mut self) {
+        self.entries.retain(|entry| entry.view.is_alive())
+    }
+}
+
+/// Center a view of the size on the output, shrinking it to fit.
+fn center(size: Size, output: Area) -> Area {
+    let width = size.width.min(output.size.width);
+    let height = size.height.min(output.size.height);
+    let x = output.origin.x + (output.size.width - width) / 2;
+    let y = output.origin.y + (output.size.height - height) / 2;
+    Area::new(Origin::new(x, y), Size::new(width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wm::View;
+
+    const TERMINAL: View = View(1, true);
+    const NOTES: View = View(2, true);
+    const EDITOR: View = View(3, true);
+
+    fn output() -> Area {
+        Area::new(Origin::new(100, 0), Size::new(1000, 800))
+    }
+
+    /// The editor is focused, with the notes focused before it.
+    fn views() -> (Stack<View>, History<View>) {
+        let mut stack = Stack::new();
+        let mut history = History::new();
+        stack.insert(EDITOR, Layer::Normal);
+        stack.insert(NOTES, Layer::Top);
+        history.focus(EDITOR);
+        history.focus(NOTES);
+        (stack, history)
+    }
+
+    #[test]
+    fn add_hides_the_view() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        let next = scratchpad.add(NOTES, Size::new(300, 200), &mut stack, &mut history);
+        assert_eq!(next, Some(EDITOR));
+        assert!(scratchpad.contains(&NOTES));
+        assert!(scratchpad.is_hidden(&NOTES));
+        assert!(!stack.contains(&NOTES));
+        assert_eq!(history.focused(), Some(&EDITOR));
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn hide_ignores_views_not_in_the_scratchpad() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        assert_eq!(scratchpad.hide(&EDITOR, Size::new(300, 200), &mut stack, &mut history),
+                   None);
+        assert!(stack.contains(&EDITOR));
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn show_centers_the_view_in_its_layer() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        scratchpad.add(NOTES, Size::new(300, 200), &mut stack, &mut history);
+        assert_eq!(scratchpad.show(&NOTES, output(), &mut stack, &mut history),
+                   Some(Area::new(Origin::new(450, 300), Size::new(300, 200))));
+        assert!(!scratchpad.is_hidden(&NOTES));
+        assert!(scratchpad.contains(&NOTES));
+        assert_eq!(stack.layer(&NOTES), Some(Layer::Top));
+        assert_eq!(history.focused(), Some(&NOTES));
+        // Already shown.
+        assert_eq!(scratchpad.show(&NOTES, output(), &mut stack, &mut history), None);
+    }
+
+    #[test]
+    fn show_shrinks_the_view_to_the_output() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        scratchpad.add(NOTES, Size::new(2000, 100), &mut stack, &mut history);
+        assert_eq!(scratchpad.show(&NOTES, output(), &mut stack, &mut history),
+                   Some(Area::new(Origin::new(100, 350), Size::new(1000, 100))));
+    }
+
+    #[test]
+    fn toggle_shows_the_views_in_the_order_they_were_hidden() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        scratchpad.add(TERMINAL, Size::new(600, 400), &mut stack, &mut history);
+        scratchpad.add(NOTES, Size::new(300, 200), &mut stack, &mut history);
+        assert_eq!(scratchpad.hidden().collect::<Vec<_>>(), vec![&TERMINAL, &NOTES]);
+
+        let area = Area::new(Origin::new(300, 200), Size::new(600, 400));
+        assert_eq!(scratchpad.toggle(output(), |_| unreachable!(), &mut stack, &mut history),
+                   Some((TERMINAL, Some(area))));
+        assert_eq!(history.focused(), Some(&TERMINAL));
+        assert_eq!(stack.layer(&TERMINAL), Some(Layer::Normal));
+
+        // The focused view is hidden again at its current size, after the
+        // notes.
+        assert_eq!(scratchpad.toggle(output(),
+                                     |_| Size::new(200, 100),
+                                     &mut stack,
+                                     &mut history),
+                   Some((EDITOR, None)));
+        assert!(!stack.contains(&TERMINAL));
+        assert_eq!(scratchpad.hidden().collect::<Vec<_>>(), vec![&NOTES, &TERMINAL]);
+
+        let area = Area::new(Origin::new(450, 300), Size::new(300, 200));
+        assert_eq!(scratchpad.toggle(output(), |_| unreachable!(), &mut stack, &mut history),
+                   Some((NOTES, Some(area))));
+        scratchpad.hide(&NOTES, Size::new(300, 200), &mut stack, &mut history);
+        let area = Area::new(Origin::new(500, 350), Size::new(200, 100));
+        assert_eq!(scratchpad.toggle(output(), |_| unreachable!(), &mut stack, &mut history),
+                   Some((TERMINAL, Some(area))));
+    }
+
+    #[test]
+    fn toggle_leaves_other_focused_views_alone() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        assert_eq!(scratchpad.toggle(output(), |_| unreachable!(), &mut stack, &mut history),
+                   None);
+        assert!(stack.contains(&NOTES));
+        assert_eq!(history.focused(), Some(&NOTES));
+    }
+
+    #[test]
+    fn destroyed_views_are_forgotten() {
+        let (mut stack, mut history) = views();
+        let mut scratchpad = Scratchpad::new();
+        scratchpad.add(View(4, false), Size::new(300, 200), &mut stack, &mut history);
+        scratchpad.add(TERMINAL, Size::new(600, 400), &mut stack, &mut history);
+        scratchpad.prune();
+        assert!(!scratchpad.contains(&View(4, false)));
+        assert_eq!(scratchpad.hidden().collect::<Vec<_>>(), vec![&TERMINAL]);
+    }
+}
@@ -0,0 +1,275 @@
+//! The sizes a client accepts, to keep resizes within them.
+//!
+//! Clients tell the compositor how small and how large their windows may
+//! get, and X11 clients may also ask for a fixed aspect ratio or for sizes
+//! in steps, e.g terminals in whole character cells. A configure outside of
+//! those limits is either ignored or answered with a size the compositor
+//! didn't expect, so clamp the size first:
+//!
+//! ```rust,no_run,ignore
+//! // When tiling an XDG toplevel.
+//! let size = SizeLimits::from(&*toplevel).clamp(tile.size);
+//! toplevel.set_size(size.width as u32, size.height as u32);
+//! // On every motion of an interactive resize of an Xwayland surface.
+//! let area = SizeLimits::from(surface.size_hints()).resize(start, edges, wanted);
+//! surface.configure(area.origin.x as i16, area.origin.y as i16,
+//!                   area.size.width as u16, area.size.height as u16);
+//! ```
+//!
+//! The limits change whenever the client commits new ones, so look them up
+//! again for every resize instead of keeping them around.
+
+use area::{Area, Size};
+use shell::{xdg_shell, xdg_shell_v6};
+use utils::edges::Edges;
+use xwayland;
+
+/// The program specified a minimum size (`PMinSize` of `WM_NORMAL_HINTS`).
+const P_MIN_SIZE: u32 = 1 << 4;
+/// The program specified a maximum size.
+const P_MAX_SIZE: u32 = 1 << 5;
+/// The program specified resize increments.
+const P_RESIZE_INC: u32 = 1 << 6;
+/// The program specified a range of aspect ratios.
+const P_ASPECT: u32 = 1 << 7;
+/// The program specified a base size for the increments.
+const P_BASE_SIZE: u32 = 1 << 8;
+
+/// The limits a client puts on the size of a view.
+///
+/// Dimensions of zero are unbounded, e.g a maximum width of zero lets the
+/// view get as wide as it likes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SizeLimits {
+    pub min: Size,
+    pub max: Size,
+    /// The smallest and the largest ratio of width to height.
+    pub aspect: Option<(f64, f64)>,
+    /// The size the increments are counted from.
+    pub base: Size,
+    /// The steps the size grows and shrinks in.
+    pub increment: Size
+}
+
+impl SizeLimits {
+    /// Limits of just a minimum and a maximum size.
+    pub fn new(min: Size, max: Size) -> Self {
+        SizeLimits { min,
+                     max,
+                     ..SizeLimits::default() }
+    }
+
+    /// Keep the ratio of width to height between `min` and `max`, e.g
+    /// `16.0 / 9.0` for both to only allow wide screen sizes.
+    pub fn with_aspect(mut self, min: f64, max: f64) -> Self {
+        self.aspect = if min > 0.0 && max >= min { Some((min, max)) } else { None };
+        self
+    }
+
+    /// Only allow sizes of `base` plus a multiple of `increment`.
+    pub fn with_increment(mut self, base: Size, increment: Size) -> Self {
+        self.base = base;
+        self.increment = increment;
+        self
+    }
+
+    /// Whether the client wants the view to always have the same size, e.g
+    /// a dialog, which is usually better floated than tiled.
+    pub fn is_fixed(&self) -> bool {
+        self.min.width > 0 && self.min.height > 0 && self.min == self.max
+    }
+
+    /// Get the size closest to `size` that the client accepts.
+    ///
+    /// The minimum and maximum win over the aspect ratio and the
+    /// increments if they can't all be met. A dimension of zero is left
+    /// alone, since it lets the client pick the size itself.
+    pub fn clamp(&self, size: Size) -> Size {
+        let Size { mut width, mut height } = size;
+        if let Some((min_aspect, max_aspect)) = self.aspect {
+            if width > 0 && height > 0 {
+                let ratio = f64::from(width) / f64::from(height);
+                if ratio < min_aspect {
+                    height = (f64::from(width) / min_aspect).round() as i32;
+                } else if ratio > max_aspect {
+                    width = (f64::from(height) * max_aspect).round() as i32;
+                }
+            }
+        }
+        width = step(width, self.base.width, self.increment.width);
+        height = step(height, self.base.height, self.increment.height);
+        Size::new(limit(width, self.min.width, self.max.width),
+                  limit(height, self.min.height, self.max.height))
+    }
+
+    /// Resize `area` to the size closest to `size` that the client accepts,
+    /// keeping the edges opposite of `edges` where they are.
+    ///
+    /// This is what an interactive resize from `edges` needs, so the view
+    /// doesn't move when it can't shrink any further.
+    pub fn resize(&self, area: Area, edges: Edges, size: Size) -> Area {
+        let size = self.clamp(size);
+        let mut origin = area.origin;
+        if edges.contains(Edges::WLR_EDGE_LEFT) {
+            origin.x += area.size.width - size.width;
+        }
+        if edges.contains(Edges::WLR_EDGE_TOP) {
+            origin.y += area.size.height - size.height;
+        }
+        Area::new(origin, size)
+    }
+}
+
+/// Round `value` down to `base` plus a multiple of `increment`.
+fn step(value: i32, base: i32, increment: i32) -> i32 {
+    if value <= 0 || increment <= 0 || value < base {
+        return value
+    }
+    value - (value - base) % increment
+}
+
+/// Keep `value` between `min` and `max`, where zero is unbounded.
+fn limit(value: i32, min: i32, max: i32) -> i32 {
+    if value <= 0 {
+        return value
+    }
+    let value = if max > 0 { value.min(max) } else { value };
+    value.max(min)
+}
+
+impl<'a> From<&'a xdg_shell::TopLevel> for SizeLimits {
+    fn from(toplevel: &'a xdg_shell::TopLevel) -> Self {
+        SizeLimits::new(toplevel.min_size(), toplevel.max_size())
+    }
+}
+
+impl<'a> From<&'a xdg_shell_v6::TopLevel> for SizeLimits {
+    fn from(toplevel: &'a xdg_shell_v6::TopLevel) -> Self {
+        SizeLimits::new(toplevel.min_size(), toplevel.max_size())
+    }
+}
+
+impl<'surface> From<xwayland::surface::SizeHints<'surface>> for SizeLimits {
+    fn from(hints: xwayland::surface::SizeHints<'surface>) -> Self {
+        let flags = hints.flags();
+        let size = |(width, height): (i32, i32)| Size::new(width.max(0), height.max(0));
+        let min = if flags & P_MIN_SIZE != 0 {
+            size(hints.min_dimensions())
+        } else {
+            Size::default()
+        };
+        let max = if flags & P_MAX_SIZE != 0 {
+            size(hints.max_dimensions())
+        } else {
+            Size::default()
+        };
+        let mut limits = SizeLimits::new(min, max);
+        if flags & P_ASPECT != 0 && hints.min_aspect_den() > 0 && hints.max_aspect_den() > 0 {
+            let ratio = |num: i32, den: i32| f64::from(num) / f64::from(den);
+            limits = limits.with_aspect(ratio(hints.min_aspect_num(), hints.min_aspect_den()),
+                                        ratio(hints.max_aspect_num(), hints.max_aspect_den()));
+        }
+        if flags & P_RESIZE_INC != 0 {
+            // Without a base size, ICCCM counts the increments from the
+            // minimum size.
+            let base = if flags & P_BASE_SIZE != 0 { size(hints.base_dimensions()) } else { min };
+            limits = limits.with_increment(base, size(hints.inc_dimensions()));
+        }
+        limits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use area::Origin;
+
+    use super::*;
+
+    #[test]
+    fn clamp_keeps_between_min_and_max() {
+        let limits = SizeLimits::new(Size::new(200, 100), Size::new(800, 600));
+        assert_eq!(limits.clamp(Size::new(500, 400)), Size::new(500, 400));
+        assert_eq!(limits.clamp(Size::new(50, 50)), Size::new(200, 100));
+        assert_eq!(limits.clamp(Size::new(1000, 1000)), Size::new(800, 600));
+    }
+
+    #[test]
+    fn zero_is_unbounded() {
+        let limits = SizeLimits::new(Size::new(0, 100), Size::new(800, 0));
+        assert_eq!(limits.clamp(Size::new(10, 5000)), Size::new(10, 5000));
+        assert_eq!(limits.clamp(Size::new(5000, 10)), Size::new(800, 100));
+        // A size of zero lets the client pick, so it's left alone.
+        assert_eq!(limits.clamp(Size::new(0, 0)), Size::new(0, 0));
+        assert_eq!(SizeLimits::default().clamp(Size::new(123, 45)), Size::new(123, 45));
+    }
+
+    #[test]
+    fn clamp_keeps_the_aspect_ratio() {
+        let limits = SizeLimits::default().with_aspect(1.0, 2.0);
+        assert_eq!(limits.clamp(Size::new(300, 200)), Size::new(300, 200));
+        // Too tall loses height, too wide loses width.
+        assert_eq!(limits.clamp(Size::new(300, 600)), Size::new(300, 300));
+        assert_eq!(limits.clamp(Size::new(1000, 100)), Size::new(200, 100));
+        let wide = SizeLimits::default().with_aspect(16.0 / 9.0, 16.0 / 9.0);
+        assert_eq!(wide.clamp(Size::new(1600, 1600)), Size::new(1600, 900));
+        assert_eq!(wide.clamp(Size::new(1000, 1000)), Size::new(1000, 563));
+        assert_eq!(wide.clamp(Size::new(1000, 0)), Size::new(1000, 0));
+    }
+
+    #[test]
+    fn invalid_aspect_ratios_are_ignored() {
+        assert_eq!(SizeLimits::default().with_aspect(0.0, 2.0).aspect, None);
+        assert_eq!(SizeLimits::default().with_aspect(2.0, 1.0).aspect, None);
+        assert_eq!(SizeLimits::default().with_aspect(1.0, 1.0).aspect, Some((1.0, 1.0)));
+    }
+
+    #[test]
+    fn clamp_steps_in_increments() {
+        // A terminal of 8x16 cells with a 4 pixel border.
+        let limits = SizeLimits::default().with_increment(Size::new(4, 4), Size::new(8, 16));
+        assert_eq!(limits.clamp(Size::new(804, 404)), Size::new(804, 404));
+        assert_eq!(limits.clamp(Size::new(811, 419)), Size::new(804, 404));
+        assert_eq!(limits.clamp(Size::new(812, 420)), Size::new(812, 420));
+        // Below the base size there is nothing to step from.
+        assert_eq!(limits.clamp(Size::new(3, 2)), Size::new(3, 2));
+        let width_only = SizeLimits::default().with_increment(Size::default(), Size::new(10, 0));
+        assert_eq!(width_only.clamp(Size::new(95, 95)), Size::new(90, 95));
+    }
+
+    #[test]
+    fn min_and_max_win_over_aspect_and_increments() {
+        let limits = SizeLimits::new(Size::new(100, 100), Size::new(500, 500))
+            .with_aspect(2.0, 2.0)
+            .with_increment(Size::default(), Size::new(30, 30));
+        // 2:1 would be 400x200, stepped to 390x180.
+        assert_eq!(limits.clamp(Size::new(400, 400)), Size::new(390, 180));
+        // 2:1 would be 100x50, but the minimum height is 100.
+        assert_eq!(limits.clamp(Size::new(100, 100)), Size::new(100, 100));
+        // 2:1 would be 1000x500, stepped to 990x480, but at most 500 wide.
+        assert_eq!(limits.clamp(Size::new(1000, 1000)), Size::new(500, 480));
+    }
+
+    #[test]
+    fn is_fixed() {
+        let dialog = SizeLimits::new(Size::new(400, 300), Size::new(400, 300));
+        assert!(dialog.is_fixed());
+        assert!(!SizeLimits::new(Size::new(400, 300), Size::new(800, 600)).is_fixed());
+        assert!(!SizeLimits::new(Size::new(0, 300), Size::new(0, 300)).is_fixed());
+        assert!(!SizeLimits::default().is_fixed());
+    }
+
+    #[test]
+    fn resize_keeps_the_opposite_edges() {
+        let limits = SizeLimits::new(Size::new(200, 100), Size::default());
+        let area = Area::new(Origin::new(100, 100), Size::new(400, 300));
+        let shrunk = Size::new(50, 50);
+        let bottom_right = Edges::WLR_EDGE_BOTTOM | Edges::WLR_EDGE_RIGHT;
+        assert_eq!(limits.resize(area, bottom_right, shrunk),
+                   Area::new(Origin::new(100, 100), Size::new(200, 100)));
+        let top_left = Edges::WLR_EDGE_TOP | Edges::WLR_EDGE_LEFT;
+        assert_eq!(limits.resize(area, top_left, shrunk),
+                   Area::new(Origin::new(300, 300), Size::new(200, 100)));
+        assert_eq!(limits.resize(area, Edges::WLR_EDGE_LEFT, Size::new(500, 300)),
+                   Area::new(Origin::new(0, 100), Size::new(500, 300)));
+    }
+}
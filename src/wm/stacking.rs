@@ -0,0 +1,351 @@
+//! Stacking (z-order) of views.
+//!
+//! Views are kept in a `Stack` from bottom to top. The render loop should
+//! draw them with `Stack::iter` and hit-testing should walk them with
+//! `Stack::iter_top_down`, so both always agree on what is on top.
+
+use std::cmp;
+
+/// The category a view is stacked in.
+///
+/// Views in a higher layer are always above views in a lower layer,
+/// regardless of how they are raised or lowered.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Layer {
+    Background,
+    Bottom,
+    Normal,
+    Top,
+    /// Fullscreen views, which cover panels but not overlays such as lock
+    /// screens.
+    Fullscreen,
+    Overlay
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Layer::Normal
+    }
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    view: T,
+    layer: Layer,
+    always_on_top: bool,
+    /// The view this one is transient for (e.g the parent of a dialog).
+    parent: Option<T>
+}
+
+/// The stacking order of a set of views.
+#[derive(Debug)]
+pub struct Stack<T> {
+    /// Ordered from bottom to top.
+    entries: Vec<Entry<T>>
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Stack { entries: Vec::new() }
+    }
+}
+
+impl<T: Clone + PartialEq> Stack<T> {
+    pub fn new() -> Self {
+        Stack::default()
+    }
+
+    /// Add a view on top of the other views in its layer.
+    ///
+    /// If the view is already in the stack it is moved to the layer and raised.
+    pub fn insert(&mut self, view: T, layer: Layer) {
+        match self.position(&view) {
+            Some(index) => self.entries[index].layer = layer,
+            None => {
+                self.entries.push(Entry { view: view.clone(),
+                                          layer,
+                                          always_on_top: false,
+                                          parent: None })
+            }
+        }
+        self.raise(&view);
+    }
+
+    /// Remove a view from the stack.
+    ///
+    /// Views that were transient for it are no longer transient for anything.
+    pub fn remove(&mut self, view: &T) {
+        self.entries.retain(|entry| entry.view != *view);
+        for entry in &mut self.entries {
+            if entry.parent.as_ref() == Some(view) {
+                entry.parent = None;
+            }
+        }
+    }
+
+    /// Determines if the view is in the stack.
+    pub fn contains(&self, view: &T) -> bool {
+        self.position(view).is_some()
+    }
+
+    /// The number of views in the stack.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Move the view to the top of its layer.
+    ///
+    /// The views that are transient for it are raised along with it, so they
+    /// stay above it.
+    pub fn raise(&mut self, view: &T) {
+        let family = self.take_family(view);
+        self.entries.extend(family);
+        self.restack();
+    }
+
+    /// Move the view to the bottom of its layer.
+    ///
+    /// The views that are transient for it are lowered along with it, but
+    /// stay above it.
+    pub fn lower(&mut self, view: &T) {
+        let family = self.take_family(view);
+        let rest = ::std::mem::replace(&mut self.entries, family);
+        self.entries.extend(rest);
+        self.restack();
+    }
+
+    /// Get the layer a view was put in.
+    pub fn layer(&self, view: &T) -> Option<Layer> {
+        self.position(view).map(|index| self.entries[index].layer)
+    }
+
+    /// Move the view to another layer, on top of the views already there.
+    pub fn set_layer(&mut self, view: &T, layer: Layer) {
+        if let Some(index) = self.position(view) {
+            self.entries[index].layer = layer;
+            self.raise(view);
+        }
+    }
+
+    /// Whether the view is kept above the other views in its layer.
+    pub fn always_on_top(&self, view: &T) -> bool {
+        self.position(view).map(|index| self.entries[index].always_on_top)
+            .unwrap_or(false)
+    }
+
+    /// Keep the view above the other views in its layer, even when they are
+    /// raised.
+    pub fn set_always_on_top(&mut self, view: &T, always_on_top: bool) {
+        if let Some(index) = self.position(view) {
+            self.entries[index].always_on_top = always_on_top;
+            self.raise(view);
+        }
+    }
+
+    /// Get the view that this view is transient for.
+    pub fn transient_for(&self, view: &T) -> Option<&T> {
+        self.position(view).and_then(|index| self.entries[index].parent.as_ref())
+    }
+
+    /// Mark the view as transient for `parent`, e.g because it's a dialog.
+    ///
+    /// A transient view is always stacked above its parent and moves with it
+    /// when the parent is raised or lowered.
+    ///
+    /// Setting a parent that would create a cycle is ignored.
+    pub fn set_transient_for(&mut self, view: &T, parent: Option<T>) {
+        if let Some(ref parent) = parent {
+            if parent == view || self.is_ancestor(view, parent) {
+                wlr_log!(WLR_ERROR, "Ignoring transient parent that would create a cycle");
+                return
+            }
+        }
+        if let Some(index) = self.position(view) {
+            self.entries[index].parent = parent;
+            self.raise(view);
+        }
+    }
+
+    /// The topmost view, if there is one.
+    pub fn top(&self) -> Option<&T> {
+        self.entries.last().map(|entry| &entry.view)
+    }
+
+    /// Iterate over the views from bottom to top, the order they should be
+    /// rendered in.
+    pub fn iter<'a>(&'a self) -> impl DoubleEndedIterator<Item = &'a T> + 'a {
+        self.entries.iter().map(|entry| &entry.view)
+    }
+
+    /// Iterate over the views from top to bottom, the order they should be
+    /// hit-tested in.
+    pub fn iter_top_down<'a>(&'a self) -> impl Iterator<Item = &'a T> + 'a {
+        self.iter().rev()
+    }
+
+    /// Iterate over the views in a single layer from bottom to top.
+    pub fn iter_layer<'a>(&'a self, layer: Layer) -> impl Iterator<Item = &'a T> + 'a {
+        self.entries.iter()
+            .filter(move |entry| entry.layer == layer)
+            .map(|entry| &entry.view)
+    }
+
+    fn position(&self, view: &T) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.view == *view)
+    }
+
+    /// Determines if `ancestor` is somewhere up the transient chain of `view`.
+    fn is_ancestor(&self, ancestor: &T, view: &T) -> bool {
+        let mut current = self.transient_for(view).cloned();
+        // Bounded by the length in case a cycle slipped in somehow.
+        for _ in 0..self.entries.len() {
+            match current {
+                Some(ref parent) if parent == ancestor => return true,
+                Some(parent) => current = self.transient_for(&parent).cloned(),
+                None => return false
+            }
+        }
+        false
+    }
+
+    /// Remove the view and everything transient for it, keeping their
+    /// relative order.
+    fn take_family(&mut self, view: &T) -> Vec<Entry<T>> {
+        if !self.contains(view) {
+            return Vec::new()
+        }
+        let in_family: Vec<bool> =
+            self.entries.iter()
+                .map(|entry| entry.view == *view || self.is_ancestor(view, &entry.view))
+                .collect();
+        let mut family = Vec::new();
+        let mut rest = Vec::with_capacity(self.entries.len());
+        for (entry, in_family) in self.entries.drain(..).zip(in_family) {
+            if in_family {
+                family.push(entry)
+            } else {
+                rest.push(entry)
+            }
+        }
+        self.entries = rest;
+        // The view itself goes first so the views transient for it stay above it.
+        if let Some(index) = family.iter().position(|entry| entry.view == *view) {
+            let entry = family.remove(index);
+            family.insert(0, entry);
+        }
+        family
+    }
+
+    /// The band a view is sorted into. Transient views are never in a lower
+    /// band than their parent.
+    fn band(&self, index: usize) -> (Layer, bool) {
+        let entry = &self.entries[index];
+        let mut band = (entry.layer, entry.always_on_top);
+        let mut current = entry.parent.clone();
+        for _ in 0..self.entries.len() {
+            match current.and_then(|parent| self.position(&parent)) {
+                Some(parent) => {
+                    let parent = &self.entries[parent];
+                    band = cmp::max(band, (parent.layer, parent.always_on_top));
+                    current = parent.parent.clone();
+                }
+                None => break
+            }
+        }
+        band
+    }
+
+    /// Stable sort the entries by their band, keeping the relative order
+    /// inside of each band.
+    fn restack(&mut self) {
+        let bands: Vec<_> = (0..self.entries.len()).map(|index| self.band(index)).collect();
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by_key(|&index| bands[index]);
+        let mut entries: Vec<Option<Entry<T>>> = self.entries.drain(..).map(Some).collect();
+        self.entries = order.into_iter()
+                            .map(|index| entries[index].take().unwrap())
+                            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(stack: &Stack<u32>) -> Vec<u32> {
+        stack.iter().cloned().collect()
+    }
+
+    #[test]
+    fn layers_stay_in_order() {
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Top);
+        stack.insert(2, Layer::Normal);
+        stack.insert(3, Layer::Background);
+        stack.insert(4, Layer::Normal);
+        assert_eq!(order(&stack), vec![3, 2, 4, 1]);
+        stack.raise(&2);
+        assert_eq!(order(&stack), vec![3, 4, 2, 1]);
+        stack.lower(&2);
+        assert_eq!(order(&stack), vec![3, 2, 4, 1]);
+        stack.set_layer(&3, Layer::Overlay);
+        assert_eq!(order(&stack), vec![2, 4, 1, 3]);
+        assert_eq!(stack.top(), Some(&3));
+        assert_eq!(stack.iter_top_down().cloned().collect::<Vec<_>>(), vec![3, 1, 4, 2]);
+        assert_eq!(stack.iter_layer(Layer::Normal).cloned().collect::<Vec<_>>(), vec![2, 4]);
+    }
+
+    #[test]
+    fn always_on_top_stays_above_raised_views() {
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Normal);
+        stack.insert(2, Layer::Normal);
+        stack.set_always_on_top(&1, true);
+        stack.raise(&2);
+        assert_eq!(order(&stack), vec![2, 1]);
+        // But not above a higher layer.
+        stack.insert(3, Layer::Top);
+        assert_eq!(order(&stack), vec![2, 1, 3]);
+        stack.set_always_on_top(&1, false);
+        stack.raise(&2);
+        assert_eq!(order(&stack), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn transient_views_move_with_their_parent() {
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Normal);
+        stack.insert(2, Layer::Normal);
+        stack.insert(3, Layer::Normal);
+        stack.set_transient_for(&3, Some(1));
+        stack.raise(&2);
+        assert_eq!(order(&stack), vec![1, 3, 2]);
+        stack.raise(&1);
+        assert_eq!(order(&stack), vec![2, 1, 3]);
+        stack.lower(&1);
+        assert_eq!(order(&stack), vec![1, 3, 2]);
+        // Transient views are never below the layer of their parent.
+        stack.set_layer(&1, Layer::Top);
+        assert_eq!(order(&stack), vec![2, 1, 3]);
+        stack.remove(&1);
+        assert_eq!(stack.transient_for(&3), None);
+        assert_eq!(order(&stack), vec![2, 3]);
+    }
+
+    #[test]
+    fn transient_cycles_are_ignored() {
+        let mut stack = Stack::new();
+        stack.insert(1, Layer::Normal);
+        stack.insert(2, Layer::Normal);
+        stack.set_transient_for(&2, Some(1));
+        stack.set_transient_for(&1, Some(2));
+        stack.set_transient_for(&1, Some(1));
+        assert_eq!(stack.transient_for(&1), None);
+        assert_eq!(stack.transient_for(&2), Some(&1));
+    }
+}
@@ -0,0 +1,326 @@
+//! An alt-tab style window switcher.
+//!
+//! `Switcher` implements the interaction: holding the modifier and pressing
+//! the trigger key opens it on the previously focused view, every further
+//! press moves to the next view (the previous one with shift held), escape
+//! closes it and releasing the modifier focuses the selected view. The views
+//! are offered in most recently used order, from a `history::History`.
+//!
+//! While it's open the switcher grabs the keyboard of its seat, so clients
+//! don't see the keys and keyboard focus stays where it is until the
+//! selection is committed.
+//!
+//! What it looks like is up to you. Set a `Style`, which is asked to draw
+//! the switcher whenever `Switcher::render` is called while it's open. It's
+//! given a thumbnail of every view that `set_thumbnails` can map to a
+//! `capture::Window`, captured when the switcher is first drawn.
+//!
+//! ```rust,no_run,ignore
+//! // In the keyboard handler.
+//! fn on_key(&mut self, compositor: compositor::Handle, keyboard: keyboard::Handle, key: &KeyEvent) {
+//!     let modifiers = keyboard.run(|keyboard| keyboard.get_modifiers()).unwrap();
+//!     for keysym in key.pressed_keys() {
+//!         match state.switcher.key(&state.history, keysym, key.key_state(), modifiers) {
+//!             Response::Pass => { /* Send the key to the focused client. */ },
+//!             Response::Consumed | Response::Selected(_) => schedule_frames(),
+//!             Response::Cancelled => schedule_frames(),
+//!             Response::Commit(view) => focus(view)
+//!         }
+//!     }
+//! }
+//!
+//! fn modifiers(&mut self, compositor: compositor::Handle, keyboard: keyboard::Handle) {
+//!     let modifiers = keyboard.run(|keyboard| keyboard.get_modifiers()).unwrap();
+//!     if let Response::Commit(view) = state.switcher.modifiers(modifiers) {
+//!         focus(view)
+//!     }
+//! }
+//! ```
+
+use std::mem;
+
+use wlroots_sys::{wlr_keyboard_grab_interface, wlr_keyboard_modifiers, wlr_seat_keyboard_end_grab,
+                  wlr_seat_keyboard_grab, wlr_seat_keyboard_start_grab, wlr_surface,
+                  wlr_key_state::{self, WLR_KEY_PRESSED}};
+use xkbcommon::xkb::keysyms;
+
+use {capture::{self, WindowCapture},
+     input::keyboard::{Key, Modifier},
+     render::Renderer,
+     seat,
+     wm::history::{Alive, History}};
+
+/// Draws the switcher while it's open.
+pub trait Style<T> {
+    /// `views` are the views that can be switched to, most recently used
+    /// first, and `selected` is the index of the one that will be focused.
+    ///
+    /// `thumbnails` has an entry for every view, which is `None` if the view
+    /// couldn't be captured. Turn them into textures with
+    /// `Renderer::create_texture_from_pixels` to draw them.
+    fn draw(&mut self,
+            renderer: &mut Renderer,
+            views: &[T],
+            thumbnails: &[Option<WindowCapture>],
+            selected: usize);
+}
+
+/// What the compositor should do with a key, or because of it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Response<T> {
+    /// The key has nothing to do with the switcher, handle it like usual.
+    Pass,
+    /// The switcher used the key, don't send it to the client.
+    Consumed,
+    /// The switcher opened or moved to another view. Don't send the key to
+    /// the client, and redraw the switcher.
+    Selected(T),
+    /// The switcher was closed without switching. Don't send the key to the
+    /// client, and redraw.
+    Cancelled,
+    /// The switcher was closed, focus the view.
+    ///
+    /// The key or modifier change that caused this should still be sent to
+    /// the client, which also saw the modifier being pressed.
+    Commit(T)
+}
+
+struct Active<T> {
+    views: Vec<T>,
+    /// Empty until the switcher is first drawn.
+    thumbnails: Vec<Option<WindowCapture>>,
+    selected: usize,
+    /// Ends the grab when the switcher closes.
+    _grab: Option<KeyboardGrab>
+}
+
+/// The state of an alt-tab style window switcher.
+pub struct Switcher<T> {
+    seat: seat::Handle,
+    modifier: Modifier,
+    trigger: Key,
+    active: Option<Active<T>>,
+    style: Option<Box<Style<T>>>,
+    window: Option<Box<FnMut(&T) -> Option<capture::Window>>>
+}
+
+impl<T: Clone + PartialEq + Alive> Switcher<T> {
+    /// Make a switcher for `seat` that opens when `trigger` is pressed while
+    /// `modifier` is held, e.g `Modifier::WLR_MODIFIER_ALT` and
+    /// `keysyms::KEY_Tab`.
+    pub fn new(seat: seat::Handle, modifier: Modifier, trigger: Key) -> Self {
+        Switcher { seat,
+                   modifier,
+                   trigger,
+                   active: None,
+                   style: None,
+                   window: None }
+    }
+
+    /// Set how the switcher is drawn, replacing the previous style.
+    pub fn set_style<S: Style<T> + 'static>(&mut self, style: S) {
+        self.style = Some(Box::new(style))
+    }
+
+    /// Set how to find the window of a view, to capture its thumbnail.
+    ///
+    /// Without it, or for views it returns `None` for, the `Style` gets no
+    /// thumbnail.
+    pub fn set_thumbnails<F>(&mut self, window: F)
+        where F: FnMut(&T) -> Option<capture::Window> + 'static
+    {
+        self.window = Some(Box::new(window))
+    }
+
+    /// Whether the switcher is open.
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// The view that will be focused when the switcher is closed.
+    pub fn selected(&self) -> Option<&T> {
+        self.active.as_ref().and_then(|active| active.views.get(active.selected))
+    }
+
+    /// Feed a key event through the switcher.
+    ///
+    /// `modifiers` are the modifiers of the keyboard the key came from.
+    pub fn key(&mut self,
+               history: &History<T>,
+               keysym: Key,
+               state: wlr_key_state,
+               modifiers: Modifier)
+               -> Response<T> {
+        let pressed = state == WLR_KEY_PRESSED;
+        if self.active.is_none() {
+            if !pressed || keysym != self.trigger || !modifiers.contains(self.modifier) {
+                return Response::Pass
+            }
+            let views: Vec<T> = history.iter().cloned().collect();
+            if views.is_empty() {
+                return Response::Pass
+            }
+            let selected = if views.len() > 1 { 1 } else { 0 };
+            let view = views[selected].clone();
+            let grab = KeyboardGrab::start(&self.seat);
+            self.active = Some(Active { views,
+                                        thumbnails: Vec::new(),
+                                        selected,
+                                        _grab: grab });
+            return Response::Selected(view)
+        }
+        if !pressed {
+            return Response::Consumed
+        }
+        if keysym == keysyms::KEY_Escape {
+            self.active = None;
+            return Response::Cancelled
+        }
+        let reverse = modifiers.contains(Modifier::WLR_MODIFIER_SHIFT);
+        if keysym == self.trigger || keysym == keysyms::KEY_ISO_Left_Tab {
+            if let Some(view) = self.cycle(reverse) {
+                return Response::Selected(view)
+            }
+        }
+        Response::Consumed
+    }
+
+    /// Tell the switcher the modifiers changed. Releasing the modifier closes
+    /// the switcher and commits the selection.
+    ///
+    /// The keyboard grab has ended by the time `Response::Commit` is
+    /// returned, so the view can be focused right away.
+    pub fn modifiers(&mut self, modifiers: Modifier) -> Response<T> {
+        if self.active.is_none() || modifiers.contains(self.modifier) {
+            return Response::Pass
+        }
+        let active = self.active.take().unwrap();
+        match active.views.into_iter().nth(active.selected) {
+            Some(ref view) if view.is_alive() => Response::Commit(view.clone()),
+            _ => Response::Cancelled
+        }
+    }
+
+    /// Close the switcher without switching, e.g because the keyboard lost
+    /// focus.
+    pub fn cancel(&mut self) {
+        self.active = None
+    }
+
+    /// Draw the switcher with the `Style`, if it's open.
+    ///
+    /// Returns whether anything was drawn.
+    pub fn render(&mut self, renderer: &mut Renderer) -> bool {
+        let active = match self.active.as_mut() {
+            Some(active) => active,
+            None => return false
+        };
+        if active.thumbnails.len() != active.views.len() {
+            let window = &mut self.window;
+            active.thumbnails =
+                active.views
+                      .iter()
+                      .map(|view| {
+                               let window = match window.as_mut() {
+                                   Some(window) => window(view),
+                                   None => None
+                               };
+                               window.and_then(|window| capture::capture_view(renderer, &window))
+                           })
+                      .collect();
+        }
+        let mut index = 0;
+        while index < active.views.len() {
+            if active.views[index].is_alive() {
+                index += 1;
+            } else {
+                active.views.remove(index);
+                active.thumbnails.remove(index);
+            }
+        }
+        if active.views.is_empty() {
+            return false
+        }
+        active.selected = active.selected.min(active.views.len() - 1);
+        match self.style.as_mut() {
+            Some(style) => {
+                style.draw(renderer, &active.views, &active.thumbnails, active.selected);
+                true
+            }
+            None => false
+        }
+    }
+
+    fn cycle(&mut self, reverse: bool) -> Option<T> {
+        let active = self.active.as_mut()?;
+        let len = active.views.len();
+        if len == 0 {
+            return None
+        }
+        active.selected = if reverse {
+            (active.selected + len - 1) % len
+        } else {
+            (active.selected + 1) % len
+        };
+        active.views.get(active.selected).cloned()
+    }
+}
+
+/// Keeps the keys from the clients while the switcher is open.
+struct KeyboardGrab {
+    seat: seat::Handle,
+    grab: Box<wlr_seat_keyboard_grab>
+}
+
+static SWITCHER_GRAB: wlr_keyboard_grab_interface =
+    wlr_keyboard_grab_interface { enter: Some(grab_enter),
+                                  key: Some(grab_key),
+                                  modifiers: Some(grab_modifiers),
+                                  cancel: Some(grab_cancel) };
+
+impl KeyboardGrab {
+    fn start(seat: &seat::Handle) -> Option<Self> {
+        if !seat.is_alive() {
+            return None
+        }
+        unsafe {
+            let mut grab: Box<wlr_seat_keyboard_grab> = Box::new(mem::zeroed());
+            grab.interface = &SWITCHER_GRAB;
+            wlr_seat_keyboard_start_grab(seat.as_ptr(), &mut *grab);
+            Some(KeyboardGrab { seat: seat.clone(),
+                                grab })
+        }
+    }
+}
+
+impl Drop for KeyboardGrab {
+    fn drop(&mut self) {
+        if !self.seat.is_alive() {
+            return
+        }
+        unsafe {
+            let seat_ptr = self.seat.as_ptr();
+            // Leave alone a grab that replaced this one.
+            if (*seat_ptr).keyboard_state.grab == &mut *self.grab as *mut _ {
+                wlr_seat_keyboard_end_grab(seat_ptr)
+            }
+        }
+    }
+}
+
+// Keyboard focus doesn't move and clients don't get the keys while the
+// switcher is open. The switcher sees the keys before they get to the seat.
+unsafe extern "C" fn grab_enter(_: *mut wlr_seat_keyboard_grab,
+                                _: *mut wlr_surface,
+                                _: *mut u32,
+                                _: usize,
+                                _: *mut wlr_keyboard_modifiers) {
+}
+
+unsafe extern "C" fn grab_key(_: *mut wlr_seat_keyboard_grab, _: u32, _: u32, _: u32) {}
+
+unsafe extern "C" fn grab_modifiers(_: *mut wlr_seat_keyboard_grab,
+                                    _: *mut wlr_keyboard_modifiers) {
+}
+
+unsafe extern "C" fn grab_cancel(_: *mut wlr_seat_keyboard_grab) {}
@@ -0,0 +1,318 @@
+//! Throttling clients whose views nobody can see.
+//!
+//! A client that keeps animating only redraws when it gets a frame callback.
+//! `FrameThrottle` decides which views should get them: views that are hidden
+//! (e.g because they are on a workspace that isn't shown) and views that are
+//! fully covered by other views are left without frame callbacks, so their
+//! clients go idle until they are visible again.
+//!
+//! ```rust,no_run
+//! # extern crate wlroots;
+//! # use wlroots::{area::{Area, Origin, Size},
+//! #               wm::{occlusion::Shape, stacking::{Layer, Stack}, throttle::FrameThrottle}};
+//! # fn send_frame_done(_view: u32) {}
+//! # fn main() {
+//! let (terminal, browser) = (1u32, 2u32);
+//! let mut stack = Stack::new();
+//! stack.insert(terminal, Layer::Normal);
+//! stack.insert(browser, Layer::Normal);
+//! let screens = [Area::new(Origin::new(0, 0), Size::new(1920, 1080))];
+//! let mut throttle = FrameThrottle::new();
+//! // Whenever the stack or a view's geometry changed. The browser is
+//! // maximized and opaque, so it covers the terminal.
+//! throttle.update(&stack, &screens, |_view| Some(Shape::opaque(screens[0])));
+//! // When sending frame done events.
+//! for view in stack.iter() {
+//!     if throttle.should_send_frame(view) {
+//!         send_frame_done(*view);
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! `CommitLimiter` handles the opposite problem, clients that keep committing
+//! as fast as they can while nobody can see them.
+
+use std::{cmp, time::Duration};
+
+use area::Area;
+use wm::{occlusion::{Shape, Visibility}, stacking::Stack};
+
+/// Decides whether a view gets a frame callback.
+///
+/// Called with the view and whether the throttle would send it one.
+pub type Policy<T> = Box<Fn(&T, bool) -> bool>;
+
+/// Tracks which views should be sent frame callbacks.
+pub struct FrameThrottle<T> {
+    hidden: Vec<T>,
+    suspend_occluded: bool,
+    visibility: Option<Visibility<T>>,
+    policy: Option<Policy<T>>
+}
+
+impl<T: Clone + PartialEq> Default for FrameThrottle<T> {
+    fn default() -> Self {
+        FrameThrottle { hidden: Vec::new(),
+                        suspend_occluded: true,
+                        visibility: None,
+                        policy: None }
+    }
+}
+
+impl<T: Clone + PartialEq> FrameThrottle<T> {
+    pub fn new() -> Self {
+        FrameThrottle::default()
+    }
+
+    /// Mark the view as hidden, e.g because the workspace it's on isn't shown.
+    ///
+    /// Hidden views don't get frame callbacks, regardless of occlusion.
+    pub fn set_hidden(&mut self, view: &T, hidden: bool) {
+        let position = self.hidden.iter().position(|other| other == view);
+        match (hidden, position) {
+            (true, None) => self.hidden.push(view.clone()),
+            (false, Some(index)) => {
+                self.hidden.remove(index);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn is_hidden(&self, view: &T) -> bool {
+        self.hidden.contains(view)
+    }
+
+    /// Whether views covered entirely by other views are suspended.
+    ///
+    /// Defaults to true.
+    pub fn set_suspend_occluded(&mut self, suspend_occluded: bool) {
+        self.suspend_occluded = suspend_occluded
+    }
+
+    /// Override the decision for some views, e.g to keep a video call running
+    /// in the background.
+    pub fn set_policy<F>(&mut self, policy: F)
+        where F: Fn(&T, bool) -> bool + 'static
+    {
+        self.policy = Some(Box::new(policy))
+    }
+
+    pub fn clear_policy(&mut self) {
+        self.policy = None
+    }
+
+    /// Recompute occlusion, see `occlusion::Visibility::compute`.
+    pub fn update<F>(&mut self, stack: &Stack<T>, screens: &[Area], shape: F)
+        where F: FnMut(&T) -> Option<Shape>
+    {
+        self.visibility = Some(Visibility::compute(stack, screens, shape));
+    }
+
+    /// Forget a view, e.g because it was destroyed.
+    ///
+    /// The views it covered stay suspended until the next `update`.
+    pub fn remove(&mut self, view: &T) {
+        self.hidden.retain(|other| other != view);
+        if let Some(visibility) = self.visibility.as_mut() {
+            visibility.remove(view);
+        }
+    }
+
+    /// Whether the view should be sent a frame callback.
+    ///
+    /// Until `update` is called occlusion isn't known and only hidden views
+    /// are suspended.
+    pub fn should_send_frame(&self, view: &T) -> bool {
+        let mut send = !self.is_hidden(view);
+        if send && self.suspend_occluded {
+            if let Some(visibility) = self.visibility.as_ref() {
+                send = visibility.is_visible(view);
+            }
+        }
+        match self.policy {
+            Some(ref policy) => policy(view, send),
+            None => send
+        }
+    }
+}
+
+/// What to do with a commit passed to `CommitLimiter::commit`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Decision {
+    /// Handle the commit like usual.
+    Allow,
+    /// The client is over its limit. Hold back its frame done events and
+    /// configures with `CommitLimiter::defer`.
+    Defer
+}
+
+/// Overrides the limit for a client, in commits per second. `None` doesn't
+/// limit the client at all.
+pub type RatePolicy<K> = Box<Fn(&K) -> Option<u32>>;
+
+struct Bucket<K> {
+    client: K,
+    tokens: f64,
+    last_commit: Duration,
+    deferred: Vec<Box<FnMut()>>
+}
+
+/// Rate limits clients that commit while none of their views can be seen.
+///
+/// Clients are throttled by holding back the events that let them draw the
+/// next frame (frame done events and configures), which a well behaved
+/// client waits for anyway. Visible commits are never limited.
+///
+/// `K` identifies a client, e.g the `wl_client` pointer of the surface.
+pub struct CommitLimiter<K> {
+    rate: u32,
+    burst: u32,
+    buckets: Vec<Bucket<K>>,
+    policy: Option<RatePolicy<K>>
+}
+
+impl<K: Clone + PartialEq> CommitLimiter<K> {
+    /// Allow `rate` offscreen commits per second per client, with bursts of
+    /// up to `burst` commits.
+    pub fn new(rate: u32, burst: u32) -> Self {
+        CommitLimiter { rate,
+                        burst: cmp::max(burst, 1),
+                        buckets: Vec::new(),
+                        policy: None }
+    }
+
+    /// Use a different limit for some clients.
+    pub fn set_policy<F>(&mut self, policy: F)
+        where F: Fn(&K) -> Option<u32> + 'static
+    {
+        self.policy = Some(Box::new(policy))
+    }
+
+    /// Record a commit from `client` at `time` (e.g `utils::current_time()`).
+    pub fn commit(&mut self, client: &K, visible: bool, time: Duration) -> Decision {
+        let rate = match self.policy {
+            Some(ref policy) => policy(client),
+            None => Some(self.rate)
+        };
+        let burst = self.burst as f64;
+        let index = match self.buckets.iter().position(|bucket| bucket.client == *client) {
+            Some(index) => index,
+            None => {
+                self.buckets.push(Bucket { client: client.clone(),
+                                           tokens: burst,
+                                           last_commit: time,
+                                           deferred: Vec::new() });
+                self.buckets.len() - 1
+            }
+        };
+        let bucket = &mut self.buckets[index];
+        let rate = match rate {
+            Some(rate) if !visible => rate as f64,
+            _ => {
+                bucket.tokens = burst;
+                bucket.last_commit = time;
+                return Decision::Allow
+            }
+        };
+        bucket.refill(rate, burst, time);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Decision::Allow
+        } else {
+            wlr_log!(WLR_DEBUG, "Throttling commits of an offscreen client");
+            Decision::Defer
+        }
+    }
+
+    /// Hold back an event for a client, until `release` decides it may be
+    /// sent.
+    pub fn defer<F>(&mut self, client: &K, event: F)
+        where F: FnMut() + 'static
+    {
+        match self.buckets.iter_mut().find(|bucket| bucket.client == *client) {
+            Some(bucket) => bucket.deferred.push(Box::new(event)),
+            None => {
+                let mut event = event;
+                event()
+            }
+        }
+    }
+
+    /// Send the held back events of the clients that are under their limit
+    /// again. Call this periodically, e.g from a timer or every frame.
+    ///
+    /// Returns how many events were sent.
+    pub fn release(&mut self, time: Duration) -> usize {
+        let (default_rate, burst) = (self.rate, self.burst as f64);
+        let mut released = 0;
+        for bucket in &mut self.buckets {
+            if bucket.deferred.is_empty() {
+                continue
+            }
+            let rate = match self.policy {
+                Some(ref policy) => policy(&bucket.client),
+                None => Some(default_rate)
+            };
+            if let Some(rate) = rate {
+                bucket.refill(rate as f64, burst, time);
+                if bucket.tokens < 1.0 {
+                    continue
+                }
+            }
+            for mut event in bucket.deferred.drain(..) {
+                event();
+                released += 1;
+            }
+        }
+        released
+    }
+
+    /// Send the held back events of a client right away, e.g because one of
+    /// its views became visible.
+    pub fn flush(&mut self, client: &K) {
+        if let Some(bucket) = self.buckets.iter_mut().find(|bucket| bucket.client == *client) {
+            for mut event in bucket.deferred.drain(..) {
+                event()
+            }
+        }
+    }
+
+    /// Forget a client, e.g because it disconnected. Events held back for it
+    /// are dropped.
+    pub fn remove(&mut self, client: &K) {
+        self.buckets.retain(|bucket| bucket.client != *client)
+    }
+}
+
+impl<K> Bucket<K> {
+    fn refill(&mut self, rate: f64, burst: f64, time: Duration) {
+        if time > self.last_commit {
+            let elapsed = time - self.last_commit;
+            let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+            self.tokens = (self.tokens + elapsed * rate).min(burst);
+            self.last_commit = time;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use area::{Origin, Size};
+    use wm::stacking::Layer;
+
+    use super::*;
+
+    #[test]
+    fn removed_views_are_dropped_from_visibility() {
+        let screen = Area::new(Origin::new(0, 0), Size::new(100, 100));
+        let mut stack = Stack::new();
+        stack.insert(1u32, Layer::Normal);
+        let mut throttle = FrameThrottle::new();
+        throttle.update(&stack, &[screen], |_| Some(Shape::opaque(screen)));
+        assert!(throttle.should_send_frame(&1));
+        throttle.remove(&1);
+        // A view reusing the id isn't known to be visible any more.
+        assert!(!throttle.should_send_frame(&1));
+    }
+}
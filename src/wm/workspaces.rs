@@ -0,0 +1,416 @@
+//! Workspaces per output, and sticky views that are on all of them.
+//!
+//! Every output shows one of its numbered workspaces at a time, and every
+//! view is on one workspace of one output. `Workspaces` keeps track of that
+//! and works out which views to hide and show when the workspace of an
+//! output is switched. Hidden views should be removed from the `Stack` and
+//! not be rendered, like the views of the `wm::scratchpad`.
+//!
+//! A sticky view is on every workspace of its output, e.g a picture in
+//! picture video: it's never hidden by switching, keeps its position, and
+//! keeps focus if it had it.
+//!
+//! ```rust,no_run,ignore
+//! let switch = state.workspaces.switch(&output, 2);
+//! for view in &switch.hidden {
+//!     state.stack.remove(view);
+//! }
+//! for view in switch.shown {
+//!     state.stack.insert(view, Layer::Normal);
+//! }
+//! let focus = state.workspaces.most_recent_visible(&output, &state.history).cloned();
+//! ```
+//!
+//! Switches are published as `bus::WorkspaceChanged` events. For status
+//! bars, workspaces are reported as tags like `river_status` expects them,
+//! with sticky views on all tags.
+
+use std::u32;
+
+use {bus,
+     wm::history::{Alive, History}};
+
+#[derive(Debug, Clone)]
+struct Entry<K, T> {
+    view: T,
+    output: K,
+    workspace: u32,
+    sticky: bool
+}
+
+/// The views that have to be hidden and shown after a workspace switch.
+#[derive(Debug, Clone)]
+pub struct Switch<T> {
+    pub hidden: Vec<T>,
+    pub shown: Vec<T>
+}
+
+/// The workspaces of the outputs, identified by `K` (e.g `output::Handle`),
+/// and the views on them.
+#[derive(Debug, Clone)]
+pub struct Workspaces<K, T> {
+    /// The active workspace of each output that switched away from 0.
+    active: Vec<(K, u32)>,
+    views: Vec<Entry<K, T>>
+}
+
+impl<K, T> Default for Workspaces<K, T> {
+    fn default() -> Self {
+        Workspaces { active: Vec::new(),
+                     views: Vec::new() }
+    }
+}
+
+impl<K, T> Workspaces<K, T>
+    where K: Clone + PartialEq + 'static,
+          T: Clone + PartialEq + Alive
+{
+    /// Start with every output on workspace 0.
+    pub fn new() -> Self {
+        Workspaces::default()
+    }
+
+    /// Get the workspace the output shows.
+    pub fn active(&self, output: &K) -> u32 {
+        self.active
+            .iter()
+            .find(|&&(ref other, _)| other == output)
+            .map(|&(_, workspace)| workspace)
+            .unwrap_or(0)
+    }
+
+    /// Put a new view on the workspace the output shows.
+    pub fn add(&mut self, view: T, output: K) {
+        self.remove(&view);
+        let workspace = self.active(&output);
+        self.views.push(Entry { view,
+                                output,
+                                workspace,
+                                sticky: false })
+    }
+
+    pub fn remove(&mut self, view: &T) {
+        self.views.retain(|entry| entry.view != *view)
+    }
+
+    /// Show another workspace on the output.
+    pub fn switch(&mut self, output: &K, workspace: u32) -> Switch<T> {
+        let previous = self.active(output);
+        let mut switch = Switch { hidden: Vec::new(),
+                                  shown: Vec::new() };
+        if previous == workspace {
+            return switch
+        }
+        self.prune();
+        self.active.retain(|&(ref other, _)| other != output);
+        self.active.push((output.clone(), workspace));
+        for entry in &mut self.views {
+            if entry.output != *output {
+                continue
+            }
+            if entry.sticky {
+                // It comes along, so it's reported on the shown workspace.
+                entry.workspace = workspace;
+            } else if entry.workspace == previous {
+                switch.hidden.push(entry.view.clone())
+            } else if entry.workspace == workspace {
+                switch.shown.push(entry.view.clone())
+            }
+        }
+        bus::publish(&bus::WorkspaceChanged { output: output.clone(),
+                                              previous,
+                                              workspace });
+        switch
+    }
+
+    /// Move the view to another workspace of its output.
+    ///
+    /// Returns whether it's still visible. A sticky view stays visible, and
+    /// stays on the shown workspace.
+    pub fn move_to(&mut self, view: &T, workspace: u32) -> bool {
+        let active = match self.entry(view) {
+            Some(entry) => self.active(&entry.output),
+            None => return false
+        };
+        match self.entry_mut(view) {
+            Some(entry) => {
+                if !entry.sticky {
+                    entry.workspace = workspace;
+                }
+                entry.sticky || entry.workspace == active
+            }
+            None => false
+        }
+    }
+
+    /// Move the view to the workspace another output shows.
+    pub fn move_to_output(&mut self, view: &T, output: K) {
+        let workspace = self.active(&output);
+        if let Some(entry) = self.entry_mut(view) {
+            entry.output = output;
+            entry.workspace = workspace;
+        }
+    }
+
+    /// Make the view visible on all workspaces of its output, or only on
+    /// the one it's on now.
+    pub fn set_sticky(&mut self, view: &T, sticky: bool) {
+        let active = match self.entry(view) {
+            Some(entry) => self.active(&entry.output),
+            None => return
+        };
+        if let Some(entry) = self.entry_mut(view) {
+            entry.sticky = sticky;
+            if sticky {
+                entry.workspace = active;
+            }
+        }
+    }
+
+    pub fn is_sticky(&self, view: &T) -> bool {
+        self.entry(view).map(|entry| entry.sticky).unwrap_or(false)
+    }
+
+    /// Get the output and workspace of the view. Sticky views are on the
+    /// workspace their output shows.
+    pub fn workspace_of(&self, view: &T) -> Option<(K, u32)> {
+        self.entry(view).map(|entry| (entry.output.clone(), entry.workspace))
+    }
+
+    /// Whether the view is on a workspace that is shown.
+    pub fn is_visible(&self, view: &T) -> bool {
+        match self.entry(view) {
+            Some(entry) => entry.sticky || entry.workspace == self.active(&entry.output),
+            None => false
+        }
+    }
+
+    /// Get the views the output shows, sticky ones included.
+    pub fn visible<'a>(&'a self, output: &'a K) -> impl Iterator<Item = &'a T> + 'a {
+        let active = self.active(output);
+        self.views
+            .iter()
+            .filter(move |entry| {
+                        entry.output == *output && (entry.sticky || entry.workspace == active)
+                    })
+            .map(|entry| &entry.view)
+    }
+
+    /// Get the view the output shows that was focused last, which should
+    /// get focus after a switch.
+    pub fn most_recent_visible<'a>(&self, output: &K, history: &'a History<T>) -> Option<&'a T> {
+        history.iter().find(|view| {
+                                self.entry(view)
+                                    .map(|entry| {
+                                             entry.output == *output
+                                             && (entry.sticky
+                                                 || entry.workspace == self.active(output))
+                                         })
+                                    .unwrap_or(false)
+                            })
+    }
+
+    /// Get the tags of the view for status bars: the bit of its workspace,
+    /// or all bits for a sticky view.
+    pub fn tags(&self, view: &T) -> u32 {
+        match self.entry(view) {
+            Some(entry) if entry.sticky => u32::MAX,
+            Some(entry) => tag(entry.workspace),
+            None => 0
+        }
+    }
+
+    /// Get the tags of every view on the output, for
+    /// `river_status::ZManagerV1::set_view_tags`.
+    pub fn view_tags(&self, output: &K) -> Vec<u32> {
+        self.views
+            .iter()
+            .filter(|entry| entry.output == *output)
+            .map(|entry| self.tags(&entry.view))
+            .collect()
+    }
+
+    /// Get the tag of the workspace the output shows, for
+    /// `river_status::ZManagerV1::set_focused_tags`.
+    pub fn focused_tags(&self, output: &K) -> u32 {
+        tag(self.active(output))
+    }
+
+    /// Forget views that were destroyed.
+    pub fn prune(&mut self) {
+        self.views.retain(|entry| entry.view.is_alive())
+    }
+
+    fn entry(&self, view: &T) -> Option<&Entry<K, T>> {
+        self.views.iter().find(|entry| entry.view == *view)
+    }
+
+    fn entry_mut(&mut self, view: &T) -> Option<&mut Entry<K, T>> {
+        self.views.iter_mut().find(|entry| entry.view == *view)
+    }
+}
+
+/// Tags only have room for 32 workspaces, the others don't get one.
+fn tag(workspace: u32) -> u32 {
+    if workspace < 32 {
+        1 << workspace
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use wm::View;
+
+    const LEFT: &str = "DP-1";
+    const RIGHT: &str = "HDMI-A-1";
+
+    const BROWSER: View = View(1, true);
+    const TERMINAL: View = View(2, true);
+    const VIDEO: View = View(3, true);
+    const MAIL: View = View(4, true);
+
+    /// The browser and the terminal on the left output, the mail on the
+    /// right one, all on workspace 0.
+    fn workspaces() -> Workspaces<&'static str, View> {
+        let mut workspaces = Workspaces::new();
+        workspaces.add(BROWSER, LEFT);
+        workspaces.add(TERMINAL, LEFT);
+        workspaces.add(MAIL, RIGHT);
+        workspaces
+    }
+
+    #[test]
+    fn switch_hides_and_shows_the_views_of_the_output() {
+        let mut workspaces = workspaces();
+        let switch = workspaces.switch(&LEFT, 1);
+        assert_eq!(switch.hidden, vec![BROWSER, TERMINAL]);
+        assert!(switch.shown.is_empty());
+        assert_eq!(workspaces.active(&LEFT), 1);
+        assert_eq!(workspaces.active(&RIGHT), 0);
+        assert!(workspaces.is_visible(&MAIL));
+        assert!(!workspaces.is_visible(&BROWSER));
+
+        // New views go on the workspace that is shown.
+        workspaces.add(VIDEO, LEFT);
+        assert_eq!(workspaces.workspace_of(&VIDEO), Some((LEFT, 1)));
+        let switch = workspaces.switch(&LEFT, 0);
+        assert_eq!(switch.hidden, vec![VIDEO]);
+        assert_eq!(switch.shown, vec![BROWSER, TERMINAL]);
+        assert_eq!(workspaces.visible(&LEFT).collect::<Vec<_>>(), vec![&BROWSER, &TERMINAL]);
+    }
+
+    #[test]
+    fn switching_to_the_shown_workspace_does_nothing() {
+        let mut workspaces = workspaces();
+        let switch = workspaces.switch(&LEFT, 0);
+        assert!(switch.hidden.is_empty());
+        assert!(switch.shown.is_empty());
+    }
+
+    #[test]
+    fn switches_are_published() {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let seen = changes.clone();
+        let subscription =
+            bus::subscribe(move |event: &bus::WorkspaceChanged<&'static str>| {
+                               seen.borrow_mut()
+                                   .push((event.output, event.previous, event.workspace))
+                           });
+        let mut workspaces = workspaces();
+        workspaces.switch(&LEFT, 2);
+        workspaces.switch(&LEFT, 2);
+        workspaces.switch(&RIGHT, 5);
+        bus::unsubscribe(subscription);
+        assert_eq!(*changes.borrow(), vec![(LEFT, 0, 2), (RIGHT, 0, 5)]);
+    }
+
+    #[test]
+    fn sticky_views_come_along() {
+        let mut workspaces = workspaces();
+        workspaces.set_sticky(&BROWSER, true);
+        assert!(workspaces.is_sticky(&BROWSER));
+        let switch = workspaces.switch(&LEFT, 3);
+        assert_eq!(switch.hidden, vec![TERMINAL]);
+        assert!(workspaces.is_visible(&BROWSER));
+        assert_eq!(workspaces.workspace_of(&BROWSER), Some((LEFT, 3)));
+        // Moving doesn't take it off the shown workspace.
+        assert!(workspaces.move_to(&BROWSER, 4));
+        assert_eq!(workspaces.workspace_of(&BROWSER), Some((LEFT, 3)));
+
+        // Unsticking leaves it on the workspace it was last shown on.
+        workspaces.set_sticky(&BROWSER, false);
+        let switch = workspaces.switch(&LEFT, 0);
+        assert_eq!(switch.hidden, vec![BROWSER]);
+        assert_eq!(switch.shown, vec![TERMINAL]);
+    }
+
+    #[test]
+    fn move_to_another_workspace() {
+        let mut workspaces = workspaces();
+        assert!(!workspaces.move_to(&TERMINAL, 2));
+        assert!(!workspaces.is_visible(&TERMINAL));
+        assert!(workspaces.move_to(&TERMINAL, 0));
+        assert!(!workspaces.move_to(&VIDEO, 0));
+        workspaces.move_to(&TERMINAL, 2);
+        let switch = workspaces.switch(&LEFT, 2);
+        assert_eq!(switch.hidden, vec![BROWSER]);
+        assert_eq!(switch.shown, vec![TERMINAL]);
+    }
+
+    #[test]
+    fn move_to_output_uses_the_shown_workspace() {
+        let mut workspaces = workspaces();
+        workspaces.switch(&RIGHT, 7);
+        workspaces.move_to_output(&TERMINAL, RIGHT);
+        assert_eq!(workspaces.workspace_of(&TERMINAL), Some((RIGHT, 7)));
+        assert!(workspaces.is_visible(&TERMINAL));
+        assert_eq!(workspaces.visible(&LEFT).collect::<Vec<_>>(), vec![&BROWSER]);
+    }
+
+    #[test]
+    fn most_recent_visible_skips_hidden_views() {
+        let mut workspaces = workspaces();
+        let mut history = History::new();
+        history.focus(BROWSER);
+        history.focus(MAIL);
+        history.focus(TERMINAL);
+        assert_eq!(workspaces.most_recent_visible(&LEFT, &history), Some(&TERMINAL));
+        assert_eq!(workspaces.most_recent_visible(&RIGHT, &history), Some(&MAIL));
+        workspaces.move_to(&TERMINAL, 1);
+        assert_eq!(workspaces.most_recent_visible(&LEFT, &history), Some(&BROWSER));
+        workspaces.switch(&LEFT, 1);
+        assert_eq!(workspaces.most_recent_visible(&LEFT, &history), Some(&TERMINAL));
+        workspaces.switch(&LEFT, 2);
+        assert_eq!(workspaces.most_recent_visible(&LEFT, &history), None);
+    }
+
+    #[test]
+    fn tags_for_status_bars() {
+        let mut workspaces = workspaces();
+        workspaces.move_to(&TERMINAL, 3);
+        workspaces.switch(&RIGHT, 40);
+        workspaces.add(VIDEO, RIGHT);
+        assert_eq!(workspaces.tags(&BROWSER), 1);
+        assert_eq!(workspaces.tags(&TERMINAL), 1 << 3);
+        assert_eq!(workspaces.tags(&VIDEO), 0);
+        workspaces.set_sticky(&VIDEO, true);
+        assert_eq!(workspaces.tags(&VIDEO), u32::MAX);
+        assert_eq!(workspaces.view_tags(&LEFT), vec![1, 1 << 3]);
+        assert_eq!(workspaces.focused_tags(&LEFT), 1);
+        assert_eq!(workspaces.focused_tags(&RIGHT), 0);
+    }
+
+    #[test]
+    fn destroyed_views_are_forgotten() {
+        let mut workspaces = workspaces();
+        workspaces.add(View(5, false), LEFT);
+        let switch = workspaces.switch(&LEFT, 1);
+        assert_eq!(switch.hidden, vec![BROWSER, TERMINAL]);
+        assert_eq!(workspaces.workspace_of(&View(5, false)), None);
+    }
+}
@@ -99,14 +99,15 @@ impl<'surface> SizeHints<'surface> {
         unsafe { ((*self.hints).max_width, (*self.hints).max_height) }
     }
 
-    /// TODO What is this
+    /// Get the steps the surface wants to be resized in, e.g the size of a
+    /// character cell of a terminal.
     ///
     /// Return format is (width, height).
     pub fn inc_dimensions(&self) -> (int32_t, int32_t) {
         unsafe { ((*self.hints).width_inc, (*self.hints).height_inc) }
     }
 
-    /// TODO What is this
+    /// Get the size the resize increments are counted from.
     ///
     /// Return format is (width, height).
     pub fn base_dimensions(&self) -> (int32_t, int32_t) {
@@ -11,7 +11,7 @@ use libc;
 use wayland_sys::server::signal::wl_signal_add;
 use wlroots_sys::wlr_xwayland_surface;
 
-use {compositor, xwayland, utils::Handleable};
+use {compositor, snapshot, xwayland, utils::Handleable};
 
 /// Callback that's triggered when the XWayland library is ready.
 pub type OnReady = fn(compositor::Handle);
@@ -45,6 +45,9 @@ wayland_listener_static! {
                 None => return
             };
             let shell_surface = xwayland::surface::Surface::new(surface_ptr);
+            snapshot::track(|tracked| {
+                                tracked.add_xwayland_surface(shell_surface.weak_reference())
+                            });
             let xwayland_handler = manager.surface_added
                 .and_then(|f| f(compositor, shell_surface.weak_reference()));
             let mut shell = xwayland::surface::Shell::new((shell_surface, xwayland_handler));
@@ -10,7 +10,7 @@ use {area::{Area, Size, Origin},
      compositor,
      surface::{self, InternalState},
      xwayland,
-     utils::{self, HandleErr, HandleResult, Handleable, c_to_rust_string}};
+     utils::{self, HandleErr, HandleResult, Handleable, Id, c_to_rust_string}};
 pub use xwayland::hints::{Hints, SizeHints};
 
 pub type Handle = utils::Handle<(), wlr_xwayland_surface, Surface>;
@@ -341,7 +341,8 @@ wayland_listener!(pub(crate) Shell, (Surface, Option<Box<Handler>>), [
 
 pub(crate) struct State {
     pub(crate) shell: *mut Shell,
-    handle: Weak<Cell<bool>>
+    handle: Weak<Cell<bool>>,
+    id: Id
 }
 
 /// An Xwayland user interface component. It has an absolute position in
@@ -362,12 +363,22 @@ impl Surface {
     pub(crate) unsafe fn new(shell_surface: *mut wlr_xwayland_surface) -> Self {
         (*shell_surface).data = ptr::null_mut();
         let liveliness = Rc::new(Cell::new(false));
-        let state = Box::new(State { shell: ptr::null_mut(), handle: Rc::downgrade(&liveliness) });
+        let state = Box::new(State { shell: ptr::null_mut(),
+                                     handle: Rc::downgrade(&liveliness),
+                                     id: Id::next() });
         (*shell_surface).data = Box::into_raw(state) as *mut _;
         Surface { liveliness,
                           shell_surface }
     }
 
+    /// Get the stable identifier of this surface.
+    ///
+    /// Unlike the X11 window id this is never reused, so it can be used to
+    /// refer to this view from outside of the compositor.
+    pub fn id(&self) -> Id {
+        unsafe { (*((*self.shell_surface).data as *mut State)).id }
+    }
+
     /// Get the window id for this surface.
     pub fn window_id(&self) -> xcb_window_t {
         unsafe { (*self.shell_surface).window_id }
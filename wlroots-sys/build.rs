@@ -8,7 +8,15 @@ use std::{env, io, fs};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// The wlroots release the bindings are written against.
+///
+/// The headers are always taken from the `wlroots` submodule, so it has to
+/// be checked out at this release, and so does the library that's linked
+/// dynamically.
+const WLROOTS_VERSION: &str = "0.3.0";
+
 fn main() {
+    check_wlroots_version();
     meson();
     let protocol_header_path =
         generate_protocol_headers().expect("Could not generate header files for wayland protocols");
@@ -21,6 +29,7 @@ fn main() {
         .whitelisted_type(r"^wlr_.*$")
         .whitelisted_type(r"^xkb_.*$")
         .whitelisted_type(r"^XKB_.*$")
+        .whitelisted_type(r"^zwlr_.*$")
         .whitelisted_function(r"^_?pixman_.*$")
         .whitelisted_function(r"^_?wlr_.*$")
         .whitelisted_function(r"^xkb_.*$")
@@ -101,6 +110,33 @@ fn main() {
     generate_protocols();
 }
 
+/// Fails the build if the `wlroots` submodule, or the installed wlroots
+/// when linking dynamically, isn't at `WLROOTS_VERSION`.
+fn check_wlroots_version() {
+    let meson_build = fs::read_to_string("wlroots/meson.build")
+        .expect("The `wlroots` submodule does not exist");
+    let version = meson_build.find("version: '")
+                             .map(|start| &meson_build[start + "version: '".len()..])
+                             .and_then(|rest| rest.split('\'').next())
+                             .unwrap_or("unknown");
+    if version != WLROOTS_VERSION {
+        panic!("The `wlroots` submodule is at version {}, but wlroots-sys needs {}",
+               version,
+               WLROOTS_VERSION);
+    }
+    if cfg!(feature = "static") {
+        return
+    }
+    let installed = pkg_config::Config::new().cargo_metadata(false).probe("wlroots");
+    if let Ok(library) = installed {
+        if library.version != WLROOTS_VERSION {
+            panic!("wlroots {} is installed, but wlroots-sys needs {}",
+                   library.version,
+                   WLROOTS_VERSION);
+        }
+    }
+}
+
 #[cfg(not(feature = "static"))]
 fn meson() {}
 
@@ -149,6 +185,9 @@ fn meson() {
 ///
 /// The path to the folder with the generated headers is returned. It will
 /// have two directories, `stable`, and `unstable`.
+///
+/// Headers for the wlroots protocols its public headers depend on are
+/// generated in there as well.
 fn generate_protocol_headers() -> io::Result<PathBuf> {
     let output_dir_str = env::var("OUT_DIR").unwrap();
     let out_path: PathBuf = format!("{}/wayland-protocols", output_dir_str).into();
@@ -176,6 +215,17 @@ fn generate_protocol_headers() -> io::Result<PathBuf> {
                                            .unwrap();
         }
     }
+    // Headers for the protocols that are defined in wlroots itself and are
+    // included by its public headers.
+    for protocol in &["wlr-layer-shell-unstable-v1"] {
+        Command::new("wayland-scanner").arg("server-header")
+                                       .arg(format!("wlroots/protocol/{}.xml", protocol))
+                                       .arg(format!("{}/{}-protocol.h",
+                                                    out_path.to_str().unwrap(),
+                                                    protocol))
+                                       .status()
+                                       .unwrap();
+    }
     Ok(out_path)
 }
 
@@ -188,7 +238,9 @@ fn generate_protocols() {
                       ("./wlroots/protocol/wlr-gamma-control-unstable-v1.xml", "gamma_control"),
                       ("./wlroots/protocol/wlr-screencopy-unstable-v1.xml", "screencopy"),
                       ("./wlroots/protocol/screenshooter.xml", "screenshooter"),
-                      ("./wlroots/protocol/idle.xml", "idle")
+                      ("./wlroots/protocol/idle.xml", "idle"),
+                      // Not implemented by wlroots, the bindings implement it.
+                      ("./protocols/river-status-unstable-v1.xml", "river_status")
     ];
 
     for protocol in protocols {
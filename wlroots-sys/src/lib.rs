@@ -54,6 +54,27 @@ mod generated {
                 include!(concat!(env!("OUT_DIR"), "/idle_server_api.rs"));
             }
         }
+        pub mod river_status {
+            #![allow(unused_imports)]
+            #![allow(unused_variables)]
+            // Public, the global is created from these in the wlroots crate.
+            pub mod c_interfaces {
+                use wayland_server::sys::protocol_interfaces::{wl_output_interface,
+                                                               wl_seat_interface};
+                include!(concat!(env!("OUT_DIR"), "/river_status_interfaces.rs"));
+            }
+
+            pub mod server {
+                pub(crate) use wayland_server::{NewResource, Resource};
+                pub(crate) use wayland_commons::{AnonymousObject, Interface, MessageGroup,
+                                                 wire::{Argument, ArgumentType, Message, MessageDesc},
+                                                 map::{Object, ObjectMetadata}};
+                pub(crate) use wayland_sys as sys;
+                use wayland_server::{*, protocol::{wl_output, wl_seat}};
+                use wayland_sys::common::{wl_interface, wl_argument};
+                include!(concat!(env!("OUT_DIR"), "/river_status_server_api.rs"));
+            }
+        }
 
     }
 }